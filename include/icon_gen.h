@@ -0,0 +1,57 @@
+#ifndef ICON_GEN_H
+#define ICON_GEN_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The call succeeded
+#define ICON_GEN_OK 0
+
+// The call failed; `icon_gen_last_error` describes why
+#define ICON_GEN_ERROR 1
+
+// A byte buffer allocated by the library, released with `icon_gen_buffer_free`
+typedef struct IconGenBuffer {
+  uint8_t *data;
+  size_t len;
+} IconGenBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Generate icons from an encoded source image (PNG, JPEG, ...) into a zip archive
+//
+// `platforms` is a comma-separated list of platform names as given to the command
+// line, e.g. `"windows,macos,linux"`; NULL or an empty string generates every
+// platform. On success the archive is stored in `*out` and `ICON_GEN_OK` is
+// returned; on failure `*out` is left empty and `ICON_GEN_ERROR` is returned.
+//
+// # Safety
+//
+// `source` must point to `source_len` readable bytes, `platforms` must be NULL or
+// a NUL-terminated string, and `out` must point to a writable `IconGenBuffer`.
+int icon_gen_generate_zip(const uint8_t *source,
+                          size_t source_len,
+                          const char *platforms,
+                          struct IconGenBuffer *out);
+
+// Release a buffer returned by the library; an empty buffer is ignored
+//
+// # Safety
+//
+// `buffer` must have been returned by this library and not been freed before.
+void icon_gen_buffer_free(struct IconGenBuffer buffer);
+
+// The message of the last failed call on this thread, or NULL after a success
+// The string stays valid until the next call on the same thread.
+const char *icon_gen_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ICON_GEN_H */
@@ -0,0 +1,372 @@
+//! Zip and tar archive writers
+//!
+//! Generated sets are small and written in one go, so archives are built in
+//! memory. Every entry gets a fixed timestamp so that the same files always
+//! produce the same archive. Zip entries are deflated unless that doesn't make
+//! them smaller (PNGs are already compressed); Zip64 isn't needed for icon sets
+//! and isn't supported.
+
+use crate::error::IconGenError;
+use anyhow::{bail, Result};
+use std::path::Path;
+
+/// Layout of an archive, chosen from the extension of its file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// `.zip`
+    Zip,
+    /// `.tar`
+    Tar,
+    /// `.tar.gz` or `.tgz`
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn from_path(path: &Path) -> crate::error::Result<Self> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name.ends_with(".zip") {
+            Ok(Self::Zip)
+        } else if name.ends_with(".tar") {
+            Ok(Self::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Ok(Self::TarGz)
+        } else {
+            Err(IconGenError::UnsupportedFormat(format!(
+                "Unknown archive format: {}. Expected a .zip, .tar, .tar.gz or .tgz file",
+                path.display()
+            )))
+        }
+    }
+
+    /// Build an archive of `entries`, given as `/`-separated names and their contents
+    pub(crate) fn encode(self, entries: &[(String, &[u8])]) -> Result<Vec<u8>> {
+        match self {
+            Self::Zip => zip(entries),
+            Self::Tar => tar(entries),
+            Self::TarGz => Ok(gzip(&tar(entries)?)),
+        }
+    }
+}
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+
+/// Version 2.0: deflate and directories
+const VERSION: u16 = 20;
+
+/// Names are UTF-8
+const FLAG_UTF8: u16 = 1 << 11;
+
+const METHOD_STORE: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+
+/// 1980-01-01 00:00:00, the earliest DOS timestamp
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = 1 << 5 | 1;
+
+fn zip(entries: &[(String, &[u8])]) -> Result<Vec<u8>> {
+    let mut archive = Vec::new();
+    let mut central = Vec::new();
+    let mut count = 0usize;
+
+    for &(ref name, data) in entries {
+        let deflated = miniz_oxide::deflate::compress_to_vec(data, 9);
+        let (method, stored) = if deflated.len() < data.len() {
+            (METHOD_DEFLATE, deflated.as_slice())
+        } else {
+            (METHOD_STORE, data)
+        };
+        let crc = crc32fast::hash(data);
+        let offset = archive.len();
+        if offset > u32::MAX as usize || data.len() > u32::MAX as usize {
+            bail!("{name} doesn't fit in a zip archive without Zip64");
+        }
+
+        let header = |buf: &mut Vec<u8>| {
+            push16(buf, method);
+            push16(buf, DOS_TIME);
+            push16(buf, DOS_DATE);
+            push32(buf, crc);
+            push32(buf, stored.len() as u32);
+            push32(buf, data.len() as u32);
+            push16(buf, name.len() as u16);
+            push16(buf, 0);
+        };
+
+        push32(&mut archive, LOCAL_HEADER);
+        push16(&mut archive, VERSION);
+        push16(&mut archive, FLAG_UTF8);
+        header(&mut archive);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(stored);
+
+        push32(&mut central, CENTRAL_HEADER);
+        push16(&mut central, VERSION);
+        push16(&mut central, VERSION);
+        push16(&mut central, FLAG_UTF8);
+        header(&mut central);
+        push16(&mut central, 0); // comment length
+        push16(&mut central, 0); // disk number
+        push16(&mut central, 0); // internal attributes
+        push32(&mut central, 0); // external attributes
+        push32(&mut central, offset as u32);
+        central.extend_from_slice(name.as_bytes());
+        count += 1;
+    }
+
+    if count > u16::MAX as usize || archive.len() + central.len() > u32::MAX as usize {
+        bail!("Too many files for a zip archive without Zip64");
+    }
+
+    let central_offset = archive.len() as u32;
+    let central_size = central.len() as u32;
+    archive.append(&mut central);
+    push32(&mut archive, END_OF_CENTRAL_DIRECTORY);
+    push16(&mut archive, 0); // this disk
+    push16(&mut archive, 0); // disk with the central directory
+    push16(&mut archive, count as u16);
+    push16(&mut archive, count as u16);
+    push32(&mut archive, central_size);
+    push32(&mut archive, central_offset);
+    push16(&mut archive, 0); // comment length
+    Ok(archive)
+}
+
+/// Size of a tar header and the unit data is padded to
+const TAR_BLOCK: usize = 512;
+
+/// A POSIX ustar archive of regular files, readable by every tar
+fn tar(entries: &[(String, &[u8])]) -> Result<Vec<u8>> {
+    let mut archive = Vec::new();
+    for (name, data) in entries {
+        let mut header = [0u8; TAR_BLOCK];
+        let (prefix, name) = split_tar_name(name)?;
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        put_octal(&mut header[100..108], 0o644);
+        put_octal(&mut header[108..116], 0);
+        put_octal(&mut header[116..124], 0);
+        put_octal(&mut header[124..136], data.len() as u64);
+        put_octal(&mut header[136..148], 0);
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+        // The checksum is taken with its own field filled with spaces
+        header[148..156].fill(b' ');
+        let checksum = header.iter().map(|&byte| byte as u64).sum::<u64>();
+        put_octal(&mut header[148..155], checksum);
+
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(data);
+        archive.resize(archive.len().next_multiple_of(TAR_BLOCK), 0);
+    }
+    // Two empty blocks end the archive
+    archive.resize(archive.len() + 2 * TAR_BLOCK, 0);
+    Ok(archive)
+}
+
+/// Split a name into the ustar prefix and name fields, of up to 155 and 100 bytes
+fn split_tar_name(name: &str) -> Result<(&str, &str)> {
+    if name.len() <= 100 {
+        return Ok(("", name));
+    }
+    name.match_indices('/')
+        .map(|(at, _)| (&name[..at], &name[at + 1..]))
+        .find(|(prefix, rest)| prefix.len() <= 155 && rest.len() <= 100)
+        .ok_or_else(|| anyhow::anyhow!("{name} is too long for a tar archive"))
+}
+
+/// Write `value` as zero-padded octal digits followed by a NUL, filling `field`
+fn put_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{value:0width$o}", width = field.len() - 1);
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+}
+
+/// Wrap `data` in a gzip member, with no name and a zero timestamp
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut gz = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 2, 0xff];
+    gz.extend_from_slice(&miniz_oxide::deflate::compress_to_vec(data, 9));
+    push32(&mut gz, crc32fast::hash(data));
+    push32(&mut gz, data.len() as u32);
+    gz
+}
+
+fn push16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read16(buf: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(buf[at..at + 2].try_into().unwrap())
+    }
+
+    fn read32(buf: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(buf[at..at + 4].try_into().unwrap())
+    }
+
+    /// Read the entries back through the central directory
+    fn unzip(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let end = archive.len() - 22;
+        assert_eq!(read32(archive, end), END_OF_CENTRAL_DIRECTORY);
+        let count = read16(archive, end + 10) as usize;
+        let mut at = read32(archive, end + 16) as usize;
+
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            assert_eq!(read32(archive, at), CENTRAL_HEADER);
+            let method = read16(archive, at + 10);
+            let crc = read32(archive, at + 16);
+            let stored_size = read32(archive, at + 20) as usize;
+            let name_len = read16(archive, at + 28) as usize;
+            let local = read32(archive, at + 42) as usize;
+            let name = String::from_utf8(archive[at + 46..at + 46 + name_len].to_vec()).unwrap();
+
+            assert_eq!(read32(archive, local), LOCAL_HEADER);
+            let data_start = local + 30 + read16(archive, local + 26) as usize;
+            let stored = &archive[data_start..data_start + stored_size];
+            let data = match method {
+                METHOD_STORE => stored.to_vec(),
+                METHOD_DEFLATE => miniz_oxide::inflate::decompress_to_vec(stored).unwrap(),
+                method => panic!("Unexpected method {method}"),
+            };
+            assert_eq!(crc32fast::hash(&data), crc);
+            entries.push((name, data));
+            at += 46 + name_len;
+        }
+        entries
+    }
+
+    #[test]
+    fn test_zip_round_trips() {
+        let text = b"abcabcabcabcabcabcabcabc".repeat(100);
+        let noise = (0..=255u8).collect::<Vec<_>>();
+        let archive = zip(&[
+            ("windows/icon.ico".to_string(), text.as_slice()),
+            ("noise.bin".to_string(), noise.as_slice()),
+            ("empty".to_string(), &[][..]),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            unzip(&archive),
+            vec![
+                ("windows/icon.ico".to_string(), text.clone()),
+                ("noise.bin".to_string(), noise),
+                ("empty".to_string(), Vec::new()),
+            ]
+        );
+        assert!(archive.len() < text.len());
+    }
+
+    #[test]
+    fn test_empty_archive() {
+        let archive = zip(&[]).unwrap();
+        assert_eq!(archive.len(), 22);
+        assert!(unzip(&archive).is_empty());
+    }
+
+    /// Read the regular files of a ustar archive back
+    fn untar(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let field = |header: &[u8], range: std::ops::Range<usize>| {
+            let text = std::str::from_utf8(&header[range]).unwrap();
+            text.trim_end_matches('\0').to_string()
+        };
+        let octal = |header: &[u8], range| u64::from_str_radix(&field(header, range), 8).unwrap();
+
+        let mut entries = Vec::new();
+        let mut at = 0;
+        while archive[at..at + TAR_BLOCK].iter().any(|&byte| byte != 0) {
+            let header = &archive[at..at + TAR_BLOCK];
+            let mut blank = header.to_vec();
+            blank[148..156].fill(b' ');
+            assert_eq!(
+                octal(header, 148..155),
+                blank.iter().map(|&byte| byte as u64).sum::<u64>()
+            );
+            assert_eq!(&header[257..263], b"ustar\0");
+
+            let prefix = field(header, 345..500);
+            let name = field(header, 0..100);
+            let name = if prefix.is_empty() {
+                name
+            } else {
+                format!("{prefix}/{name}")
+            };
+            let size = octal(header, 124..136) as usize;
+            let data = archive[at + TAR_BLOCK..at + TAR_BLOCK + size].to_vec();
+            entries.push((name, data));
+            at += TAR_BLOCK + size.next_multiple_of(TAR_BLOCK);
+        }
+        assert_eq!(archive.len(), at + 2 * TAR_BLOCK);
+        entries
+    }
+
+    #[test]
+    fn test_tar_round_trips() {
+        let long_name = format!(
+            "{}{}",
+            "ios/".repeat(30),
+            "AppIcon.appiconset/Contents.json"
+        );
+        let text = b"abcabcabcabcabcabcabcabc".repeat(100);
+        let entries = [
+            ("windows/icon.ico".to_string(), text.as_slice()),
+            (long_name.clone(), &[1, 2, 3][..]),
+            ("empty".to_string(), &[][..]),
+        ];
+
+        let archive = ArchiveFormat::Tar.encode(&entries).unwrap();
+        assert_eq!(archive.len() % TAR_BLOCK, 0);
+        assert_eq!(
+            untar(&archive),
+            vec![
+                ("windows/icon.ico".to_string(), text.clone()),
+                (long_name, vec![1, 2, 3]),
+                ("empty".to_string(), Vec::new()),
+            ]
+        );
+
+        let gz = ArchiveFormat::TarGz.encode(&entries).unwrap();
+        assert_eq!(gz[..3], [0x1f, 0x8b, 8]);
+        let inflated = miniz_oxide::inflate::decompress_to_vec(&gz[10..gz.len() - 8]).unwrap();
+        assert_eq!(inflated, archive);
+        assert_eq!(
+            gz[gz.len() - 8..gz.len() - 4],
+            crc32fast::hash(&archive).to_le_bytes()
+        );
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("icons.zip")).unwrap(),
+            ArchiveFormat::Zip
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("out/Icons.TAR")).unwrap(),
+            ArchiveFormat::Tar
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("icons.tar.gz")).unwrap(),
+            ArchiveFormat::TarGz
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("icons.tgz")).unwrap(),
+            ArchiveFormat::TarGz
+        );
+        assert!(ArchiveFormat::from_path(Path::new("icons.rar")).is_err());
+    }
+}
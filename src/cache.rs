@@ -0,0 +1,157 @@
+//! Incremental generation cache
+//!
+//! Every generated set (the main icons and each `--variant` / `--hue-variants`
+//! set) is recorded in `.icon-gen-cache.json` in the output directory, under a
+//! fingerprint of the input files and options it was generated from, together
+//! with a content hash of every file it wrote. A rerun with the same fingerprint
+//! skips the set as long as those files are still in place and unchanged. The
+//! recorded files are also what the tool owns: other files in the directory,
+//! e.g. the rest of an asset catalog or Android `res/` tree, are left alone.
+
+use crate::icon_gen::write_output;
+use crate::output::collect_files;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+/// Name of the cache file kept in the output directory
+pub const CACHE_FILE: &str = ".icon-gen-cache.json";
+
+/// Bumped whenever the layout of the cache file changes
+const CACHE_VERSION: u32 = 1;
+
+/// 64-bit FNV-1a, stable across runs and toolchains unlike `DefaultHasher`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fingerprint(u64);
+
+impl Default for Fingerprint {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fingerprint {
+    /// Mix in a byte string, length first so that consecutive fields can't run together
+    pub(crate) fn add(mut self, bytes: &[u8]) -> Self {
+        for &byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+        self
+    }
+
+    /// Mix in the contents of a file
+    pub(crate) fn add_file(self, path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read {} for the cache", path.display()))?;
+        Ok(self.add(&bytes))
+    }
+
+    pub(crate) fn hex(self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    /// Generated sets, keyed by their output directory name
+    targets: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    fingerprint: String,
+    /// Content hash of each file in the set, keyed by its path relative to the set directory
+    files: BTreeMap<String, String>,
+}
+
+/// The cache of the sets generated into one output directory
+pub(crate) struct GenerationCache {
+    path: PathBuf,
+    file: CacheFile,
+}
+
+impl GenerationCache {
+    /// Load the cache of `output_dir`; a missing, unreadable or outdated cache starts empty
+    pub(crate) fn load(output_dir: &Path) -> Self {
+        let path = output_dir.join(CACHE_FILE);
+        let file = std::fs::read(&path)
+            .ok()
+            .and_then(|json| serde_json::from_slice::<CacheFile>(&json).ok())
+            .filter(|file| file.version == CACHE_VERSION)
+            .unwrap_or_default();
+        Self { path, file }
+    }
+
+    /// Whether `target` was generated into `dir` with this fingerprint and its files are intact
+    pub(crate) fn is_fresh(&self, target: &str, fingerprint: &str, dir: &Path) -> bool {
+        let Some(entry) = self.file.targets.get(target) else {
+            return false;
+        };
+        entry.fingerprint == fingerprint
+            && !entry.files.is_empty()
+            && entry.files.iter().all(|(file, hash)| {
+                std::fs::read(dir.join(file))
+                    .is_ok_and(|bytes| Fingerprint::default().add(&bytes).hex() == *hash)
+            })
+    }
+
+    /// The files recorded as the output of `target` in `dir` by an earlier run
+    pub(crate) fn generated_files(&self, target: &str, dir: &Path) -> HashSet<PathBuf> {
+        self.file
+            .targets
+            .get(target)
+            .map(|entry| entry.files.keys().map(|file| dir.join(file)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Record `paths`, files in `dir`, as the output of `target` with this fingerprint
+    pub(crate) fn record(
+        &mut self,
+        target: &str,
+        fingerprint: &str,
+        dir: &Path,
+        paths: &[PathBuf],
+    ) -> Result<()> {
+        let mut files = BTreeMap::new();
+        for path in paths {
+            let relative = path.strip_prefix(dir).unwrap_or(path);
+            let key = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.insert(key, Fingerprint::default().add_file(path)?.hex());
+        }
+        self.file.targets.insert(
+            target.to_string(),
+            CacheEntry {
+                fingerprint: fingerprint.to_string(),
+                files,
+            },
+        );
+        Ok(())
+    }
+
+    /// Write the cache back to the output directory
+    pub(crate) fn save(mut self) -> Result<()> {
+        self.file.version = CACHE_VERSION;
+        let json = serde_json::to_string_pretty(&self.file)?;
+        write_output(&self.path, json.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Every file under a set directory, apart from the cache file itself
+pub(crate) fn set_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if dir.is_dir() {
+        collect_files(dir, &mut files)?;
+    }
+    files.retain(|path| path != &dir.join(CACHE_FILE));
+    Ok(files)
+}
@@ -0,0 +1,409 @@
+//! Tab-completion scripts for the command line (`icon-gen completions <shell>`)
+//!
+//! The scripts are generated from the clap definition of the binary, so a new
+//! option completes as soon as it exists. Options that take a file or
+//! directory complete paths, options with a fixed set of values complete those
+//! values, and the source image completes as a file name.
+
+use clap::{Arg, ArgAction, Command, ValueHint};
+use icon_gen::error::{IconGenError, Result};
+use std::fmt::Write;
+
+/// Shells [`generate`] writes scripts for
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell"];
+
+/// The completion script of `command` for `shell`, one of [`SHELLS`]
+pub fn generate(shell: &str, command: &mut Command) -> Result<String> {
+    // Adds --help, --version and the help command
+    command.build();
+    let cli = Cli::new(command);
+    match shell {
+        "bash" => Ok(bash(&cli)),
+        "zsh" => Ok(zsh(&cli)),
+        "fish" => Ok(fish(&cli)),
+        "powershell" => Ok(powershell(&cli)),
+        _ => Err(IconGenError::InvalidInput(format!(
+            "Unknown shell: {}. Available shells: {}",
+            shell,
+            SHELLS.join(", ")
+        ))),
+    }
+}
+
+/// What the command line offers for completion
+struct Cli {
+    name: String,
+    options: Vec<Opt>,
+    commands: Vec<Subcommand>,
+}
+
+/// An option with what its value completes to
+struct Opt {
+    short: Option<char>,
+    long: Option<String>,
+    help: String,
+    value: Completion,
+    value_name: String,
+    /// The value may be left out, e.g. `--quantize [COLORS]`
+    value_optional: bool,
+    repeatable: bool,
+}
+
+/// A command such as `completions`, with the values of its first argument
+struct Subcommand {
+    name: String,
+    help: String,
+    values: Vec<String>,
+}
+
+/// What an option value completes to
+enum Completion {
+    /// The option takes no value
+    Flag,
+    Choices(Vec<String>),
+    File,
+    Dir,
+    /// Free text, nothing to complete
+    Text,
+}
+
+impl Cli {
+    fn new(command: &Command) -> Self {
+        let options = command
+            .get_arguments()
+            .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+            .map(Opt::new)
+            .collect();
+        let commands = command
+            .get_subcommands()
+            .filter(|subcommand| !subcommand.is_hide_set())
+            .map(|subcommand| Subcommand {
+                name: subcommand.get_name().to_string(),
+                help: subcommand
+                    .get_about()
+                    .map(|about| about.to_string())
+                    .unwrap_or_default(),
+                values: subcommand
+                    .get_positionals()
+                    .next()
+                    .map(choices)
+                    .unwrap_or_default(),
+            })
+            .collect();
+        Self {
+            name: command.get_name().to_string(),
+            options,
+            commands,
+        }
+    }
+
+    /// Every spelling of the options, e.g. `-o --output`
+    fn option_words(&self) -> Vec<String> {
+        self.options.iter().flat_map(Opt::spellings).collect()
+    }
+
+    fn command_names(&self) -> Vec<&str> {
+        self.commands
+            .iter()
+            .map(|command| command.name.as_str())
+            .collect()
+    }
+}
+
+impl Opt {
+    fn new(arg: &Arg) -> Self {
+        let value = if !arg.get_action().takes_values() {
+            Completion::Flag
+        } else if !choices(arg).is_empty() {
+            Completion::Choices(choices(arg))
+        } else {
+            match arg.get_value_hint() {
+                ValueHint::DirPath => Completion::Dir,
+                ValueHint::AnyPath | ValueHint::FilePath | ValueHint::ExecutablePath => {
+                    Completion::File
+                }
+                _ => Completion::Text,
+            }
+        };
+        Self {
+            short: arg.get_short(),
+            long: arg.get_long().map(str::to_string),
+            help: arg
+                .get_help()
+                .map(|help| help.to_string())
+                .unwrap_or_default(),
+            value,
+            value_name: arg
+                .get_value_names()
+                .and_then(|names| names.first())
+                .map_or_else(
+                    || arg.get_id().as_str().to_uppercase(),
+                    |name| name.to_string(),
+                ),
+            value_optional: arg
+                .get_num_args()
+                .is_some_and(|range| range.min_values() == 0),
+            repeatable: matches!(arg.get_action(), ArgAction::Append | ArgAction::Count),
+        }
+    }
+
+    fn spellings(&self) -> Vec<String> {
+        let short = self.short.map(|short| format!("-{short}"));
+        let long = self.long.as_ref().map(|long| format!("--{long}"));
+        short.into_iter().chain(long).collect()
+    }
+
+    /// Whether the next word is always this option's value
+    fn takes_next_word(&self) -> bool {
+        !matches!(self.value, Completion::Flag) && !self.value_optional
+    }
+}
+
+/// The values `arg` accepts, when it has a fixed set
+fn choices(arg: &Arg) -> Vec<String> {
+    arg.get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+fn bash(cli: &Cli) -> String {
+    let function = format!("_{}", cli.name.replace('-', "_"));
+    let mut script = format!(
+        "{function}() {{\n    local cur prev\n    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n\n"
+    );
+
+    script
+        .push_str("    if [[ ${COMP_CWORD} -gt 1 ]]; then\n        case \"${COMP_WORDS[1]}\" in\n");
+    for command in &cli.commands {
+        let _ = write!(
+            script,
+            "            {})\n                [[ ${{COMP_CWORD}} -eq 2 ]] && COMPREPLY=($(compgen -W \"{}\" -- \"${{cur}}\"))\n                return 0\n                ;;\n",
+            command.name,
+            command.values.join(" ")
+        );
+    }
+    script.push_str("        esac\n    fi\n\n    case \"${prev}\" in\n");
+    for option in cli.options.iter().filter(|option| option.takes_next_word()) {
+        let reply = match &option.value {
+            Completion::Choices(values) => {
+                format!(
+                    "COMPREPLY=($(compgen -W \"{}\" -- \"${{cur}}\"))",
+                    values.join(" ")
+                )
+            }
+            Completion::File => "COMPREPLY=($(compgen -f -- \"${cur}\"))".to_string(),
+            Completion::Dir => "COMPREPLY=($(compgen -d -- \"${cur}\"))".to_string(),
+            Completion::Flag | Completion::Text => "COMPREPLY=()".to_string(),
+        };
+        let _ = write!(
+            script,
+            "        {})\n            {reply}\n            return 0\n            ;;\n",
+            option.spellings().join("|")
+        );
+    }
+    let _ = write!(
+        script,
+        "    esac\n\n    if [[ \"${{cur}}\" == -* ]]; then\n        COMPREPLY=($(compgen -W \"{}\" -- \"${{cur}}\"))\n        return 0\n    fi\n\n    local commands=\"\"\n    [[ ${{COMP_CWORD}} -eq 1 ]] && commands=\"{}\"\n    COMPREPLY=($(compgen -W \"${{commands}}\" -- \"${{cur}}\") $(compgen -f -- \"${{cur}}\"))\n}}\n\ncomplete -F {function} -o filenames {}\n",
+        cli.option_words().join(" "),
+        cli.command_names().join(" "),
+        cli.name
+    );
+    script
+}
+
+fn zsh(cli: &Cli) -> String {
+    let function = format!("_{}", cli.name);
+    let mut script = format!(
+        "#compdef {}\n\n{function}() {{\n    if (( CURRENT > 2 )); then\n        case ${{words[2]}} in\n",
+        cli.name
+    );
+    for command in &cli.commands {
+        let values = match command.values.is_empty() {
+            true => String::new(),
+            false => format!(
+                "(( CURRENT == 3 )) && compadd -- {}; ",
+                command.values.join(" ")
+            ),
+        };
+        let _ = writeln!(script, "            ({}) {values}return ;;", command.name);
+    }
+    script.push_str("        esac\n    fi\n\n    _arguments -s -S \\\n");
+    for option in &cli.options {
+        let repeat = if option.repeatable { "*" } else { "" };
+        let help = zsh_escape(&option.help);
+        let value = match &option.value {
+            Completion::Flag => String::new(),
+            value => {
+                let action = match value {
+                    Completion::Choices(values) => format!("({})", values.join(" ")),
+                    Completion::File => "_files".to_string(),
+                    Completion::Dir => "_files -/".to_string(),
+                    Completion::Flag | Completion::Text => " ".to_string(),
+                };
+                let colon = if option.value_optional { "::" } else { ":" };
+                format!("{colon}{}:{action}", zsh_escape(&option.value_name))
+            }
+        };
+        let takes_value = !matches!(option.value, Completion::Flag);
+        if let Some(short) = option.short {
+            let suffix = match (takes_value, option.value_optional) {
+                (false, _) => "",
+                (true, false) => "+",
+                (true, true) => "-",
+            };
+            let _ = writeln!(
+                script,
+                "        '{repeat}-{short}{suffix}[{help}]{value}' \\"
+            );
+        }
+        if let Some(long) = &option.long {
+            let suffix = match (takes_value, option.value_optional) {
+                (false, _) => "",
+                (true, false) => "=",
+                (true, true) => "=-",
+            };
+            let _ = writeln!(
+                script,
+                "        '{repeat}--{long}{suffix}[{help}]{value}' \\"
+            );
+        }
+    }
+    let _ = write!(
+        script,
+        "        '1: :{function}_input'\n}}\n\n{function}_input() {{\n    local -a commands\n    commands=(\n"
+    );
+    for command in &cli.commands {
+        let _ = writeln!(
+            script,
+            "        '{}:{}'",
+            command.name,
+            zsh_escape(&command.help)
+        );
+    }
+    let _ = write!(
+        script,
+        "    )\n    _describe -t commands command commands\n    _files\n}}\n\nif [ \"$funcstack[1]\" = \"{function}\" ]; then\n    {function} \"$@\"\nelse\n    compdef {function} {}\nfi\n",
+        cli.name
+    );
+    script
+}
+
+/// Escape text for a single-quoted `_arguments` spec
+fn zsh_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+        .replace('$', "\\$")
+        .replace('`', "\\`")
+        .replace('\n', " ")
+}
+
+fn fish(cli: &Cli) -> String {
+    let mut script = String::new();
+    for command in &cli.commands {
+        let _ = writeln!(
+            script,
+            "complete -c {} -n \"__fish_use_subcommand\" -a {} -d '{}'",
+            cli.name,
+            command.name,
+            fish_escape(&command.help)
+        );
+        if !command.values.is_empty() {
+            let _ = writeln!(
+                script,
+                "complete -c {} -n \"__fish_seen_subcommand_from {}\" -f -a \"{}\"",
+                cli.name,
+                command.name,
+                command.values.join(" ")
+            );
+        }
+    }
+    let no_command = format!(
+        "not __fish_seen_subcommand_from {}",
+        cli.command_names().join(" ")
+    );
+    for option in &cli.options {
+        let mut line = format!("complete -c {} -n \"{no_command}\"", cli.name);
+        if let Some(short) = option.short {
+            let _ = write!(line, " -s {short}");
+        }
+        if let Some(long) = &option.long {
+            let _ = write!(line, " -l {long}");
+        }
+        let _ = write!(line, " -d '{}'", fish_escape(&option.help));
+        if !option.value_optional {
+            match &option.value {
+                Completion::Flag => {}
+                Completion::Choices(values) => {
+                    let _ = write!(line, " -x -a \"{}\"", values.join(" "));
+                }
+                Completion::File => line.push_str(" -r -F"),
+                Completion::Dir => line.push_str(" -x -a \"(__fish_complete_directories)\""),
+                Completion::Text => line.push_str(" -x"),
+            }
+        }
+        script.push_str(&line);
+        script.push('\n');
+    }
+    script
+}
+
+/// Escape text for a single-quoted fish string
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\'', "\\'")
+        .replace('\n', " ")
+}
+
+fn powershell(cli: &Cli) -> String {
+    let mut script = format!(
+        "using namespace System.Management.Automation\n\nRegister-ArgumentCompleter -Native -CommandName '{}' -ScriptBlock {{\n    param($wordToComplete, $commandAst, $cursorPosition)\n\n    $elements = @($commandAst.CommandElements | Select-Object -Skip 1 | ForEach-Object {{ $_.ToString() }})\n    $command = if ($elements.Count -gt 0 -and $elements[0] -ne $wordToComplete) {{ $elements[0] }} else {{ '' }}\n    $completions = @(switch ($command) {{\n",
+        powershell_escape(&cli.name)
+    );
+    let value = |text: &str, help: &str| {
+        format!(
+            "[CompletionResult]::new('{0}', '{0}', [CompletionResultType]::ParameterValue, '{1}')",
+            powershell_escape(text),
+            powershell_escape(if help.is_empty() { text } else { help })
+        )
+    };
+    for command in &cli.commands {
+        let _ = writeln!(script, "        '{}' {{", powershell_escape(&command.name));
+        for shell in &command.values {
+            let _ = writeln!(script, "            {}", value(shell, ""));
+        }
+        script.push_str("            break\n        }\n");
+    }
+    script.push_str("        default {\n");
+    for option in &cli.options {
+        for spelling in option.spellings() {
+            let _ = writeln!(
+                script,
+                "            [CompletionResult]::new('{0}', '{0}', [CompletionResultType]::ParameterName, '{1}')",
+                spelling,
+                powershell_escape(&option.help)
+            );
+        }
+    }
+    script.push_str("            if ($command -eq '') {\n");
+    for command in &cli.commands {
+        let _ = writeln!(
+            script,
+            "                {}",
+            value(&command.name, &command.help)
+        );
+    }
+    script.push_str(
+        "            }\n        }\n    })\n\n    $completions.Where{ $_.CompletionText -like \"$wordToComplete*\" } |\n        Sort-Object -Property ListItemText\n}\n",
+    );
+    script
+}
+
+/// Escape text for a single-quoted PowerShell string
+fn powershell_escape(text: &str) -> String {
+    text.replace('\'', "''").replace('\n', " ")
+}
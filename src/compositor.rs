@@ -0,0 +1,751 @@
+//! Layered compositing of generated icons
+//!
+//! Every icon is built from the same stack, bottom to top: an optional background
+//! fill, the resized artwork, then the decoration layers (`--overlay` images, the
+//! dev badge, the `--badge-ribbon` and the `--stamp-text`) in `--layers` order.
+
+use crate::error::IconGenError;
+use crate::icon_gen::{parse_color_checked, Args, Resampler};
+use anyhow::{Context, Result};
+use image::{imageops::FilterType, DynamicImage, ImageBuffer, Rgba, RgbaImage};
+use rusttype::{point, Font, Scale};
+use std::{f32::consts::PI, path::Path, str::FromStr};
+
+/// Where an overlay sits on each icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OverlayPosition {
+    Center,
+    Top,
+    Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Positions accepted in `--overlay <file>[:position[:scale]]`
+pub const OVERLAY_POSITIONS: &[&str] = &[
+    "center",
+    "top",
+    "bottom",
+    "top-left",
+    "top-right",
+    "bottom-left",
+    "bottom-right",
+];
+
+/// Gap between a corner overlay and the icon edges, as a fraction of the icon's shorter side
+const OVERLAY_MARGIN: f32 = 0.05;
+
+/// Size of an overlay's longer side when `--overlay` gives no scale
+const DEFAULT_OVERLAY_SCALE: f32 = 0.25;
+
+impl FromStr for OverlayPosition {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "center" => Ok(Self::Center),
+            "top" => Ok(Self::Top),
+            "bottom" => Ok(Self::Bottom),
+            "top-left" => Ok(Self::TopLeft),
+            "top-right" => Ok(Self::TopRight),
+            "bottom-left" => Ok(Self::BottomLeft),
+            "bottom-right" => Ok(Self::BottomRight),
+            _ => anyhow::bail!(
+                "Unknown overlay position: {}. Available positions: {}",
+                name,
+                OVERLAY_POSITIONS.join(", ")
+            ),
+        }
+    }
+}
+
+impl OverlayPosition {
+    /// Top-left corner for an `item_width` x `item_height` item on a `width` x `height`
+    /// icon, keeping `margin` pixels from the edges it is aligned to
+    fn place(
+        self,
+        width: u32,
+        height: u32,
+        item_width: u32,
+        item_height: u32,
+        margin: u32,
+    ) -> (u32, u32) {
+        let free_x = width.saturating_sub(item_width);
+        let free_y = height.saturating_sub(item_height);
+        let left = margin.min(free_x);
+        let top = margin.min(free_y);
+        let right = free_x.saturating_sub(margin);
+        let bottom = free_y.saturating_sub(margin);
+        match self {
+            Self::Center => (free_x / 2, free_y / 2),
+            Self::Top => (free_x / 2, top),
+            Self::Bottom => (free_x / 2, bottom),
+            Self::TopLeft => (left, top),
+            Self::TopRight => (right, top),
+            Self::BottomLeft => (left, bottom),
+            Self::BottomRight => (right, bottom),
+        }
+    }
+}
+
+/// An image composited onto every generated icon after it is resized
+#[derive(Debug, Clone)]
+struct Overlay {
+    image: DynamicImage,
+    position: OverlayPosition,
+    /// Longer side of the overlay as a fraction of the icon's shorter side
+    scale: f32,
+    angle_degrees: f32,
+}
+
+impl Overlay {
+    fn apply(&self, img: &mut DynamicImage) {
+        let width = img.width();
+        let height = img.height();
+        let min_dim = width.min(height);
+
+        // Resize the overlay while maintaining aspect ratio
+        let size = ((min_dim as f32 * self.scale) as u32).max(1);
+        let resized = resize_with_aspect_ratio(&self.image, size);
+
+        // Rotate the overlay if angle is not 0
+        let overlay = if self.angle_degrees != 0.0 {
+            rotate_image(&resized, self.angle_degrees)
+        } else {
+            resized
+        };
+
+        let margin = (min_dim as f32 * OVERLAY_MARGIN) as u32;
+        let (x, y) = self
+            .position
+            .place(width, height, overlay.width(), overlay.height(), margin);
+
+        image::imageops::overlay(img, &overlay, x.into(), y.into());
+    }
+}
+
+/// Icons smaller than this are left unstamped, since no text stays legible on them
+const STAMP_MIN_SIZE: u32 = 32;
+
+/// Text drawn onto every icon with `--stamp-text`, on a plate that contrasts with its color
+#[derive(Debug, Clone)]
+struct TextStamp {
+    text: String,
+    font: Font<'static>,
+    color: Rgba<u8>,
+    position: OverlayPosition,
+}
+
+impl TextStamp {
+    fn from_args(args: &Args) -> Result<Option<Self>> {
+        let Some(text) = args
+            .stamp_text
+            .as_deref()
+            .filter(|text| !text.trim().is_empty())
+        else {
+            return Ok(None);
+        };
+
+        let font = match &args.stamp_font {
+            Some(path) => {
+                let data = std::fs::read(path)
+                    .with_context(|| format!("Failed to read stamp font: {}", path.display()))?;
+                Font::try_from_vec(data)
+                    .with_context(|| format!("Failed to load stamp font: {}", path.display()))?
+            }
+            None => embedded_font()?,
+        };
+        Ok(Some(Self {
+            text: text.to_string(),
+            font,
+            color: parse_color_checked("stamp color", &args.stamp_color)?,
+            position: args.stamp_position.parse()?,
+        }))
+    }
+
+    fn apply(&self, img: &mut DynamicImage) {
+        let width = img.width();
+        let height = img.height();
+        let min_dim = width.min(height);
+        if min_dim < STAMP_MIN_SIZE {
+            return;
+        }
+
+        let margin = (min_dim as f32 * OVERLAY_MARGIN) as u32;
+        let padding = (min_dim as f32 * 0.03).ceil();
+
+        // A fifth of the icon tall, shrunk to fit between the margins
+        let mut scale = Scale::uniform(min_dim as f32 * 0.2);
+        let max_width = width as f32 - 2.0 * (margin as f32 + padding);
+        let natural_width = text_width(&self.font, &self.text, scale);
+        if natural_width > max_width {
+            scale = Scale::uniform(scale.y * max_width / natural_width);
+        }
+
+        let v_metrics = self.font.v_metrics(scale);
+        let plate_width = (text_width(&self.font, &self.text, scale) + 2.0 * padding).ceil() as u32;
+        let plate_height = (v_metrics.ascent - v_metrics.descent + 2.0 * padding).ceil() as u32;
+        let (x, y) = self
+            .position
+            .place(width, height, plate_width, plate_height, margin);
+
+        let mut plate_color = contrasting_text_color(self.color);
+        plate_color[3] = 176;
+        let mut canvas = img.to_rgba8();
+        image::imageops::overlay(
+            &mut canvas,
+            &RgbaImage::from_pixel(plate_width, plate_height, plate_color),
+            x.into(),
+            y.into(),
+        );
+        draw_text(
+            &mut canvas,
+            &self.font,
+            &self.text,
+            scale,
+            (x as f32 + padding, y as f32 + padding),
+            self.color,
+        );
+        *img = DynamicImage::ImageRgba8(canvas);
+    }
+}
+
+/// Ribbon color when `--badge-ribbon` gives none
+const DEFAULT_RIBBON_COLOR: &str = "#e53935";
+
+/// Distances of the ribbon's inner and outer edges from the corner, along each
+/// icon edge, as fractions of the icon size
+const RIBBON_EDGES: (f32, f32) = (0.25, 0.45);
+
+/// Subsamples per pixel side used to anti-alias the ribbon
+const RIBBON_SUPERSAMPLE: u32 = 4;
+
+/// Ribbon text shorter than this, in pixels, is left out rather than drawn illegibly
+const RIBBON_MIN_TEXT_HEIGHT: f32 = 5.0;
+
+/// A diagonal band across the top-right corner with a label along it (`--badge-ribbon`)
+/// Drawn from geometry at each size, so its edges stay crisp on small icons
+#[derive(Debug, Clone)]
+struct Ribbon {
+    text: String,
+    color: Rgba<u8>,
+    font: Font<'static>,
+}
+
+impl Ribbon {
+    /// Parse `TEXT[:COLOR]`, e.g. `BETA:#e53935`
+    fn parse(spec: &str) -> Result<Self> {
+        let (text, color) = match spec.rsplit_once(':') {
+            Some((text, color)) => (text, color),
+            None => (spec, DEFAULT_RIBBON_COLOR),
+        };
+        if text.trim().is_empty() {
+            anyhow::bail!("Ribbon text must not be empty: {}", spec);
+        }
+        Ok(Self {
+            text: text.to_string(),
+            color: parse_color_checked("ribbon color", color)?,
+            font: embedded_font()?,
+        })
+    }
+
+    fn apply(&self, img: &mut DynamicImage) {
+        let mut canvas = img.to_rgba8();
+        let size = canvas.width().min(canvas.height()) as f32;
+        let right = canvas.width() as f32;
+        let inner = size * RIBBON_EDGES.0;
+        let outer = size * RIBBON_EDGES.1;
+        let samples = RIBBON_SUPERSAMPLE as f32;
+
+        // Lay the label out along the band's center line, rendered at the subsample
+        // resolution; its top faces the corner
+        let band_width = (outer - inner) / 2f32.sqrt();
+        let text_height = band_width * 0.6;
+        let label = (text_height >= RIBBON_MIN_TEXT_HEIGHT).then(|| {
+            let mut scale = Scale::uniform(text_height * samples);
+            let max_width = inner * 2f32.sqrt() * 0.9 * samples;
+            let natural_width = text_width(&self.font, &self.text, scale);
+            if natural_width > max_width {
+                scale = Scale::uniform(scale.y * max_width / natural_width);
+            }
+            let v_metrics = self.font.v_metrics(scale);
+            let mut label = RgbaImage::new(
+                text_width(&self.font, &self.text, scale).ceil() as u32 + 1,
+                (v_metrics.ascent - v_metrics.descent).ceil() as u32 + 1,
+            );
+            draw_text(
+                &mut label,
+                &self.font,
+                &self.text,
+                scale,
+                (0.0, 0.0),
+                Rgba([255, 255, 255, 255]),
+            );
+            label
+        });
+        let text_color = contrasting_text_color(self.color);
+        let center = (inner + outer) / 4.0;
+        let (center_x, center_y) = (right - center, center);
+
+        let start_x = (right - outer).floor().max(0.0) as u32;
+        let end_y = (outer.ceil() as u32).min(canvas.height());
+        for y in 0..end_y {
+            for x in start_x..canvas.width() {
+                let mut coverage = 0.0;
+                let mut text_coverage = 0.0;
+                for sy in 0..RIBBON_SUPERSAMPLE {
+                    for sx in 0..RIBBON_SUPERSAMPLE {
+                        let px = x as f32 + (sx as f32 + 0.5) / samples;
+                        let py = y as f32 + (sy as f32 + 0.5) / samples;
+                        let distance = (right - px) + py;
+                        if distance < inner || distance > outer {
+                            continue;
+                        }
+                        coverage += 1.0;
+
+                        let Some(label) = &label else {
+                            continue;
+                        };
+                        let (dx, dy) = (px - center_x, py - center_y);
+                        let along = (dx + dy) / 2f32.sqrt() * samples + label.width() as f32 / 2.0;
+                        let across =
+                            (dy - dx) / 2f32.sqrt() * samples + label.height() as f32 / 2.0;
+                        if along >= 0.0
+                            && across >= 0.0
+                            && (along as u32) < label.width()
+                            && (across as u32) < label.height()
+                        {
+                            text_coverage +=
+                                label.get_pixel(along as u32, across as u32)[3] as f32 / 255.0;
+                        }
+                    }
+                }
+                if coverage == 0.0 {
+                    continue;
+                }
+
+                let count = samples * samples;
+                let text_mix = text_coverage / coverage;
+                let alpha = coverage / count;
+                let pixel = canvas.get_pixel_mut(x, y);
+                for channel in 0..3 {
+                    let band = self.color[channel] as f32 * (1.0 - text_mix)
+                        + text_color[channel] as f32 * text_mix;
+                    let under = pixel[channel] as f32 * pixel[3] as f32 / 255.0;
+                    let out_alpha = alpha + pixel[3] as f32 / 255.0 * (1.0 - alpha);
+                    pixel[channel] =
+                        ((band * alpha + under * (1.0 - alpha)) / out_alpha).round() as u8;
+                }
+                pixel[3] =
+                    ((alpha + pixel[3] as f32 / 255.0 * (1.0 - alpha)) * 255.0).round() as u8;
+            }
+        }
+
+        *img = DynamicImage::ImageRgba8(canvas);
+    }
+}
+
+/// Decoration layers drawn over the artwork, in the order given by `--layers`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    /// The `--overlay` images, in the order they were given
+    Overlays,
+    /// The `--dev-mode` bug or `--dev-badge-file`
+    Badge,
+    Ribbon,
+    Stamp,
+}
+
+/// Layer names accepted by `--layers`
+pub const LAYER_NAMES: &[&str] = &["overlays", "badge", "ribbon", "stamp"];
+
+impl FromStr for Layer {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "overlays" => Ok(Self::Overlays),
+            "badge" => Ok(Self::Badge),
+            "ribbon" => Ok(Self::Ribbon),
+            "stamp" => Ok(Self::Stamp),
+            _ => anyhow::bail!(
+                "Unknown layer: {}. Available layers: {}",
+                name,
+                LAYER_NAMES.join(", ")
+            ),
+        }
+    }
+}
+
+/// Parse `--layers`, bottom to top; layers left out are not drawn
+fn parse_layers(names: &[String]) -> Result<Vec<Layer>> {
+    let mut layers = Vec::new();
+    for name in names {
+        let layer = name.trim().parse()?;
+        if layers.contains(&layer) {
+            anyhow::bail!("Layer listed twice in --layers: {}", name.trim());
+        }
+        layers.push(layer);
+    }
+    Ok(layers)
+}
+
+/// Everything drawn over each icon once it is resized, stacked in `--layers` order
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Overlays {
+    images: Vec<Overlay>,
+    badge: Option<Overlay>,
+    ribbon: Option<Ribbon>,
+    stamp: Option<TextStamp>,
+    order: Vec<Layer>,
+}
+
+impl Overlays {
+    pub(crate) fn from_args(args: &Args) -> Result<Self> {
+        let images = args
+            .overlay
+            .iter()
+            .map(|spec| parse_overlay(spec))
+            .collect::<Result<Vec<_>>>()?;
+
+        let badge = if args.dev_mode {
+            if !(0.1..=1.0).contains(&args.badge_scale) {
+                anyhow::bail!(
+                    "Invalid badge scale: {}. Scale must be between 0.1 and 1",
+                    args.badge_scale
+                );
+            }
+
+            // The team's own artwork, or one of the embedded bug images
+            let image = match &args.dev_badge_file {
+                Some(path) => load_overlay_image(path)?,
+                None => {
+                    let bug_data = get_embedded_bug_image(&args.dev_bug)?;
+                    image::load_from_memory(bug_data).with_context(|| {
+                        format!("Failed to load embedded bug image: {}", args.dev_bug)
+                    })?
+                }
+            };
+            Some(Overlay {
+                image,
+                position: args.badge_position.parse()?,
+                scale: args.badge_scale,
+                angle_degrees: badge_angle(args),
+            })
+        } else {
+            None
+        };
+
+        Ok(Self {
+            images,
+            badge,
+            ribbon: args
+                .badge_ribbon
+                .as_deref()
+                .map(Ribbon::parse)
+                .transpose()?,
+            stamp: TextStamp::from_args(args)?,
+            order: parse_layers(&args.layers)?,
+        })
+    }
+
+    pub(crate) fn apply(&self, img: &mut DynamicImage) {
+        for layer in &self.order {
+            match layer {
+                Layer::Overlays => {
+                    for overlay in &self.images {
+                        overlay.apply(img);
+                    }
+                }
+                Layer::Badge => {
+                    if let Some(badge) = &self.badge {
+                        badge.apply(img);
+                    }
+                }
+                Layer::Ribbon => {
+                    if let Some(ribbon) = &self.ribbon {
+                        ribbon.apply(img);
+                    }
+                }
+                Layer::Stamp => {
+                    if let Some(stamp) = &self.stamp {
+                        stamp.apply(img);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Builds one output image from the layer stack: an optional background fill, the
+/// artwork resized and centered, then the decoration layers
+/// With a background the result is fully opaque, as iOS and App Clip icons require
+pub(crate) struct Compositor<'a> {
+    resampler: Resampler<'a>,
+    overlays: &'a Overlays,
+    background: Option<Rgba<u8>>,
+    /// Artwork side as a fraction of the canvas, e.g. the adaptive icon safe zone
+    artwork_scale: f32,
+}
+
+impl<'a> Compositor<'a> {
+    pub(crate) fn new(resampler: Resampler<'a>, overlays: &'a Overlays) -> Self {
+        Self {
+            resampler,
+            overlays,
+            background: None,
+            artwork_scale: 1.0,
+        }
+    }
+
+    pub(crate) fn background(mut self, color: Rgba<u8>) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    pub(crate) fn artwork_scale(mut self, scale: f32) -> Self {
+        self.artwork_scale = scale;
+        self
+    }
+
+    /// Render the source onto a `size` x `size` canvas
+    pub(crate) fn render(&self, source: &DynamicImage, size: u32) -> DynamicImage {
+        let artwork_size = (size as f32 * self.artwork_scale) as u32;
+        let artwork = self.resampler.resize(source, artwork_size, artwork_size);
+        let offset = (size - artwork_size) / 2;
+
+        let mut canvas =
+            RgbaImage::from_pixel(size, size, self.background.unwrap_or(Rgba([0, 0, 0, 0])));
+        image::imageops::overlay(&mut canvas, &artwork, offset.into(), offset.into());
+
+        let mut image = DynamicImage::ImageRgba8(canvas);
+        self.overlays.apply(&mut image);
+
+        if self.background.is_some() {
+            // Blending rounds some pixels down to 254 alpha
+            if let DynamicImage::ImageRgba8(canvas) = &mut image {
+                for pixel in canvas.pixels_mut() {
+                    pixel[3] = 255;
+                }
+            }
+        }
+        image
+    }
+}
+
+/// Parse an `--overlay` value given as `<file>[:position[:scale]]`
+fn parse_overlay(spec: &str) -> Result<Overlay> {
+    let (path, position, scale) = split_overlay_spec(spec);
+    if !(scale > 0.0 && scale <= 1.0) {
+        anyhow::bail!("Overlay scale must be between 0 and 1, got {}", scale);
+    }
+
+    Ok(Overlay {
+        image: load_overlay_image(Path::new(path))?,
+        position,
+        scale,
+        angle_degrees: 0.0,
+    })
+}
+
+/// The image file named by an `--overlay` value
+pub(crate) fn overlay_file(spec: &str) -> &Path {
+    Path::new(split_overlay_spec(spec).0)
+}
+
+/// Split an `--overlay` value into its file, position and scale
+/// The suffixes are matched from the right, so paths may contain colons
+fn split_overlay_spec(spec: &str) -> (&str, OverlayPosition, f32) {
+    let mut path = spec;
+    let mut position = OverlayPosition::BottomRight;
+    let mut scale = DEFAULT_OVERLAY_SCALE;
+
+    if let Some((rest, last)) = spec.rsplit_once(':') {
+        if let Ok(parsed) = last.parse::<OverlayPosition>() {
+            path = rest;
+            position = parsed;
+        } else if let Some((file, name)) = rest.rsplit_once(':') {
+            if let (Ok(parsed), Ok(value)) = (name.parse::<OverlayPosition>(), last.parse::<f32>())
+            {
+                path = file;
+                position = parsed;
+                scale = value;
+            }
+        }
+    }
+
+    (path, position, scale)
+}
+
+/// Load user-supplied overlay artwork (`--overlay`, `--dev-badge-file`)
+fn load_overlay_image(path: &Path) -> Result<DynamicImage> {
+    if path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"))
+    {
+        anyhow::bail!(IconGenError::UnsupportedFormat(format!(
+            "SVG overlays are not supported: {} (export the artwork as PNG)",
+            path.display()
+        )));
+    }
+    image::open(path).with_context(|| format!("Failed to load overlay image: {}", path.display()))
+}
+
+/// Rotation of the dev badge: only the moth turns, by an angle derived from
+/// `--badge-seed`, so the same seed always produces byte-identical icons
+fn badge_angle(args: &Args) -> f32 {
+    match args.badge_seed {
+        Some(seed) if args.dev_badge_file.is_none() && args.dev_bug == "moth" => {
+            // SplitMix64, to spread consecutive seeds across the full circle
+            let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            ((z ^ (z >> 31)) % 360) as f32
+        }
+        _ => 0.0,
+    }
+}
+
+/// Get embedded bug image data based on bug type
+fn get_embedded_bug_image(bug_type: &str) -> Result<&'static [u8]> {
+    match bug_type {
+        "moth" => Ok(include_bytes!("bugs/moth.png")),
+        "cockroach" => Ok(include_bytes!("bugs/cockroach.png")),
+        "ladybug" => Ok(include_bytes!("bugs/ladybug.png")),
+        "spider" => Ok(include_bytes!("bugs/spider.png")),
+        "caterpillar" => Ok(include_bytes!("bugs/caterpillar.png")),
+        _ => Err(anyhow::anyhow!(
+            "Unknown bug type: {}. Available types: moth, cockroach, ladybug, spider, caterpillar",
+            bug_type
+        )),
+    }
+}
+
+/// Rotate an image by the given angle in degrees
+fn rotate_image(img: &DynamicImage, angle_degrees: f32) -> DynamicImage {
+    let angle_radians = angle_degrees * PI / 180.0;
+
+    // For simplicity, we'll implement a basic rotation
+    // This could be enhanced with more sophisticated rotation algorithms
+    let width = img.width();
+    let height = img.height();
+
+    // Create a new image with the same dimensions
+    let mut rotated = ImageBuffer::from_fn(width, height, |_, _| Rgba([0, 0, 0, 0]));
+
+    // Center of rotation
+    let center_x = width as f32 / 2.0;
+    let center_y = height as f32 / 2.0;
+
+    let rgba_img = img.to_rgba8();
+
+    // Simple nearest-neighbor rotation
+    for y in 0..height {
+        for x in 0..width {
+            // Translate to center
+            let dx = x as f32 - center_x;
+            let dy = y as f32 - center_y;
+
+            // Rotate
+            let rotated_x = dx * angle_radians.cos() - dy * angle_radians.sin();
+            let rotated_y = dx * angle_radians.sin() + dy * angle_radians.cos();
+
+            // Translate back
+            let source_x = (rotated_x + center_x) as i32;
+            let source_y = (rotated_y + center_y) as i32;
+
+            // Check bounds and copy pixel
+            if source_x >= 0 && source_x < width as i32 && source_y >= 0 && source_y < height as i32
+            {
+                let pixel = rgba_img.get_pixel(source_x as u32, source_y as u32);
+                rotated.put_pixel(x, y, *pixel);
+            }
+        }
+    }
+
+    DynamicImage::ImageRgba8(rotated)
+}
+
+/// Resize an overlay image to fit the given size, maintaining the aspect ratio
+fn resize_with_aspect_ratio(bug_img: &DynamicImage, target_size: u32) -> DynamicImage {
+    let original_width = bug_img.width() as f32;
+    let original_height = bug_img.height() as f32;
+    let target_size_f32 = target_size as f32;
+
+    // Calculate aspect ratio
+    let aspect_ratio = original_width / original_height;
+
+    // Calculate new dimensions to fit within target_size while maintaining aspect ratio
+    let (new_width, new_height) = if aspect_ratio > 1.0 {
+        // Image is wider than tall
+        let width = target_size_f32;
+        let height = target_size_f32 / aspect_ratio;
+        (width as u32, height as u32)
+    } else {
+        // Image is taller than wide (or square)
+        let height = target_size_f32;
+        let width = target_size_f32 * aspect_ratio;
+        (width as u32, height as u32)
+    };
+
+    // Resize the image with the calculated dimensions, keeping at least a pixel on each side
+    bug_img.resize_exact(new_width.max(1), new_height.max(1), FilterType::Lanczos3)
+}
+
+/// Load the bundled font used for text drawn onto generated images
+pub(crate) fn embedded_font() -> Result<Font<'static>> {
+    Font::try_from_bytes(include_bytes!("fonts/DejaVuSans-Bold.ttf"))
+        .context("Failed to load embedded font")
+}
+
+/// Width in pixels of a single line of text
+pub(crate) fn text_width(font: &Font, text: &str, scale: Scale) -> f32 {
+    font.layout(text, scale, point(0.0, 0.0))
+        .last()
+        .map(|glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+        .unwrap_or(0.0)
+}
+
+/// Draw a single line of text with its top-left corner at `origin`, blending the
+/// anti-aliased glyph coverage onto the canvas
+pub(crate) fn draw_text(
+    canvas: &mut RgbaImage,
+    font: &Font,
+    text: &str,
+    scale: Scale,
+    origin: (f32, f32),
+    color: Rgba<u8>,
+) {
+    let ascent = font.v_metrics(scale).ascent;
+    for glyph in font.layout(text, scale, point(origin.0, origin.1 + ascent)) {
+        let Some(bounds) = glyph.pixel_bounding_box() else {
+            continue;
+        };
+        glyph.draw(|gx, gy, coverage| {
+            let x = gx as i32 + bounds.min.x;
+            let y = gy as i32 + bounds.min.y;
+            if x < 0 || y < 0 || x >= canvas.width() as i32 || y >= canvas.height() as i32 {
+                return;
+            }
+
+            let alpha = coverage * color[3] as f32 / 255.0;
+            let pixel = canvas.get_pixel_mut(x as u32, y as u32);
+            for (channel, text_channel) in pixel.0.iter_mut().zip(color.0).take(3) {
+                *channel = (*channel as f32 * (1.0 - alpha) + text_channel as f32 * alpha) as u8;
+            }
+            pixel[3] = pixel[3].max((alpha * 255.0) as u8);
+        });
+    }
+}
+
+/// Pick dark or light text depending on the perceived brightness of the background
+pub(crate) fn contrasting_text_color(bg_color: Rgba<u8>) -> Rgba<u8> {
+    let luma = 0.299 * bg_color[0] as f32 + 0.587 * bg_color[1] as f32 + 0.114 * bg_color[2] as f32;
+    if luma > 140.0 {
+        Rgba([36, 41, 47, 255])
+    } else {
+        Rgba([255, 255, 255, 255])
+    }
+}
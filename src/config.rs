@@ -0,0 +1,519 @@
+//! Project configuration file (`icon-gen.toml`)
+//!
+//! The file sets command line options by their long name, so a team can commit
+//! one source of truth instead of long command lines in scripts:
+//!
+//! ```toml
+//! input = "assets/logo.png"
+//! output = "src-tauri/icons"
+//! windows = true
+//! macos = true
+//! background = "#1e88e5"
+//! background-for = ["windows", "ios"]
+//! ```
+//!
+//! Named profiles in `[profile.<name>]` tables are layered over the shared keys
+//! with `--profile <name>`:
+//!
+//! ```toml
+//! [profile.dev]
+//! dev = true
+//! output = "icons-dev"
+//! ```
+//!
+//! Options given on the command line win over the profile, which wins over the
+//! shared keys. Only the part of TOML that options need is understood: tables,
+//! strings, integers, floats, booleans and arrays of those.
+
+use icon_gen::error::{IconGenError, Result};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// The file name looked for in the working directory
+pub const CONFIG_FILE_NAME: &str = "icon-gen.toml";
+
+/// A value in the configuration file
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl fmt::Display for Value {
+    /// The value as it would be written on the command line
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(value) => f.write_str(value),
+            Value::Integer(value) => write!(f, "{value}"),
+            Value::Float(value) => write!(f, "{value}"),
+            Value::Boolean(value) => write!(f, "{value}"),
+            Value::Array(values) => {
+                let values = values.iter().map(Value::to_string).collect::<Vec<_>>();
+                f.write_str(&values.join(","))
+            }
+        }
+    }
+}
+
+/// Key/value pairs in the order they appear in the file
+pub type Entries = Vec<(String, Value)>;
+
+/// A parsed configuration file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    /// The keys before the first table header
+    pub root: Entries,
+    /// Every `[table]`, by its dotted name, e.g. `profile.dev`
+    pub tables: Vec<(String, Entries)>,
+}
+
+impl Config {
+    /// `icon-gen.toml` in `dir`, if there is one
+    pub fn discover(dir: &Path) -> Option<PathBuf> {
+        Some(dir.join(CONFIG_FILE_NAME)).filter(|path| path.is_file())
+    }
+
+    /// Read and parse a configuration file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|source| IconGenError::IoError {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&text).map_err(|error| match error {
+            IconGenError::InvalidInput(message) => IconGenError::InvalidInput(format!(
+                "Invalid config file {}: {message}",
+                path.display()
+            )),
+            error => error,
+        })
+    }
+
+    /// Parse a configuration file's contents
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser { text, pos: 0 };
+        let mut config = Config::default();
+        let mut table: Option<usize> = None;
+
+        loop {
+            parser.skip_blank_lines();
+            if parser.at_end() {
+                break;
+            }
+
+            if parser.eat('[') {
+                let name = parser.table_name()?;
+                if config.tables.iter().any(|(existing, _)| *existing == name) {
+                    return Err(parser.error(&format!("Table [{name}] is defined twice")));
+                }
+                config.tables.push((name, Entries::new()));
+                table = Some(config.tables.len() - 1);
+            } else {
+                let key = parser.key()?;
+                parser.skip_spaces();
+                if !parser.eat('=') {
+                    return Err(parser.error(&format!("Expected = after {key}")));
+                }
+                parser.skip_spaces();
+                let value = parser.value()?;
+
+                let entries = match table {
+                    Some(index) => &mut config.tables[index].1,
+                    None => &mut config.root,
+                };
+                if entries.iter().any(|(existing, _)| *existing == key) {
+                    return Err(parser.error(&format!("{key} is set twice")));
+                }
+                entries.push((key, value));
+            }
+            parser.end_of_line()?;
+        }
+        Ok(config)
+    }
+
+    /// The names of the `[profile.<name>]` tables
+    pub fn profiles(&self) -> Vec<&str> {
+        self.tables
+            .iter()
+            .filter_map(|(table, _)| table.strip_prefix("profile."))
+            .collect()
+    }
+
+    /// The options in effect with the given profile: the profile's own, then the
+    /// shared ones it doesn't set
+    pub fn options(&self, profile: Option<&str>) -> Result<Entries> {
+        if let Some((table, _)) = self
+            .tables
+            .iter()
+            .find(|(table, _)| table.split('.').count() != 2 || !table.starts_with("profile."))
+        {
+            return Err(IconGenError::InvalidInput(format!(
+                "Unknown table [{}]; profiles are named [profile.<name>]",
+                table
+            )));
+        }
+
+        let mut options = match profile {
+            Some(name) => self
+                .table(&format!("profile.{name}"))
+                .ok_or_else(|| {
+                    let profiles = self.profiles();
+                    IconGenError::InvalidInput(if profiles.is_empty() {
+                        format!("Unknown profile: {}. The config file has no profiles", name)
+                    } else {
+                        format!(
+                            "Unknown profile: {}. Available profiles: {}",
+                            name,
+                            profiles.join(", ")
+                        )
+                    })
+                })?
+                .clone(),
+            None => Entries::new(),
+        };
+        let normalize = |key: &str| key.replace('_', "-");
+        for (key, value) in &self.root {
+            if !options
+                .iter()
+                .any(|(set, _)| normalize(set) == normalize(key))
+            {
+                options.push((key.clone(), value.clone()));
+            }
+        }
+        Ok(options)
+    }
+
+    /// The entries of the table with the given dotted name
+    pub fn table(&self, name: &str) -> Option<&Entries> {
+        self.tables
+            .iter()
+            .find(|(table, _)| table == name)
+            .map(|(_, entries)| entries)
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos == self.text.len()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(expected);
+        if found {
+            self.pos += expected.len_utf8();
+        }
+        found
+    }
+
+    fn error(&self, message: &str) -> IconGenError {
+        let line = self.text[..self.pos].matches('\n').count() + 1;
+        IconGenError::InvalidInput(format!("{message} (line {line})"))
+    }
+
+    /// Skip spaces, tabs and a trailing comment, stopping at the line end
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+        if self.peek() == Some('#') {
+            self.pos += self.rest().find('\n').unwrap_or(self.rest().len());
+        }
+    }
+
+    /// Skip whitespace, comments and line ends
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            if !(self.eat('\n') || self.eat('\r')) {
+                break;
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<()> {
+        self.skip_spaces();
+        self.eat('\r');
+        if self.eat('\n') || self.at_end() {
+            Ok(())
+        } else {
+            Err(self.error("Expected the end of the line"))
+        }
+    }
+
+    fn bare_key(&mut self) -> &str {
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(self.rest().len());
+        let key = &self.text[self.pos..self.pos + len];
+        self.pos += len;
+        key
+    }
+
+    fn key(&mut self) -> Result<String> {
+        let key = match self.peek() {
+            Some('"') | Some('\'') => self.string()?,
+            _ => self.bare_key().to_string(),
+        };
+        if key.is_empty() {
+            return Err(self.error("Expected a key"));
+        }
+        if self.peek() == Some('.') {
+            return Err(self.error("Dotted keys are not supported; use a [table] instead"));
+        }
+        Ok(key)
+    }
+
+    /// The dotted name of a table header, after its `[`
+    fn table_name(&mut self) -> Result<String> {
+        if self.peek() == Some('[') {
+            return Err(self.error("Arrays of tables are not supported"));
+        }
+        let mut parts = Vec::new();
+        loop {
+            self.skip_spaces();
+            let part = self.bare_key().to_string();
+            if part.is_empty() {
+                return Err(self.error("Expected a table name"));
+            }
+            parts.push(part);
+            self.skip_spaces();
+            if self.eat(']') {
+                return Ok(parts.join("."));
+            }
+            if !self.eat('.') {
+                return Err(self.error("Expected ] after the table name"));
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        match self.peek() {
+            Some('"') | Some('\'') => Ok(Value::String(self.string()?)),
+            Some('[') => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                loop {
+                    self.skip_blank_lines();
+                    if self.eat(']') {
+                        return Ok(Value::Array(values));
+                    }
+                    values.push(self.value()?);
+                    self.skip_blank_lines();
+                    if self.eat(']') {
+                        return Ok(Value::Array(values));
+                    }
+                    if !self.eat(',') {
+                        return Err(self.error("Expected , or ] in array"));
+                    }
+                }
+            }
+            Some('{') => Err(self.error("Inline tables are not supported")),
+            _ => {
+                let len = self
+                    .rest()
+                    .find(|c: char| c.is_whitespace() || matches!(c, ',' | ']' | '#'))
+                    .unwrap_or(self.rest().len());
+                let word = &self.text[self.pos..self.pos + len];
+                let value = match word {
+                    "true" => Value::Boolean(true),
+                    "false" => Value::Boolean(false),
+                    _ => {
+                        let number = word.replace('_', "");
+                        if let Ok(integer) = number.parse::<i64>() {
+                            Value::Integer(integer)
+                        } else if let Ok(float) = number.parse::<f64>() {
+                            Value::Float(float)
+                        } else {
+                            return Err(
+                                self.error(&format!("Invalid value: {word}. Strings need quotes"))
+                            );
+                        }
+                    }
+                };
+                self.pos += len;
+                Ok(value)
+            }
+        }
+    }
+
+    /// A basic ("...") or literal ('...') string on a single line
+    fn string(&mut self) -> Result<String> {
+        let quote = self.peek().unwrap();
+        if self.rest().starts_with(&quote.to_string().repeat(3)) {
+            return Err(self.error("Multi-line strings are not supported"));
+        }
+        self.pos += 1;
+
+        let mut value = String::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error("Unterminated string"));
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '\n' => return Err(self.error("Unterminated string")),
+                c if c == quote => return Ok(value),
+                '\\' if quote == '"' => {
+                    let escape = self
+                        .peek()
+                        .ok_or_else(|| self.error("Unterminated string"))?;
+                    self.pos += escape.len_utf8();
+                    match escape {
+                        '"' => value.push('"'),
+                        '\\' => value.push('\\'),
+                        'n' => value.push('\n'),
+                        't' => value.push('\t'),
+                        'r' => value.push('\r'),
+                        'u' | 'U' => {
+                            let len = if escape == 'u' { 4 } else { 8 };
+                            let code = self
+                                .rest()
+                                .get(..len)
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("Invalid unicode escape"))?;
+                            self.pos += len;
+                            value.push(code);
+                        }
+                        escape => return Err(self.error(&format!("Invalid escape \\{escape}"))),
+                    }
+                }
+                c => value.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_values() {
+        let config = Config::parse(
+            r#"
+# Shared settings
+input = "assets/logo.png"   # trailing comment
+'padding-color' = 'C:\tmp'
+padding = 8.5
+badge-seed = 1_000
+windows = true
+macos = false
+background-for = [
+    "windows",
+    "ios", # comment
+]
+stamp-text = "v1 \"beta\" \u00e9"
+
+[profile.dev]
+dev = true
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.root,
+            [
+                ("input".into(), Value::String("assets/logo.png".into())),
+                ("padding-color".into(), Value::String("C:\\tmp".into())),
+                ("padding".into(), Value::Float(8.5)),
+                ("badge-seed".into(), Value::Integer(1000)),
+                ("windows".into(), Value::Boolean(true)),
+                ("macos".into(), Value::Boolean(false)),
+                (
+                    "background-for".into(),
+                    Value::Array(vec![
+                        Value::String("windows".into()),
+                        Value::String("ios".into())
+                    ])
+                ),
+                ("stamp-text".into(), Value::String("v1 \"beta\" é".into())),
+            ]
+        );
+        assert_eq!(
+            config.table("profile.dev"),
+            Some(&vec![("dev".to_string(), Value::Boolean(true))])
+        );
+        assert_eq!(
+            Value::Array(vec![Value::Integer(16), Value::Integer(32)]).to_string(),
+            "16,32"
+        );
+    }
+
+    #[test]
+    fn test_profile_options() {
+        let config = Config::parse(
+            r#"
+input = "logo.png"
+dev_bug = "beetle"
+
+[profile.dev]
+dev = true
+dev-bug = "spider"
+
+[profile.release]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.profiles(), ["dev", "release"]);
+        assert_eq!(
+            config.options(Some("dev")).unwrap(),
+            [
+                ("dev".to_string(), Value::Boolean(true)),
+                ("dev-bug".to_string(), Value::String("spider".into())),
+                ("input".to_string(), Value::String("logo.png".into())),
+            ]
+        );
+        assert_eq!(config.options(Some("release")).unwrap(), config.root);
+        assert_eq!(config.options(None).unwrap(), config.root);
+
+        let error = config.options(Some("qa")).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "Unknown profile: qa. Available profiles: dev, release"
+        );
+        let config = Config::parse(
+            "[build]
+windows = true",
+        )
+        .unwrap();
+        assert!(config
+            .options(None)
+            .unwrap_err()
+            .to_string()
+            .contains("Unknown table [build]"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |text: &str| format!("{:#}", Config::parse(text).unwrap_err());
+
+        assert!(error("output = icons").contains("Strings need quotes (line 1)"));
+        assert!(error("a = 1\na = 2").contains("a is set twice (line 2)"));
+        assert!(error("[p]\n[p]").contains("defined twice"));
+        assert!(error("output = \"icons").contains("Unterminated string"));
+        assert!(error("padding 8").contains("Expected = after padding"));
+        assert!(error("a.b = 1").contains("Dotted keys"));
+        assert!(error("a = 1 2").contains("Expected the end of the line"));
+        assert!(error("a = [1, 2").contains("Expected , or ]"));
+    }
+}
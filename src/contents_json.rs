@@ -3,18 +3,29 @@
 //! This module defines the data structures that mirror Apple's official asset catalog
 //! Contents.json schema as documented in the Asset Catalog Format Reference.
 //! These structures are reusable for both iOS and macOS asset catalogs.
+//!
+//! Fields the model doesn't know, such as the `appearances` of dark and tinted
+//! icons, are kept in `extra` when a file is read, so that
+//! [`ContentsFile::merge`] can update an existing catalog without losing them.
 
-use anyhow::{Context, Result};
-use serde::Serialize;
+use crate::error::{IconGenError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::path::Path;
 
+/// Modes of `--contents-json`: replace existing Contents.json files, or merge
+/// the generated entries into them
+pub const CONTENTS_JSON_MODES: &[&str] = &["replace", "merge"];
+
 /// Root structure of a Contents.json file
 ///
 /// Represents the complete asset catalog metadata structure that includes
 /// images, versioning information, and optional properties.
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ContentsFile {
     /// Array of image entries for different scales, sizes, and device types
+    /// (left out of folders that hold no images, such as the catalog root)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub images: Vec<ImageEntry>,
 
     /// Versioning and authorship information
@@ -23,13 +34,17 @@ pub struct ContentsFile {
     /// Optional properties for the asset catalog
     #[serde(skip_serializing_if = "Option::is_none")]
     pub properties: Option<Properties>,
+
+    /// Fields not covered by the model, kept as read
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Individual image entry within an asset catalog
 ///
 /// Contains metadata and attributes for a specific image file, including
 /// device targeting, scaling, sizing, and optional characteristics.
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ImageEntry {
     /// The filename for the image file (.png, .jpg, .pdf, .heif, .heic, .avci)
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -59,6 +74,25 @@ pub struct ImageEntry {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub subtype: Option<String>,
 
+    /// The orientation of a launch image ("portrait" or "landscape")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<String>,
+
+    /// The extent of a launch image (e.g., "full-screen")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extent: Option<String>,
+
+    /// The minimum OS version a launch image applies to (e.g., "8.0")
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        rename = "minimum-system-version"
+    )]
+    pub minimum_system_version: Option<String>,
+
+    /// The platform for universal-idiom entries (e.g., "ios" for Messages extension icons)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+
     /// Target folder for organized icon generation
     #[serde(skip_serializing_if = "Option::is_none")]
     pub folder: Option<String>,
@@ -108,13 +142,17 @@ pub struct ImageEntry {
     /// Height class for size classes (e.g., "compact", "regular")
     #[serde(skip_serializing_if = "Option::is_none", rename = "height-class")]
     pub height_class: Option<String>,
+
+    /// Fields not covered by the model, e.g. `appearances`, kept as read
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Versioning and authorship information for the asset catalog
 ///
 /// Contains metadata about the tool that created the asset catalog
 /// and the format version being used.
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Info {
     /// The format version of the asset catalog (typically 1)
     pub version: u8,
@@ -122,13 +160,17 @@ pub struct Info {
     /// The application or tool that authored the asset catalog
     /// Apple recommends using your bundle ID (e.g., "com.company.app")
     pub author: String,
+
+    /// Fields not covered by the model, kept as read
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Optional properties for the asset catalog
 ///
 /// Contains additional metadata such as on-demand resource tags
 /// and vector preservation settings.
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Properties {
     /// On-demand resource tags for the asset catalog
     #[serde(
@@ -143,6 +185,10 @@ pub struct Properties {
         rename = "preserves-vector-representation"
     )]
     pub preserves_vector_representation: Option<bool>,
+
+    /// Fields not covered by the model, e.g. `pre-rendered`, kept as read
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 impl ContentsFile {
@@ -156,8 +202,13 @@ impl ContentsFile {
     pub fn new(author: String) -> Self {
         Self {
             images: Vec::new(),
-            info: Info { version: 1, author },
+            info: Info {
+                version: 1,
+                author,
+                extra: Map::new(),
+            },
             properties: None,
+            extra: Map::new(),
         }
     }
 
@@ -177,6 +228,28 @@ impl ContentsFile {
     pub fn set_properties(&mut self, properties: Properties) {
         self.properties = Some(properties);
     }
+
+    /// Merges generated entries into this file, read from an existing catalog
+    ///
+    /// Each generated entry updates the entry of the same slot (idiom, size,
+    /// scale, appearance, ...), keeping the fields the generated entry leaves
+    /// out; entries for new slots are appended. Entries for slots that weren't
+    /// generated, the info, the properties and unknown fields stay as they are.
+    ///
+    /// # Arguments
+    /// * `generated` - The ContentsFile the tool would have written
+    pub fn merge(&mut self, generated: ContentsFile) {
+        for image in generated.images {
+            match self
+                .images
+                .iter_mut()
+                .find(|existing| existing.slot() == image.slot())
+            {
+                Some(existing) => existing.update(image),
+                None => self.images.push(image),
+            }
+        }
+    }
 }
 
 impl ImageEntry {
@@ -198,6 +271,10 @@ impl ImageEntry {
             expected_size: None,
             role: None,
             subtype: None,
+            orientation: None,
+            extent: None,
+            minimum_system_version: None,
+            platform: None,
             folder: None,
             graphics_feature_set: None,
             memory: None,
@@ -209,6 +286,7 @@ impl ImageEntry {
             template_rendering_intent: None,
             width_class: None,
             height_class: None,
+            extra: Map::new(),
         }
     }
 
@@ -238,6 +316,10 @@ impl ImageEntry {
             expected_size: Some(size),
             role,
             subtype: None,
+            orientation: None,
+            extent: None,
+            minimum_system_version: None,
+            platform: None,
             folder: None,
             graphics_feature_set: None,
             memory: None,
@@ -249,6 +331,7 @@ impl ImageEntry {
             template_rendering_intent: None,
             width_class: None,
             height_class: None,
+            extra: Map::new(),
         }
     }
 
@@ -264,17 +347,72 @@ impl ImageEntry {
         self
     }
 
-    /// Sets the folder for organized output
+    /// Creates a new full-screen launch image entry
+    ///
+    /// # Arguments
+    /// * `filename` - The filename for the launch image
+    /// * `idiom` - The device idiom ("iphone" or "ipad")
+    /// * `orientation` - "portrait" or "landscape"
+    /// * `scale` - The scale factor
+    /// * `minimum_system_version` - The first OS version the image applies to
+    ///
+    /// # Returns
+    /// A new ImageEntry configured for a LaunchImage asset catalog
+    pub fn new_launch_image(
+        filename: String,
+        idiom: String,
+        orientation: String,
+        scale: String,
+        minimum_system_version: String,
+    ) -> Self {
+        let mut entry = Self::new(filename, idiom, scale);
+        entry.orientation = Some(orientation);
+        entry.extent = Some("full-screen".to_string());
+        entry.minimum_system_version = Some(minimum_system_version);
+        entry
+    }
+
+    /// Sets the platform for universal-idiom entries
     ///
     /// # Arguments
-    /// * `folder` - The target folder path
+    /// * `platform` - The platform name (e.g., "ios")
     ///
     /// # Returns
     /// Self for method chaining
-    pub fn with_folder(mut self, folder: String) -> Self {
-        self.folder = Some(folder);
+    pub fn with_platform(mut self, platform: String) -> Self {
+        self.platform = Some(platform);
         self
     }
+
+    /// The fields that tell the slots of a catalog apart; an entry with
+    /// `appearances` (a dark or tinted icon) is a slot of its own
+    fn slot(&self) -> [Option<String>; 9] {
+        [
+            self.idiom.clone(),
+            self.size.clone(),
+            self.scale.clone(),
+            self.subtype.clone(),
+            self.platform.clone(),
+            self.orientation.clone(),
+            self.extent.clone(),
+            self.minimum_system_version.clone(),
+            self.extra.get("appearances").map(Value::to_string),
+        ]
+    }
+
+    /// Overwrites the fields `generated` sets, keeping the others
+    fn update(&mut self, generated: ImageEntry) {
+        let (Ok(Value::Object(mut fields)), Ok(Value::Object(generated))) = (
+            serde_json::to_value(&*self),
+            serde_json::to_value(generated),
+        ) else {
+            return;
+        };
+        fields.extend(generated);
+        if let Ok(updated) = serde_json::from_value(Value::Object(fields)) {
+            *self = updated;
+        }
+    }
 }
 
 impl Default for Info {
@@ -283,6 +421,7 @@ impl Default for Info {
         Self {
             version: 1,
             author: "icon-generator".to_string(),
+            extra: Map::new(),
         }
     }
 }
@@ -311,11 +450,14 @@ pub fn write_contents_json(dir: &Path, images: Vec<ImageEntry>) -> Result<()> {
         info: Info {
             version: 1,
             author: "icon-gen".to_string(),
+            extra: Map::new(),
         },
         properties: None,
+        extra: Map::new(),
     };
-    let json = serde_json::to_string_pretty(&cf)?;
-    std::fs::write(dir.join("Contents.json"), json).context("write Contents.json")
+    let json = serde_json::to_string_pretty(&cf)
+        .map_err(|error| IconGenError::EncodeError(error.to_string()))?;
+    crate::icon_gen::write_output(&dir.join("Contents.json"), json.as_bytes())
 }
 
 #[cfg(test)]
@@ -479,6 +621,102 @@ mod tests {
         println!("Single entry Contents.json serialization test passed");
     }
 
+    #[test]
+    fn test_platform_serialization() {
+        let entry = ImageEntry::new_app_icon(
+            "iMessage-27x20@2x.png".to_string(),
+            "universal".to_string(),
+            "27x20".to_string(),
+            "2x".to_string(),
+            None,
+        )
+        .with_platform("ios".to_string());
+
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["platform"], "ios");
+
+        // Entries without a platform omit the key entirely
+        let plain = ImageEntry::new(
+            "test.png".to_string(),
+            "iphone".to_string(),
+            "2x".to_string(),
+        );
+        assert!(serde_json::to_value(&plain)
+            .unwrap()
+            .get("platform")
+            .is_none());
+    }
+
+    #[test]
+    fn test_launch_image_serialization() {
+        let entry = ImageEntry::new_launch_image(
+            "LaunchImage-1125x2436-portrait.png".to_string(),
+            "iphone".to_string(),
+            "portrait".to_string(),
+            "3x".to_string(),
+            "11.0".to_string(),
+        )
+        .with_subtype("2436h".to_string());
+
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["orientation"], "portrait");
+        assert_eq!(json["extent"], "full-screen");
+        assert_eq!(json["minimum-system-version"], "11.0");
+        assert_eq!(json["subtype"], "2436h");
+        assert!(json.get("size").is_none());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut existing: ContentsFile = serde_json::from_str(
+            r#"{
+              "images": [
+                {"filename": "old.png", "idiom": "universal", "platform": "ios", "size": "1024x1024", "locked": true},
+                {"appearances": [{"appearance": "luminosity", "value": "dark"}],
+                 "filename": "dark.png", "idiom": "universal", "platform": "ios", "size": "1024x1024"},
+                {"filename": "watch.png", "idiom": "watch", "size": "44x44", "scale": "2x"}
+              ],
+              "info": {"version": 1, "author": "xcode"},
+              "properties": {"pre-rendered": true}
+            }"#,
+        )
+        .unwrap();
+
+        let mut generated = ContentsFile::new("icon-generator".to_string());
+        let mut marketing = ImageEntry::new_app_icon(
+            "AppIcon-1024.png".to_string(),
+            "universal".to_string(),
+            "1024x1024".to_string(),
+            String::new(),
+            None,
+        );
+        marketing.scale = None;
+        marketing.platform = Some("ios".to_string());
+        generated.add_image(marketing);
+        generated.add_image(ImageEntry::new_app_icon(
+            "AppIcon-60x60@2x.png".to_string(),
+            "iphone".to_string(),
+            "60x60".to_string(),
+            "2x".to_string(),
+            None,
+        ));
+        existing.merge(generated);
+
+        let json = serde_json::to_value(&existing).unwrap();
+        let images = json["images"].as_array().unwrap();
+        assert_eq!(images.len(), 4);
+        // The matching slot is updated and keeps the fields it had
+        assert_eq!(images[0]["filename"], "AppIcon-1024.png");
+        assert_eq!(images[0]["locked"], true);
+        // The dark appearance is a slot of its own, and other slots are kept
+        assert_eq!(images[1]["filename"], "dark.png");
+        assert_eq!(images[1]["appearances"][0]["value"], "dark");
+        assert_eq!(images[2]["filename"], "watch.png");
+        assert_eq!(images[3]["filename"], "AppIcon-60x60@2x.png");
+        assert_eq!(json["info"]["author"], "xcode");
+        assert_eq!(json["properties"]["pre-rendered"], true);
+    }
+
     #[test]
     fn test_write_contents_json() {
         use std::env;
@@ -0,0 +1,148 @@
+//! Edge contrast of the artwork against the backgrounds it is shown on (`--contrast-check`)
+//!
+//! Icons that keep their transparency are drawn straight onto docks, launchers
+//! and wallpapers, and flattened icons onto the `--background` fill. A white
+//! logo on a transparent canvas disappears on a white fill, a black one on a
+//! dark dock. [`Edge`] collects the colors along the outline of the artwork and
+//! rates them against a backdrop with the WCAG contrast ratio.
+
+use image::{DynamicImage, Rgba};
+
+/// Side of the copy of the image whose outline is measured
+const SAMPLE_SIZE: u32 = 256;
+
+/// Contrast ratio below which the outline is hard to make out
+pub(crate) const MIN_CONTRAST: f32 = 1.5;
+
+/// Backgrounds that transparent icons are commonly shown on: light and dark
+/// mode docks and launchers, and the typical wallpaper tones in between
+pub(crate) const BACKDROPS: &[(&str, Rgba<u8>)] = &[
+    ("white", Rgba([255, 255, 255, 255])),
+    ("light gray", Rgba([229, 229, 229, 255])),
+    ("dark gray", Rgba([43, 43, 43, 255])),
+    ("black", Rgba([0, 0, 0, 255])),
+];
+
+/// The outline of the artwork: the opaque pixels next to transparent ones
+pub(crate) struct Edge {
+    /// Relative luminance of each outline pixel
+    luminances: Vec<f32>,
+}
+
+impl Edge {
+    /// The outline of the artwork in `image`
+    /// None when the image has no transparent areas to outline
+    pub(crate) fn of(image: &DynamicImage) -> Option<Edge> {
+        // Resized with premultiplied alpha, so that the color of clear pixels
+        // doesn't bleed into the outline
+        let mut premultiplied = image.to_rgba8();
+        for pixel in premultiplied.pixels_mut() {
+            for channel in 0..3 {
+                pixel[channel] = (pixel[channel] as u16 * pixel[3] as u16 / 255) as u8;
+            }
+        }
+        let mut sample = image::imageops::resize(
+            &premultiplied,
+            SAMPLE_SIZE,
+            SAMPLE_SIZE,
+            image::imageops::FilterType::Triangle,
+        );
+        for pixel in sample.pixels_mut() {
+            if pixel[3] > 0 {
+                for channel in 0..3 {
+                    pixel[channel] = (pixel[channel] as u16 * 255 / pixel[3] as u16).min(255) as u8;
+                }
+            }
+        }
+        let solid = |x: u32, y: u32| sample.get_pixel(x, y)[3] >= 128;
+
+        let mut luminances = Vec::new();
+        for (x, y, pixel) in sample.enumerate_pixels() {
+            if !solid(x, y) {
+                continue;
+            }
+            let borders_clear = (x > 0 && !solid(x - 1, y))
+                || (y > 0 && !solid(x, y - 1))
+                || (x + 1 < SAMPLE_SIZE && !solid(x + 1, y))
+                || (y + 1 < SAMPLE_SIZE && !solid(x, y + 1));
+            if borders_clear {
+                luminances.push(luminance(pixel));
+            }
+        }
+        (!luminances.is_empty()).then_some(Edge { luminances })
+    }
+
+    /// The median contrast ratio between the outline and `backdrop`
+    pub(crate) fn contrast(&self, backdrop: Rgba<u8>) -> f32 {
+        let backdrop = luminance(&backdrop);
+        let mut ratios = self
+            .luminances
+            .iter()
+            .map(|&luminance| contrast_ratio(luminance, backdrop))
+            .collect::<Vec<_>>();
+        ratios.sort_by(f32::total_cmp);
+        ratios[ratios.len() / 2]
+    }
+}
+
+/// WCAG relative luminance of an sRGB color
+fn luminance(pixel: &Rgba<u8>) -> f32 {
+    let linear = |channel: u8| {
+        let value = channel as f32 / 255.0;
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(pixel[0]) + 0.7152 * linear(pixel[1]) + 0.0722 * linear(pixel[2])
+}
+
+/// WCAG contrast ratio of two relative luminances, from 1 to 21
+fn contrast_ratio(a: f32, b: f32) -> f32 {
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    /// A centered square of `color` on a transparent canvas
+    fn logo(color: Rgba<u8>) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 64, |x, y| {
+            if (16..48).contains(&x) && (16..48).contains(&y) {
+                color
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        }))
+    }
+
+    #[test]
+    fn test_contrast_ratio() {
+        let white = luminance(&Rgba([255, 255, 255, 255]));
+        let black = luminance(&Rgba([0, 0, 0, 255]));
+        assert!((contrast_ratio(white, black) - 21.0).abs() < 0.01);
+        assert_eq!(contrast_ratio(white, white), 1.0);
+    }
+
+    #[test]
+    fn test_edge_contrast() {
+        let white = Rgba([255, 255, 255, 255]);
+        let black = Rgba([0, 0, 0, 255]);
+
+        let edge = Edge::of(&logo(white)).unwrap();
+        assert!(edge.contrast(white) < MIN_CONTRAST);
+        assert!(edge.contrast(black) > 20.0);
+
+        let edge = Edge::of(&logo(Rgba([30, 90, 200, 255]))).unwrap();
+        assert!(BACKDROPS
+            .iter()
+            .all(|&(_, backdrop)| edge.contrast(backdrop) >= MIN_CONTRAST));
+
+        // An opaque image has no outline against the backdrop
+        let opaque = DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 64, white));
+        assert!(Edge::of(&opaque).is_none());
+    }
+}
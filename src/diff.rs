@@ -0,0 +1,321 @@
+//! Perceptual comparison of two output trees (`icon-gen diff`)
+//!
+//! Regenerated icons rarely come out byte-identical: a resampling tweak or a
+//! new encoder setting touches every file, which makes a PR diff useless for
+//! telling what actually looks different. [`diff`] pairs the files of two
+//! directories by path and rates each image pair with two perceptual metrics:
+//!
+//! - SSIM (structural similarity) of the luma, from 1 for identical pictures
+//!   down to 0 for unrelated ones; the lower of the comparisons flattened onto
+//!   white and onto black, so that changes in transparency count as well
+//! - the mean CIE76 color difference (ΔE) of the pixels, where about 2.3 is
+//!   just noticeable
+//!
+//! Files whose pixels are unchanged are left out, even when their bytes differ.
+
+use crate::cache::set_files;
+use crate::error::{IconGenError, Result};
+use image::{DynamicImage, Rgba, RgbaImage};
+use rayon::prelude::*;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+/// Side of the square windows SSIM is computed over
+const SSIM_WINDOW: u32 = 8;
+
+/// How a file changed between the two directories
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// Only in the second directory
+    Added,
+    /// Only in the first directory
+    Removed,
+    /// An image whose dimensions changed, so its pixels can't be compared
+    Resized {
+        before: (u32, u32),
+        after: (u32, u32),
+    },
+    /// An image of the same dimensions with different pixels
+    Pixels {
+        width: u32,
+        height: u32,
+        /// Structural similarity, 1 for identical pictures
+        ssim: f64,
+        /// Mean CIE76 color difference of the pixels
+        delta_e: f64,
+    },
+    /// A file that isn't a readable image, e.g. `Contents.json` or `icon.icns`,
+    /// with different contents
+    Contents,
+}
+
+/// A file that differs, by its path relative to the compared directories
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    pub change: Change,
+}
+
+/// What [`diff`] found
+#[derive(Debug, Default)]
+pub struct Diff {
+    /// The number of distinct paths in the two directories
+    pub compared: usize,
+    /// The files that differ, in order of their paths
+    pub changes: Vec<FileDiff>,
+}
+
+/// Compare the files of the output directories `before` and `after`
+pub fn diff(before: &Path, after: &Path) -> Result<Diff> {
+    let before_files = relative_files(before)?;
+    let after_files = relative_files(after)?;
+    let paths = before_files
+        .union(&after_files)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let changes = paths
+        .par_iter()
+        .map(|path| {
+            let change = match (before_files.contains(path), after_files.contains(path)) {
+                (true, false) => Some(Change::Removed),
+                (false, true) => Some(Change::Added),
+                _ => compare_files(&before.join(path), &after.join(path))?,
+            };
+            Ok(change.map(|change| FileDiff {
+                path: path.clone(),
+                change,
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Diff {
+        compared: paths.len(),
+        changes: changes.into_iter().flatten().collect(),
+    })
+}
+
+/// The files under `dir` relative to it, leaving out the generation cache
+fn relative_files(dir: &Path) -> Result<BTreeSet<PathBuf>> {
+    if !dir.is_dir() {
+        return Err(IconGenError::io(
+            dir,
+            std::io::Error::new(std::io::ErrorKind::NotFound, "Not a directory"),
+        ));
+    }
+    let files = set_files(dir).map_err(IconGenError::from_anyhow)?;
+    Ok(files
+        .iter()
+        .filter_map(|path| path.strip_prefix(dir).ok())
+        .map(Path::to_path_buf)
+        .collect())
+}
+
+fn compare_files(before: &Path, after: &Path) -> Result<Option<Change>> {
+    let read = |path: &Path| std::fs::read(path).map_err(|error| IconGenError::io(path, error));
+    let (before, after) = (read(before)?, read(after)?);
+    if before == after {
+        return Ok(None);
+    }
+    let (Ok(before), Ok(after)) = (
+        image::load_from_memory(&before),
+        image::load_from_memory(&after),
+    ) else {
+        return Ok(Some(Change::Contents));
+    };
+    Ok(compare_images(&before, &after))
+}
+
+/// Rate how `after` differs from `before`; None when the pixels are the same
+fn compare_images(before: &DynamicImage, after: &DynamicImage) -> Option<Change> {
+    let (before, after) = (before.to_rgba8(), after.to_rgba8());
+    if before.dimensions() != after.dimensions() {
+        return Some(Change::Resized {
+            before: before.dimensions(),
+            after: after.dimensions(),
+        });
+    }
+    if before == after {
+        return None;
+    }
+
+    let mut ssim = f64::INFINITY;
+    let mut delta_e = 0.0f64;
+    for backdrop in [[255, 255, 255], [0, 0, 0]] {
+        let (flat_before, flat_after) = (flatten(&before, backdrop), flatten(&after, backdrop));
+        ssim = ssim.min(luma_ssim(&flat_before, &flat_after, before.width()));
+        delta_e = delta_e.max(mean_delta_e(&flat_before, &flat_after));
+    }
+    Some(Change::Pixels {
+        width: before.width(),
+        height: before.height(),
+        ssim,
+        delta_e,
+    })
+}
+
+/// The pixels composited onto an opaque backdrop, as RGB triples from 0 to 1
+fn flatten(image: &RgbaImage, backdrop: [u8; 3]) -> Vec<[f64; 3]> {
+    image
+        .pixels()
+        .map(|&Rgba([red, green, blue, alpha])| {
+            let alpha = alpha as f64 / 255.0;
+            let blend = |channel: u8, backdrop: u8| {
+                (channel as f64 * alpha + backdrop as f64 * (1.0 - alpha)) / 255.0
+            };
+            [
+                blend(red, backdrop[0]),
+                blend(green, backdrop[1]),
+                blend(blue, backdrop[2]),
+            ]
+        })
+        .collect()
+}
+
+/// Mean SSIM of the luma over windows of `SSIM_WINDOW` pixels, overlapping by half
+fn luma_ssim(before: &[[f64; 3]], after: &[[f64; 3]], width: u32) -> f64 {
+    const C1: f64 = 0.01 * 0.01;
+    const C2: f64 = 0.03 * 0.03;
+    let luma = |pixel: &[f64; 3]| 0.299 * pixel[0] + 0.587 * pixel[1] + 0.114 * pixel[2];
+    let width = width as usize;
+    let height = before.len() / width;
+    // Images smaller than a window are compared as a whole
+    let window = (SSIM_WINDOW as usize).min(width).min(height);
+    let step = (window / 2).max(1);
+
+    let mut total = 0.0;
+    let mut windows = 0;
+    for top in (0..=height - window).step_by(step) {
+        for left in (0..=width - window).step_by(step) {
+            let pairs = (top..top + window).flat_map(|y| {
+                (left..left + window)
+                    .map(move |x| (luma(&before[y * width + x]), luma(&after[y * width + x])))
+            });
+            let count = (window * window) as f64;
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) =
+                (0.0, 0.0, 0.0, 0.0, 0.0);
+            for (a, b) in pairs {
+                sum_a += a;
+                sum_b += b;
+                sum_aa += a * a;
+                sum_bb += b * b;
+                sum_ab += a * b;
+            }
+            let (mean_a, mean_b) = (sum_a / count, sum_b / count);
+            let variance_a = sum_aa / count - mean_a * mean_a;
+            let variance_b = sum_bb / count - mean_b * mean_b;
+            let covariance = sum_ab / count - mean_a * mean_b;
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (variance_a + variance_b + C2));
+            windows += 1;
+        }
+    }
+    total / windows as f64
+}
+
+/// Mean CIE76 color difference between corresponding pixels
+fn mean_delta_e(before: &[[f64; 3]], after: &[[f64; 3]]) -> f64 {
+    let total = before
+        .iter()
+        .zip(after)
+        .map(|(before, after)| {
+            let (before, after) = (lab(before), lab(after));
+            (0..3)
+                .map(|i| (before[i] - after[i]).powi(2))
+                .sum::<f64>()
+                .sqrt()
+        })
+        .sum::<f64>();
+    total / before.len() as f64
+}
+
+/// CIE L*a*b* of an sRGB color, with the D65 white point
+fn lab(rgb: &[f64; 3]) -> [f64; 3] {
+    let linear = rgb.map(|channel| {
+        if channel <= 0.04045 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    let [r, g, b] = linear;
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f64| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(size: u32) -> RgbaImage {
+        RgbaImage::from_fn(size, size, |x, y| {
+            Rgba([(x * 255 / size) as u8, (y * 255 / size) as u8, 120, 255])
+        })
+    }
+
+    #[test]
+    fn test_lab() {
+        let white = lab(&[1.0, 1.0, 1.0]);
+        assert!((white[0] - 100.0).abs() < 0.01);
+        assert!(white[1].abs() < 0.1 && white[2].abs() < 0.1);
+        assert!(lab(&[0.0, 0.0, 0.0])[0].abs() < 0.01);
+    }
+
+    #[test]
+    fn test_compare_images() {
+        let image = DynamicImage::ImageRgba8(gradient(32));
+        assert_eq!(compare_images(&image, &image.clone()), None);
+
+        let resized = DynamicImage::ImageRgba8(gradient(16));
+        assert_eq!(
+            compare_images(&image, &resized),
+            Some(Change::Resized {
+                before: (32, 32),
+                after: (16, 16)
+            })
+        );
+
+        // A one-pixel tweak is barely visible, an inverted image very much so
+        let mut tweaked = gradient(32);
+        tweaked.put_pixel(5, 5, Rgba([0, 0, 0, 255]));
+        let Some(Change::Pixels { ssim, delta_e, .. }) =
+            compare_images(&image, &DynamicImage::ImageRgba8(tweaked))
+        else {
+            panic!("Expected changed pixels");
+        };
+        assert!(ssim > 0.95 && ssim < 1.0, "{ssim}");
+        assert!(delta_e < 0.5, "{delta_e}");
+
+        let mut inverted = image.clone();
+        inverted.invert();
+        let Some(Change::Pixels { ssim, delta_e, .. }) = compare_images(&image, &inverted) else {
+            panic!("Expected changed pixels");
+        };
+        assert!(ssim < 0.5, "{ssim}");
+        assert!(delta_e > 20.0, "{delta_e}");
+
+        // Transparency alone shows up against one of the backdrops
+        let mut faded = gradient(32);
+        for pixel in faded.pixels_mut() {
+            pixel[3] = 128;
+        }
+        let Some(Change::Pixels { delta_e, .. }) =
+            compare_images(&image, &DynamicImage::ImageRgba8(faded))
+        else {
+            panic!("Expected changed pixels");
+        };
+        assert!(delta_e > 5.0, "{delta_e}");
+    }
+}
@@ -0,0 +1,217 @@
+//! Errors of the library API
+//!
+//! Generation reports failures as an [`IconGenError`], so embedders can tell a
+//! bad source image from a full disk without parsing messages:
+//!
+//! ```no_run
+//! use icon_gen::{IconGenError, IconGenerator};
+//!
+//! match IconGenerator::new("logo.png").run() {
+//!     Ok(()) => {}
+//!     Err(IconGenError::InvalidInput(message)) => eprintln!("Fix the source: {message}"),
+//!     Err(IconGenError::IoError { path, source }) => {
+//!         eprintln!("Can't access {}: {source}", path.display())
+//!     }
+//!     Err(error) => eprintln!("{error}"),
+//! }
+//! ```
+//!
+//! The messages carry the whole chain of context, e.g. `Failed to load image:
+//! The image format could not be determined`.
+
+use std::{fmt, io, path::PathBuf};
+
+/// `Result` with an [`IconGenError`]
+pub type Result<T, E = IconGenError> = std::result::Result<T, E>;
+
+/// Why generating icons failed
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum IconGenError {
+    /// The source image or an option is unusable, e.g. a non-square source, an
+    /// out of range `--quantize` value or an unknown platform or preset name
+    InvalidInput(String),
+    /// A file is in a format that can't be read or written, e.g. an SVG overlay
+    /// or a `.rar` archive
+    UnsupportedFormat(String),
+    /// An image couldn't be encoded into one of the output formats
+    EncodeError(String),
+    /// A file couldn't be read or written; `path` is empty when unknown, and the
+    /// message is then what was being done, e.g. `Can't create output directory`
+    IoError { path: PathBuf, source: io::Error },
+    /// The run was stopped by [`crate::interrupt::interrupt`], e.g. on Ctrl-C
+    Interrupted,
+    /// Platforms failed under `keep_going`, which carried on with the others;
+    /// `generated` platforms were written, so none were when it is 0
+    PlatformsFailed {
+        failures: Vec<PlatformFailure>,
+        generated: usize,
+    },
+    /// A `strict` run warned `count` times, e.g. about an upscaled source; the
+    /// files were written
+    Warnings { count: usize },
+}
+
+/// A platform that failed in a `keep_going` run
+#[derive(Debug, Clone)]
+pub struct PlatformFailure {
+    /// The output directory of its set, e.g. `icons_gray` for a variant
+    pub set: PathBuf,
+    /// The platform or extra output, e.g. `macos` or `ios-splash`
+    pub platform: String,
+    /// Why it failed, with the whole chain of context
+    pub message: String,
+}
+
+impl IconGenError {
+    /// Prefix the message with `context`, like `anyhow::Context`
+    /// I/O errors keep their path and source; interruptions, platform failures and warnings stay as they are
+    pub(crate) fn context(self, context: impl fmt::Display) -> Self {
+        match self {
+            Self::InvalidInput(message) => Self::InvalidInput(format!("{context}: {message}")),
+            Self::UnsupportedFormat(message) => {
+                Self::UnsupportedFormat(format!("{context}: {message}"))
+            }
+            Self::EncodeError(message) => Self::EncodeError(format!("{context}: {message}")),
+            error @ (Self::IoError { .. }
+            | Self::Interrupted
+            | Self::PlatformsFailed { .. }
+            | Self::Warnings { .. }) => error,
+        }
+    }
+
+    /// Classify an error of the generation internals, which build up context with `anyhow`
+    pub(crate) fn from_anyhow(error: anyhow::Error) -> Self {
+        let message = format!("{error:#}");
+        // The context above an I/O error, e.g. `Can't create output directory`
+        let context = error
+            .chain()
+            .take_while(|cause| !cause.is::<io::Error>() && !cause.is::<image::ImageError>())
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        // Errors raised as an `IconGenError`, e.g. by a custom platform, keep their kind
+        let error = match error.downcast::<IconGenError>() {
+            Ok(Self::InvalidInput(_)) => return Self::InvalidInput(message),
+            Ok(Self::UnsupportedFormat(_)) => return Self::UnsupportedFormat(message),
+            Ok(Self::EncodeError(_)) => return Self::EncodeError(message),
+            Ok(
+                error @ (Self::IoError { .. }
+                | Self::Interrupted
+                | Self::PlatformsFailed { .. }
+                | Self::Warnings { .. }),
+            ) => return error,
+            Err(error) => error,
+        };
+        let error = match error.downcast::<image::ImageError>() {
+            Ok(image::ImageError::Unsupported(_)) => return Self::UnsupportedFormat(message),
+            Ok(image::ImageError::Encoding(_)) => return Self::EncodeError(message),
+            Ok(image::ImageError::IoError(source)) => return Self::io_in(context, source),
+            Ok(_) => return Self::InvalidInput(message),
+            Err(error) => error,
+        };
+        if error.downcast_ref::<png::EncodingError>().is_some() {
+            return Self::EncodeError(message);
+        }
+        match error.downcast::<io::Error>() {
+            Ok(source) => Self::io_in(context, source),
+            Err(_) => Self::InvalidInput(message),
+        }
+    }
+
+    /// An I/O error without a path, keeping the `context` it was raised in
+    fn io_in(context: Vec<String>, source: io::Error) -> Self {
+        if context.is_empty() {
+            return Self::io(PathBuf::new(), source);
+        }
+        let context = IoContext {
+            context: context.join(": "),
+            source,
+        };
+        Self::io(
+            PathBuf::new(),
+            io::Error::new(context.source.kind(), context),
+        )
+    }
+
+    pub(crate) fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Self::IoError {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for IconGenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidInput(message)
+            | Self::UnsupportedFormat(message)
+            | Self::EncodeError(message) => f.write_str(message),
+            Self::IoError { path, source } if path.as_os_str().is_empty() => {
+                match IoContext::of(source) {
+                    Some(context) => f.write_str(&context.context),
+                    None => f.write_str("I/O error"),
+                }
+            }
+            Self::IoError { path, .. } => write!(f, "Failed to access {}", path.display()),
+            Self::Interrupted => f.write_str("Interrupted"),
+            Self::Warnings { count: 1 } => f.write_str("1 warning with --strict"),
+            Self::Warnings { count } => write!(f, "{count} warnings with --strict"),
+            Self::PlatformsFailed {
+                failures,
+                generated,
+            } => {
+                let platforms = failures
+                    .iter()
+                    .map(|failure| failure.set.join(&failure.platform).display().to_string())
+                    .collect::<Vec<_>>();
+                write!(
+                    f,
+                    "{} of {} platforms failed: {}",
+                    failures.len(),
+                    failures.len() + generated,
+                    platforms.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for IconGenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            // The context is the message, so the cause is the error under it
+            Self::IoError { source, .. } => match IoContext::of(source) {
+                Some(context) => Some(&context.source),
+                None => Some(source),
+            },
+            _ => None,
+        }
+    }
+}
+
+/// An I/O error under the context it was raised in, as the source of a
+/// pathless `IoError`, so that the error still has the kind of the original
+#[derive(Debug)]
+struct IoContext {
+    context: String,
+    source: io::Error,
+}
+
+impl IoContext {
+    fn of(error: &io::Error) -> Option<&Self> {
+        error.get_ref()?.downcast_ref()
+    }
+}
+
+impl fmt::Display for IoContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.context, self.source)
+    }
+}
+
+impl std::error::Error for IoContext {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
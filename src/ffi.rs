@@ -0,0 +1,154 @@
+//! C API for native tooling that links the generator directly
+//!
+//! The declarations are in `include/icon_gen.h` (regenerate with
+//! `cbindgen --config cbindgen.toml --output include/icon_gen.h`). A call takes an
+//! encoded source image and returns a zip archive of the generated files, named by
+//! their paths in the output directory, e.g. `windows/icon.ico`. Errors are
+//! reported through the return code and `icon_gen_last_error`; panics never cross
+//! the boundary.
+
+use crate::archive::ArchiveFormat;
+use crate::generator::{IconGenerator, Platform};
+use crate::output::ArchiveSink;
+use anyhow::{Context, Result};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr,
+};
+
+/// The call succeeded
+pub const ICON_GEN_OK: c_int = 0;
+/// The call failed; `icon_gen_last_error` describes why
+pub const ICON_GEN_ERROR: c_int = 1;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// A byte buffer allocated by the library, released with `icon_gen_buffer_free`
+#[repr(C)]
+#[derive(Debug)]
+pub struct IconGenBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl IconGenBuffer {
+    fn empty() -> Self {
+        Self {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let bytes = Box::into_raw(bytes.into_boxed_slice());
+        Self {
+            data: bytes.cast(),
+            len: bytes.len(),
+        }
+    }
+}
+
+/// Generate icons from an encoded source image (PNG, JPEG, ...) into a zip archive
+///
+/// `platforms` is a comma-separated list of platform names as given to the command
+/// line, e.g. `"windows,macos,linux"`; NULL or an empty string generates every
+/// platform. On success the archive is stored in `*out` and `ICON_GEN_OK` is
+/// returned; on failure `*out` is left empty and `ICON_GEN_ERROR` is returned.
+///
+/// # Safety
+///
+/// `source` must point to `source_len` readable bytes, `platforms` must be NULL or
+/// a NUL-terminated string, and `out` must point to a writable `IconGenBuffer`.
+#[no_mangle]
+pub unsafe extern "C" fn icon_gen_generate_zip(
+    source: *const u8,
+    source_len: usize,
+    platforms: *const c_char,
+    out: *mut IconGenBuffer,
+) -> c_int {
+    if out.is_null() {
+        set_last_error("out must not be NULL");
+        return ICON_GEN_ERROR;
+    }
+    *out = IconGenBuffer::empty();
+    if source.is_null() {
+        set_last_error("source must not be NULL");
+        return ICON_GEN_ERROR;
+    }
+    let source = std::slice::from_raw_parts(source, source_len);
+    let platforms = (!platforms.is_null()).then(|| CStr::from_ptr(platforms));
+
+    let result = catch_unwind(AssertUnwindSafe(|| generate_zip(source, platforms)))
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("Icon generation panicked")));
+    match result {
+        Ok(archive) => {
+            *out = IconGenBuffer::from_vec(archive);
+            clear_last_error();
+            ICON_GEN_OK
+        }
+        Err(error) => {
+            set_last_error(&format!("{error:#}"));
+            ICON_GEN_ERROR
+        }
+    }
+}
+
+fn generate_zip(source: &[u8], platforms: Option<&CStr>) -> Result<Vec<u8>> {
+    let platforms = platforms
+        .map(|names| names.to_str().context("platforms is not valid UTF-8"))
+        .transpose()?
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::parse::<Platform>)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let sink = ArchiveSink::new("", ArchiveFormat::Zip);
+    IconGenerator::from_bytes(source)
+        .platforms(platforms)
+        .output("")
+        .run_with(&sink)?;
+    Ok(sink.finish()?)
+}
+
+/// Release a buffer returned by the library; an empty buffer is ignored
+///
+/// # Safety
+///
+/// `buffer` must have been returned by this library and not been freed before.
+#[no_mangle]
+pub unsafe extern "C" fn icon_gen_buffer_free(buffer: IconGenBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+/// The message of the last failed call on this thread, or NULL after a success
+/// The string stays valid until the next call on the same thread.
+#[no_mangle]
+pub extern "C" fn icon_gen_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+fn set_last_error(message: &str) {
+    // Interior NULs can't be represented in a C string
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+fn clear_last_error() {
+    LAST_ERROR.with(|error| *error.borrow_mut() = None);
+}
@@ -0,0 +1,292 @@
+//! Builder API for embedding icon generation in other Rust tools
+//!
+//! ```no_run
+//! use icon_gen::{IconGenerator, Platform};
+//!
+//! IconGenerator::new("logo.png")
+//!     .platforms([Platform::Windows, Platform::Macos, Platform::Linux])
+//!     .output("src-tauri/icons")
+//!     .run()?;
+//! # Ok::<(), icon_gen::IconGenError>(())
+//! ```
+//!
+//! Options left unset keep the command line defaults, so a bare
+//! `IconGenerator::new(source).run()` generates the same icons as `icon-gen <source>`.
+
+use crate::error::{IconGenError, Result};
+use crate::icon_gen::{
+    default_output_dir, generate_icons, generate_into, generate_to_memory, Args,
+};
+use crate::output::OutputSink;
+use crate::platform::PlatformGenerator;
+use std::{collections::BTreeMap, path::PathBuf, str::FromStr, sync::Arc};
+
+/// A target platform or asset set, matching the command line flag of the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Platform {
+    /// `windows/icon.ico` (`--windows`)
+    Windows,
+    /// `macos/icon.icns` (`--macos`)
+    Macos,
+    /// `linux/` PNGs (`--linux`)
+    Linux,
+    /// `tray/` icons (`--tray`)
+    Tray,
+    /// `android/` launcher icons (`--android`)
+    Android,
+    /// `ios/` app icon set (`--ios`)
+    Ios,
+    /// `tauri-desktop/` icons (`--tauri-desktop`)
+    TauriDesktop,
+    /// `imessage/` icon set (`--imessage`)
+    IMessage,
+    /// `app-clip/` icon set (`--app-clip`)
+    AppClip,
+    /// `ios-splash/` launch images (`--ios-splash`)
+    IosSplash,
+    /// Android 12 splash screen icons (`--android-splash`)
+    AndroidSplash,
+    /// `web/splash/` PWA startup images (`--pwa-startup`)
+    PwaStartup,
+    /// `windows/cursor.cur` (`--cursor`)
+    Cursor,
+}
+
+impl Platform {
+    /// Every platform, in the order of the command line flags
+    pub const ALL: [Platform; 13] = [
+        Platform::Windows,
+        Platform::Macos,
+        Platform::Linux,
+        Platform::Tray,
+        Platform::Android,
+        Platform::Ios,
+        Platform::TauriDesktop,
+        Platform::IMessage,
+        Platform::AppClip,
+        Platform::IosSplash,
+        Platform::AndroidSplash,
+        Platform::PwaStartup,
+        Platform::Cursor,
+    ];
+
+    /// The name of the command line flag, e.g. `tauri-desktop`
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::Windows => "windows",
+            Platform::Macos => "macos",
+            Platform::Linux => "linux",
+            Platform::Tray => "tray",
+            Platform::Android => "android",
+            Platform::Ios => "ios",
+            Platform::TauriDesktop => "tauri-desktop",
+            Platform::IMessage => "imessage",
+            Platform::AppClip => "app-clip",
+            Platform::IosSplash => "ios-splash",
+            Platform::AndroidSplash => "android-splash",
+            Platform::PwaStartup => "pwa-startup",
+            Platform::Cursor => "cursor",
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = IconGenError;
+
+    /// Parse the name of the command line flag, e.g. `tauri-desktop`
+    fn from_str(name: &str) -> Result<Self> {
+        Platform::ALL
+            .into_iter()
+            .find(|platform| platform.name() == name)
+            .ok_or_else(|| {
+                let names = Platform::ALL.map(Platform::name);
+                IconGenError::InvalidInput(format!(
+                    "Unknown platform: {}. Available platforms: {}",
+                    name,
+                    names.join(", ")
+                ))
+            })
+    }
+}
+
+/// Generates icon sets from a source image, configured like the command line
+#[derive(Debug, Clone)]
+pub struct IconGenerator {
+    args: Args,
+}
+
+impl IconGenerator {
+    /// Start from the command line defaults for the given source image, writing to
+    /// `icon-generator-<source name>` unless `output` is set
+    pub fn new(source: impl Into<PathBuf>) -> Self {
+        let input = source.into();
+        Self {
+            args: Args {
+                output: default_output_dir(&input),
+                input,
+                ..Args::default()
+            },
+        }
+    }
+
+    /// Start from an encoded source image (PNG, JPEG, ...) already in memory, e.g. in a
+    /// WebAssembly build without a filesystem; writes to `icon-generator-icon` unless
+    /// `output` is set
+    pub fn from_bytes(source: impl Into<Arc<[u8]>>) -> Self {
+        Self {
+            args: Args {
+                input_bytes: Some(source.into()),
+                ..Args::default()
+            },
+        }
+    }
+
+    /// Start from a complete set of options, e.g. parsed by another command line tool
+    pub fn from_args(args: Args) -> Self {
+        Self { args }
+    }
+
+    /// The directory the icons are written to
+    pub fn output(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.args.output = dir.into();
+        self
+    }
+
+    /// Write the icons into this archive (.zip, .tar or .tar.gz) instead of `output` (`--archive`)
+    pub fn archive(mut self, path: impl Into<PathBuf>) -> Self {
+        self.args.archive = Some(path.into());
+        self
+    }
+
+    /// Write `icons.manifest.json` with the SHA-256 of every file (`--manifest`)
+    pub fn manifest(mut self, manifest: bool) -> Self {
+        self.args.manifest = manifest;
+        self
+    }
+
+    /// Generate only these platforms; without this call every platform is generated
+    pub fn platforms(mut self, platforms: impl IntoIterator<Item = Platform>) -> Self {
+        for platform in platforms {
+            let flag = match platform {
+                Platform::Windows => &mut self.args.windows,
+                Platform::Macos => &mut self.args.macos,
+                Platform::Linux => &mut self.args.linux,
+                Platform::Tray => &mut self.args.tray,
+                Platform::Android => &mut self.args.android,
+                Platform::Ios => &mut self.args.ios,
+                Platform::TauriDesktop => &mut self.args.tauri_desktop,
+                Platform::IMessage => &mut self.args.imessage,
+                Platform::AppClip => &mut self.args.app_clip,
+                Platform::IosSplash => &mut self.args.ios_splash,
+                Platform::AndroidSplash => &mut self.args.android_splash,
+                Platform::PwaStartup => &mut self.args.pwa_startup,
+                Platform::Cursor => &mut self.args.cursor,
+            };
+            *flag = true;
+        }
+        self
+    }
+
+    /// Also generate a platform of your own; like `platforms`, registering one
+    /// limits the built-in platforms to those selected explicitly
+    pub fn register(mut self, generator: impl PlatformGenerator + 'static) -> Self {
+        self.args.custom_platforms.push(Arc::new(generator));
+        self
+    }
+
+    /// Generate only these PNG sizes into the output directory (`--png`)
+    pub fn png_sizes(mut self, sizes: impl IntoIterator<Item = u32>) -> Self {
+        self.args.png = Some(sizes.into_iter().collect());
+        self
+    }
+
+    /// Inset the artwork by this percentage of the side on every edge (`--padding`)
+    pub fn padding(mut self, percent: f32) -> Self {
+        self.args.padding = Some(percent);
+        self
+    }
+
+    /// Background color used to flatten transparency, in CSS color format (`--background`)
+    pub fn background(mut self, color: impl Into<String>) -> Self {
+        self.args.background = color.into();
+        self
+    }
+
+    /// Flatten the source onto the background for every target (`--flatten`)
+    pub fn flatten(mut self, flatten: bool) -> Self {
+        self.args.flatten = flatten;
+        self
+    }
+
+    /// Clip outputs to a rounded rectangle, e.g. `24px` or `12%` (`--radius`)
+    pub fn radius(mut self, radius: impl Into<String>) -> Self {
+        self.args.radius = Some(radius.into());
+        self
+    }
+
+    /// Also generate a variant set into a sibling directory, e.g. `grayscale` (`--variant`)
+    pub fn variant(mut self, name: impl Into<String>) -> Self {
+        self.args.variant.push(name.into());
+        self
+    }
+
+    /// Resampling filter for every resize, e.g. `nearest` for pixel art (`--filter`)
+    pub fn filter(mut self, name: impl Into<String>) -> Self {
+        self.args.filter = name.into();
+        self
+    }
+
+    /// Losslessly re-encode every generated PNG in its smallest form (`--optimize`)
+    pub fn optimize(mut self, optimize: bool) -> Self {
+        self.args.optimize = optimize;
+        self
+    }
+
+    /// Number of worker threads; every CPU is used by default (`--jobs`)
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.args.jobs = Some(jobs);
+        self
+    }
+
+    /// Skip sets that are up to date according to `.icon-gen-cache.json` (on by default)
+    pub fn cache(mut self, enabled: bool) -> Self {
+        self.args.no_cache = !enabled;
+        self
+    }
+
+    /// Carry on with the other platforms when one fails, failing with
+    /// `IconGenError::PlatformsFailed` at the end (`--keep-going`)
+    pub fn keep_going(mut self, keep_going: bool) -> Self {
+        self.args.keep_going = keep_going;
+        self
+    }
+
+    /// Fail with `IconGenError::Warnings` after a run that warned, e.g. about an
+    /// upscaled source (`--strict`)
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.args.strict = strict;
+        self
+    }
+
+    /// Every option, for those without a builder method of their own
+    pub fn args_mut(&mut self) -> &mut Args {
+        &mut self.args
+    }
+
+    /// Generate the icons
+    pub fn run(self) -> Result<()> {
+        generate_icons(self.args)
+    }
+
+    /// Generate the icons into memory, leaving the filesystem untouched
+    /// Files are keyed by the path `run` would write them to, under `output`
+    pub fn generate_to_memory(self) -> Result<BTreeMap<PathBuf, Vec<u8>>> {
+        generate_to_memory(self.args)
+    }
+
+    /// Generate the icons through a sink, e.g. an `ArchiveSink` to build a zip archive
+    pub fn run_with(self, sink: &dyn OutputSink) -> Result<()> {
+        generate_into(self.args, sink)
+    }
+}
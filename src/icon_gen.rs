@@ -1,755 +1,4413 @@
-use crate::contents_json::{ContentsFile, ImageEntry};
+use crate::archive::ArchiveFormat;
+use crate::cache::{set_files, Fingerprint, GenerationCache};
+use crate::compositor::{
+    contrasting_text_color, draw_text, embedded_font, overlay_file, text_width, Compositor,
+    Overlays,
+};
+use crate::contents_json::{ContentsFile, ImageEntry, CONTENTS_JSON_MODES};
+use crate::contrast::{Edge, BACKDROPS, MIN_CONTRAST};
+use crate::error::{self, IconGenError, PlatformFailure};
+use crate::interrupt;
+use crate::manifest::write_manifests;
+use crate::output::{ArchiveSink, FileSystemSink, MemorySink, OutputSink};
+use crate::overwrite::OverwriteGuard;
+use crate::platform::{PlatformContext, PlatformGenerator};
+use crate::png_metadata::PNG_METADATA_MODES;
+use crate::png_passes::{
+    optimize_pngs, quantize_small_pngs, recompress_pngs_zopfli, set_pngs_metadata,
+};
+use crate::presets::{preset_assets, store_icons, PresetAsset};
+use crate::progress::{self, ProgressSink, PROGRESS_MODES};
+use crate::report::{ReportingSink, RunReport, REPORT_FORMATS};
+use crate::safe_zone::{self, SafeZone, OUTSIDE_TOLERANCE, SAFE_ZONE_MODES};
+use crate::spec::{load_spec, SpecTarget};
+use crate::splash::{APPLE_STARTUP_DEVICES, IOS_LAUNCH_IMAGES};
+use crate::store_limits::{self, APP_STORE_ICON};
+use crate::verify;
 use anyhow::{Context, Result};
-use icns::{IconFamily, IconType};
+use icns::{Encoding, IconElement, IconFamily, IconType, PixelFormat};
 use image::{
     codecs::{
+        gif::{GifDecoder, GifEncoder, Repeat},
         ico::{IcoEncoder, IcoFrame},
-        png::{CompressionType, FilterType as PngFilterType, PngEncoder},
+        png::{CompressionType, FilterType as PngFilterType, PngDecoder, PngEncoder},
     },
     imageops::FilterType,
-    ColorType, DynamicImage, ImageBuffer, ImageEncoder, Rgba,
+    AnimationDecoder, ColorType, Delay, DynamicImage, Frame, ImageBuffer, ImageEncoder,
+    ImageFormat, Pixel, Rgba, Rgba32FImage, RgbaImage,
 };
-use serde::Deserialize;
+use rayon::prelude::*;
+use rusttype::Scale;
 use std::{
-    collections::HashMap,
-    f32::consts::PI,
-    fs::{create_dir_all, File},
-    io::{BufWriter, Write},
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::File,
+    io::{Cursor, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
 };
 
-// Define Args struct for library compilation
-#[derive(Debug)]
+/// Every generation option, one field per command line option
+/// Library users would normally go through `IconGenerator` instead
+#[derive(Debug, Clone)]
 pub struct Args {
     pub input: PathBuf,
+    /// Encoded source image used instead of reading `input`, for callers without
+    /// a filesystem such as WebAssembly builds; there is no command line option for it
+    pub input_bytes: Option<Arc<[u8]>>,
     pub output: PathBuf,
+    pub archive: Option<PathBuf>,
+    /// Print a report of the generated files in this format instead of the log;
+    /// only `generate_icons` prints it, other callers can wrap their sink in a `ReportingSink`
+    pub report: Option<String>,
+    /// Write an `icons.manifest.json` with the SHA-256 of every file into each set directory
+    pub manifest: bool,
+    /// Only resolve the files a run would generate, from a blank stand-in for the source
+    pub dry_run: bool,
+    /// How progress is reported: `auto` or `json` (one of `PROGRESS_MODES`)
+    pub progress: String,
     pub png: Option<Vec<u32>>,
+    pub formats: Vec<String>,
+    pub padding: Option<f32>,
+    pub padding_color: Option<String>,
+    pub variant: Vec<String>,
+    pub hue_variants: Vec<String>,
+    pub adjust: Vec<String>,
+    pub tint: Option<String>,
+    pub tint_mode: String,
     pub desktop_only: bool,
     pub mobile_only: bool,
     pub tauri_desktop: bool,
     pub windows: bool,
     pub macos: bool,
+    pub macos_style: String,
+    pub icns_types: String,
     pub linux: bool,
+    pub tray: bool,
     pub android: bool,
     pub android_round: bool,
     pub android_adaptive: bool,
     pub android_adaptive_bg: String,
+    pub android_legacy_shape: String,
     pub ios: bool,
-    pub ios_color: String,
+    pub background: String,
+    pub background_for: Vec<String>,
+    pub background_image: Option<PathBuf>,
+    pub flatten: bool,
+    pub quantize: Option<u16>,
+    pub optimize: bool,
+    pub compression: String,
+    pub encode_profile: String,
+    pub png_metadata: String,
+    pub filter: String,
+    pub sharpen: Option<f32>,
+    pub supersample: bool,
+    pub legibility: bool,
+    pub jobs: Option<usize>,
+    pub resize_strategy: String,
+    pub max_working_size: u32,
+    pub no_cache: bool,
+    pub skip_existing: bool,
+    pub if_newer: bool,
+    pub force: bool,
+    pub clean: bool,
+    pub no_clobber: bool,
+    pub keep_going: bool,
+    pub verify: bool,
+    pub name_template: Option<String>,
+    pub ios_layout: String,
+    pub contents_json: String,
+    pub safe_zone: String,
+    pub contrast_check: bool,
+    pub strict: bool,
+    pub ios_squircle: bool,
+    pub imessage: bool,
+    pub app_clip: bool,
+    pub ios_splash: bool,
+    pub android_splash: bool,
+    pub pwa_startup: bool,
+    pub splash_bg: String,
+    pub windows_rc: bool,
+    pub cursor: bool,
+    pub cursor_hotspot: Vec<u32>,
+    pub cursor_frames: Option<Vec<PathBuf>>,
+    pub cursor_delay: u32,
+    pub animated_favicon: bool,
+    pub favicon_fps: Option<u32>,
+    pub preset: Vec<String>,
+    pub preset_bg: String,
+    pub spec: Option<PathBuf>,
+    pub project_name: Option<String>,
+    pub radius: Option<String>,
+    pub radius_for: Vec<String>,
+    pub border: Option<String>,
+    pub border_for: Vec<String>,
+    pub shadow: bool,
+    pub shadow_blur: f32,
+    pub shadow_offset: Vec<i32>,
+    pub shadow_opacity: f32,
+    pub shadow_for: Vec<String>,
     pub dev_mode: bool,
     pub dev_bug: String,
+    pub dev_badge_file: Option<PathBuf>,
+    pub badge_position: String,
+    pub badge_scale: f32,
+    pub badge_seed: Option<u64>,
+    pub overlay: Vec<String>,
+    pub layers: Vec<String>,
+    pub size_rule: Vec<String>,
+    pub stamp_text: Option<String>,
+    pub stamp_font: Option<PathBuf>,
+    pub stamp_color: String,
+    pub stamp_position: String,
+    pub badge_ribbon: Option<String>,
+    pub channel: Option<String>,
+    pub channel_tint: bool,
+    /// Platforms registered by library users, generated after the built-in ones;
+    /// there is no command line option for them
+    pub custom_platforms: Vec<Arc<dyn PlatformGenerator>>,
 }
 
-#[derive(Debug, Deserialize)]
-struct IcnsEntry {
-    size: u32,
-    ostype: String,
+impl Default for Args {
+    /// The command line defaults, generating every platform from `icon.png`
+    fn default() -> Self {
+        let strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+        Self {
+            input: PathBuf::from("icon.png"),
+            input_bytes: None,
+            output: default_output_dir(Path::new("icon.png")),
+            archive: None,
+            report: None,
+            manifest: false,
+            dry_run: false,
+            progress: "auto".to_string(),
+            png: None,
+            formats: strings(&["png"]),
+            padding: None,
+            padding_color: None,
+            variant: Vec::new(),
+            hue_variants: Vec::new(),
+            adjust: Vec::new(),
+            tint: None,
+            tint_mode: "recolor".to_string(),
+            desktop_only: false,
+            mobile_only: false,
+            tauri_desktop: false,
+            windows: false,
+            macos: false,
+            macos_style: "none".to_string(),
+            icns_types: "default".to_string(),
+            linux: false,
+            tray: false,
+            android: false,
+            android_round: false,
+            android_adaptive: false,
+            android_adaptive_bg: "#ffffff".to_string(),
+            android_legacy_shape: "none".to_string(),
+            ios: false,
+            background: "#ffffff".to_string(),
+            background_for: strings(&["ios"]),
+            background_image: None,
+            flatten: false,
+            quantize: None,
+            optimize: false,
+            compression: "best".to_string(),
+            encode_profile: "best".to_string(),
+            png_metadata: "keep".to_string(),
+            filter: "lanczos3".to_string(),
+            sharpen: None,
+            supersample: false,
+            legibility: false,
+            jobs: None,
+            resize_strategy: "mipmap".to_string(),
+            max_working_size: 2048,
+            no_cache: false,
+            skip_existing: false,
+            if_newer: false,
+            force: false,
+            clean: false,
+            no_clobber: false,
+            keep_going: false,
+            verify: false,
+            name_template: None,
+            ios_layout: "flat".to_string(),
+            contents_json: "replace".to_string(),
+            safe_zone: "off".to_string(),
+            contrast_check: false,
+            strict: false,
+            ios_squircle: false,
+            imessage: false,
+            app_clip: false,
+            ios_splash: false,
+            android_splash: false,
+            pwa_startup: false,
+            splash_bg: "#ffffff".to_string(),
+            windows_rc: false,
+            cursor: false,
+            cursor_hotspot: vec![0, 0],
+            cursor_frames: None,
+            cursor_delay: 100,
+            animated_favicon: false,
+            favicon_fps: None,
+            preset: Vec::new(),
+            preset_bg: "#000000".to_string(),
+            spec: None,
+            project_name: None,
+            radius: None,
+            radius_for: Vec::new(),
+            border: None,
+            border_for: Vec::new(),
+            shadow: false,
+            shadow_blur: 20.0,
+            shadow_offset: vec![0, 10],
+            shadow_opacity: 0.4,
+            shadow_for: Vec::new(),
+            dev_mode: false,
+            dev_bug: "moth".to_string(),
+            dev_badge_file: None,
+            badge_position: "center".to_string(),
+            badge_scale: 0.25,
+            badge_seed: None,
+            overlay: Vec::new(),
+            layers: strings(&["overlays", "badge", "ribbon", "stamp"]),
+            size_rule: Vec::new(),
+            stamp_text: None,
+            stamp_font: None,
+            stamp_color: "#ffffff".to_string(),
+            stamp_position: "bottom".to_string(),
+            badge_ribbon: None,
+            channel: None,
+            channel_tint: false,
+            custom_platforms: Vec::new(),
+        }
+    }
 }
 
-/// Apply a bug overlay to an image for development mode
-/// Places a bug image in the center of the icon with optional rotation
-pub fn apply_dev_badge_with_bug(
-    img: &mut DynamicImage,
-    bug_type: &str,
-    angle_degrees: f32,
-) -> Result<()> {
-    let width = img.width();
-    let height = img.height();
-    let min_dim = width.min(height);
-
-    // Load the embedded bug image
-    let bug_data = get_embedded_bug_image(bug_type)?;
-    let bug_img = image::load_from_memory(bug_data)
-        .with_context(|| format!("Failed to load embedded bug image: {}", bug_type))?;
-
-    // Calculate bug size - bug should be 1/4 of the minimum dimension
-    let bug_size = min_dim / 4;
+/// The output directory used when none is given: `icon-generator-<source name>`
+pub fn default_output_dir(input: &Path) -> PathBuf {
+    let source_stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("icon");
+    PathBuf::from(format!("icon-generator-{}", source_stem))
+}
 
-    // Resize the bug image while maintaining aspect ratio
-    let resized_bug = resize_bug_with_aspect_ratio(&bug_img, bug_size);
+/// Variables of `--name-template`
+const NAME_TEMPLATE_VARIABLES: &[&str] = &["app", "platform", "size", "scale", "density"];
 
-    // Rotate the bug if angle is not 0
-    let final_bug = if angle_degrees != 0.0 {
-        rotate_image(&resized_bug, angle_degrees)
-    } else {
-        resized_bug
+/// The file name `--name-template` gives an icon of `platform` (`png` for custom
+/// `--png` sizes), `None` without a template. `size` is the side in points, the
+/// pixel side divided by `scale`; the template's `.png` becomes `.{extension}`
+/// for the other `--formats`
+fn icon_file_name(
+    args: &Args,
+    platform: &str,
+    size: u32,
+    scale: u32,
+    extension: &str,
+) -> Result<Option<String>> {
+    let Some(template) = &args.name_template else {
+        return Ok(None);
     };
-
-    // Calculate position to center the bug
-    let bug_width = final_bug.width();
-    let bug_height = final_bug.height();
-    let x = (width.saturating_sub(bug_width)) / 2;
-    let y = (height.saturating_sub(bug_height)) / 2;
-
-    // Overlay the bug onto the main image
-    image::imageops::overlay(img, &final_bug, x.into(), y.into());
-
-    Ok(())
+    // In-memory sources keep the `icon.png` name of the defaults
+    let app = args
+        .input
+        .file_stem()
+        .map_or_else(|| "icon".into(), |stem| stem.to_string_lossy());
+
+    let mut name = String::new();
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .with_context(|| format!("Unclosed {{ in --name-template: {}", template))?;
+        match &rest[start + 1..start + end] {
+            "app" => name.push_str(&app),
+            "platform" => name.push_str(platform),
+            "size" => name.push_str(&size.to_string()),
+            "scale" => name.push_str(&scale.to_string()),
+            "density" if scale > 1 => name.push_str(&format!("@{scale}x")),
+            "density" => {}
+            variable => anyhow::bail!(
+                "Unknown variable {{{}}} in --name-template: {}. Available variables: {}",
+                variable,
+                template,
+                NAME_TEMPLATE_VARIABLES
+                    .iter()
+                    .map(|name| format!("{{{name}}}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    name.push_str(rest);
+    name.truncate(name.len() - ".png".len());
+    Ok(Some(format!("{name}.{extension}")))
 }
 
-/// Get embedded bug image data based on bug type
-fn get_embedded_bug_image(bug_type: &str) -> Result<&'static [u8]> {
-    match bug_type {
-        "moth" => Ok(include_bytes!("bugs/moth.png")),
-        "cockroach" => Ok(include_bytes!("bugs/cockroach.png")),
-        "ladybug" => Ok(include_bytes!("bugs/ladybug.png")),
-        "spider" => Ok(include_bytes!("bugs/spider.png")),
-        "caterpillar" => Ok(include_bytes!("bugs/caterpillar.png")),
-        _ => Err(anyhow::anyhow!(
-            "Unknown bug type: {}. Available types: moth, cockroach, ladybug, spider, caterpillar",
-            bug_type
-        )),
+/// Check `--name-template` before anything is written
+fn validate_name_template(args: &Args) -> Result<()> {
+    let Some(template) = &args.name_template else {
+        return Ok(());
+    };
+    if !template.to_ascii_lowercase().ends_with(".png") {
+        anyhow::bail!("--name-template must end in .png, got {}", template);
+    }
+    if template.contains(['/', '\\']) {
+        anyhow::bail!(
+            "--name-template names a file, not a path; icons stay in their platform folders: {}",
+            template
+        );
     }
+    if !template.contains("{size}") {
+        anyhow::bail!(
+            "--name-template must contain {{size}} to tell the sizes apart: {}",
+            template
+        );
+    }
+    icon_file_name(args, "png", 1, 1, "png").map(|_| ())
 }
 
-/// Rotate an image by the given angle in degrees
-fn rotate_image(img: &DynamicImage, angle_degrees: f32) -> DynamicImage {
-    let angle_radians = angle_degrees * PI / 180.0;
+/// Platforms whose icons can be given their own source treatment (e.g. `--radius-for`)
+/// Custom `--png` sizes and tauri-desktop PNGs count as linux
+pub const PLATFORM_NAMES: &[&str] = &["windows", "macos", "linux", "tray", "android", "ios"];
 
-    // For simplicity, we'll implement a basic rotation
-    // This could be enhanced with more sophisticated rotation algorithms
-    let width = img.width();
-    let height = img.height();
+/// The source image as seen by each platform, with per-platform treatments
+/// such as the `--background` fill and `--radius` mask already applied
+#[derive(Debug)]
+pub(crate) struct PlatformSources<'a> {
+    original: &'a DynamicImage,
+    treated: HashMap<&'static str, DynamicImage>,
+    /// Halving chains of the original and of each treated source, for `--resize-strategy mipmap`
+    original_mipmaps: Vec<DynamicImage>,
+    mipmaps: HashMap<&'static str, Vec<DynamicImage>>,
+    resampler: Resampler<'a>,
+    /// The sources prepared for each `--size-rule`, by the size they apply below
+    size_rules: Vec<(u32, PlatformSources<'a>)>,
+    /// Every resize of a source so far, keyed by the source's address and the size
+    resized: Mutex<HashMap<ResizeKey, Arc<OnceLock<DynamicImage>>>>,
+}
 
-    // Create a new image with the same dimensions
-    let mut rotated = ImageBuffer::from_fn(width, height, |_, _| Rgba([0, 0, 0, 0]));
+/// A source image, identified by its address, and an output width and height
+type ResizeKey = (usize, u32, u32);
 
-    // Center of rotation
-    let center_x = width as f32 / 2.0;
-    let center_y = height as f32 / 2.0;
+impl<'a> PlatformSources<'a> {
+    fn new(original: &'a DynamicImage, args: &Args) -> Result<Self> {
+        let resampler = Resampler::from_args(args)?;
+        let bg_color = parse_color(&args.background);
+        let backdrop = match &args.background_image {
+            Some(path) => Some(load_backdrop(path, original.width(), resampler)?),
+            None => None,
+        };
+        let background_platforms = selected_platforms(&args.background_for, "--background-for")?;
+        let radius_platforms = selected_platforms(&args.radius_for, "--radius-for")?;
+        let radius = args
+            .radius
+            .as_deref()
+            .map(|radius| parse_radius(radius, original.width()))
+            .transpose()?;
+        let border_platforms = selected_platforms(&args.border_for, "--border-for")?;
+        let border = args.border.as_deref().map(parse_border).transpose()?;
+        let shadow_platforms = selected_platforms(&args.shadow_for, "--shadow-for")?;
+        let shadow = if args.shadow {
+            Some(parse_shadow(args)?)
+        } else {
+            None
+        };
+        let big_sur = match args.macos_style.as_str() {
+            "none" => false,
+            "big-sur" => true,
+            style => anyhow::bail!(
+                "Unknown macOS style: {}. Available styles: none, big-sur",
+                style
+            ),
+        };
 
-    let rgba_img = img.to_rgba8();
+        let mut treated = HashMap::new();
+        for &platform in PLATFORM_NAMES {
+            // Flatten first so that a --radius mask cuts a colored tile
+            let mut image = background_platforms
+                .contains(&platform)
+                .then(|| match &backdrop {
+                    Some(backdrop) => composite_over(original, backdrop),
+                    None => flatten_alpha(original, bg_color),
+                });
+
+            // The macOS style brings its own tile shape, so it replaces any --radius mask
+            if platform == "macos" && big_sur {
+                image = Some(apply_big_sur_style(
+                    image.as_ref().unwrap_or(original),
+                    resampler,
+                ));
+            } else {
+                let radius = radius.filter(|_| radius_platforms.contains(&platform));
+                if let Some(radius) = radius {
+                    image = Some(apply_rounded_corners(
+                        image.as_ref().unwrap_or(original),
+                        radius,
+                    ));
+                }
 
-    // Simple nearest-neighbor rotation
-    for y in 0..height {
-        for x in 0..width {
-            // Translate to center
-            let dx = x as f32 - center_x;
-            let dy = y as f32 - center_y;
-
-            // Rotate
-            let rotated_x = dx * angle_radians.cos() - dy * angle_radians.sin();
-            let rotated_y = dx * angle_radians.sin() + dy * angle_radians.cos();
-
-            // Translate back
-            let source_x = (rotated_x + center_x) as i32;
-            let source_y = (rotated_y + center_y) as i32;
-
-            // Check bounds and copy pixel
-            if source_x >= 0 && source_x < width as i32 && source_y >= 0 && source_y < height as i32
-            {
-                let pixel = rgba_img.get_pixel(source_x as u32, source_y as u32);
-                rotated.put_pixel(x, y, *pixel);
+                // The border traces the outline left by the mask, or the canvas edge without one
+                if let Some(border) = border.filter(|_| border_platforms.contains(&platform)) {
+                    image = Some(apply_border(
+                        image.as_ref().unwrap_or(original),
+                        border,
+                        radius.unwrap_or(0.0),
+                    ));
+                }
+            }
+
+            // The shadow follows the final outline, so it comes last
+            if let Some(shadow) = shadow.filter(|_| shadow_platforms.contains(&platform)) {
+                image = Some(apply_drop_shadow(
+                    image.as_ref().unwrap_or(original),
+                    shadow,
+                    resampler,
+                ));
+            }
+
+            if let Some(image) = image {
+                treated.insert(platform, image);
             }
         }
+
+        let (original_mipmaps, mipmaps) = if resampler.mipmap {
+            let mipmaps = treated
+                .iter()
+                .map(|(&platform, image)| (platform, build_mipmaps(image, resampler.filter)))
+                .collect();
+            (build_mipmaps(original, resampler.filter), mipmaps)
+        } else {
+            (Vec::new(), HashMap::new())
+        };
+
+        Ok(Self {
+            original,
+            treated,
+            original_mipmaps,
+            mipmaps,
+            resampler,
+            size_rules: Vec::new(),
+            resized: Mutex::default(),
+        })
     }
 
-    DynamicImage::ImageRgba8(rotated)
-}
+    /// Add the sources for each `--size-rule`, treated with the rule's own settings
+    fn with_size_rules(mut self, rules: &[(&SizeRule, &'a DynamicImage)]) -> Result<Self> {
+        for (rule, source) in rules {
+            self.size_rules
+                .push((rule.below, PlatformSources::new(source, &rule.args)?));
+        }
+        // The tightest rule wins where several apply
+        self.size_rules.sort_by_key(|(below, _)| *below);
+        Ok(self)
+    }
 
-pub fn generate_icons(args: Args) -> Result<()> {
-    // Load source image
-    let source = load_image(&args.input)?;
+    /// The source to use for the given platform
+    pub(crate) fn get(&self, platform: &str) -> &DynamicImage {
+        self.treated.get(platform).unwrap_or(self.original)
+    }
 
-    // Ensure the output directory exists
-    std::fs::create_dir_all(&args.output).context("Can't create output directory")?;
+    /// The halving chain of the given platform's source, largest level first
+    fn mipmaps(&self, platform: &str) -> &[DynamicImage] {
+        self.mipmaps.get(platform).unwrap_or(&self.original_mipmaps)
+    }
 
-    // Check if any platform-specific flags are set
-    let has_platform_flags = args.windows || args.macos || args.linux || args.android || args.ios;
+    /// The sources for an output of `size` pixels, taking the `--size-rule`
+    /// overrides into account
+    fn for_size(&self, size: u32) -> &PlatformSources<'a> {
+        self.size_rules
+            .iter()
+            .find(|(below, _)| size < *below)
+            .map_or(self, |(_, sources)| sources)
+    }
 
-    // Determine which platforms should generate icons
-    let should_generate_ios = should_invoke_ios_writer(&args, has_platform_flags);
-    let should_generate_macos = should_invoke_macos_writer(&args, has_platform_flags);
+    /// `image` resized to `width` x `height` by `resize`, which only runs the first
+    /// time a source is resized to that size, e.g. for the 256px frame shared by
+    /// `icon.ico`, `icon.icns` and the linux icons
+    fn resized(
+        &self,
+        image: &DynamicImage,
+        width: u32,
+        height: u32,
+        resize: impl FnOnce() -> DynamicImage,
+    ) -> DynamicImage {
+        let key = (image as *const DynamicImage as usize, width, height);
+        let cell = self.resized.lock().unwrap().entry(key).or_default().clone();
+        // Other workers wait here for the resize in progress instead of repeating it
+        cell.get_or_init(resize).clone()
+    }
 
-    // Generate icons based on options
-    if args.tauri_desktop {
-        // Generate Tauri desktop icons (requires Windows, macOS, Linux icons first)
-        generate_ico(&source, &args.output, args.dev_mode, &args.dev_bug)?;
-        if should_generate_macos {
-            generate_icns(&source, &args.output, args.dev_mode, &args.dev_bug)?;
+    /// The resampler for the given platform's icons, which swaps in the
+    /// `--size-rule` sources whenever the platform source is resized
+    pub(crate) fn resampler(&self, platform: &'static str) -> Resampler<'_> {
+        Resampler {
+            sources: Some((self, platform)),
+            ..self.resampler
         }
-        generate_linux_icons(&source, &args.output, args.dev_mode, &args.dev_bug)?;
-        generate_tauri_desktop_icons(&source, &args.output, args.dev_mode, &args.dev_bug)?;
-    } else if args.desktop_only {
-        generate_desktop_only(&source, &args, should_generate_macos)?;
-    } else if args.mobile_only {
-        generate_mobile_only(&source, &args, should_generate_ios)?;
-    } else if has_platform_flags {
-        generate_platforms(&source, &args, should_generate_ios, should_generate_macos)?;
-    } else {
-        generate_all(&source, &args, should_generate_ios, should_generate_macos)?;
     }
-
-    Ok(())
 }
 
-fn load_image(path: &Path) -> Result<DynamicImage> {
-    let source = image::open(path).context("Failed to load image")?;
+/// A `--size-rule`: outputs smaller than `below` pixels get their own source treatment
+#[derive(Debug, Clone)]
+struct SizeRule {
+    below: u32,
+    /// The arguments with the rule's settings applied
+    args: Args,
+}
 
-    // Ensure the image is square
-    if source.width() != source.height() {
-        anyhow::bail!("Source image must be square (width == height)");
+/// Settings a `--size-rule` can override
+pub const SIZE_RULE_SETTINGS: &[&str] = &["source", "padding", "shadow", "radius", "border"];
+
+/// Parse a `--size-rule` given as `<SIZE:key=value,...>`, e.g. `<48:shadow=off,padding=0`
+fn parse_size_rule(spec: &str, args: &Args) -> Result<SizeRule> {
+    let (size, settings) = spec
+        .split_once(':')
+        .with_context(|| format!("Size rule must be given as <SIZE:key=value,...: {}", spec))?;
+    let below = size
+        .trim()
+        .strip_prefix('<')
+        .and_then(|size| size.trim().parse::<u32>().ok())
+        .filter(|&size| size > 1)
+        .with_context(|| format!("Invalid size in size rule, expected e.g. <48: {}", spec))?;
+
+    let mut rule_args = args.clone();
+    rule_args.size_rule.clear();
+    for setting in settings.split(',') {
+        let (key, value) = setting.split_once('=').with_context(|| {
+            format!("Size rule setting must be given as key=value: {}", setting)
+        })?;
+        let value = value.trim();
+        let off = value == "off";
+        match key.trim() {
+            "source" => {
+                rule_args.input = PathBuf::from(value);
+                rule_args.input_bytes = None;
+            }
+            "padding" => {
+                rule_args.padding = (!off)
+                    .then(|| value.parse::<f32>())
+                    .transpose()
+                    .with_context(|| format!("Invalid padding in size rule: {}", value))?
+            }
+            "shadow" => {
+                rule_args.shadow = match value {
+                    "on" => true,
+                    "off" => false,
+                    _ => anyhow::bail!("Size rule shadow must be on or off, got {}", value),
+                }
+            }
+            "radius" => rule_args.radius = (!off).then(|| value.to_string()),
+            "border" => rule_args.border = (!off).then(|| value.to_string()),
+            key => anyhow::bail!(
+                "Unknown size rule setting: {}. Available settings: {}",
+                key,
+                SIZE_RULE_SETTINGS.join(", ")
+            ),
+        }
     }
 
-    Ok(source)
+    Ok(SizeRule {
+        below,
+        args: rule_args,
+    })
 }
 
-/// Determine when the iOS writer should be invoked
-/// Only invoke when args.ios flag is on OR iOS icons are produced via generate_all/mobile_only
-fn should_invoke_ios_writer(args: &Args, has_platform_flags: bool) -> bool {
-    // If specific iOS flag is set
-    if args.ios {
-        return true;
+/// Resolve a `--*-for` platform list, where an empty list means every platform
+fn selected_platforms(names: &[String], flag: &str) -> Result<Vec<&'static str>> {
+    if names.is_empty() {
+        return Ok(PLATFORM_NAMES.to_vec());
     }
 
-    // If no platform flags are set (default case - generate_all)
-    if !has_platform_flags && !args.desktop_only && !args.mobile_only && !args.tauri_desktop {
-        return true;
-    }
+    names
+        .iter()
+        .map(|name| {
+            PLATFORM_NAMES
+                .iter()
+                .copied()
+                .find(|platform| platform == name)
+                .with_context(|| {
+                    format!(
+                        "Unknown platform for {}: {}. Available platforms: {}",
+                        flag,
+                        name,
+                        PLATFORM_NAMES.join(", ")
+                    )
+                })
+        })
+        .collect()
+}
 
-    // If mobile_only is set
-    if args.mobile_only {
-        return true;
-    }
+/// An ICNS member: its Contents.json size name, pixel size and OSType
+#[derive(Debug, Clone, Copy)]
+struct IcnsEntry {
+    name: &'static str,
+    size: u32,
+    ostype: &'static str,
+}
 
-    false
+/// A member type of icon.icns files: its OSType and pixel size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IcnsMember {
+    ostype: &'static str,
+    size: u32,
 }
 
-/// Determine when the macOS writer should be invoked
-/// Only invoke when macOS icons are produced (macos, desktop_only, tauri_desktop, default)
-fn should_invoke_macos_writer(args: &Args, has_platform_flags: bool) -> bool {
-    // If specific macOS flag is set
-    if args.macos {
-        return true;
+/// The member types `--icns-types` can select, in the order they are written:
+/// every type the icns crate encodes, and ic04/ic05, the 16px and 32px types
+/// that hold a PNG
+const ICNS_MEMBERS: &[IcnsMember] = &[
+    IcnsMember {
+        ostype: "is32",
+        size: 16,
+    },
+    IcnsMember {
+        ostype: "icp4",
+        size: 16,
+    },
+    IcnsMember {
+        ostype: "ic04",
+        size: 16,
+    },
+    IcnsMember {
+        ostype: "ic11",
+        size: 32,
+    },
+    IcnsMember {
+        ostype: "il32",
+        size: 32,
+    },
+    IcnsMember {
+        ostype: "icp5",
+        size: 32,
+    },
+    IcnsMember {
+        ostype: "ic05",
+        size: 32,
+    },
+    IcnsMember {
+        ostype: "ih32",
+        size: 48,
+    },
+    IcnsMember {
+        ostype: "ic12",
+        size: 64,
+    },
+    IcnsMember {
+        ostype: "icp6",
+        size: 64,
+    },
+    IcnsMember {
+        ostype: "it32",
+        size: 128,
+    },
+    IcnsMember {
+        ostype: "ic07",
+        size: 128,
+    },
+    IcnsMember {
+        ostype: "ic13",
+        size: 256,
+    },
+    IcnsMember {
+        ostype: "ic08",
+        size: 256,
+    },
+    IcnsMember {
+        ostype: "ic14",
+        size: 512,
+    },
+    IcnsMember {
+        ostype: "ic09",
+        size: 512,
+    },
+    IcnsMember {
+        ostype: "ic10",
+        size: 1024,
+    },
+];
+
+/// Named member sets of `--icns-types`: `default` with the legacy is32/il32
+/// members for the 1x sizes, `modern` with the PNG ic04/ic05 members instead,
+/// and `retina` with nothing but the @2x members
+pub const ICNS_TYPE_PRESETS: &[(&str, &[&str])] = &[
+    (
+        "default",
+        &[
+            "is32", "ic11", "il32", "ic12", "ic07", "ic13", "ic08", "ic14", "ic09", "ic10",
+        ],
+    ),
+    (
+        "modern",
+        &[
+            "ic04", "ic11", "ic05", "ic12", "ic07", "ic13", "ic08", "ic14", "ic09", "ic10",
+        ],
+    ),
+    ("retina", &["ic11", "ic12", "ic13", "ic14", "ic10"]),
+];
+
+/// The members of icon.icns files that `--icns-types` selects: a preset name or
+/// a comma-separated list of OSTypes, written in the order of `ICNS_MEMBERS`
+fn icns_members(types: &str) -> Result<Vec<IcnsMember>> {
+    let ostypes = match ICNS_TYPE_PRESETS.iter().find(|(name, _)| *name == types) {
+        Some((_, ostypes)) => ostypes.to_vec(),
+        None => types.split(',').map(str::trim).collect(),
+    };
+    for ostype in &ostypes {
+        if !ICNS_MEMBERS.iter().any(|member| member.ostype == *ostype) {
+            anyhow::bail!(
+                "Unknown ICNS type: {}. Available types: {}, or a preset: {}",
+                ostype,
+                ICNS_MEMBERS
+                    .iter()
+                    .map(|member| member.ostype)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                ICNS_TYPE_PRESETS
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
     }
-
-    // If desktop_only is set
-    if args.desktop_only {
-        return true;
+    // Presets keep their own order, so that the default icon.icns stays as it was
+    if let Some((_, preset)) = ICNS_TYPE_PRESETS.iter().find(|(name, _)| *name == types) {
+        return Ok(preset
+            .iter()
+            .filter_map(|ostype| ICNS_MEMBERS.iter().find(|member| member.ostype == *ostype))
+            .copied()
+            .collect());
     }
+    Ok(ICNS_MEMBERS
+        .iter()
+        .filter(|member| ostypes.contains(&member.ostype))
+        .copied()
+        .collect())
+}
 
-    // If tauri_desktop is set
-    if args.tauri_desktop {
-        return true;
-    }
+/// Release channels accepted by `--channel`
+pub const CHANNEL_NAMES: &[&str] = &["dev", "alpha", "beta", "rc", "nightly"];
 
-    // If no platform flags are set (default case - generate_all)
-    if !has_platform_flags && !args.desktop_only && !args.mobile_only && !args.tauri_desktop {
-        return true;
+/// Fill in the badge options implied by `--channel`: a ribbon in the channel's
+/// color, the dev bug for `dev`, and with `--channel-tint` a multiply tint
+/// Options given explicitly are left alone
+fn apply_channel(mut args: Args) -> Result<Args> {
+    let Some(channel) = args.channel.as_deref() else {
+        return Ok(args);
+    };
+    let (label, color) = match channel {
+        "dev" => ("DEV", "#1e88e5"),
+        "alpha" => ("ALPHA", "#8e24aa"),
+        "beta" => ("BETA", "#e53935"),
+        "rc" => ("RC", "#43a047"),
+        "nightly" => ("NIGHTLY", "#37474f"),
+        _ => anyhow::bail!(
+            "Unknown channel: {}. Available channels: {}",
+            channel,
+            CHANNEL_NAMES.join(", ")
+        ),
+    };
+
+    if channel == "dev" {
+        args.dev_mode = true;
+    }
+    args.badge_ribbon
+        .get_or_insert_with(|| format!("{label}:{color}"));
+    if args.channel_tint && args.tint.is_none() {
+        args.tint = Some(color.to_string());
+        args.tint_mode = "multiply".to_string();
     }
 
-    false
+    Ok(args)
 }
 
-fn generate_all(
-    source: &DynamicImage,
-    args: &Args,
-    should_generate_ios: bool,
-    should_generate_macos: bool,
-) -> Result<()> {
-    if let Some(sizes) = &args.png {
-        generate_custom_sizes(source, sizes, &args.output, args.dev_mode, &args.dev_bug)?;
-    } else {
-        // Generate default formats when no specific platform flags are set
-        generate_ico(source, &args.output, args.dev_mode, &args.dev_bug)?;
+pub fn generate_icons(args: Args) -> error::Result<()> {
+    generate(args).map_err(IconGenError::from_anyhow)
+}
 
-        if should_generate_macos {
-            generate_icns(&source, &args.output, args.dev_mode, &args.dev_bug)?;
+fn generate(args: Args) -> Result<()> {
+    if let Some(format) = &args.report {
+        if !REPORT_FORMATS.contains(&format.as_str()) {
+            anyhow::bail!(
+                "Unknown report format: {}. Available formats: {}",
+                format,
+                REPORT_FORMATS.join(", ")
+            );
         }
+    }
 
-        generate_linux_icons(source, &args.output, args.dev_mode, &args.dev_bug)?;
-        generate_tauri_desktop_icons(source, &args.output, args.dev_mode, &args.dev_bug)?;
-        generate_mobile(source, args, should_generate_ios)?;
+    if args.progress == "json" && (args.report.is_some() || args.dry_run) {
+        anyhow::bail!("--progress json can't be combined with --report or --dry-run, which print to stdout as well");
+    }
+    if args.dry_run {
+        return generate_dry_run(args);
     }
 
+    let report = match args.archive.clone() {
+        Some(archive) => generate_archive(args, &archive)?,
+        None => generate_reported(args, &FileSystemSink)?,
+    };
+    if let Some(report) = report {
+        println!("{}", report.to_json());
+    }
     Ok(())
 }
 
-fn generate_desktop_only(
-    source: &DynamicImage,
-    args: &Args,
-    should_generate_macos: bool,
-) -> Result<()> {
-    if let Some(sizes) = &args.png {
-        generate_custom_sizes(source, sizes, &args.output, args.dev_mode, &args.dev_bug)?;
-    } else {
-        generate_ico(source, &args.output, args.dev_mode, &args.dev_bug)?;
+/// Generate through `output`, describing every file when `--report` is set
+fn generate_reported(args: Args, output: &dyn OutputSink) -> Result<Option<RunReport>> {
+    if args.report.is_none() {
+        generate_into(args, output)?;
+        return Ok(None);
+    }
+    let sink = ReportingSink::new(output);
+    generate_into(args, &sink)?;
+    Ok(Some(sink.into_report()))
+}
 
-        if should_generate_macos {
-            generate_icns(source, &args.output, args.dev_mode, &args.dev_bug)?;
+/// Print every file a run would generate (`--dry-run`), as a list or with
+/// `--report json` as JSON, by generating into memory from a blank stand-in
+/// for the source; the cache isn't consulted and nothing is written
+fn generate_dry_run(args: Args) -> Result<()> {
+    let archive = args.archive.clone();
+    let json = args.report.is_some();
+    let memory = MemorySink::new();
+    let sink = ReportingSink::new(&memory);
+    generate_into(args, &sink)?;
+    let plan = sink.into_report();
+
+    if json {
+        println!("{}", plan.to_plan_json());
+        return Ok(());
+    }
+    for file in &plan.files {
+        match (file.width, file.height) {
+            (Some(width), Some(height)) => {
+                println!("{} ({width}x{height})", file.path.display())
+            }
+            _ => println!("{}", file.path.display()),
         }
-
-        generate_linux_icons(source, &args.output, args.dev_mode, &args.dev_bug)?;
-        generate_tauri_desktop_icons(source, &args.output, args.dev_mode, &args.dev_bug)?;
+    }
+    match archive {
+        Some(archive) => println!(
+            "{} files would be written into {}",
+            plan.files.len(),
+            archive.display()
+        ),
+        None => println!("{} files would be generated", plan.files.len()),
     }
     Ok(())
 }
 
-fn generate_mobile_only(
-    source: &DynamicImage,
-    args: &Args,
-    should_generate_ios: bool,
-) -> Result<()> {
-    generate_mobile(source, args, should_generate_ios)?;
-    Ok(())
+/// How a run with `args` reports its progress
+fn progress_mode(args: &Args) -> Result<progress::Mode> {
+    Ok(match args.progress.as_str() {
+        "auto" if args.report.is_some() || args.dry_run => progress::Mode::Quiet,
+        "auto" => progress::Mode::Auto,
+        "json" => progress::Mode::Json,
+        mode => anyhow::bail!(
+            "Unknown progress mode: {}. Available modes: {}",
+            mode,
+            PROGRESS_MODES.join(", ")
+        ),
+    })
 }
 
-fn generate_platforms(
-    source: &DynamicImage,
-    args: &Args,
-    should_generate_ios: bool,
-    should_generate_macos: bool,
-) -> Result<()> {
-    let has_desktop_platform = args.windows || args.macos || args.linux;
-
-    if args.windows {
-        generate_ico(source, &args.output, args.dev_mode, &args.dev_bug)?;
+/// Generate every file into a single archive (`--archive`), named by its path
+/// relative to the parent of the output directory, e.g. `icons/windows/icon.ico`
+/// and `icons_gray/windows/icon.ico` for a variant
+fn generate_archive(args: Args, archive: &Path) -> Result<Option<RunReport>> {
+    let format = ArchiveFormat::from_path(archive)?;
+    let root = args.output.parent().unwrap_or(Path::new("")).to_path_buf();
+    let sink = ArchiveSink::new(root, format);
+    let mode = progress_mode(&args)?;
+    let report = generate_reported(args, &sink)?;
+
+    if let Some(parent) = archive
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Can't create output directory {}", parent.display()))?;
     }
-
-    if args.macos && should_generate_macos {
-        generate_icns(source, &args.output, args.dev_mode, &args.dev_bug)?;
+    write_output(archive, &sink.finish()?)?;
+    // The run is over, so the note is only printed where it printed its log
+    if mode == progress::Mode::Auto {
+        println!("✓ Wrote {}", archive.display());
     }
+    Ok(report)
+}
 
-    if args.linux {
-        if let Some(sizes) = &args.png {
-            generate_custom_sizes(source, sizes, &args.output, args.dev_mode, &args.dev_bug)?;
-        } else {
-            generate_linux_icons(source, &args.output, args.dev_mode, &args.dev_bug)?;
+/// Generate every file into memory instead of the output directory, keyed by
+/// the path it would be written to, e.g. `<output>/windows/icon.ico`
+/// Variant sets keep their sibling directories, e.g. `<output>_gray/linux/32x32.png`,
+/// and the generation cache is neither read nor written
+pub fn generate_to_memory(args: Args) -> error::Result<BTreeMap<PathBuf, Vec<u8>>> {
+    let output = MemorySink::new();
+    generate_into(args, &output)?;
+    Ok(output.into_files())
+}
+
+/// Generate every file through `output`, e.g. an `ArchiveSink` or a sink of your own
+pub fn generate_into(args: Args, output: &dyn OutputSink) -> error::Result<()> {
+    generate_through(args, output).map_err(IconGenError::from_anyhow)
+}
+
+fn generate_through(args: Args, output: &dyn OutputSink) -> Result<()> {
+    let args = apply_channel(args)?;
+
+    if let Some(colors) = args.quantize {
+        if !(2..=256).contains(&colors) {
+            anyhow::bail!("Invalid --quantize value: {colors}. Expected 2 to 256 colors");
         }
     }
 
-    // Generate tauri-desktop icons when any desktop platform is enabled
-    if has_desktop_platform {
-        generate_tauri_desktop_icons(source, &args.output, args.dev_mode, &args.dev_bug)?;
+    if let Some(color) = &args.padding_color {
+        parse_color_checked("--padding-color", color)?;
+    }
+    parse_color_checked("--background", &args.background)?;
+    parse_color_checked("--android-adaptive-bg", &args.android_adaptive_bg)?;
+    if args.ios_splash || args.android_splash || args.pwa_startup {
+        parse_color_checked("--splash-bg", &args.splash_bg)?;
+    }
+    if !args.preset.is_empty() {
+        parse_color_checked("--preset-bg", &args.preset_bg)?;
     }
 
-    if args.android {
-        generate_android_icons_extended(source, args)?;
+    let resampler = Resampler::from_args(&args)?;
+    // Fail on a missing or malformed overlay or badge option before writing anything
+    Overlays::from_args(&args)?;
+
+    match args.compression.as_str() {
+        "best" | "zopfli" => {}
+        compression => anyhow::bail!(
+            "Unknown compression: {}. Available modes: best, zopfli",
+            compression
+        ),
+    }
+    if !PNG_METADATA_MODES.contains(&args.png_metadata.as_str()) {
+        anyhow::bail!(
+            "Unknown PNG metadata mode: {}. Available modes: {}",
+            args.png_metadata,
+            PNG_METADATA_MODES.join(", ")
+        );
     }
 
-    if args.ios && should_generate_ios {
-        generate_ios_icons(
-            source,
-            &args.output,
-            &args.ios_color,
-            args.dev_mode,
-            &args.dev_bug,
-        )?;
+    if !ENCODE_PROFILES.contains(&args.encode_profile.as_str()) {
+        anyhow::bail!(
+            "Unknown encode profile: {}. Available profiles: {}",
+            args.encode_profile,
+            ENCODE_PROFILES.join(", ")
+        );
+    }
+    ios_icon_dir(&args)?;
+    icns_members(&args.icns_types)?;
+    if !CONTENTS_JSON_MODES.contains(&args.contents_json.as_str()) {
+        anyhow::bail!(
+            "Unknown Contents.json mode: {}. Available modes: {}",
+            args.contents_json,
+            CONTENTS_JSON_MODES.join(", ")
+        );
+    }
+    if !SAFE_ZONE_MODES.contains(&args.safe_zone.as_str()) {
+        anyhow::bail!(
+            "Unknown safe zone mode: {}. Available modes: {}",
+            args.safe_zone,
+            SAFE_ZONE_MODES.join(", ")
+        );
     }
 
+    let mode = progress_mode(&args)?;
+
+    if args.max_working_size == 0 {
+        anyhow::bail!("Invalid --max-working-size value: 0. Expected at least 1");
+    }
+
+    // Sizes are resized and encoded in parallel; rayon uses every core by default.
+    // wasm32 can't start threads, but rayon's global pool falls back to running
+    // everything on the calling thread there
+    if args.jobs == Some(0) {
+        anyhow::bail!("Invalid --jobs value: 0. Expected at least 1");
+    }
+    if [args.force, args.clean, args.no_clobber]
+        .iter()
+        .filter(|&&set| set)
+        .count()
+        > 1
+    {
+        anyhow::bail!("Only one of --force, --clean and --no-clobber can be given");
+    }
+    validate_name_template(&args)?;
+    if args.clean && (args.skip_existing || args.if_newer) {
+        anyhow::bail!("--clean regenerates every set, so it can't be combined with --skip-existing or --if-newer");
+    }
+    let output = &ProgressSink(output);
+    // The run reports its progress and counts its warnings apart from any other
+    // run at the same time, on this thread and the workers of its own pool
+    let run = Arc::new(progress::Run::default());
+    let _entered = run.enter();
+    let result = if cfg!(target_arch = "wasm32") {
+        generate_with_manifest(&args, resampler, mode, output)
+    } else {
+        let workers = Arc::clone(&run);
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(args.jobs.unwrap_or(0))
+            .start_handler(move |_| workers.adopt())
+            .build()
+            .context("Failed to start the worker threads")?
+            .install(|| generate_with_manifest(&args, resampler, mode, output))
+    };
+    progress::finish();
+    result?;
+    let count = run.warnings();
+    if args.strict && count > 0 {
+        return Err(IconGenError::Warnings { count }.into());
+    }
     Ok(())
 }
 
-fn generate_ico(
-    source: &DynamicImage,
-    out_dir: &Path,
-    dev_mode: bool,
-    dev_bug: &str,
+/// Generate every set, followed by their `icons.manifest.json` with `--manifest`
+fn generate_with_manifest(
+    args: &Args,
+    resampler: Resampler<'_>,
+    mode: progress::Mode,
+    output: &dyn OutputSink,
 ) -> Result<()> {
-    let windows_dir = out_dir.join("windows");
-    create_dir_all(&windows_dir)?;
+    if !args.manifest {
+        return generate_sets(args, resampler, mode, output);
+    }
+    // The manifests cover the files kept from earlier runs as well as the written ones
+    let sink = ReportingSink::new(output);
+    generate_sets(args, resampler, mode, &sink)?;
+    write_manifests(&args.output, &sink.into_report(), output)
+}
 
-    println!("Generating windows/icon.ico...");
-    let mut frames = Vec::new();
+/// Prepare the source, its size rules and variants, and generate every set
+fn generate_sets(
+    args: &Args,
+    resampler: Resampler<'_>,
+    mode: progress::Mode,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    // Every size rule brings its own source, prepared the same way
+    let mut rules = args
+        .size_rule
+        .iter()
+        .map(|spec| parse_size_rule(spec, args))
+        .collect::<Result<Vec<_>>>()?;
+    let (source, scale) = prepare_source(args, resampler)?;
+    let args = &scale_pixel_options(args, scale);
+    let mut rule_sources = Vec::new();
+    for rule in &mut rules {
+        let (rule_source, scale) = prepare_source(&rule.args, resampler)?;
+        rule.args = scale_pixel_options(&rule.args, scale);
+        rule_sources.push(rule_source);
+    }
 
-    // Common ICO sizes
-    for size in [16, 24, 32, 48, 64, 256] {
-        let mut resized = source.resize_exact(size, size, FilterType::Lanczos3);
+    // Check the variants up front so nothing is written on a typo; their sources
+    // are only derived when their set is generated, keeping one set in memory at a time
+    let specs = args
+        .variant
+        .iter()
+        .chain(&args.hue_variants)
+        .collect::<Vec<_>>();
+    let probe = DynamicImage::new_rgba8(1, 1);
+    for index in 0..specs.len() {
+        derive_variant(&probe, index, args)?;
+    }
 
-        // Apply dev badge before encoding
-        if dev_mode {
-            apply_dev_badge_with_bug(&mut resized, dev_bug, 0.0)?;
+    // Sets whose inputs and options are unchanged since the last run are skipped;
+    // there is nothing to compare against when the files don't outlive the run
+    let mut cache = if output.is_persistent() {
+        std::fs::create_dir_all(&args.output).context("Can't create output directory")?;
+        Some(GenerationCache::load(&args.output))
+    } else {
+        None
+    };
+    progress::start(1 + specs.len(), mode);
+    let inputs = input_files(args, &rules);
+    let fingerprint = input_fingerprint(args, &inputs)?;
+    let mut failures = Failures::default();
+    let mut recorded = false;
+    let mut generate_cached = |source: &DynamicImage,
+                               size_rules: &[(&SizeRule, &DynamicImage)],
+                               set_args: &Args,
+                               spec: &str| {
+        let target = set_args
+            .output
+            .file_name()
+            .map_or_else(|| ".".into(), |name| name.to_string_lossy());
+        let fingerprint = fingerprint.add(spec.as_bytes()).hex();
+        progress::begin_set(&set_args.output);
+        let Some(cache) = cache.as_mut() else {
+            return generate_set(source, size_rules, set_args, &mut failures, output);
+        };
+        let existing = set_files(&set_args.output)?;
+        let generated = cache.generated_files(&target, &set_args.output);
+        // Only the set's own files count, not those of the project it was written into
+        let owned = existing
+            .iter()
+            .filter(|path| generated.contains(*path))
+            .cloned()
+            .collect::<Vec<_>>();
+        let keep_owned = || owned.iter().try_for_each(|path| output.keep(path));
+        // A strict run has to see the warnings of every set, so nothing counts as up to date
+        if !args.no_cache
+            && !args.clean
+            && !args.strict
+            && cache.is_fresh(&target, &fingerprint, &set_args.output)
+        {
+            progress::note(format_args!(
+                "✓ {} is up to date",
+                set_args.output.display()
+            ));
+            return Ok(keep_owned()?);
         }
+        if args.skip_existing && !owned.is_empty() {
+            progress::note(format_args!(
+                "✓ {} already exists, skipped",
+                set_args.output.display()
+            ));
+            return Ok(keep_owned()?);
+        }
+        if args.if_newer && !owned.is_empty() && outputs_are_newer(&owned, &inputs)? {
+            progress::note(format_args!(
+                "✓ {} is newer than its inputs, skipped",
+                set_args.output.display()
+            ));
+            return Ok(keep_owned()?);
+        }
+        let mut guard = OverwriteGuard::new(output, args, &existing, &generated);
+        let (failed, succeeded) = (failures.failed.len(), failures.generated);
+        generate_set(source, size_rules, set_args, &mut failures, &guard)?;
+        guard.check()?;
+        if let Err(error) = guard.commit() {
+            // A file held back for the protected ones can't be put down to its
+            // platform, so --keep-going generates the set again, writing as it goes
+            if !args.keep_going || interrupt::is_interrupted() {
+                return Err(error.into());
+            }
+            failures.failed.truncate(failed);
+            failures.generated = succeeded;
+            guard.write_through();
+            generate_set(source, size_rules, set_args, &mut failures, &guard)?;
+        }
+        // A set with failed platforms is incomplete, so it's generated again next time
+        if failures.failed.len() > failed {
+            return Ok(());
+        }
+        if args.clean {
+            guard.remove_stale(&set_args.output)?;
+        }
+        cache.record(
+            &target,
+            &fingerprint,
+            &set_args.output,
+            &guard.owned_files(),
+        )?;
+        recorded = true;
+        Ok(())
+    };
 
-        let rgba_image = resized.to_rgba8();
-
-        // Only the 256px layer can be compressed according to the ico specs
-        if size == 256 {
-            let mut buf = Vec::new();
-            write_png(rgba_image.as_raw(), &mut buf, size)?;
-            frames.push(IcoFrame::with_encoded(buf, size, size, ColorType::Rgba8)?);
-        } else {
-            frames.push(IcoFrame::as_png(
-                rgba_image.as_raw(),
-                size,
-                size,
-                ColorType::Rgba8,
-            )?);
+    let mut generate_all = || -> Result<()> {
+        let size_rules = rules.iter().zip(&rule_sources).collect::<Vec<_>>();
+        generate_cached(&source, &size_rules, args, "")?;
+
+        // Each variant is a full parallel set in a sibling directory, e.g. icons_gray/
+        for (index, spec) in specs.iter().enumerate() {
+            let (suffix, variant_source) = derive_variant(&source, index, args)?;
+            let mut variant_output = args.output.clone().into_os_string();
+            variant_output.push(suffix);
+            let variant_args = Args {
+                output: variant_output.into(),
+                ..args.clone()
+            };
+            let rule_variants = rule_sources
+                .iter()
+                .map(|rule_source| Ok(derive_variant(rule_source, index, args)?.1))
+                .collect::<Result<Vec<_>>>()?;
+            let size_rules = rules.iter().zip(&rule_variants).collect::<Vec<_>>();
+
+            progress::step(format_args!(
+                "Generating {} variant...",
+                variant_args.output.display()
+            ));
+            generate_cached(&variant_source, &size_rules, &variant_args, spec)?;
         }
+        Ok(())
+    };
+    // Sets finished before a failure or Ctrl-C stay cached; the one cut short
+    // isn't recorded, so the next run generates it again. A run that fails
+    // before finishing any set leaves the cache file as it was
+    let result = generate_all();
+    if result.is_ok() || recorded {
+        cache.map_or(Ok(()), GenerationCache::save)?;
     }
+    result?;
+    failures.finish()
+}
 
-    let mut out_file = BufWriter::new(File::create(windows_dir.join("icon.ico"))?);
-    let encoder = IcoEncoder::new(&mut out_file);
-    encoder.encode_images(&frames)?;
-    out_file.flush()?;
-
-    println!("✓ Generated windows/icon.ico");
-    Ok(())
+/// Every file a set is generated from: the source, unless it was given as bytes,
+/// and the files named by the options
+fn input_files<'a>(args: &'a Args, rules: &'a [SizeRule]) -> Vec<&'a Path> {
+    let mut files = std::iter::once(args)
+        .chain(rules.iter().map(|rule| &rule.args))
+        .filter(|args| args.input_bytes.is_none())
+        .map(|args| args.input.as_path())
+        .collect::<Vec<_>>();
+    files.extend(args.background_image.as_deref());
+    files.extend(args.dev_badge_file.as_deref());
+    files.extend(args.stamp_font.as_deref());
+    files.extend(args.overlay.iter().map(|spec| overlay_file(spec)));
+    files.extend(args.cursor_frames.iter().flatten().map(PathBuf::as_path));
+    files.extend(args.spec.as_deref());
+    files
 }
 
-fn generate_icns(
-    source: &DynamicImage,
+/// Whether every output was written after the last change to any input (`--if-newer`)
+fn outputs_are_newer(outputs: &[PathBuf], inputs: &[&Path]) -> Result<bool> {
+    let modified = |path: &Path| {
+        std::fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .with_context(|| format!("Failed to read the modification time of {}", path.display()))
+    };
+
+    let mut newest_input = None;
+    for input in inputs {
+        newest_input = newest_input.max(Some(modified(input)?));
+    }
+    for output in outputs {
+        if Some(modified(output)?) < newest_input {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Fingerprint of everything a set is generated from: the tool version, the
+/// options and the contents of every input file
+/// The variant and output options are left out, as each set is keyed by its own
+/// directory and variant, and `--jobs`, `--report`, `--manifest` and `--progress`
+/// don't change the icons
+fn input_fingerprint(args: &Args, files: &[&Path]) -> Result<Fingerprint> {
+    let options = Args {
+        input: PathBuf::new(),
+        input_bytes: None,
+        output: PathBuf::new(),
+        variant: Vec::new(),
+        hue_variants: Vec::new(),
+        report: None,
+        manifest: false,
+        progress: String::new(),
+        jobs: None,
+        no_cache: false,
+        skip_existing: false,
+        if_newer: false,
+        force: false,
+        clean: false,
+        no_clobber: false,
+        keep_going: false,
+        verify: false,
+        strict: false,
+        ..args.clone()
+    };
+
+    let mut fingerprint = Fingerprint::default()
+        .add(env!("CARGO_PKG_VERSION").as_bytes())
+        .add(format!("{options:?}").as_bytes())
+        .add(args.input_bytes.as_deref().unwrap_or_default());
+    for file in files {
+        fingerprint = fingerprint.add_file(file)?;
+    }
+    Ok(fingerprint)
+}
+
+/// Load the source and apply the treatments shared by every target:
+/// adjustments, tint, padding and `--flatten`
+/// Returns the factor the source was downscaled by to fit `--max-working-size`
+fn prepare_source(args: &Args, resampler: Resampler<'_>) -> Result<(DynamicImage, f32)> {
+    let mut source = load_source(args)?;
+
+    // Downscale an enormous source once, rather than for every target and treatment
+    let working_size = args
+        .png
+        .iter()
+        .flatten()
+        .fold(args.max_working_size, |max, &size| max.max(size));
+    let scale = if source.width() > working_size {
+        let scale = working_size as f32 / source.width() as f32;
+        source = resampler.resize(&source, working_size, working_size);
+        scale
+    } else {
+        1.0
+    };
+
+    if !args.adjust.is_empty() {
+        source = apply_adjustments(&source, &args.adjust)?;
+    }
+
+    // Tint before padding so that a --padding-color keeps its own color
+    if let Some(tint) = &args.tint {
+        let color = parse_color_checked("--tint color", tint)?;
+        source = apply_tint(&source, color, &args.tint_mode)?;
+    }
+
+    // Padding applies to every target, so it is baked into the source itself
+    if let Some(padding) = args.padding {
+        let bg_color = args
+            .padding_color
+            .as_deref()
+            .map(|color| parse_color_checked("--padding-color", color))
+            .transpose()?
+            .unwrap_or(Rgba([0, 0, 0, 0]));
+        source = apply_padding(&source, padding, bg_color, resampler)?;
+    }
+
+    // After padding, so that the margin is filled as well
+    if args.flatten {
+        source = flatten_alpha(&source, parse_color(&args.background));
+    }
+
+    Ok((source, scale))
+}
+
+/// Scale the options given in source pixels (`--radius`, `--border`, the shadow and the
+/// cursor hotspot) by the factor the source was downscaled by, so they keep their meaning
+/// Malformed values are left as they are for validation to report
+fn scale_pixel_options(args: &Args, scale: f32) -> Args {
+    if scale == 1.0 {
+        return args.clone();
+    }
+
+    // Percentages don't parse here and are kept, as they scale with the source anyway
+    let radius = args.radius.as_ref().map(|radius| {
+        match radius.trim().trim_end_matches("px").trim().parse::<f32>() {
+            Ok(value) => format!("{}px", value * scale),
+            Err(_) => radius.clone(),
+        }
+    });
+    let border = args.border.as_ref().map(|border| {
+        let parsed = border.split_once(',').and_then(|(width, color)| {
+            let width = width.trim().trim_end_matches("px").parse::<f32>().ok()?;
+            Some(format!("{},{}", width * scale, color))
+        });
+        parsed.unwrap_or_else(|| border.clone())
+    });
+
+    Args {
+        radius,
+        border,
+        shadow_blur: args.shadow_blur * scale,
+        shadow_offset: args
+            .shadow_offset
+            .iter()
+            .map(|offset| (*offset as f32 * scale).round() as i32)
+            .collect(),
+        cursor_hotspot: args
+            .cursor_hotspot
+            .iter()
+            .map(|coordinate| (*coordinate as f32 * scale) as u32)
+            .collect(),
+        ..args.clone()
+    }
+}
+
+/// Derive the source of the `index`th variant set, counting `--variant` then `--hue-variants`,
+/// with its output suffix
+fn derive_variant(
+    source: &DynamicImage,
+    index: usize,
+    args: &Args,
+) -> Result<(String, DynamicImage)> {
+    match args.variant.get(index) {
+        Some(name) => apply_variant(source, name),
+        None => apply_hue_variant(source, &args.hue_variants[index - args.variant.len()]),
+    }
+}
+
+/// Generate every selected target from a loaded source into `args.output`
+fn generate_set(
+    source: &DynamicImage,
+    size_rules: &[(&SizeRule, &DynamicImage)],
+    args: &Args,
+    failures: &mut Failures,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let resampler = Resampler::from_args(args)?;
+    let overlays = Overlays::from_args(args)?;
+    let sources = PlatformSources::new(source, args)?.with_size_rules(size_rules)?;
+
+    // Check if any platform-specific flags are set
+    let has_platform_flags = args.windows
+        || args.macos
+        || args.linux
+        || args.android
+        || args.ios
+        || args.tray
+        || args.imessage
+        || args.app_clip
+        || args.ios_splash
+        || args.android_splash
+        || args.pwa_startup
+        || args.cursor
+        || args.animated_favicon
+        || !args.preset.is_empty()
+        || args.spec.is_some()
+        || !args.custom_platforms.is_empty();
+
+    // Determine which platforms should generate icons
+    let should_generate_ios = should_invoke_ios_writer(args, has_platform_flags);
+    let should_generate_macos = should_invoke_macos_writer(args, has_platform_flags);
+
+    // Generate icons based on options
+    if args.tauri_desktop {
+        // Generate Tauri desktop icons (requires Windows, macOS, Linux icons first)
+        failures.attempt(args, "windows", || {
+            emit_platform(&WindowsIcons, &sources, args, &overlays, output)
+        })?;
+        if should_generate_macos {
+            failures.attempt(args, "macos", || {
+                emit_platform(&MacosIcons, &sources, args, &overlays, output)
+            })?;
+        }
+        failures.attempt(args, "linux", || {
+            emit_platform(&LinuxIcons, &sources, args, &overlays, output)
+        })?;
+        failures.attempt(args, "tauri-desktop", || {
+            generate_tauri_desktop_icons(
+                sources.get("linux"),
+                args,
+                sources.resampler("linux"),
+                &overlays,
+                output,
+            )
+        })?;
+    } else if args.desktop_only {
+        generate_desktop_only(&sources, args, should_generate_macos, failures, output)?;
+    } else if args.mobile_only {
+        generate_mobile_only(&sources, args, should_generate_ios, failures, output)?;
+    } else if has_platform_flags {
+        generate_platforms(
+            &sources,
+            args,
+            should_generate_ios,
+            should_generate_macos,
+            failures,
+            output,
+        )?;
+    } else {
+        generate_all(
+            &sources,
+            args,
+            should_generate_ios,
+            should_generate_macos,
+            failures,
+            output,
+        )?;
+    }
+
+    // Resource scripts accompany icon.ico whenever it was generated in this run,
+    // not an icon.ico left by an earlier one
+    if args.windows_rc && failures.generated(args, "windows") {
+        failures.attempt(args, "windows-rc", || {
+            generate_windows_resources(&args.output, output)
+        })?;
+    }
+
+    // Squircle previews accompany the iOS icon set whenever it was generated in this run
+    if args.ios_squircle && failures.generated(args, "ios") {
+        failures.attempt(args, "ios-squircle", || {
+            generate_ios_squircle_previews(
+                sources.get("ios"),
+                &args.output,
+                &args.background,
+                sources.resampler("ios"),
+                &overlays,
+                args.png_encoding(),
+                output,
+            )
+        })?;
+    }
+
+    if args.imessage {
+        failures.attempt(args, "imessage", || {
+            generate_imessage_icons(
+                source,
+                &args.output,
+                &args.background,
+                resampler,
+                &overlays,
+                args,
+                output,
+            )
+        })?;
+    }
+
+    if args.app_clip {
+        failures.attempt(args, "app-clip", || {
+            generate_app_clip_icons(
+                source,
+                &args.output,
+                &args.background,
+                resampler,
+                &overlays,
+                args,
+                output,
+            )
+        })?;
+    }
+
+    if args.ios_splash {
+        failures.attempt(args, "ios-splash", || {
+            generate_ios_splash(
+                source,
+                &args.output,
+                &args.splash_bg,
+                resampler,
+                &overlays,
+                args,
+                output,
+            )
+        })?;
+    }
+
+    if args.android_splash {
+        failures.attempt(args, "android-splash", || {
+            generate_android_splash(
+                source,
+                &args.output,
+                &args.splash_bg,
+                resampler,
+                &overlays,
+                args.png_encoding(),
+                output,
+            )
+        })?;
+    }
+
+    if args.pwa_startup {
+        failures.attempt(args, "pwa-startup", || {
+            generate_pwa_startup_images(
+                source,
+                &args.output,
+                &args.splash_bg,
+                resampler,
+                &overlays,
+                args.png_encoding(),
+                output,
+            )
+        })?;
+    }
+
+    if args.tray {
+        failures.attempt(args, "tray", || {
+            generate_tray_icons(
+                sources.get("tray"),
+                args,
+                sources.resampler("tray"),
+                &overlays,
+                output,
+            )
+        })?;
+    }
+
+    // Cursors are only produced on request, alongside whatever else was selected
+    if args.cursor {
+        failures.attempt(args, "cursor", || generate_cursor(source, args, output))?;
+    }
+
+    if args.animated_favicon {
+        failures.attempt(args, "animated-favicon", || {
+            generate_animated_favicon(args, output)
+        })?;
+    }
+
+    for preset in &args.preset {
+        failures.attempt(args, preset, || {
+            generate_preset(source, preset, args, output)
+        })?;
+    }
+
+    if let Some(spec) = &args.spec {
+        failures.attempt(args, "spec", || {
+            let targets = SpecTargets(load_spec(spec)?);
+            emit_platform(&targets, &sources, args, &overlays, output)
+        })?;
+    }
+
+    for generator in &args.custom_platforms {
+        progress::step(format_args!("Generating {} icons...", generator.name()));
+        failures.attempt(args, generator.name(), || {
+            emit_platform(generator.as_ref(), &sources, args, &overlays, output)
+        })?;
+    }
+
+    // These run over everything written above, so they have to come last
+    if let Some(colors) = args.quantize {
+        quantize_small_pngs(&args.output, colors, output)?;
+    }
+    if args.optimize {
+        optimize_pngs(&args.output, output)?;
+    }
+    if args.compression == "zopfli" {
+        recompress_pngs_zopfli(&args.output, output)?;
+    }
+    if args.png_metadata != "keep" {
+        set_pngs_metadata(&args.output, &args.png_metadata, output)?;
+    }
+    check_store_limits(args, output)?;
+
+    Ok(())
+}
+
+/// Warn about the icons of the set meant for a store upload that the store
+/// would reject, e.g. a Play Store icon over 1024 KB
+fn check_store_limits(args: &Args, output: &dyn OutputSink) -> Result<()> {
+    let mut icons = Vec::new();
+    if args.ios {
+        icons.push((
+            ios_icon_dir(args)?.join(IOS_MARKETING_FILE),
+            &APP_STORE_ICON,
+        ));
+    }
+    if args.app_clip {
+        icons.push((Path::new("app-clip").join(APP_CLIP_FILE), &APP_STORE_ICON));
+    }
+    for preset in &args.preset {
+        for (filename, limit) in store_icons(preset) {
+            icons.push((Path::new(preset).join(filename), limit));
+        }
+    }
+
+    for (icon, limit) in icons {
+        let path = args.output.join(&icon);
+        if !output.exists(&path) {
+            continue;
+        }
+        let bytes = output.read(&path)?;
+        let oversized = limit.max_bytes.is_some_and(|max| bytes.len() > max);
+        for (index, violation) in store_limits::violations(&bytes, limit)
+            .into_iter()
+            .enumerate()
+        {
+            // The size comes first, and only the lossless passes not on yet can help with it
+            // (--quantize only rewrites icons up to QUANTIZE_MAX_SIZE)
+            let hint = match (
+                oversized && index == 0,
+                args.optimize,
+                args.compression.as_str(),
+            ) {
+                (false, ..) => String::new(),
+                (true, false, "zopfli") => "; try --optimize".to_string(),
+                (true, false, _) => "; try --optimize and --compression zopfli".to_string(),
+                (true, true, "zopfli") => {
+                    "; simplify the artwork, e.g. with fewer gradients and less noise".to_string()
+                }
+                (true, true, _) => "; try --compression zopfli".to_string(),
+            };
+            progress::warn(format_args!(
+                "Warning: {} {violation}{hint}",
+                icon.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The platforms that failed in a run; with `--keep-going` a failed platform
+/// is recorded here and the run carries on with the others, instead of stopping
+#[derive(Debug, Default)]
+struct Failures {
+    failed: Vec<PlatformFailure>,
+    generated: usize,
+    /// The platforms each set generated, keyed by its output directory
+    succeeded: HashSet<(PathBuf, String)>,
+}
+
+impl Failures {
+    /// Generate `platform` of the set of `args`, recording its failure under `--keep-going`
+    /// An interruption always stops the run
+    fn attempt(
+        &mut self,
+        args: &Args,
+        platform: &str,
+        generate: impl FnOnce() -> Result<()>,
+    ) -> Result<()> {
+        match generate() {
+            Ok(()) => {
+                self.generated += 1;
+                self.succeeded
+                    .insert((args.output.clone(), platform.to_string()));
+                Ok(())
+            }
+            Err(error) if args.keep_going && !interrupt::is_interrupted() => {
+                progress::warn(format_args!("✗ {platform} failed: {error:#}"));
+                self.failed.push(PlatformFailure {
+                    set: args.output.clone(),
+                    platform: platform.to_string(),
+                    message: format!("{error:#}"),
+                });
+                Ok(())
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Whether the set of `args` generated `platform` in this run
+    fn generated(&self, args: &Args, platform: &str) -> bool {
+        self.succeeded
+            .contains(&(args.output.clone(), platform.to_string()))
+    }
+
+    /// Fail with every recorded failure, if there were any
+    fn finish(self) -> Result<()> {
+        if self.failed.is_empty() {
+            return Ok(());
+        }
+        Err(IconGenError::PlatformsFailed {
+            failures: self.failed,
+            generated: self.generated,
+        }
+        .into())
+    }
+}
+
+/// Load the source from `input_bytes` when given, otherwise from the `input` file
+fn load_source(args: &Args) -> Result<DynamicImage> {
+    if args.dry_run {
+        return stand_in_source(args);
+    }
+    match &args.input_bytes {
+        Some(bytes) => {
+            ensure_square(image::load_from_memory(bytes).context("Failed to load image")?)
+        }
+        None => load_image(&args.input),
+    }
+}
+
+pub(crate) fn load_image(path: &Path) -> Result<DynamicImage> {
+    let image = match image::open(path) {
+        Err(image::ImageError::IoError(source)) => {
+            Err(IconGenError::io(path, source)).context("Failed to load image")?
+        }
+        image => image.context("Failed to load image")?,
+    };
+    ensure_square(image)
+}
+
+/// A blank source as large as the real one, whose size is read from its header
+/// without decoding it (`--dry-run`)
+fn stand_in_source(args: &Args) -> Result<DynamicImage> {
+    let (width, height) = match &args.input_bytes {
+        Some(bytes) => image::io::Reader::new(std::io::Cursor::new(bytes))
+            .with_guessed_format()?
+            .into_dimensions(),
+        None => image::image_dimensions(&args.input),
+    }
+    .context("Failed to load image")?;
+    if width != height {
+        anyhow::bail!("Source image must be square (width == height)");
+    }
+
+    let side = width.min(args.max_working_size);
+    Ok(DynamicImage::new_rgba8(side, side))
+}
+
+fn ensure_square(source: DynamicImage) -> Result<DynamicImage> {
+    if source.width() != source.height() {
+        anyhow::bail!("Source image must be square (width == height)");
+    }
+
+    Ok(source)
+}
+
+/// Names of the parallel icon sets available with `--variant`
+pub const VARIANT_NAMES: &[&str] = &["grayscale"];
+
+/// Derive the source for a `--variant` set, with the suffix for its output directory
+fn apply_variant(source: &DynamicImage, name: &str) -> Result<(String, DynamicImage)> {
+    match name {
+        // Desaturated for disabled states and monochrome contexts, keeping the alpha
+        "grayscale" => Ok((
+            "_gray".to_string(),
+            DynamicImage::ImageRgba8(source.grayscale().to_rgba8()),
+        )),
+        _ => anyhow::bail!(
+            "Unknown variant: {}. Available variants: {}",
+            name,
+            VARIANT_NAMES.join(", ")
+        ),
+    }
+}
+
+/// Derive the source for a `--hue-variants` channel given as `name=degrees`,
+/// with the channel name as the suffix for its output directory
+fn apply_hue_variant(source: &DynamicImage, channel: &str) -> Result<(String, DynamicImage)> {
+    let (name, degrees) = channel.split_once('=').with_context(|| {
+        format!("Invalid hue variant: {channel}. Use name=degrees, e.g. dev=120")
+    })?;
+
+    let name = name.trim();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        anyhow::bail!(
+            "Invalid hue variant name: {}. Use letters, digits, '-' and '_'",
+            name
+        );
+    }
+
+    let degrees: i32 = degrees
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid hue rotation for {name}: {}", degrees.trim()))?;
+
+    Ok((format!("_{name}"), source.huerotate(degrees)))
+}
+
+/// Apply `--adjust` corrections such as `brightness=1.1` to the full-size source
+/// Each factor is relative to the original, so 1.0 leaves the image unchanged
+fn apply_adjustments(img: &DynamicImage, adjustments: &[String]) -> Result<DynamicImage> {
+    let (mut brightness, mut contrast, mut saturation) = (1.0, 1.0, 1.0);
+
+    for adjustment in adjustments {
+        let (name, value) = adjustment
+            .split_once('=')
+            .with_context(|| format!("Invalid adjustment: {adjustment}. Use name=factor"))?;
+        let value: f32 = value
+            .trim()
+            .parse()
+            .ok()
+            .filter(|value: &f32| value.is_finite() && *value >= 0.0)
+            .with_context(|| format!("Invalid adjustment factor: {adjustment}"))?;
+
+        match name.trim() {
+            "brightness" => brightness = value,
+            "contrast" => contrast = value,
+            "saturation" => saturation = value,
+            _ => anyhow::bail!(
+                "Unknown adjustment: {}. Available adjustments: brightness, contrast, saturation",
+                name.trim()
+            ),
+        }
+    }
+
+    let mut rgba_img = img.to_rgba8();
+    for pixel in rgba_img.pixels_mut() {
+        let mut rgb = [0.0; 3];
+        for (value, &channel) in rgb.iter_mut().zip(&pixel.0[..3]) {
+            *value = channel as f32 / 255.0 * brightness;
+            *value = (*value - 0.5) * contrast + 0.5;
+        }
+
+        // Move each channel towards or away from the pixel's luma
+        let luma = 0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2];
+        for (channel, value) in pixel.0[..3].iter_mut().zip(rgb) {
+            let value = luma + (value - luma) * saturation;
+            *channel = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+
+    Ok(DynamicImage::ImageRgba8(rgba_img))
+}
+
+/// Recolor the artwork with a tint, preserving alpha
+/// `recolor` paints every pixel in the tint (for neutral glyphs), while `multiply`
+/// multiplies each channel so shading is kept
+fn apply_tint(img: &DynamicImage, tint: Rgba<u8>, mode: &str) -> Result<DynamicImage> {
+    let multiply = match mode {
+        "recolor" => false,
+        "multiply" => true,
+        _ => anyhow::bail!(
+            "Unknown tint mode: {}. Available modes: recolor, multiply",
+            mode
+        ),
+    };
+
+    let mut rgba_img = img.to_rgba8();
+    for pixel in rgba_img.pixels_mut() {
+        for channel in 0..3 {
+            pixel[channel] = if multiply {
+                (pixel[channel] as u16 * tint[channel] as u16 / 255) as u8
+            } else {
+                tint[channel]
+            };
+        }
+    }
+
+    Ok(DynamicImage::ImageRgba8(rgba_img))
+}
+
+/// Apply the `--safe-zone` check of the artwork against `zone` to a compositor that
+/// draws it at `artwork_scale`: warns when the artwork reaches outside the zone,
+/// and in `fix` mode shrinks it to fit, filling the margin with the color around it
+fn fit_safe_zone<'a>(
+    compositor: Compositor<'a>,
+    source: &DynamicImage,
+    zone: SafeZone,
+    mode: &str,
+    artwork_scale: f32,
+) -> Compositor<'a> {
+    let compositor = compositor.artwork_scale(artwork_scale);
+    if mode == "off" {
+        return compositor;
+    }
+    let Some(fit) = safe_zone::measure(source, zone) else {
+        progress::warn(format_args!(
+            "Warning: can't tell the artwork from its background, so it isn't checked against the {}",
+            zone.describe()
+        ));
+        return compositor;
+    };
+    if fit.outside <= OUTSIDE_TOLERANCE {
+        return compositor;
+    }
+    if mode != "fix" {
+        progress::warn(format_args!(
+            "Warning: {:.1}% of the artwork lies outside the {}, where it can be clipped; \
+             pass --safe-zone fix to scale it in, or add --padding {:.1}",
+            fit.outside * 100.0,
+            zone.describe(),
+            (1.0 - fit.scale) * 50.0
+        ));
+        return compositor;
+    }
+
+    progress::note(format_args!(
+        "  Scaled the artwork to {:.0}% to fit the {}",
+        fit.scale * 100.0,
+        zone.describe()
+    ));
+    let compositor = compositor.artwork_scale(artwork_scale * fit.scale);
+    match safe_zone::background(source) {
+        Some(fill) if fill[3] == 255 => compositor.background(fill),
+        _ => compositor,
+    }
+}
+
+/// Inset the artwork by `percent` of the side on every edge, keeping the canvas size
+fn apply_padding(
+    img: &DynamicImage,
+    percent: f32,
+    bg_color: Rgba<u8>,
+    resampler: Resampler<'_>,
+) -> Result<DynamicImage> {
+    if !(0.0..50.0).contains(&percent) {
+        anyhow::bail!(
+            "Invalid padding: {}. Padding must be at least 0% and less than 50%",
+            percent
+        );
+    }
+
+    let size = img.width();
+    let inset = (size as f32 * percent / 100.0).round() as u32;
+    let art_size = size - 2 * inset;
+    let artwork = resampler.resize(img, art_size, art_size);
+
+    let mut canvas = ImageBuffer::from_pixel(size, size, bg_color);
+    image::imageops::overlay(&mut canvas, &artwork, inset.into(), inset.into());
+    Ok(DynamicImage::ImageRgba8(canvas))
+}
+
+/// Determine when the iOS writer should be invoked
+/// Only invoke when args.ios flag is on OR iOS icons are produced via generate_all/mobile_only
+fn should_invoke_ios_writer(args: &Args, has_platform_flags: bool) -> bool {
+    // If specific iOS flag is set
+    if args.ios {
+        return true;
+    }
+
+    // If no platform flags are set (default case - generate_all)
+    if !has_platform_flags && !args.desktop_only && !args.mobile_only && !args.tauri_desktop {
+        return true;
+    }
+
+    // If mobile_only is set
+    if args.mobile_only {
+        return true;
+    }
+
+    false
+}
+
+/// Determine when the macOS writer should be invoked
+/// Only invoke when macOS icons are produced (macos, desktop_only, tauri_desktop, default)
+fn should_invoke_macos_writer(args: &Args, has_platform_flags: bool) -> bool {
+    // If specific macOS flag is set
+    if args.macos {
+        return true;
+    }
+
+    // If desktop_only is set
+    if args.desktop_only {
+        return true;
+    }
+
+    // If tauri_desktop is set
+    if args.tauri_desktop {
+        return true;
+    }
+
+    // If no platform flags are set (default case - generate_all)
+    if !has_platform_flags && !args.desktop_only && !args.mobile_only && !args.tauri_desktop {
+        return true;
+    }
+
+    false
+}
+
+fn generate_all(
+    sources: &PlatformSources,
+    args: &Args,
+    should_generate_ios: bool,
+    should_generate_macos: bool,
+    failures: &mut Failures,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    generate_desktop_only(sources, args, should_generate_macos, failures, output)?;
+    // Custom sizes replace the platform icons
+    if args.png.is_none() {
+        generate_mobile(sources, args, should_generate_ios, failures, output)?;
+    }
+    Ok(())
+}
+
+fn generate_desktop_only(
+    sources: &PlatformSources,
+    args: &Args,
+    should_generate_macos: bool,
+    failures: &mut Failures,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let overlays = Overlays::from_args(args)?;
+    if let Some(sizes) = &args.png {
+        failures.attempt(args, "png", || {
+            generate_custom_sizes(
+                sources.get("linux"),
+                sizes,
+                sources.resampler("linux"),
+                args,
+                output,
+            )
+        })?;
+    } else {
+        failures.attempt(args, "windows", || {
+            emit_platform(&WindowsIcons, sources, args, &overlays, output)
+        })?;
+
+        if should_generate_macos {
+            failures.attempt(args, "macos", || {
+                emit_platform(&MacosIcons, sources, args, &overlays, output)
+            })?;
+        }
+
+        failures.attempt(args, "linux", || {
+            emit_platform(&LinuxIcons, sources, args, &overlays, output)
+        })?;
+        failures.attempt(args, "tauri-desktop", || {
+            generate_tauri_desktop_icons(
+                sources.get("linux"),
+                args,
+                sources.resampler("linux"),
+                &overlays,
+                output,
+            )
+        })?;
+    }
+    Ok(())
+}
+
+fn generate_mobile_only(
+    sources: &PlatformSources,
+    args: &Args,
+    should_generate_ios: bool,
+    failures: &mut Failures,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    generate_mobile(sources, args, should_generate_ios, failures, output)?;
+    Ok(())
+}
+
+fn generate_platforms(
+    sources: &PlatformSources,
+    args: &Args,
+    should_generate_ios: bool,
+    should_generate_macos: bool,
+    failures: &mut Failures,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let overlays = Overlays::from_args(args)?;
+    let has_desktop_platform = args.windows || args.macos || args.linux;
+
+    if args.windows {
+        failures.attempt(args, "windows", || {
+            emit_platform(&WindowsIcons, sources, args, &overlays, output)
+        })?;
+    }
+
+    if args.macos && should_generate_macos {
+        failures.attempt(args, "macos", || {
+            emit_platform(&MacosIcons, sources, args, &overlays, output)
+        })?;
+    }
+
+    if args.linux {
+        if let Some(sizes) = &args.png {
+            failures.attempt(args, "png", || {
+                generate_custom_sizes(
+                    sources.get("linux"),
+                    sizes,
+                    sources.resampler("linux"),
+                    args,
+                    output,
+                )
+            })?;
+        } else {
+            failures.attempt(args, "linux", || {
+                emit_platform(&LinuxIcons, sources, args, &overlays, output)
+            })?;
+        }
+    }
+
+    // Generate tauri-desktop icons when any desktop platform is enabled
+    if has_desktop_platform {
+        failures.attempt(args, "tauri-desktop", || {
+            generate_tauri_desktop_icons(
+                sources.get("linux"),
+                args,
+                sources.resampler("linux"),
+                &overlays,
+                output,
+            )
+        })?;
+    }
+
+    if args.android {
+        failures.attempt(args, "android", || {
+            emit_platform(&AndroidIcons, sources, args, &overlays, output)
+        })?;
+    }
+
+    if args.ios && should_generate_ios {
+        failures.attempt(args, "ios", || {
+            emit_platform(&IosIcons, sources, args, &overlays, output)
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Run a generator with the source of the built-in platform of the same name,
+/// or the untreated source for custom platforms
+fn emit_platform(
+    generator: &dyn PlatformGenerator,
+    sources: &PlatformSources,
+    args: &Args,
+    overlays: &Overlays,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let platform = PLATFORM_NAMES
+        .iter()
+        .copied()
+        .find(|&name| name == generator.name())
+        .unwrap_or("");
+    let largest = generator.sizes().into_iter().max().unwrap_or(0);
+    if largest > sources.original.width() {
+        progress::warn(format_args!(
+            "Warning: the source is {}px, smaller than the {largest}px {} icons, which are \
+             upscaled and can look blurry",
+            sources.original.width(),
+            generator.name()
+        ));
+    }
+    if args.contrast_check && !platform.is_empty() {
+        check_contrast(sources, platform, args)?;
+    }
+    generator
+        .emit(&PlatformContext {
+            sources,
+            platform,
+            args,
+            overlays,
+            output,
+        })
+        .map_err(Into::into)
+}
+
+/// The `--contrast-check` of a built-in platform's icons: the outline of the
+/// artwork against the `--background` fill of flattened icons, or against the
+/// common launcher and wallpaper backgrounds of icons that keep their transparency
+fn check_contrast(sources: &PlatformSources, platform: &str, args: &Args) -> Result<()> {
+    let treated = sources.get(platform);
+    let fill = args
+        .background_image
+        .is_none()
+        .then(|| parse_color(&args.background));
+    let (outlined, fill) = if has_transparency(treated) {
+        // The iOS writer flattens its icons onto the background color itself
+        (treated, fill.filter(|_| platform == "ios"))
+    } else if selected_platforms(&args.background_for, "--background-for")?.contains(&platform)
+        && fill.is_some()
+    {
+        // The flattening hides the outline the fill meets
+        (sources.original, fill)
+    } else {
+        return Ok(());
+    };
+    let Some(edge) = Edge::of(outlined) else {
+        return Ok(());
+    };
+
+    if let Some(fill) = fill {
+        let contrast = edge.contrast(fill);
+        if contrast < MIN_CONTRAST {
+            progress::warn(format_args!(
+                "Warning: the edge of the artwork has {contrast:.1}:1 contrast with the {} \
+                 background of the {platform} icons; pick a --background that sets it apart",
+                args.background
+            ));
+        }
+        return Ok(());
+    }
+
+    let low = BACKDROPS
+        .iter()
+        .map(|&(name, backdrop)| (name, edge.contrast(backdrop)))
+        .filter(|&(_, contrast)| contrast < MIN_CONTRAST)
+        .map(|(name, contrast)| format!("{name} ({contrast:.1}:1)"))
+        .collect::<Vec<_>>();
+    if !low.is_empty() {
+        progress::warn(format_args!(
+            "Warning: the edge of the transparent {platform} icons has too little contrast \
+             on {} backgrounds; consider --border, --shadow or --background-for {platform}",
+            low.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// `windows/icon.ico`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WindowsIcons;
+
+impl PlatformGenerator for WindowsIcons {
+    fn name(&self) -> &str {
+        "windows"
+    }
+
+    fn sizes(&self) -> Vec<u32> {
+        ICO_SIZES.to_vec()
+    }
+
+    fn emit(&self, ctx: &PlatformContext<'_>) -> error::Result<()> {
+        generate_ico(
+            ctx.source(),
+            ctx.output_dir(),
+            ctx.resampler(),
+            ctx.overlays,
+            ctx.args,
+            ctx.output,
+        )
+        .map_err(IconGenError::from_anyhow)
+    }
+}
+
+/// `macos/icon.icns` and its `AppIcon.appiconset`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MacosIcons;
+
+impl PlatformGenerator for MacosIcons {
+    fn name(&self) -> &str {
+        "macos"
+    }
+
+    fn sizes(&self) -> Vec<u32> {
+        let mut sizes = ICNS_ENTRIES
+            .iter()
+            .map(|entry| entry.size)
+            .collect::<Vec<_>>();
+        sizes.dedup();
+        sizes
+    }
+
+    fn emit(&self, ctx: &PlatformContext<'_>) -> error::Result<()> {
+        generate_icns(
+            ctx.source(),
+            ctx.output_dir(),
+            ctx.resampler(),
+            ctx.overlays,
+            ctx.args,
+            ctx.output,
+        )
+        .map_err(IconGenError::from_anyhow)
+    }
+}
+
+/// The linux desktop PNGs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinuxIcons;
+
+impl PlatformGenerator for LinuxIcons {
+    fn name(&self) -> &str {
+        "linux"
+    }
+
+    fn sizes(&self) -> Vec<u32> {
+        LINUX_SIZES.to_vec()
+    }
+
+    fn emit(&self, ctx: &PlatformContext<'_>) -> error::Result<()> {
+        generate_linux_icons(
+            ctx.source(),
+            ctx.args,
+            ctx.resampler(),
+            ctx.overlays,
+            ctx.output,
+        )
+        .map_err(IconGenError::from_anyhow)
+    }
+}
+
+/// The Android launcher icons, with the round and adaptive variants as configured
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AndroidIcons;
+
+impl PlatformGenerator for AndroidIcons {
+    fn name(&self) -> &str {
+        "android"
+    }
+
+    fn sizes(&self) -> Vec<u32> {
+        ANDROID_DENSITIES.iter().map(|&(_, size)| size).collect()
+    }
+
+    fn emit(&self, ctx: &PlatformContext<'_>) -> error::Result<()> {
+        generate_android_icons_extended(ctx.source(), ctx.resampler(), ctx.args, ctx.output)
+            .map_err(IconGenError::from_anyhow)
+    }
+}
+
+/// The iOS `AppIcon.appiconset`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IosIcons;
+
+impl PlatformGenerator for IosIcons {
+    fn name(&self) -> &str {
+        "ios"
+    }
+
+    fn sizes(&self) -> Vec<u32> {
+        let mut sizes = IOS_SLOTS
+            .iter()
+            .flat_map(|&(base_size, multipliers, _, _, pixel_size)| {
+                multipliers
+                    .iter()
+                    .map(move |&multiplier| pixel_size.unwrap_or(base_size * multiplier))
+            })
+            .chain([IOS_MARKETING_SIZE])
+            .collect::<Vec<_>>();
+        sizes.sort_unstable();
+        sizes.dedup();
+        sizes
+    }
+
+    fn emit(&self, ctx: &PlatformContext<'_>) -> error::Result<()> {
+        generate_ios_icons(
+            ctx.source(),
+            ctx.output_dir(),
+            ctx.args,
+            ctx.resampler(),
+            ctx.overlays,
+            ctx.output,
+        )
+        .map_err(IconGenError::from_anyhow)
+    }
+}
+
+/// The targets of a `--spec` file
+#[derive(Debug)]
+struct SpecTargets(Vec<SpecTarget>);
+
+impl PlatformGenerator for SpecTargets {
+    fn name(&self) -> &str {
+        "spec"
+    }
+
+    fn sizes(&self) -> Vec<u32> {
+        let mut sizes = self.0.iter().map(spec_icon_size).collect::<Vec<_>>();
+        sizes.sort_unstable();
+        sizes.dedup();
+        sizes
+    }
+
+    fn emit(&self, ctx: &PlatformContext<'_>) -> error::Result<()> {
+        progress::step("Generating spec targets...");
+        self.0
+            .par_iter()
+            .try_for_each(|target| -> Result<()> {
+                let icon = ctx.render(spec_icon_size(target));
+                let mut image = if target.width == target.height {
+                    icon
+                } else {
+                    center_on_canvas(&icon, target.width, target.height, Rgba([0, 0, 0, 0]))
+                };
+                if let Some(background) = &target.background {
+                    image = flatten_alpha(&image, parse_color(background));
+                }
+
+                let output_path = ctx.output_dir().join(&target.filename);
+                match target.format {
+                    "bmp" => save_bmp(&image, &output_path, ctx.output)?,
+                    "webp" => save_webp(&image, &output_path, ctx.output)?,
+                    "jpeg" => {
+                        // JPEG has no alpha, so transparency goes onto white like BMP
+                        let flattened = flatten_alpha(&image, Rgba([255, 255, 255, 255]));
+                        let mut jpeg = Cursor::new(Vec::new());
+                        DynamicImage::ImageRgb8(flattened.to_rgb8())
+                            .write_to(&mut jpeg, image::ImageOutputFormat::Jpeg(90))
+                            .context("Failed to write JPEG")?;
+                        ctx.output.write(&output_path, jpeg.get_ref())?;
+                    }
+                    _ => save_png(&image, &output_path, ctx.args.png_encoding(), ctx.output)?,
+                }
+                progress::file(format_args!("  ✓ Generated {}", target.filename));
+                Ok(())
+            })
+            .map_err(IconGenError::from_anyhow)
+    }
+}
+
+/// The side the icon is rendered at for a spec target: the whole target when
+/// square, otherwise 80% of the shorter side, like store preset canvases
+fn spec_icon_size(target: &SpecTarget) -> u32 {
+    if target.width == target.height {
+        target.width
+    } else {
+        ((target.width.min(target.height) as f32 * 0.8) as u32).max(1)
+    }
+}
+
+/// Common ICO sizes
+pub(crate) const ICO_SIZES: &[u32] = &[16, 24, 32, 48, 64, 256];
+
+fn generate_ico(
+    source: &DynamicImage,
     out_dir: &Path,
-    dev_mode: bool,
-    dev_bug: &str,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    args: &Args,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let windows_dir = out_dir.join("windows");
+
+    progress::step("Generating windows/icon.ico...");
+
+    let ico_data = encode_ico(
+        source,
+        ICO_SIZES,
+        resampler,
+        overlays,
+        args.verify,
+        args.png_encoding(),
+    )?;
+    output.write(&windows_dir.join("icon.ico"), &ico_data)?;
+
+    progress::file("✓ Generated windows/icon.ico");
+    if args.verify {
+        progress::detail(format_args!(
+            "  ✓ Verified windows/icon.ico ({} frames)",
+            ICO_SIZES.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Write an app.rc resource script and a winres build.rs fragment next to icon.ico
+/// so Rust/Win32 projects can embed the icon without hand-writing resources
+fn generate_windows_resources(out_dir: &Path, output: &dyn OutputSink) -> Result<()> {
+    let windows_dir = out_dir.join("windows");
+
+    let app_rc = r#"// Generated by icon-gen
+// Compile with rc.exe / windres, or via the embed-resource crate
+IDI_ICON1 ICON "icon.ico"
+"#;
+    output.write(&windows_dir.join("app.rc"), app_rc.as_bytes())?;
+    progress::file("  ✓ Generated windows/app.rc");
+
+    let build_rs = r#"// Generated by icon-gen
+// Add `winres = "0.1"` to [build-dependencies] and merge this into your build.rs.
+// Adjust the path if icon.ico does not live in icons/windows/.
+fn main() {
+    if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("windows") {
+        let mut res = winres::WindowsResource::new();
+        res.set_icon("icons/windows/icon.ico");
+        res.compile().expect("Failed to compile Windows resources");
+    }
+}
+"#;
+    output.write(&windows_dir.join("winres-build.rs"), build_rs.as_bytes())?;
+    progress::file("  ✓ Generated windows/winres-build.rs");
+
+    Ok(())
+}
+
+/// Encode the source into an in-memory ICO container with one frame per size
+fn encode_ico(
+    source: &DynamicImage,
+    sizes: &[u32],
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    verify: bool,
+    encoding: PngEncoding,
+) -> Result<Vec<u8>> {
+    let (images, frames): (Vec<_>, Vec<_>) = sizes
+        .par_iter()
+        .map(|&size| {
+            let mut resized = resampler.resize(source, size, size);
+
+            // Apply overlays before encoding
+            overlays.apply(&mut resized);
+
+            let rgba_image = resized.into_rgba8();
+
+            // Only the 256px layer can be compressed according to the ico specs
+            let frame = if size == 256 {
+                let mut buf = Vec::new();
+                write_png(rgba_image.as_raw(), &mut buf, size, encoding)?;
+                IcoFrame::with_encoded(buf, size, size, ColorType::Rgba8)?
+            } else {
+                IcoFrame::as_png(rgba_image.as_raw(), size, size, ColorType::Rgba8)?
+            };
+            Ok((rgba_image, frame))
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+
+    let mut buf = Vec::new();
+    let encoder = IcoEncoder::new(&mut buf);
+    encoder.encode_images(&frames)?;
+
+    if verify {
+        verify::verify_ico(&buf, &images)?;
+    }
+    Ok(buf)
+}
+
+/// Generate a Windows cursor (windows/cursor.cur) and, when extra frames are
+/// given, an animated cursor (windows/cursor.ani)
+fn generate_cursor(source: &DynamicImage, args: &Args, output: &dyn OutputSink) -> Result<()> {
+    let resampler = Resampler::from_args(args)?;
+    let overlays = Overlays::from_args(args)?;
+    let windows_dir = args.output.join("windows");
+
+    let (hotspot_x, hotspot_y) = match args.cursor_hotspot[..] {
+        [x, y] => (x, y),
+        _ => anyhow::bail!("Cursor hotspot must be given as X,Y"),
+    };
+    if hotspot_x >= source.width() || hotspot_y >= source.height() {
+        anyhow::bail!(
+            "Cursor hotspot {},{} lies outside the {}x{} source image",
+            hotspot_x,
+            hotspot_y,
+            source.width(),
+            source.height()
+        );
+    }
+
+    progress::step("Generating windows/cursor.cur...");
+    let cursor = encode_cursor(
+        source,
+        (hotspot_x, hotspot_y),
+        resampler,
+        &overlays,
+        args.png_encoding(),
+    )?;
+    output.write(&windows_dir.join("cursor.cur"), &cursor)?;
+    progress::file("✓ Generated windows/cursor.cur");
+
+    if let Some(frame_paths) = &args.cursor_frames {
+        progress::step("Generating windows/cursor.ani...");
+
+        // The source image is the first frame, followed by the extra frames in order
+        let mut frames = vec![cursor];
+        for path in frame_paths {
+            let frame = load_image(path)
+                .with_context(|| format!("Failed to load cursor frame {}", path.display()))?;
+
+            // Keep the hotspot at the same relative position on differently sized frames
+            let hotspot = (
+                hotspot_x * frame.width() / source.width(),
+                hotspot_y * frame.height() / source.height(),
+            );
+            frames.push(encode_cursor(
+                &frame,
+                hotspot,
+                resampler,
+                &overlays,
+                args.png_encoding(),
+            )?);
+        }
+
+        // ANI frame rates are expressed in jiffies (1/60 s); any u32 delay fits
+        // once divided, but not while multiplied
+        let jiffies = ((u64::from(args.cursor_delay) * 60 / 1000) as u32).max(1);
+        let ani = encode_ani(&frames, jiffies);
+        output.write(&windows_dir.join("cursor.ani"), &ani)?;
+        progress::file(format_args!(
+            "✓ Generated windows/cursor.ani ({} frames)",
+            frames.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Encode a multi-size CUR file from the source using the ICO frame machinery
+/// A CUR file is an ICO file with image type 2, where each directory entry's
+/// planes/bit-count fields hold the hotspot instead
+fn encode_cursor(
+    source: &DynamicImage,
+    hotspot: (u32, u32),
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    encoding: PngEncoding,
+) -> Result<Vec<u8>> {
+    let sizes = [32, 48, 64];
+    let mut data = encode_ico(source, &sizes, resampler, overlays, false, encoding)?;
+
+    // ICONDIR: reserved (u16), type (u16), count (u16)
+    data[2..4].copy_from_slice(&2u16.to_le_bytes());
+
+    // ICONDIRENTRY (16 bytes each) follows the 6-byte header
+    for (i, &size) in sizes.iter().enumerate() {
+        let entry = 6 + i * 16;
+        let x = (hotspot.0 * size / source.width()) as u16;
+        let y = (hotspot.1 * size / source.height()) as u16;
+        data[entry + 4..entry + 6].copy_from_slice(&x.to_le_bytes());
+        data[entry + 6..entry + 8].copy_from_slice(&y.to_le_bytes());
+    }
+
+    Ok(data)
+}
+
+/// Assemble CUR frames into a RIFF ANI container with a fixed frame rate (in jiffies)
+fn encode_ani(frames: &[Vec<u8>], jiffies: u32) -> Vec<u8> {
+    fn push_chunk(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+        out.extend_from_slice(id);
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+        // RIFF chunks are padded to an even length
+        if data.len() % 2 == 1 {
+            out.push(0);
+        }
+    }
+
+    // ANIHEADER: cbSize, nFrames, nSteps, width, height, bitCount, planes, displayRate, flags
+    let frame_count = frames.len() as u32;
+    let mut header = Vec::with_capacity(36);
+    for value in [36, frame_count, frame_count, 0, 0, 0, 0, jiffies, 1] {
+        header.extend_from_slice(&u32::to_le_bytes(value));
+    }
+
+    let mut frame_list = b"fram".to_vec();
+    for frame in frames {
+        push_chunk(&mut frame_list, b"icon", frame);
+    }
+
+    let mut body = b"ACON".to_vec();
+    push_chunk(&mut body, b"anih", &header);
+    push_chunk(&mut body, b"LIST", &frame_list);
+
+    let mut riff = Vec::new();
+    push_chunk(&mut riff, b"RIFF", &body);
+    riff
+}
+
+fn generate_icns(
+    source: &DynamicImage,
+    out_dir: &Path,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    args: &Args,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let macos_dir = out_dir.join("macos");
+    let members = icns_members(&args.icns_types)?;
+
+    progress::step("Generating macos/icon.icns...");
+    let images = render_icns(source, &members, resampler, overlays);
+    let (icns_data, pngs) = assemble_icns(&members, &images, args.verify, args.png_encoding())?;
+    output.write(&macos_dir.join("icon.icns"), &icns_data)?;
+
+    progress::file("✓ Generated macos/icon.icns");
+    if args.verify {
+        progress::detail(format_args!(
+            "  ✓ Verified macos/icon.icns ({} icons)",
+            members.len()
+        ));
+    }
+
+    // The PNGs Contents.json names, reusing the member of the same type or at least
+    // the same size, and the encoding of the members stored as PNG
+    ICNS_ENTRIES
+        .par_iter()
+        .try_for_each(|entry| -> Result<()> {
+            let same_size = |index: &usize| members[*index].size == entry.size;
+            let member = (0..members.len())
+                .find(|&index| members[index].ostype == entry.ostype)
+                .or_else(|| {
+                    (0..members.len()).find(|index| same_size(index) && pngs[*index].is_some())
+                })
+                .or_else(|| (0..members.len()).find(same_size));
+            let png = match member.map(|index| (&images[index], &pngs[index])) {
+                Some((_, Some(png))) => png.clone(),
+                Some((image, None)) => {
+                    let mut buf = Vec::new();
+                    write_png(image.as_raw(), &mut buf, entry.size, args.png_encoding())?;
+                    buf
+                }
+                // Not a member of icon.icns, e.g. the 16x16 slot of `--icns-types retina`
+                None => {
+                    let mut image = resampler.resize(source, entry.size, entry.size);
+                    overlays.apply(&mut image);
+                    let mut buf = Vec::new();
+                    write_png(
+                        image.into_rgba8().as_raw(),
+                        &mut buf,
+                        entry.size,
+                        args.png_encoding(),
+                    )?;
+                    buf
+                }
+            };
+            let filename = macos_png_name(entry);
+            output.write(&macos_dir.join(&filename), &png)?;
+            progress::file(format_args!("  ✓ Generated macos/{filename}"));
+            Ok(())
+        })?;
+
+    // Step 3: Generate Contents.json for macOS
+    let macos_images = build_macos_contents_json(ICNS_ENTRIES)?;
+    let merge = args.contents_json == "merge";
+    write_macos_contents_json(&macos_dir, macos_images, merge, output)?;
+
+    Ok(())
+}
+
+/// File name of an ICNS member's PNG, as `iconutil` names the members of an
+/// `.iconset`, e.g. `icon_16x16@2x.png`
+fn macos_png_name(entry: &IcnsEntry) -> String {
+    format!("icon_{}.png", entry.name)
+}
+
+/// The icons of the macOS asset catalog, in order of size so that Contents.json
+/// comes out the same on every run; `--icns-types default` makes them the
+/// members of icon.icns
+const ICNS_ENTRIES: &[IcnsEntry] = &[
+    IcnsEntry {
+        name: "16x16",
+        size: 16,
+        ostype: "is32",
+    },
+    IcnsEntry {
+        name: "16x16@2x",
+        size: 32,
+        ostype: "ic11",
+    },
+    IcnsEntry {
+        name: "32x32",
+        size: 32,
+        ostype: "il32",
+    },
+    IcnsEntry {
+        name: "32x32@2x",
+        size: 64,
+        ostype: "ic12",
+    },
+    IcnsEntry {
+        name: "128x128",
+        size: 128,
+        ostype: "ic07",
+    },
+    IcnsEntry {
+        name: "128x128@2x",
+        size: 256,
+        ostype: "ic13",
+    },
+    IcnsEntry {
+        name: "256x256",
+        size: 256,
+        ostype: "ic08",
+    },
+    IcnsEntry {
+        name: "256x256@2x",
+        size: 512,
+        ostype: "ic14",
+    },
+    IcnsEntry {
+        name: "512x512",
+        size: 512,
+        ostype: "ic09",
+    },
+    IcnsEntry {
+        name: "512x512@2x",
+        size: 1024,
+        ostype: "ic10",
+    },
+];
+
+/// Encode the source into an in-memory ICNS container with the given members
+/// Members are resized and encoded in parallel, then assembled in the given order
+fn encode_icns(
+    source: &DynamicImage,
+    members: &[IcnsMember],
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    verify: bool,
+    encoding: PngEncoding,
+) -> Result<Vec<u8>> {
+    let images = render_icns(source, members, resampler, overlays);
+    let (icns, _) = assemble_icns(members, &images, verify, encoding)?;
+    Ok(icns)
+}
+
+/// Resize the source to the size of each ICNS member, with the overlays applied
+fn render_icns(
+    source: &DynamicImage,
+    members: &[IcnsMember],
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+) -> Vec<RgbaImage> {
+    members
+        .par_iter()
+        .map(|member| {
+            let mut image = resampler.resize(source, member.size, member.size);
+
+            // Apply overlays before encoding
+            overlays.apply(&mut image);
+            image.into_rgba8()
+        })
+        .collect()
+}
+
+/// The PNG encoding of each ICNS member, for the members stored as PNG
+type MemberPngs = Vec<Option<Vec<u8>>>;
+
+/// Encode the rendered members into an ICNS file
+/// Also returns the PNG encoding of each member that is stored as a PNG
+fn assemble_icns(
+    members: &[IcnsMember],
+    images: &[RgbaImage],
+    verify: bool,
+    encoding: PngEncoding,
+) -> Result<(Vec<u8>, MemberPngs)> {
+    let elements = members
+        .par_iter()
+        .zip(images)
+        .map(|(member, image)| {
+            encode_icns_member(image, member.ostype, encoding)
+                .with_context(|| format!("Can't add {} to Icns Family", member.ostype))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let pngs = members
+        .iter()
+        .zip(&elements)
+        .map(|(member, elements)| is_png_member(member.ostype).then(|| elements[0].data.clone()))
+        .collect();
+
+    let mut family = IconFamily::new();
+    family.elements = elements.into_iter().flatten().collect();
+
+    let mut buf = Vec::new();
+    family.write(&mut buf)?;
+
+    if verify {
+        let expected = members
+            .iter()
+            .zip(images)
+            .map(|(member, image)| (member.ostype.parse().unwrap(), image.clone()))
+            .collect::<Vec<_>>();
+        verify::verify_icns(&buf, &expected)?;
+    }
+    Ok((buf, pngs))
+}
+
+/// Whether the ICNS member type `ostype` is stored as a PNG: ic04/ic05, which the
+/// icns crate doesn't know, and the types it encodes as PNG or JPEG 2000
+fn is_png_member(ostype: &str) -> bool {
+    IconType::from_ostype(ostype.parse().unwrap())
+        .is_none_or(|icon_type| icon_type.encoding() == Encoding::JP2PNG)
+}
+
+/// Encode one ICNS member: PNG members take our own encoding as is, while the legacy
+/// members are RLE-compressed RGB with a separate alpha mask
+fn encode_icns_member(
+    image: &RgbaImage,
+    ostype: &str,
+    encoding: PngEncoding,
+) -> Result<Vec<IconElement>> {
+    if is_png_member(ostype) {
+        let mut buf = Vec::new();
+        write_png(image.as_raw(), &mut buf, image.width(), encoding)?;
+        return Ok(vec![IconElement::new(ostype.parse().unwrap(), buf)]);
+    }
+
+    let icon_type = IconType::from_ostype(ostype.parse().unwrap()).unwrap();
+    let image = icns::Image::from_data(
+        PixelFormat::RGBA,
+        image.width(),
+        image.height(),
+        image.as_raw().clone(),
+    )?;
+    let mut elements = vec![IconElement::encode_image_with_type(&image, icon_type)?];
+    if let Some(mask_type) = icon_type.mask_type() {
+        elements.push(IconElement::encode_image_with_type(&image, mask_type)?);
+    }
+    Ok(elements)
+}
+
+fn generate_custom_sizes(
+    source: &DynamicImage,
+    sizes: &[u32],
+    resampler: Resampler<'_>,
+    args: &Args,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let overlays = Overlays::from_args(args)?;
+    // Validate requested formats up front so nothing is written on a typo
+    for format in &args.formats {
+        if !matches!(format.as_str(), "png" | "bmp" | "webp") {
+            anyhow::bail!(IconGenError::UnsupportedFormat(format!(
+                "Unknown output format: {}. Available formats: png, bmp, webp",
+                format
+            )));
+        }
+    }
+
+    progress::step("Generating custom sizes...");
+    sizes.par_iter().try_for_each(|&size| {
+        let mut resized = resampler.resize(source, size, size);
+        overlays.apply(&mut resized);
+
+        for format in &args.formats {
+            let filename = icon_file_name(args, "png", size, 1, format)?
+                .unwrap_or_else(|| format!("{}x{}.{}", size, size, format));
+            let output_path = args.output.join(&filename);
+            match format.as_str() {
+                "bmp" => save_bmp(&resized, &output_path, output)?,
+                "webp" => save_webp(&resized, &output_path, output)?,
+                _ => save_png(&resized, &output_path, args.png_encoding(), output)?,
+            }
+            progress::file(format_args!("  ✓ Generated {}", filename));
+        }
+        Ok(())
+    })
+}
+
+/// Sizes of the linux desktop icons; the largest is written as icon.png
+const LINUX_SIZES: &[u32] = &[32, 64, 128, 256, 512];
+
+fn generate_linux_icons(
+    source: &DynamicImage,
+    args: &Args,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    output: &dyn OutputSink,
 ) -> Result<()> {
-    let macos_dir = out_dir.join("macos");
-    create_dir_all(&macos_dir)?;
+    let linux_dir = args.output.join("linux");
+
+    progress::step("Generating linux desktop icons...");
+    LINUX_SIZES.par_iter().try_for_each(|&size| {
+        let filename = match icon_file_name(args, "linux", size, 1, "png")? {
+            Some(filename) => filename,
+            None if size == 512 => "icon.png".to_string(),
+            None => format!("{size}x{size}.png"),
+        };
 
-    println!("Generating macos/icon.icns...");
-    let icns_json = r#"
-    {
-      "16x16": { "size": 16, "ostype": "is32" },
-      "16x16@2x": { "size": 32, "ostype": "ic11" },
-      "32x32": { "size": 32, "ostype": "il32" },
-      "32x32@2x": { "size": 64, "ostype": "ic12" },
-      "128x128": { "size": 128, "ostype": "ic07" },
-      "128x128@2x": { "size": 256, "ostype": "ic13" },
-      "256x256": { "size": 256, "ostype": "ic08" },
-      "256x256@2x": { "size": 512, "ostype": "ic14" },
-      "512x512": { "size": 512, "ostype": "ic09" },
-      "512x512@2x": { "size": 1024, "ostype": "ic10" }
-    }
-    "#;
-
-    let entries: HashMap<String, IcnsEntry> = serde_json::from_str(icns_json).unwrap();
-    let mut family = IconFamily::new();
+        let mut resized = resampler.resize(source, size, size);
+        overlays.apply(&mut resized);
+        save_png(
+            &resized,
+            &linux_dir.join(&filename),
+            args.png_encoding(),
+            output,
+        )?;
+        progress::file(format_args!("  ✓ Generated linux/{filename}"));
+        Ok(())
+    })
+}
+
+/// Generate system tray / status area icons in a tray/ directory
+/// Covers the Windows notification area, GNOME/KDE trays and the macOS menu bar (@1x/@2x)
+fn generate_tray_icons(
+    source: &DynamicImage,
+    args: &Args,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let tray_dir = args.output.join("tray");
+
+    progress::step("Generating tray icons...");
+    [16, 20, 22, 24, 32].into_par_iter().try_for_each(|size| {
+        let filename = icon_file_name(args, "tray", size, 1, "png")?
+            .unwrap_or_else(|| format!("{size}x{size}.png"));
+        let mut resized = resampler.resize(source, size, size);
+        overlays.apply(&mut resized);
+        save_png(
+            &resized,
+            &tray_dir.join(&filename),
+            args.png_encoding(),
+            output,
+        )?;
+        progress::file(format_args!("  ✓ Generated tray/{filename}"));
+        Ok(())
+    })
+}
 
-    for (name, entry) in &entries {
-        let mut image = source.resize_exact(entry.size, entry.size, FilterType::Lanczos3);
+/// Generate Tauri desktop icons in a tauri-desktop/ directory
+/// Contains the specific files needed for Tauri's src-tauri/icons folder
+fn generate_tauri_desktop_icons(
+    source: &DynamicImage,
+    args: &Args,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let out_dir = &args.output;
+    let tauri_dir = out_dir.join("tauri-desktop");
 
-        // Apply dev badge before encoding
-        if dev_mode {
-            apply_dev_badge_with_bug(&mut image, dev_bug, 0.0)?;
+    progress::step("Generating tauri-desktop icons...");
+
+    // 32x32.png, 128x128.png and 128x128@2x.png (256x256)
+    let mut names = Vec::new();
+    for (size, scale) in [(32, 1), (128, 1), (128, 2)] {
+        let filename = icon_file_name(args, "tauri-desktop", size, scale, "png")?.unwrap_or_else(
+            || match scale {
+                1 => format!("{size}x{size}.png"),
+                _ => format!("{size}x{size}@{scale}x.png"),
+            },
+        );
+        if names.contains(&filename) {
+            anyhow::bail!(
+                "--name-template names two tauri-desktop icons {}; add {{scale}} or {{density}} to tell the @2x icon apart",
+                filename
+            );
         }
+        let mut resized = resampler.resize(source, size * scale, size * scale);
+        overlays.apply(&mut resized);
+        save_png(
+            &resized,
+            &tauri_dir.join(&filename),
+            args.png_encoding(),
+            output,
+        )?;
+        progress::file(format_args!("  ✓ Generated tauri-desktop/{filename}"));
+        names.push(filename);
+    }
 
-        let mut buf = Vec::new();
-        let rgba_image = image.to_rgba8();
-        write_png(rgba_image.as_raw(), &mut buf, entry.size)?;
-        let image = icns::Image::read_png(&buf[..])?;
-
-        family
-            .add_icon_with_type(
-                &image,
-                IconType::from_ostype(entry.ostype.parse().unwrap()).unwrap(),
-            )
-            .with_context(|| format!("Can't add {name} to Icns Family"))?;
+    // Generate icon.ico (copy from windows directory)
+    let windows_ico = out_dir.join("windows").join("icon.ico");
+    if output.exists(&windows_ico) {
+        output.write(&tauri_dir.join("icon.ico"), &output.read(&windows_ico)?)?;
+        progress::file("  ✓ Generated tauri-desktop/icon.ico");
     }
 
-    let mut out_file = BufWriter::new(File::create(macos_dir.join("icon.icns"))?);
-    family.write(&mut out_file)?;
-    out_file.flush()?;
+    // Generate icon.icns (copy from macos directory)
+    let macos_icns = out_dir.join("macos").join("icon.icns");
+    if output.exists(&macos_icns) {
+        output.write(&tauri_dir.join("icon.icns"), &output.read(&macos_icns)?)?;
+        progress::file("  ✓ Generated tauri-desktop/icon.icns");
+    }
 
-    println!("✓ Generated macos/icon.icns");
+    Ok(())
+}
 
-    // Step 3: Generate Contents.json for macOS
-    let macos_images = build_macos_contents_json(&entries)?;
-    write_macos_contents_json(&macos_dir, macos_images)?;
+/// Generate the files for a store/engine preset into a directory named after it
+fn generate_preset(
+    source: &DynamicImage,
+    name: &str,
+    args: &Args,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let resampler = Resampler::from_args(args)?;
+    let overlays = Overlays::from_args(args)?;
+    let assets = preset_assets(name)?;
+    let preset_dir = args.output.join(name);
+
+    progress::step(format_args!("Generating {name} preset..."));
+    let bg_color = parse_color(&args.preset_bg);
+
+    for asset in &assets {
+        let output_path = preset_dir.join(asset.filename());
+
+        match *asset {
+            PresetAsset::Png { size, .. } => {
+                let mut resized = resampler.resize(source, size, size);
+                overlays.apply(&mut resized);
+                save_png(&resized, &output_path, args.png_encoding(), output)?;
+            }
+            PresetAsset::OpaquePng { size, .. } => {
+                let mut resized = resampler.resize(source, size, size);
+                overlays.apply(&mut resized);
+                save_opaque_png(
+                    flatten_alpha(&resized, bg_color),
+                    &output_path,
+                    args.png_encoding(),
+                    output,
+                )?;
+            }
+            PresetAsset::RoundPng { size, .. } => {
+                let resized = resampler.resize(source, size, size);
+                let mut round_icon = apply_circular_mask(&resized)?;
+                overlays.apply(&mut round_icon);
+                save_png(&round_icon, &output_path, args.png_encoding(), output)?;
+            }
+            PresetAsset::PaddedPng {
+                size,
+                scale_percent,
+                ..
+            } => {
+                let icon_size = size * scale_percent / 100;
+                let resized = resampler.resize(source, icon_size, icon_size);
+                let mut canvas = ImageBuffer::from_pixel(size, size, Rgba([0, 0, 0, 0]));
+                let offset = ((size - icon_size) / 2).into();
+                image::imageops::overlay(&mut canvas, &resized, offset, offset);
+                let mut padded = DynamicImage::ImageRgba8(canvas);
+                overlays.apply(&mut padded);
+                save_png(&padded, &output_path, args.png_encoding(), output)?;
+            }
+            PresetAsset::Ico { sizes, .. } => {
+                let ico_data = encode_ico(
+                    source,
+                    sizes,
+                    resampler,
+                    &overlays,
+                    args.verify,
+                    args.png_encoding(),
+                )?;
+                output.write(&output_path, &ico_data)?;
+            }
+            PresetAsset::Icns { .. } => {
+                let members = icns_members(&args.icns_types)?;
+                let icns_data = encode_icns(
+                    source,
+                    &members,
+                    resampler,
+                    &overlays,
+                    args.verify,
+                    args.png_encoding(),
+                )?;
+                output.write(&output_path, &icns_data)?;
+            }
+            PresetAsset::Jpeg { size, .. } => {
+                let mut resized = resampler.resize(source, size, size);
+                overlays.apply(&mut resized);
+                let flattened = flatten_alpha(&resized, bg_color);
+                let mut jpeg = Cursor::new(Vec::new());
+                DynamicImage::ImageRgb8(flattened.to_rgb8())
+                    .write_to(&mut jpeg, image::ImageOutputFormat::Jpeg(90))
+                    .context("Failed to write JPEG")?;
+                output.write(&output_path, jpeg.get_ref())?;
+            }
+            PresetAsset::Canvas { width, height, .. } => {
+                // The icon takes 80% of the shorter side, centered on the background
+                let icon_size = (width.min(height) as f32 * 0.8) as u32;
+                let mut icon = resampler.resize(source, icon_size, icon_size);
+                overlays.apply(&mut icon);
+                let canvas = center_on_canvas(&icon, width, height, bg_color);
+                save_png(&canvas, &output_path, args.png_encoding(), output)?;
+            }
+            PresetAsset::SocialCard { width, height, .. } => {
+                let project_name = args.project_name.clone().unwrap_or_else(|| {
+                    args.input
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                });
+                let card =
+                    render_social_card(source, width, height, &project_name, bg_color, args)?;
+                save_png(&card, &output_path, args.png_encoding(), output)?;
+            }
+            PresetAsset::Text { contents, .. } => {
+                output.write(&output_path, contents.as_bytes())?;
+            }
+        }
+        progress::file(format_args!("  ✓ Generated {name}/{}", asset.filename()));
+    }
 
     Ok(())
 }
 
-fn generate_custom_sizes(
+/// Compose a social card: the icon on the left and the project name beside it,
+/// shrunk to fit the remaining width
+fn render_social_card(
+    source: &DynamicImage,
+    width: u32,
+    height: u32,
+    project_name: &str,
+    bg_color: Rgba<u8>,
+    args: &Args,
+) -> Result<DynamicImage> {
+    let margin = height / 8;
+    let icon_size = height / 2;
+    let icon_x = margin * 3 / 2;
+
+    let mut icon = Resampler::from_args(args)?.resize(source, icon_size, icon_size);
+    Overlays::from_args(args)?.apply(&mut icon);
+
+    let mut canvas = ImageBuffer::from_pixel(width, height, bg_color);
+    image::imageops::overlay(
+        &mut canvas,
+        &icon,
+        icon_x.into(),
+        ((height - icon_size) / 2).into(),
+    );
+
+    if !project_name.is_empty() {
+        let font = embedded_font()?;
+        let text_x = icon_x + icon_size + margin;
+        let max_width = width.saturating_sub(text_x + margin * 3 / 2) as f32;
+
+        let mut scale = Scale::uniform(height as f32 * 0.15);
+        let natural_width = text_width(&font, project_name, scale);
+        if natural_width > max_width {
+            scale = Scale::uniform(scale.y * max_width / natural_width);
+        }
+
+        let v_metrics = font.v_metrics(scale);
+        let text_height = v_metrics.ascent - v_metrics.descent;
+        let text_y = (height as f32 - text_height) / 2.0;
+        draw_text(
+            &mut canvas,
+            &font,
+            project_name,
+            scale,
+            (text_x as f32, text_y),
+            contrasting_text_color(bg_color),
+        );
+    }
+
+    Ok(DynamicImage::ImageRgba8(canvas))
+}
+
+/// Generate an animated 32x32 favicon (web/favicon.gif and web/favicon.apng)
+/// Every frame of the animated GIF/APNG source is resized and badged individually
+fn generate_animated_favicon(args: &Args, output: &dyn OutputSink) -> Result<()> {
+    let resampler = Resampler::from_args(args)?;
+    let overlays = Overlays::from_args(args)?;
+    let web_dir = args.output.join("web");
+
+    progress::step("Generating animated favicon...");
+    let size = 32;
+    let source_frames = if args.dry_run {
+        vec![Frame::new(RgbaImage::new(size, size))]
+    } else {
+        load_animation_frames(args)?
+    };
+
+    let mut frames = Vec::with_capacity(source_frames.len());
+    for frame in source_frames {
+        // A fixed frame rate overrides the delays stored in the source
+        let delay_ms = match args.favicon_fps {
+            Some(fps) => 1000 / fps.max(1),
+            None => {
+                let (numer, denom) = frame.delay().numer_denom_ms();
+                numer / denom.max(1)
+            }
+        };
+
+        let mut resized =
+            resampler.resize(&DynamicImage::ImageRgba8(frame.into_buffer()), size, size);
+        overlays.apply(&mut resized);
+        frames.push((resized.into_rgba8(), delay_ms));
+    }
+
+    // GIF for broad browser support
+    let mut gif = Vec::new();
+    let mut gif_encoder = GifEncoder::new(&mut gif);
+    gif_encoder.set_repeat(Repeat::Infinite)?;
+    for (buffer, delay_ms) in &frames {
+        let delay = Delay::from_numer_denom_ms(*delay_ms, 1);
+        gif_encoder.encode_frame(Frame::from_parts(buffer.clone(), 0, 0, delay))?;
+    }
+    drop(gif_encoder);
+    output.write(&web_dir.join("favicon.gif"), &gif)?;
+    progress::file(format_args!(
+        "  ✓ Generated web/favicon.gif ({} frames)",
+        frames.len()
+    ));
+
+    // APNG keeps full 8-bit alpha
+    let mut apng = Vec::new();
+    let mut apng_encoder = png::Encoder::new(&mut apng, size, size);
+    apng_encoder.set_color(png::ColorType::Rgba);
+    apng_encoder.set_depth(png::BitDepth::Eight);
+    apng_encoder.set_animated(frames.len() as u32, 0)?;
+    let mut writer = apng_encoder.write_header()?;
+    for (buffer, delay_ms) in &frames {
+        writer.set_frame_delay((*delay_ms).min(u16::MAX as u32) as u16, 1000)?;
+        writer.write_image_data(buffer.as_raw())?;
+    }
+    writer.finish()?;
+    output.write(&web_dir.join("favicon.apng"), &apng)?;
+    progress::file(format_args!(
+        "  ✓ Generated web/favicon.apng ({} frames)",
+        frames.len()
+    ));
+
+    Ok(())
+}
+
+/// Decode every frame of an animated GIF or APNG source, from `input_bytes` when
+/// given, otherwise from the `input` file
+/// Static images are returned as a single frame
+fn load_animation_frames(args: &Args) -> Result<Vec<Frame>> {
+    let frames = match &args.input_bytes {
+        Some(bytes) => decode_frames(
+            Cursor::new(&bytes[..]),
+            image::guess_format(bytes).ok(),
+            || ensure_square(image::load_from_memory(bytes).context("Failed to load image")?),
+        )?,
+        None => {
+            let path = &args.input;
+            let extension = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            let format = match extension.as_str() {
+                "gif" => Some(ImageFormat::Gif),
+                "png" | "apng" => Some(ImageFormat::Png),
+                _ => None,
+            };
+            let reader =
+                std::io::BufReader::new(File::open(path).context("Failed to open source image")?);
+            decode_frames(reader, format, || load_image(path))?
+        }
+    };
+
+    if frames.is_empty() {
+        anyhow::bail!("Animated source contains no frames");
+    }
+
+    Ok(frames)
+}
+
+/// Decode the frames of a GIF or PNG, or load any other format as a single frame
+fn decode_frames(
+    reader: impl std::io::Read,
+    format: Option<ImageFormat>,
+    load_static: impl FnOnce() -> Result<DynamicImage>,
+) -> Result<Vec<Frame>> {
+    Ok(match format {
+        Some(ImageFormat::Gif) => GifDecoder::new(reader)?.into_frames().collect_frames()?,
+        Some(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(reader)?;
+            if decoder.is_apng() {
+                decoder.apng().into_frames().collect_frames()?
+            } else {
+                let image = DynamicImage::from_decoder(decoder)?;
+                vec![Frame::new(image.to_rgba8())]
+            }
+        }
+        _ => vec![Frame::new(load_static()?.to_rgba8())],
+    })
+}
+
+fn generate_mobile(
+    sources: &PlatformSources,
+    args: &Args,
+    should_generate_ios: bool,
+    failures: &mut Failures,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let overlays = Overlays::from_args(args)?;
+    progress::step("Generating mobile platform icons...");
+
+    // Android icons with round and adaptive support
+    failures.attempt(args, "android", || {
+        emit_platform(&AndroidIcons, sources, args, &overlays, output)
+    })?;
+
+    // iOS icons with background color - only generate when appropriate flags are set
+    if should_generate_ios {
+        failures.attempt(args, "ios", || {
+            emit_platform(&IosIcons, sources, args, &overlays, output)
+        })?;
+    }
+
+    Ok(())
+}
+
+/// An appiconset slot: (base_size, multipliers, idiom, optional size_override, pixel_size_override)
+type IosSlot = (
+    u32,
+    &'static [u32],
+    &'static str,
+    Option<&'static str>,
+    Option<u32>,
+);
+
+/// Xcode AppIcon.appiconset slots - includes all optional slots, so that Xcode
+/// shows none of the iPhone and iPad slots as missing
+#[rustfmt::skip]
+const IOS_SLOTS: &[IosSlot] = &[
+    // iPhone App icons
+    (29, &[2, 3], "iphone", None, None), // iPhone: 29x29@2x, @3x (Settings, etc.)
+    (40, &[2, 3], "iphone", None, None), // iPhone: 40x40@2x, @3x (Spotlight)
+    (60, &[2, 3], "iphone", None, None), // iPhone: 60x60@2x, @3x (App icon)
+    // iPhone Notification icons (optional slot)
+    (20, &[2, 3], "iphone", None, None), // iPhone: 20x20@2x, @3x (Notifications)
+    // iPad App icons
+    (29, &[1, 2], "ipad", None, None),   // iPad: 29x29@1x, @2x (Settings, etc.)
+    (40, &[1, 2], "ipad", None, None),   // iPad: 40x40@1x, @2x (Spotlight)
+    (76, &[1, 2], "ipad", None, None),   // iPad: 76x76@1x, @2x (App icon)
+    // iPad Notification icons (optional slot)
+    (20, &[1, 2], "ipad", None, None),   // iPad: 20x20@1x, @2x (Notifications)
+    // iPad Pro 12.9" App icon (optional slot) - 83.5pt @2x = 167px
+    (83, &[2], "ipad", Some("83.5x83.5"), Some(167)),
+];
+
+/// Side of the App Store marketing icon
+const IOS_MARKETING_SIZE: u32 = 1024;
+
+/// File of the App Store marketing icon, in the iOS icon folder
+const IOS_MARKETING_FILE: &str = "AppIcon-1024x1024.png";
+
+/// File of the App Clip icon, in `app-clip/`
+const APP_CLIP_FILE: &str = "AppClipIcon-1024x1024.png";
+
+/// Layouts of the iOS app icons (`--ios-layout`)
+pub const IOS_LAYOUTS: &[&str] = &["flat", "appiconset", "xcassets"];
+
+/// The folder of the iOS app icons and their Contents.json, relative to the
+/// output directory: `ios/` itself, or an `AppIcon.appiconset` that Xcode takes
+/// as is, on its own or inside an `Assets.xcassets` catalog
+fn ios_icon_dir(args: &Args) -> Result<PathBuf> {
+    let ios = Path::new("ios");
+    Ok(match args.ios_layout.as_str() {
+        "flat" => ios.to_path_buf(),
+        "appiconset" => ios.join("AppIcon.appiconset"),
+        "xcassets" => ios.join("Assets.xcassets").join("AppIcon.appiconset"),
+        layout => anyhow::bail!(
+            "Unknown iOS layout: {}. Available layouts: {}",
+            layout,
+            IOS_LAYOUTS.join(", ")
+        ),
+    })
+}
+
+fn generate_ios_icons(
+    source: &DynamicImage,
+    out_dir: &Path,
+    args: &Args,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let icon_dir = ios_icon_dir(args)?;
+    let ios_dir = out_dir.join(&icon_dir);
+    let color = &args.background;
+
+    // iOS icons are opaque: the artwork goes on a background color fill
+    let compositor = Compositor::new(resampler, overlays).background(parse_color(color));
+    warn_if_transparent(source, "iOS app icons", color);
+    let compositor = fit_safe_zone(compositor, source, SafeZone::IosMask, &args.safe_zone, 1.0);
+
+    let slots = IOS_SLOTS
+        .iter()
+        .flat_map(
+            |&(base_size, multipliers, idiom, size_override, pixel_size_override)| {
+                multipliers.iter().map(move |&multiplier| {
+                    (
+                        base_size,
+                        multiplier,
+                        idiom,
+                        size_override,
+                        pixel_size_override,
+                    )
+                })
+            },
+        )
+        .collect::<Vec<_>>();
+
+    // Contents.json entries, in slot order
+    let images = slots
+        .into_iter()
+        .map(
+            |(base_size, multiplier, idiom, size_override, pixel_size_override)| {
+                // Use pixel_size_override if provided (for 83.5pt -> 167px case), otherwise calculate
+                let actual_size = pixel_size_override.unwrap_or(base_size * multiplier);
+                // Files are named after the size string, e.g. AppIcon-83.5x83.5@2x.png
+                let size_str = size_override
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("{base_size}x{base_size}"));
+                let filename = format!("AppIcon-{size_str}@{multiplier}x.png");
+
+                // Create ImageEntry for Contents.json (no role field for standard AppIcon)
+                let mut image_entry = ImageEntry::new_app_icon(
+                    filename,
+                    idiom.to_string(),
+                    size_str,
+                    format!("{multiplier}x"),
+                    None, // No role for standard Xcode AppIcon
+                );
+
+                image_entry.expected_size = Some(actual_size.to_string());
+                (image_entry, actual_size)
+            },
+        )
+        .collect::<Vec<_>>();
+
+    // iPhone and iPad slots of the same size share a file, which is written once
+    let mut files = images
+        .iter()
+        .map(|(entry, size)| (entry.filename.clone().unwrap_or_default(), *size))
+        .collect::<Vec<_>>();
+    files.sort_unstable();
+    files.dedup();
+    files.into_par_iter().try_for_each(|(filename, size)| {
+        let output_path = ios_dir.join(&filename);
+        save_opaque_png(
+            compositor.render(source, size),
+            &output_path,
+            args.png_encoding(),
+            output,
+        )?;
+        progress::file(format_args!(
+            "  ✓ Generated {}",
+            icon_dir.join(&filename).display()
+        ));
+        Ok::<_, anyhow::Error>(())
+    })?;
+    let mut images = images
+        .into_iter()
+        .map(|(entry, _)| entry)
+        .collect::<Vec<_>>();
+
+    // Generate 1024pt App Store marketing icon and add to Contents.json
+    let marketing_filename = IOS_MARKETING_FILE;
+    let marketing_size = IOS_MARKETING_SIZE;
+    let marketing_path = ios_dir.join(marketing_filename);
+    save_opaque_png(
+        compositor.render(source, marketing_size),
+        &marketing_path,
+        args.png_encoding(),
+        output,
+    )?;
+    progress::file(format_args!(
+        "  ✓ Generated {} (for App Store)",
+        icon_dir.join(marketing_filename).display()
+    ));
+
+    // Add marketing icon entry to Contents.json
+    let marketing_entry = ImageEntry::new_app_icon(
+        marketing_filename.to_string(),
+        "ios-marketing".to_string(),
+        "1024x1024".to_string(),
+        "1x".to_string(),
+        None,
+    );
+    images.push(marketing_entry);
+
+    // Write Contents.json (now includes all 18 icons)
+    let merge = args.contents_json == "merge";
+    write_contents_json(out_dir, &icon_dir, images, merge, output)?;
+
+    // The asset catalog itself has a Contents.json with nothing but its info
+    if let Some(catalog) = icon_dir
+        .parent()
+        .filter(|catalog| catalog.extension().is_some_and(|ext| ext == "xcassets"))
+    {
+        write_contents_json(out_dir, catalog, Vec::new(), merge, output)?;
+    }
+
+    Ok(())
+}
+
+/// Generate squircle-masked previews of the iOS icon (ios-preview/) and a matching
+/// web/apple-touch-icon.png, showing the icon as the home screen renders it
+fn generate_ios_squircle_previews(
     source: &DynamicImage,
-    sizes: &[u32],
     out_dir: &Path,
-    dev_mode: bool,
-    dev_bug: &str,
+    color: &str,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    encoding: PngEncoding,
+    output: &dyn OutputSink,
 ) -> Result<()> {
-    println!("Generating custom PNG sizes...");
-    for &size in sizes {
-        let resized = source.resize_exact(size, size, image::imageops::FilterType::Lanczos3);
-        let output_path = out_dir.join(format!("{}x{}.png", size, size));
-        save_png(&resized, &output_path, dev_mode, dev_bug)?;
-        println!("  ✓ Generated {}x{}.png", size, size);
+    let preview_dir = out_dir.join("ios-preview");
+    let web_dir = out_dir.join("web");
+
+    progress::step("Generating iOS squircle previews...");
+    let bg_color = parse_color(color);
+    let render = |size: u32| -> Result<DynamicImage> {
+        let mut icon = resampler.resize(source, size, size);
+        overlays.apply(&mut icon);
+        Ok(apply_squircle_mask(&flatten_alpha(&icon, bg_color)))
+    };
+
+    for size in [120, 180, 512, 1024] {
+        let filename = format!("AppIcon-{size}x{size}.png");
+        save_png(
+            &render(size)?,
+            &preview_dir.join(&filename),
+            encoding,
+            output,
+        )?;
+        progress::file(format_args!("  ✓ Generated ios-preview/{filename}"));
     }
+
+    let output_path = web_dir.join("apple-touch-icon.png");
+    save_png(&render(180)?, &output_path, encoding, output)?;
+    progress::file("  ✓ Generated web/apple-touch-icon.png");
+
     Ok(())
 }
 
-fn generate_linux_icons(
+/// Generate the Messages extension icon set (imessage/) with its Contents.json
+/// Messages icons are mostly 4:3, so the square artwork fills the height and is
+/// centered on the iOS background color
+fn generate_imessage_icons(
     source: &DynamicImage,
     out_dir: &Path,
-    dev_mode: bool,
-    dev_bug: &str,
+    color: &str,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    args: &Args,
+    output: &dyn OutputSink,
 ) -> Result<()> {
-    let linux_dir = out_dir.join("linux");
-    create_dir_all(&linux_dir)?;
-
-    println!("Generating linux desktop icons...");
-    let desktop_sizes = [32, 64, 128, 256, 512];
-    for size in desktop_sizes {
-        let filename = if size == 512 {
-            "icon.png".to_string()
-        } else {
-            format!("{size}x{size}.png")
-        };
+    let encoding = args.png_encoding();
+    let merge = args.contents_json == "merge";
+    let imessage_dir = out_dir.join("imessage");
+    warn_if_transparent(source, "iMessage app icons", color);
+
+    progress::step("Generating iMessage app icons...");
+    let bg_color = parse_color(color);
+    let mut images: Vec<ImageEntry> = Vec::new();
+
+    // Xcode "iMessage App Icon" slots
+    // Each entry: (point width, point height, scales, idiom, platform)
+    let slots = [
+        // Settings
+        (29, 29, vec![2, 3], "iphone", None),
+        (29, 29, vec![2], "ipad", None),
+        // Messages app drawer and conversation
+        (60, 45, vec![2, 3], "iphone", None),
+        (67, 50, vec![2], "ipad", None),
+        (74, 55, vec![2], "ipad", None),
+        (27, 20, vec![2, 3], "universal", Some("ios")),
+        (32, 24, vec![2, 3], "universal", Some("ios")),
+        // App Store
+        (1024, 1024, vec![1], "ios-marketing", None),
+        (1024, 768, vec![1], "ios-marketing", Some("ios")),
+    ];
 
-        let resized = source.resize_exact(size, size, FilterType::Lanczos3);
-        let output_path = linux_dir.join(&filename);
-        save_png(&resized, &output_path, dev_mode, dev_bug)?;
-        println!("  ✓ Generated linux/{filename}");
+    for (width, height, scales, idiom, platform) in slots {
+        for scale in scales {
+            let (pixel_width, pixel_height) = (width * scale, height * scale);
+            let filename = if scale == 1 {
+                format!("iMessage-{width}x{height}.png")
+            } else {
+                format!("iMessage-{width}x{height}@{scale}x.png")
+            };
+
+            let icon_size = pixel_width.min(pixel_height);
+            let mut icon = resampler.resize(source, icon_size, icon_size);
+            overlays.apply(&mut icon);
+            let canvas = center_on_canvas(&icon, pixel_width, pixel_height, bg_color);
+
+            save_opaque_png(canvas, &imessage_dir.join(&filename), encoding, output)?;
+            progress::file(format_args!("  ✓ Generated imessage/{filename}"));
+
+            let mut image_entry = ImageEntry::new_app_icon(
+                filename,
+                idiom.to_string(),
+                format!("{width}x{height}"),
+                format!("{scale}x"),
+                None,
+            );
+            image_entry.expected_size = Some(format!("{pixel_width}x{pixel_height}"));
+            if let Some(platform) = platform {
+                image_entry = image_entry.with_platform(platform.to_string());
+            }
+            images.push(image_entry);
+        }
     }
+
+    write_contents_json(out_dir, Path::new("imessage"), images, merge, output)?;
+
     Ok(())
 }
 
-/// Generate Tauri desktop icons in a tauri-desktop/ directory
-/// Contains the specific files needed for Tauri's src-tauri/icons folder
-fn generate_tauri_desktop_icons(
+/// Generate the App Clip icon (app-clip/) with its Contents.json
+/// App Clip targets use a single 1024pt universal icon that Xcode scales down
+fn generate_app_clip_icons(
     source: &DynamicImage,
     out_dir: &Path,
-    dev_mode: bool,
-    dev_bug: &str,
+    color: &str,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    args: &Args,
+    output: &dyn OutputSink,
 ) -> Result<()> {
-    let tauri_dir = out_dir.join("tauri-desktop");
-    create_dir_all(&tauri_dir)?;
+    let encoding = args.png_encoding();
+    let merge = args.contents_json == "merge";
+    let app_clip_dir = out_dir.join("app-clip");
+    warn_if_transparent(source, "App Clip icons", color);
+
+    progress::step("Generating App Clip icons...");
+    let filename = APP_CLIP_FILE;
+    // App Clip icons must be opaque, like the main app icon
+    let icon = Compositor::new(resampler, overlays)
+        .background(parse_color(color))
+        .render(source, 1024);
+    save_opaque_png(icon, &app_clip_dir.join(filename), encoding, output)?;
+    progress::file(format_args!("  ✓ Generated app-clip/{filename}"));
+
+    let mut image_entry = ImageEntry::new_app_icon(
+        filename.to_string(),
+        "universal".to_string(),
+        "1024x1024".to_string(),
+        "1x".to_string(),
+        None,
+    )
+    .with_platform("ios".to_string());
+    image_entry.scale = None;
+    image_entry.expected_size = Some("1024".to_string());
+
+    write_contents_json(
+        out_dir,
+        Path::new("app-clip"),
+        vec![image_entry],
+        merge,
+        output,
+    )?;
+
+    Ok(())
+}
+
+/// Generate a LaunchImage asset catalog (ios-splash/LaunchImage.launchimage/)
+/// with a splash at every device resolution and orientation
+fn generate_ios_splash(
+    source: &DynamicImage,
+    out_dir: &Path,
+    color: &str,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    args: &Args,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let encoding = args.png_encoding();
+    let merge = args.contents_json == "merge";
+    let launch_dir = out_dir.join("ios-splash").join("LaunchImage.launchimage");
+
+    progress::step("Generating iOS launch images...");
+    let bg_color = parse_color(color);
+    let mut images: Vec<ImageEntry> = Vec::new();
+
+    for slot in IOS_LAUNCH_IMAGES {
+        let filename = slot.filename();
+        let splash = render_splash(
+            source,
+            slot.width,
+            slot.height,
+            bg_color,
+            resampler,
+            overlays,
+        )?;
+        save_opaque_png(splash, &launch_dir.join(&filename), encoding, output)?;
+        progress::file(format_args!(
+            "  ✓ Generated ios-splash/LaunchImage.launchimage/{filename}"
+        ));
+
+        let mut image_entry = ImageEntry::new_launch_image(
+            filename,
+            slot.idiom.to_string(),
+            slot.orientation.to_string(),
+            format!("{}x", slot.scale),
+            slot.minimum_system_version.to_string(),
+        );
+        if let Some(subtype) = slot.subtype {
+            image_entry = image_entry.with_subtype(subtype.to_string());
+        }
+        images.push(image_entry);
+    }
+
+    write_contents_json(
+        out_dir,
+        Path::new("ios-splash/LaunchImage.launchimage"),
+        images,
+        merge,
+        output,
+    )?;
 
-    println!("Generating tauri-desktop icons...");
+    Ok(())
+}
+
+/// Generate PWA startup images (web/splash/) and the matching
+/// `<link rel="apple-touch-startup-image">` tags (web/apple-touch-startup-image.html)
+fn generate_pwa_startup_images(
+    source: &DynamicImage,
+    out_dir: &Path,
+    color: &str,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    encoding: PngEncoding,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let web_dir = out_dir.join("web");
+    let splash_dir = web_dir.join("splash");
+
+    progress::step("Generating PWA startup images...");
+    let bg_color = parse_color(color);
+    let mut links = String::new();
+
+    for device in APPLE_STARTUP_DEVICES {
+        for landscape in [false, true] {
+            let (width, height) = device.pixel_size(landscape);
+            let filename = format!("apple-splash-{width}-{height}.png");
+            let splash = render_splash(source, width, height, bg_color, resampler, overlays)?;
+            save_png(&splash, &splash_dir.join(&filename), encoding, output)?;
+            progress::file(format_args!("  ✓ Generated web/splash/{filename}"));
+
+            links.push_str(&format!(
+                "<link rel=\"apple-touch-startup-image\" media=\"{}\" href=\"splash/{filename}\">\n",
+                device.media_query(landscape)
+            ));
+        }
+    }
+
+    output.write(
+        &web_dir.join("apple-touch-startup-image.html"),
+        links.as_bytes(),
+    )?;
+    progress::file("  ✓ Generated web/apple-touch-startup-image.html");
+
+    Ok(())
+}
+
+/// Render a full-screen splash: the icon scaled to a third of the shorter side,
+/// centered on the background color
+fn render_splash(
+    source: &DynamicImage,
+    width: u32,
+    height: u32,
+    bg_color: Rgba<u8>,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+) -> Result<DynamicImage> {
+    let icon_size = width.min(height) / 3;
+    let mut icon = resampler.resize(source, icon_size, icon_size);
+    overlays.apply(&mut icon);
+
+    Ok(center_on_canvas(&icon, width, height, bg_color))
+}
+
+/// Save an image as PNG; overlays are drawn by the caller onto the image it owns,
+/// so saving never copies the image
+pub(crate) fn save_png(
+    image: &DynamicImage,
+    path: &Path,
+    encoding: PngEncoding,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    // Encoded straight from the pixel buffer, without an RGBA copy
+    let mut png = Vec::new();
+    PngEncoder::new_with_quality(&mut png, encoding.compression, encoding.filter)
+        .write_image(
+            image.as_bytes(),
+            image.width(),
+            image.height(),
+            image.color(),
+        )
+        .context("Failed to write PNG")?;
+    output.write(path, &png)?;
+    Ok(())
+}
+
+/// Save a flattened image as an RGB PNG
+/// App Store validation rejects icons with an alpha channel even when every pixel is opaque
+fn save_opaque_png(
+    image: DynamicImage,
+    path: &Path,
+    encoding: PngEncoding,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    save_png(
+        &DynamicImage::ImageRgb8(image.into_rgb8()),
+        path,
+        encoding,
+        output,
+    )
+}
+
+/// The image as RGBA8, borrowed when it already is
+fn rgba_view(image: &DynamicImage) -> Cow<'_, RgbaImage> {
+    image
+        .as_rgba8()
+        .map_or_else(|| Cow::Owned(image.to_rgba8()), Cow::Borrowed)
+}
+
+/// Writes so far, numbering the temporary files of `write_output`
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Write `bytes` to `path` unless the file already holds exactly these bytes, so
+/// that unchanged outputs keep their modification time for incremental builds
+pub(crate) fn write_output(path: &Path, bytes: &[u8]) -> error::Result<()> {
+    let unchanged = std::fs::metadata(path).is_ok_and(|meta| meta.len() == bytes.len() as u64)
+        && std::fs::read(path).is_ok_and(|existing| existing == bytes);
+    if unchanged {
+        return Ok(());
+    }
+    // Written next to the file and renamed over it, so that an interrupted or
+    // failed write never leaves a truncated file behind; the temporary name is
+    // unique to the write, so concurrent writes of a path never share it
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    temp_name.push(format!(
+        ".{}-{}.icon-gen-tmp",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp = path.with_file_name(temp_name);
+    std::fs::write(&temp, bytes)
+        .and_then(|()| std::fs::rename(&temp, path))
+        .map_err(|source| {
+            let _ = std::fs::remove_file(&temp);
+            IconGenError::io(path, source)
+        })
+}
 
-    // Generate 32x32.png
-    let resized_32 = source.resize_exact(32, 32, FilterType::Lanczos3);
-    let output_path = tauri_dir.join("32x32.png");
-    save_png(&resized_32, &output_path, dev_mode, dev_bug)?;
-    println!("  ✓ Generated tauri-desktop/32x32.png");
+/// Save an image as a 24-bit BMP, flattening transparency onto white
+/// Installer toolchains (NSIS, Inno Setup) and legacy consumers expect opaque bitmaps
+fn save_bmp(image: &DynamicImage, path: &Path, output: &dyn OutputSink) -> Result<()> {
+    let flattened = flatten_alpha(image, Rgba([255, 255, 255, 255])).into_rgb8();
 
-    // Generate 128x128.png
-    let resized_128 = source.resize_exact(128, 128, FilterType::Lanczos3);
-    let output_path = tauri_dir.join("128x128.png");
-    save_png(&resized_128, &output_path, dev_mode, dev_bug)?;
-    println!("  ✓ Generated tauri-desktop/128x128.png");
+    let mut bmp = Cursor::new(Vec::new());
+    DynamicImage::ImageRgb8(flattened)
+        .write_to(&mut bmp, image::ImageOutputFormat::Bmp)
+        .context("Failed to write BMP")?;
+    output.write(path, bmp.get_ref())?;
+    Ok(())
+}
+
+/// Save an image as lossless WebP, keeping transparency
+pub(crate) fn save_webp(image: &DynamicImage, path: &Path, output: &dyn OutputSink) -> Result<()> {
+    let rgba_image = rgba_view(image);
+    let mut webp = Vec::new();
+    write_webp(
+        rgba_image.as_raw(),
+        &mut webp,
+        image.width(),
+        image.height(),
+    )
+    .context("Failed to write WebP")?;
+    output.write(path, &webp)?;
+    Ok(())
+}
+
+/// Center an image on a width x height canvas filled with an opaque background color
+fn center_on_canvas(
+    image: &DynamicImage,
+    width: u32,
+    height: u32,
+    bg_color: Rgba<u8>,
+) -> DynamicImage {
+    let mut canvas = ImageBuffer::from_pixel(width, height, bg_color);
+    image::imageops::overlay(
+        &mut canvas,
+        image,
+        (width.saturating_sub(image.width()) / 2).into(),
+        (height.saturating_sub(image.height()) / 2).into(),
+    );
+    DynamicImage::ImageRgba8(canvas)
+}
+
+/// Load a `--background-image` and scale it to cover a size x size canvas,
+/// cropping whatever overhangs on the longer axis
+fn load_backdrop(path: &Path, size: u32, resampler: Resampler<'_>) -> Result<DynamicImage> {
+    let backdrop = image::open(path)
+        .with_context(|| format!("Failed to load background image {}", path.display()))?;
+    Ok(backdrop.resize_to_fill(size, size, resampler.filter))
+}
+
+/// Composite an image over a backdrop of the same size
+fn composite_over(image: &DynamicImage, backdrop: &DynamicImage) -> DynamicImage {
+    let mut composited = backdrop.to_rgba8();
+    image::imageops::overlay(&mut composited, image, 0, 0);
+    DynamicImage::ImageRgba8(composited)
+}
+
+/// Whether any pixel of the image is not fully opaque
+fn has_transparency(image: &DynamicImage) -> bool {
+    image.color().has_alpha() && rgba_view(image).pixels().any(|pixel| pixel[3] < 255)
+}
+
+/// Tell the user that a target which must be opaque will fill the source's transparency
+fn warn_if_transparent(source: &DynamicImage, target: &str, color: &str) {
+    if has_transparency(source) {
+        progress::warn(format_args!(
+            "Warning: the source has transparent areas, but {target} must be opaque; \
+             flattening them onto {color} (set --background, or pass --flatten to flatten every target)"
+        ));
+    }
+}
+
+/// Composite an image onto an opaque background color, removing all transparency
+fn flatten_alpha(image: &DynamicImage, bg_color: Rgba<u8>) -> DynamicImage {
+    let mut flattened = ImageBuffer::from_pixel(image.width(), image.height(), bg_color);
+    image::imageops::overlay(&mut flattened, image, 0, 0);
+    // The blend can round semi-transparent edges down to 254, which stores reject
+    for pixel in flattened.pixels_mut() {
+        pixel[3] = 255;
+    }
+    DynamicImage::ImageRgba8(flattened)
+}
+
+// Encode image data as PNG with compression
+fn write_png<W: Write>(image_data: &[u8], w: W, size: u32, encoding: PngEncoding) -> Result<()> {
+    let encoder = PngEncoder::new_with_quality(w, encoding.compression, encoding.filter);
+    encoder.write_image(image_data, size, size, ColorType::Rgba8)?;
+    Ok(())
+}
+
+/// PNG encoding profiles available with `--encode-profile`, from fastest to smallest
+pub const ENCODE_PROFILES: &[&str] = &["fast", "balanced", "best"];
+
+/// Deflate level and row filter of the `--encode-profile` for every generated PNG
+/// The `--quantize`, `--optimize` and `--compression zopfli` passes always aim for the smallest file
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PngEncoding {
+    compression: CompressionType,
+    filter: PngFilterType,
+}
 
-    // Generate 128x128@2x.png (256x256)
-    let resized_256 = source.resize_exact(256, 256, FilterType::Lanczos3);
-    let output_path = tauri_dir.join("128x128@2x.png");
-    save_png(&resized_256, &output_path, dev_mode, dev_bug)?;
-    println!("  ✓ Generated tauri-desktop/128x128@2x.png");
+impl PngEncoding {
+    /// The encoding of one of `ENCODE_PROFILES`; `generate_through` rejects any other
+    fn from_profile(profile: &str) -> Self {
+        let (compression, filter) = match profile {
+            "fast" => (CompressionType::Fast, PngFilterType::Sub),
+            "balanced" => (CompressionType::Default, PngFilterType::Adaptive),
+            _ => (CompressionType::Best, PngFilterType::Adaptive),
+        };
+        Self {
+            compression,
+            filter,
+        }
+    }
+}
 
-    // Generate icon.ico (copy from windows directory)
-    let windows_ico = out_dir.join("windows").join("icon.ico");
-    if windows_ico.exists() {
-        std::fs::copy(&windows_ico, tauri_dir.join("icon.ico"))?;
-        println!("  ✓ Generated tauri-desktop/icon.ico");
+impl Default for PngEncoding {
+    /// The `best` profile
+    fn default() -> Self {
+        Self::from_profile("best")
     }
+}
 
-    // Generate icon.icns (copy from macos directory)
-    let macos_icns = out_dir.join("macos").join("icon.icns");
-    if macos_icns.exists() {
-        std::fs::copy(&macos_icns, tauri_dir.join("icon.icns"))?;
-        println!("  ✓ Generated tauri-desktop/icon.icns");
+impl Args {
+    /// The PNG encoding of `--encode-profile`, which every run carries on its own
+    pub(crate) fn png_encoding(&self) -> PngEncoding {
+        PngEncoding::from_profile(&self.encode_profile)
     }
+}
 
+// Encode image data as lossless WebP
+fn write_webp<W: Write>(image_data: &[u8], w: W, width: u32, height: u32) -> Result<()> {
+    let encoder = image_webp::WebPEncoder::new(w);
+    encoder.encode(image_data, width, height, image_webp::ColorType::Rgba8)?;
     Ok(())
 }
 
-fn generate_mobile(source: &DynamicImage, args: &Args, should_generate_ios: bool) -> Result<()> {
-    println!("Generating mobile platform icons...");
-
-    // Android icons with round and adaptive support
-    generate_android_icons_extended(source, args)?;
-
-    // iOS icons with background color - only generate when appropriate flags are set
-    if should_generate_ios {
-        generate_ios_icons(
-            source,
-            &args.output,
-            &args.ios_color,
-            args.dev_mode,
-            &args.dev_bug,
-        )?;
+/// Resampling filters available with `--filter`
+pub const FILTER_NAMES: &[&str] = &["lanczos3", "catmullrom", "triangle", "nearest"];
+
+/// Parse a `--filter` name into the resampling filter used for every resize
+fn parse_filter(name: &str) -> Result<FilterType> {
+    match name {
+        "lanczos3" => Ok(FilterType::Lanczos3),
+        "catmullrom" => Ok(FilterType::CatmullRom),
+        "triangle" => Ok(FilterType::Triangle),
+        "nearest" => Ok(FilterType::Nearest),
+        _ => anyhow::bail!(
+            "Unknown filter: {}. Available filters: {}",
+            name,
+            FILTER_NAMES.join(", ")
+        ),
     }
-
-    Ok(())
 }
 
-fn generate_ios_icons(
-    source: &DynamicImage,
-    out_dir: &Path,
-    color: &str,
-    dev_mode: bool,
-    dev_bug: &str,
-) -> Result<()> {
-    let ios_dir = out_dir.join("ios");
-    create_dir_all(&ios_dir)?;
+/// Largest output side, in pixels, that `--sharpen` applies to
+const SHARPEN_MAX_SIZE: u32 = 48;
 
-    // Parse background color
-    let bg_color = css_color::Srgb::from_str(color)
-        .map(|color| {
-            Rgba([
-                (color.red * 255.) as u8,
-                (color.green * 255.) as u8,
-                (color.blue * 255.) as u8,
-                255,
-            ])
-        })
-        .unwrap_or(Rgba([255, 255, 255, 255]));
+/// Blur radius of the unsharp mask, in output pixels
+const SHARPEN_SIGMA: f32 = 0.7;
 
-    // Track produced files for Contents.json
-    let mut images: Vec<ImageEntry> = Vec::new();
+/// Largest output side, in pixels, that `--supersample` applies to
+const SUPERSAMPLE_MAX_SIZE: u32 = 32;
 
-    // Xcode AppIcon.appiconset slots - includes all optional slots
-    // Each entry: (base_size, multipliers, idiom, optional size_override, pixel_size_override)
-    let sizes = [
-        // iPhone App icons
-        (29, vec![2, 3], "iphone", None, None), // iPhone: 29x29@2x, @3x (Settings, etc.)
-        (40, vec![2, 3], "iphone", None, None), // iPhone: 40x40@2x, @3x (Spotlight)
-        (60, vec![2, 3], "iphone", None, None), // iPhone: 60x60@2x, @3x (App icon)
-        // iPhone Notification icons (optional slot)
-        (20, vec![2, 3], "iphone", None, None), // iPhone: 20x20@2x, @3x (Notifications)
-        // iPad App icons
-        (29, vec![1, 2], "ipad", None, None),   // iPad: 29x29@1x, @2x (Settings, etc.)
-        (40, vec![1, 2], "ipad", None, None),   // iPad: 40x40@1x, @2x (Spotlight)
-        (76, vec![1, 2], "ipad", None, None),   // iPad: 76x76@1x, @2x (App icon)
-        // iPad Notification icons (optional slot)
-        (20, vec![2], "ipad", None, None),      // iPad: 20x20@2x (Notifications)
-        // iPad Pro 12.9" App icon (optional slot) - 83.5pt @2x = 167px
-        (83, vec![2], "ipad", Some("83.5x83.5"), Some(167)),
-    ];
+/// Scale of the intermediate rendering for `--supersample`
+const SUPERSAMPLE_FACTOR: u32 = 4;
 
-    for (base_size, multipliers, idiom, size_override, pixel_size_override) in sizes {
-        for multiplier in multipliers {
-            // Use pixel_size_override if provided (for 83.5pt -> 167px case), otherwise calculate
-            let actual_size = pixel_size_override.unwrap_or(base_size * multiplier);
-            let filename = format!("AppIcon-{base_size}x{base_size}@{multiplier}x.png");
+/// Largest output side, in pixels, that `--legibility` applies to
+const LEGIBILITY_MAX_SIZE: u32 = 32;
 
-            let mut resized = source.resize_exact(actual_size, actual_size, FilterType::Lanczos3);
+/// Contrast factor of the `--legibility` pass, around mid-gray
+const LEGIBILITY_CONTRAST: f32 = 1.25;
 
-            // Add background color for iOS icons
-            // We need to blend the source image with background color and force opacity
-            let resized_rgba = resized.to_rgba8();
-            let final_img = ImageBuffer::from_fn(actual_size, actual_size, |x, y| {
-                let src_pixel = resized_rgba.get_pixel(x, y);
-                let src_alpha = src_pixel[3] as f32 / 255.0;
+/// Share of the neighboring coverage that `--legibility` grows into each pixel
+const LEGIBILITY_DILATION: f32 = 0.5;
 
-                if src_alpha == 0.0 {
-                    // Fully transparent - use background color
-                    bg_color
-                } else {
-                    // Blend source with background and force opacity
-                    let inv_alpha = 1.0 - src_alpha;
-                    Rgba([
-                        ((src_alpha * src_pixel[0] as f32 + inv_alpha * bg_color[0] as f32)) as u8,
-                        ((src_alpha * src_pixel[1] as f32 + inv_alpha * bg_color[1] as f32)) as u8,
-                        ((src_alpha * src_pixel[2] as f32 + inv_alpha * bg_color[2] as f32)) as u8,
-                        255, // Force full opacity for iOS
-                    ])
-                }
-            });
-            resized = DynamicImage::ImageRgba8(final_img);
+/// Smallest level, in pixels, of the halving chains built for `--resize-strategy mipmap`
+const MIPMAP_MIN_SIZE: u32 = 16;
 
-            let output_path = ios_dir.join(&filename);
-            save_png(&resized, &output_path, dev_mode, dev_bug)?;
-            println!("  ✓ Generated ios/{filename}");
+/// Halve `image` repeatedly down to `MIPMAP_MIN_SIZE`, largest level first
+/// Each halving averages 2x2 blocks, except that `nearest` keeps pixel art crisp
+fn build_mipmaps(image: &DynamicImage, filter: FilterType) -> Vec<DynamicImage> {
+    let filter = match filter {
+        FilterType::Nearest => FilterType::Nearest,
+        _ => FilterType::Triangle,
+    };
 
-            // Create ImageEntry for Contents.json (no role field for standard AppIcon)
-            let size_str = size_override.unwrap_or(&format!("{base_size}x{base_size}")).to_string();
+    let mut levels: Vec<DynamicImage> = Vec::new();
+    loop {
+        let level = levels.last().unwrap_or(image);
+        let (width, height) = (level.width() / 2, level.height() / 2);
+        if width.min(height) < MIPMAP_MIN_SIZE {
+            return levels;
+        }
+        levels.push(level.resize_exact(width, height, filter));
+    }
+}
 
-            let mut image_entry = ImageEntry::new_app_icon(
-                filename,
-                idiom.to_string(),
-                size_str,
-                format!("{multiplier}x"),
-                None, // No role for standard Xcode AppIcon
-            );
+/// How the artwork is scaled to each output size (`--filter`, `--sharpen`,
+/// `--supersample`, `--legibility`, `--resize-strategy`)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Resampler<'a> {
+    filter: FilterType,
+    /// Unsharp mask amount for small outputs, 0 to leave them as resized
+    sharpen: f32,
+    /// Reach tiny outputs through a larger intermediate rendering
+    supersample: bool,
+    /// Boost contrast and thicken strokes of tiny outputs
+    legibility: bool,
+    /// Resize platform sources from the nearest larger level of their halving chain
+    mipmap: bool,
+    /// The platform sources to swap for their `--size-rule` counterparts
+    sources: Option<(&'a PlatformSources<'a>, &'static str)>,
+}
 
-            image_entry.expected_size = Some(actual_size.to_string());
+impl Resampler<'_> {
+    fn from_args(args: &Args) -> Result<Self> {
+        let sharpen = args.sharpen.unwrap_or(0.0);
+        if !sharpen.is_finite() || sharpen < 0.0 {
+            anyhow::bail!("Invalid --sharpen value: {sharpen}. Expected a non-negative amount");
+        }
 
-            images.push(image_entry);
+        Ok(Self {
+            filter: parse_filter(&args.filter)?,
+            sharpen,
+            supersample: args.supersample,
+            legibility: args.legibility,
+            mipmap: match args.resize_strategy.as_str() {
+                "direct" => false,
+                "mipmap" => true,
+                strategy => anyhow::bail!(
+                    "Unknown resize strategy: {}. Available strategies: direct, mipmap",
+                    strategy
+                ),
+            },
+            sources: None,
+        })
+    }
+
+    /// Resize to exactly `width` x `height`, sharpening small results where
+    /// downscaling leaves them soft
+    pub(crate) fn resize(&self, image: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+        match self.sources {
+            Some((sources, platform)) if std::ptr::eq(image, sources.get(platform)) => {
+                // Small outputs of the platform source are drawn from the matching size rule instead
+                let rule_sources = sources.for_size(width.max(height));
+                let image = rule_sources.get(platform);
+                let mipmaps = rule_sources.mipmaps(platform);
+                sources.resized(image, width, height, || {
+                    self.resize_from(image, mipmaps, width, height)
+                })
+            }
+            _ => self.resize_from(image, &[], width, height),
         }
     }
 
-    // Generate 1024pt App Store marketing icon and add to Contents.json
-    let marketing_filename = "AppIcon-1024x1024.png";
-    let marketing_size = 1024;
-    let marketing_icon = source.resize_exact(marketing_size, marketing_size, FilterType::Lanczos3);
-
-    // Apply background color using same blend logic
-    let marketing_rgba = marketing_icon.to_rgba8();
-    let marketing_final = ImageBuffer::from_fn(marketing_size, marketing_size, |x, y| {
-        let src_pixel = marketing_rgba.get_pixel(x, y);
-        let src_alpha = src_pixel[3] as f32 / 255.0;
-
-        if src_alpha == 0.0 {
-            // Fully transparent - use background color
-            bg_color
+    /// Resize from `image`, or from the smallest level of its halving chain that is large enough
+    fn resize_from(
+        &self,
+        image: &DynamicImage,
+        mipmaps: &[DynamicImage],
+        width: u32,
+        height: u32,
+    ) -> DynamicImage {
+        let supersample = self.supersample && width.max(height) <= SUPERSAMPLE_MAX_SIZE;
+        let factor = if supersample { SUPERSAMPLE_FACTOR } else { 1 };
+        let (first_width, first_height) = (width * factor, height * factor);
+
+        // The smallest level that still covers the first step saves resizing the full source
+        let image = mipmaps
+            .iter()
+            .rev()
+            .find(|level| level.width() >= first_width && level.height() >= first_height)
+            .unwrap_or(image);
+        let resized = image.resize_exact(first_width, first_height, self.filter);
+        // Thin strokes survive better when the final step averages a 4x rendering
+        let resized = if supersample {
+            resized.resize_exact(width, height, self.filter)
         } else {
-            // Blend source with background and force opacity
-            let inv_alpha = 1.0 - src_alpha;
-            Rgba([
-                ((src_alpha * src_pixel[0] as f32 + inv_alpha * bg_color[0] as f32)) as u8,
-                ((src_alpha * src_pixel[1] as f32 + inv_alpha * bg_color[1] as f32)) as u8,
-                ((src_alpha * src_pixel[2] as f32 + inv_alpha * bg_color[2] as f32)) as u8,
-                255, // Force full opacity for iOS
-            ])
+            resized
+        };
+        let resized = if self.sharpen > 0.0 && width.max(height) <= SHARPEN_MAX_SIZE {
+            apply_unsharp_mask(&resized, self.sharpen)
+        } else {
+            resized
+        };
+        if self.legibility && width.max(height) <= LEGIBILITY_MAX_SIZE {
+            apply_legibility_boost(&resized)
+        } else {
+            resized
         }
-    });
-
-    let marketing_icon_final = DynamicImage::ImageRgba8(marketing_final);
-    let marketing_path = ios_dir.join(marketing_filename);
-    save_png(&marketing_icon_final, &marketing_path, dev_mode, dev_bug)?;
-    println!("  ✓ Generated ios/{} (for App Store)", marketing_filename);
-
-    // Add marketing icon entry to Contents.json
-    let marketing_entry = ImageEntry::new_app_icon(
-        marketing_filename.to_string(),
-        "ios-marketing".to_string(),
-        "1024x1024".to_string(),
-        "1x".to_string(),
-        None,
-    );
-    images.push(marketing_entry);
+    }
+}
 
-    // Write Contents.json (now includes all 17 icons)
-    write_contents_json(&ios_dir, images)?;
+/// Keep tiny icons recognizable: stretch the contrast and grow the alpha
+/// partway into each pixel's 3x3 neighborhood, so thin strokes gain weight
+fn apply_legibility_boost(image: &DynamicImage) -> DynamicImage {
+    let rgba = rgba_view(image);
+    let (width, height) = rgba.dimensions();
+
+    let boosted = ImageBuffer::from_fn(width, height, |x, y| {
+        let pixel = *rgba.get_pixel(x, y);
+        let alpha = pixel[3] as f32 / 255.0;
+
+        // The most opaque neighbor lends its coverage and color to the pixel
+        let mut neighbor = pixel;
+        for ny in y.saturating_sub(1)..(y + 2).min(height) {
+            for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                let candidate = rgba.get_pixel(nx, ny);
+                if candidate[3] > neighbor[3] {
+                    neighbor = *candidate;
+                }
+            }
+        }
+        let grown = alpha.max(neighbor[3] as f32 / 255.0 * LEGIBILITY_DILATION);
+        if grown <= 0.0 {
+            return Rgba([0, 0, 0, 0]);
+        }
 
-    Ok(())
+        let color = |c: usize| {
+            let own = pixel[c] as f32 / 255.0 * alpha;
+            let lent = neighbor[c] as f32 / 255.0 * (grown - alpha);
+            let value = (own + lent) / grown;
+            let value = (value - 0.5) * LEGIBILITY_CONTRAST + 0.5;
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        };
+        Rgba([color(0), color(1), color(2), (grown * 255.0).round() as u8])
+    });
+    DynamicImage::ImageRgba8(boosted)
 }
 
-fn save_png(image: &DynamicImage, path: &Path, dev_mode: bool, dev_bug: &str) -> Result<()> {
-    let mut img = image.clone();
+/// Push every pixel away from its blurred surroundings by `amount`
+/// Works on premultiplied color, so transparent pixels don't leave a halo around the artwork
+fn apply_unsharp_mask(image: &DynamicImage, amount: f32) -> DynamicImage {
+    let rgba = rgba_view(image);
+    let premultiplied: Rgba32FImage = ImageBuffer::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let pixel = rgba.get_pixel(x, y);
+        let alpha = pixel[3] as f32 / 255.0;
+        Rgba([
+            pixel[0] as f32 / 255.0 * alpha,
+            pixel[1] as f32 / 255.0 * alpha,
+            pixel[2] as f32 / 255.0 * alpha,
+            alpha,
+        ])
+    });
+    let blurred = image::imageops::blur(&premultiplied, SHARPEN_SIGMA);
+
+    let sharpened = ImageBuffer::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let original = premultiplied.get_pixel(x, y);
+        let blurred = blurred.get_pixel(x, y);
+        let channel =
+            |c: usize| (original[c] + amount * (original[c] - blurred[c])).clamp(0.0, 1.0);
 
-    // Apply dev badge if in dev mode
-    if dev_mode {
-        apply_dev_badge_with_bug(&mut img, dev_bug, 0.0)?;
-    }
+        let alpha = channel(3);
+        if alpha <= 0.0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let color = |c: usize| ((channel(c) / alpha).min(1.0) * 255.0).round() as u8;
+        Rgba([color(0), color(1), color(2), (alpha * 255.0).round() as u8])
+    });
+    DynamicImage::ImageRgba8(sharpened)
+}
 
-    let mut file = std::fs::File::create(path).context("Failed to create PNG file")?;
-    img.write_to(&mut file, image::ImageOutputFormat::Png)
-        .context("Failed to write PNG")?;
-    Ok(())
+/// Parse the CSS color of an option into an opaque RGBA pixel, failing with
+/// `Invalid <option>: <color>` where `parse_color` would fall back to white
+pub(crate) fn parse_color_checked(option: &str, color: &str) -> Result<Rgba<u8>> {
+    css_color::Srgb::from_str(color).map_err(|_| anyhow::anyhow!("Invalid {option}: {color}"))?;
+    Ok(parse_color(color))
 }
 
-// Encode image data as PNG with compression
-fn write_png<W: Write>(image_data: &[u8], w: W, size: u32) -> Result<()> {
-    let encoder = PngEncoder::new_with_quality(w, CompressionType::Best, PngFilterType::Adaptive);
-    encoder.write_image(image_data, size, size, ColorType::Rgba8)?;
-    Ok(())
+/// Parse a CSS color string into an opaque RGBA pixel, falling back to white
+pub(crate) fn parse_color(color: &str) -> Rgba<u8> {
+    css_color::Srgb::from_str(color)
+        .map(|color| {
+            Rgba([
+                (color.red * 255.) as u8,
+                (color.green * 255.) as u8,
+                (color.blue * 255.) as u8,
+                255,
+            ])
+        })
+        .unwrap_or(Rgba([255, 255, 255, 255]))
 }
 
-/// Write Contents.json file with the provided image entries
-fn write_contents_json(ios_dir: &Path, images: Vec<ImageEntry>) -> Result<()> {
+/// Write the Contents.json of `folder` (relative to `out_dir`) with the provided image entries
+fn write_contents_json(
+    out_dir: &Path,
+    folder: &Path,
+    images: Vec<ImageEntry>,
+    merge: bool,
+    output: &dyn OutputSink,
+) -> Result<()> {
     let mut contents = ContentsFile::new("icon-generator".to_string());
 
     for image in images {
         contents.add_image(image);
     }
 
-    let contents_path = ios_dir.join("Contents.json");
+    let contents_path = folder.join("Contents.json");
+    let contents = merge_contents(&out_dir.join(&contents_path), contents, merge, output)?;
     let contents_json =
         serde_json::to_string_pretty(&contents).context("Failed to serialize Contents.json")?;
 
-    std::fs::write(&contents_path, contents_json).context("Failed to write Contents.json file")?;
+    output.write(&out_dir.join(&contents_path), contents_json.as_bytes())?;
 
-    println!("  ✓ Generated ios/Contents.json");
+    progress::file(format_args!("  ✓ Generated {}", contents_path.display()));
     Ok(())
 }
 
-/// Build a Vec<ImageEntry> for macOS using the same icns_json map (sizes 16–1024)
+/// Build a Vec<ImageEntry> for macOS from the ICNS members (sizes 16–1024), in their order
 /// Idiom = "mac", scale is "1x" or "2x" depending on whether name contains @2x
-/// Folder always "." (same directory)
-fn build_macos_contents_json(entries: &HashMap<String, IcnsEntry>) -> Result<Vec<ImageEntry>> {
+/// Each entry names the PNG written next to Contents.json
+fn build_macos_contents_json(entries: &[IcnsEntry]) -> Result<Vec<ImageEntry>> {
     let mut images = Vec::new();
 
-    for (name, entry) in entries {
+    for entry in entries {
+        let name = entry.name;
         // Determine scale based on whether name contains @2x
         let scale = if name.contains("@2x") {
             "2x".to_string()
@@ -761,28 +4419,44 @@ fn build_macos_contents_json(entries: &HashMap<String, IcnsEntry>) -> Result<Vec
         let base_name = if name.contains("@2x") {
             name.replace("@2x", "")
         } else {
-            name.clone()
+            name.to_string()
         };
 
-        // Create filename based on the entry size and scale
-        let filename = format!("icon_{}.png", entry.size);
-
-        let mut image_entry = ImageEntry::new(filename, "mac".to_string(), scale);
+        let mut image_entry = ImageEntry::new(macos_png_name(entry), "mac".to_string(), scale);
 
         // Set size to the base name (e.g., "16x16", "32x32", etc.)
         image_entry.size = Some(base_name);
 
-        // Set folder to "." (same directory)
-        image_entry = image_entry.with_folder(".".to_string());
-
         images.push(image_entry);
     }
 
     Ok(images)
 }
 
+/// With `--contents-json merge`, the Contents.json already at `path` with the
+/// `generated` entries merged in; otherwise, or when there is none, `generated`
+fn merge_contents(
+    path: &Path,
+    generated: ContentsFile,
+    merge: bool,
+    output: &dyn OutputSink,
+) -> Result<ContentsFile> {
+    if !merge || !output.exists(path) {
+        return Ok(generated);
+    }
+    let mut existing = serde_json::from_slice::<ContentsFile>(&output.read(path)?)
+        .with_context(|| format!("Can't merge into {}", path.display()))?;
+    existing.merge(generated);
+    Ok(existing)
+}
+
 /// Write Contents.json file for macOS next to icon.icns
-fn write_macos_contents_json(out_dir: &Path, images: Vec<ImageEntry>) -> Result<()> {
+fn write_macos_contents_json(
+    out_dir: &Path,
+    images: Vec<ImageEntry>,
+    merge: bool,
+    output: &dyn OutputSink,
+) -> Result<()> {
     let mut contents = ContentsFile::new("icon-generator".to_string());
 
     for image in images {
@@ -790,97 +4464,153 @@ fn write_macos_contents_json(out_dir: &Path, images: Vec<ImageEntry>) -> Result<
     }
 
     let contents_path = out_dir.join("Contents.json");
+    let contents = merge_contents(&contents_path, contents, merge, output)?;
     let contents_json = serde_json::to_string_pretty(&contents)
         .context("Failed to serialize macOS Contents.json")?;
 
-    std::fs::write(&contents_path, contents_json)
-        .context("Failed to write macOS Contents.json file")?;
+    output.write(&contents_path, contents_json.as_bytes())?;
 
-    println!("  ✓ Generated Contents.json");
+    progress::file("  ✓ Generated macos/Contents.json");
     Ok(())
 }
 
+/// Launcher icon size of each Android density bucket
+pub(crate) const ANDROID_DENSITIES: [(&str, u32); 5] = [
+    ("mdpi", 48),
+    ("hdpi", 72),
+    ("xhdpi", 96),
+    ("xxhdpi", 144),
+    ("xxxhdpi", 192),
+];
+
 /// Generate Android icons with support for round and adaptive icons
-fn generate_android_icons_extended(source: &DynamicImage, args: &Args) -> Result<()> {
+fn generate_android_icons_extended(
+    source: &DynamicImage,
+    resampler: Resampler<'_>,
+    args: &Args,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let overlays = Overlays::from_args(args)?;
     let android_dir = args.output.join("android");
-    create_dir_all(&android_dir)?;
-
-    println!("Generating Android icons...");
 
-    let densities = [
-        ("mdpi", 48),
-        ("hdpi", 72),
-        ("xhdpi", 96),
-        ("xxhdpi", 144),
-        ("xxxhdpi", 192),
-    ];
+    progress::step("Generating Android icons...");
 
-    // Generate standard square icons (ic_launcher.png)
-    for (density, size) in densities {
-        let mipmap_dir = android_dir.join(format!("mipmap-{density}"));
-        create_dir_all(&mipmap_dir)?;
+    let densities = ANDROID_DENSITIES;
 
-        let resized = source.resize_exact(size, size, FilterType::Lanczos3);
-        let output_path = mipmap_dir.join("ic_launcher.png");
-        save_png(&resized, &output_path, args.dev_mode, &args.dev_bug)?;
-        println!("  ✓ Generated android/mipmap-{density}/ic_launcher.png");
-    }
+    // Legacy shapes are filled with the same background as the adaptive icon layers
+    let legacy_bg = parse_color(&args.android_adaptive_bg);
 
-    // Generate round icons if requested (enabled by default with --android)
-    if args.android_round {
-        println!("Generating Android round icons...");
-        for (density, size) in densities {
+    // Generate standard square icons (ic_launcher.png)
+    densities
+        .into_par_iter()
+        .try_for_each(|(density, size)| -> Result<()> {
             let mipmap_dir = android_dir.join(format!("mipmap-{density}"));
 
-            // Create a round version by applying a circular mask
-            let resized = source.resize_exact(size, size, FilterType::Lanczos3);
-            let round_icon = apply_circular_mask(&resized)?;
+            let mut resized = resampler.resize(source, size, size);
+            if args.android_legacy_shape != "none" {
+                resized =
+                    apply_legacy_shape(&resized, &args.android_legacy_shape, legacy_bg, resampler)?;
+            }
+            overlays.apply(&mut resized);
+            save_png(
+                &resized,
+                &mipmap_dir.join("ic_launcher.png"),
+                args.png_encoding(),
+                output,
+            )?;
+            progress::file(format_args!(
+                "  ✓ Generated android/mipmap-{density}/ic_launcher.png"
+            ));
+            Ok(())
+        })?;
 
-            let output_path = mipmap_dir.join("ic_launcher_round.png");
-            save_png(&round_icon, &output_path, args.dev_mode, &args.dev_bug)?;
-            println!("  ✓ Generated android/mipmap-{density}/ic_launcher_round.png");
-        }
+    // Generate round icons if requested (enabled by default with --android)
+    if args.android_round || args.android {
+        progress::step("Generating Android round icons...");
+        densities
+            .into_par_iter()
+            .try_for_each(|(density, size)| -> Result<()> {
+                let mipmap_dir = android_dir.join(format!("mipmap-{density}"));
+
+                // Create a round version by applying a circular mask (or the legacy circle shape)
+                let resized = resampler.resize(source, size, size);
+                let mut round_icon = if args.android_legacy_shape != "none" {
+                    apply_legacy_shape(&resized, "circle", legacy_bg, resampler)?
+                } else {
+                    apply_circular_mask(&resized)?
+                };
+
+                overlays.apply(&mut round_icon);
+                save_png(
+                    &round_icon,
+                    &mipmap_dir.join("ic_launcher_round.png"),
+                    args.png_encoding(),
+                    output,
+                )?;
+                progress::file(format_args!(
+                    "  ✓ Generated android/mipmap-{density}/ic_launcher_round.png"
+                ));
+                Ok(())
+            })?;
     }
 
     // Generate adaptive icons if requested
     if args.android_adaptive {
-        println!("Generating Android adaptive icons...");
-        generate_adaptive_icons(
-            source,
-            &android_dir,
-            &args.android_adaptive_bg,
-            args.dev_mode,
-            &args.dev_bug,
-        )?;
+        progress::step("Generating Android adaptive icons...");
+        generate_adaptive_icons(source, &android_dir, resampler, &overlays, args, output)?;
     }
 
     Ok(())
 }
 
+/// Subsamples per pixel side used to measure the circular mask's edge coverage
+const CIRCLE_SUPERSAMPLE: u32 = 4;
+
 /// Apply a circular mask to an image to create a round icon
+/// Edge pixels keep alpha in proportion to how much of them the circle covers,
+/// measured on a 4x4 grid, so small round icons get a smooth outline
 fn apply_circular_mask(img: &DynamicImage) -> Result<DynamicImage> {
     let width = img.width();
     let height = img.height();
     let center_x = width as f32 / 2.0;
     let center_y = height as f32 / 2.0;
     let radius = width.min(height) as f32 / 2.0;
+    let samples = CIRCLE_SUPERSAMPLE as f32;
 
     let mut rgba_img = img.to_rgba8();
 
     for y in 0..height {
         for x in 0..width {
-            let dx = x as f32 - center_x;
-            let dy = y as f32 - center_y;
+            let dx = x as f32 + 0.5 - center_x;
+            let dy = y as f32 + 0.5 - center_y;
             let distance = (dx * dx + dy * dy).sqrt();
 
-            // Apply anti-aliasing at the edge
-            if distance > radius {
+            // Pixels well inside the circle are kept as they are, and only the ones
+            // within half a diagonal of the edge need sampling
+            if distance <= radius - std::f32::consts::FRAC_1_SQRT_2 {
+                continue;
+            }
+            let coverage = if distance >= radius + std::f32::consts::FRAC_1_SQRT_2 {
+                0.0
+            } else {
+                let mut inside = 0;
+                for sy in 0..CIRCLE_SUPERSAMPLE {
+                    for sx in 0..CIRCLE_SUPERSAMPLE {
+                        let sample_x = x as f32 + (sx as f32 + 0.5) / samples - center_x;
+                        let sample_y = y as f32 + (sy as f32 + 0.5) / samples - center_y;
+                        if sample_x * sample_x + sample_y * sample_y <= radius * radius {
+                            inside += 1;
+                        }
+                    }
+                }
+                inside as f32 / (samples * samples)
+            };
+
+            if coverage == 0.0 {
                 rgba_img.put_pixel(x, y, Rgba([0, 0, 0, 0]));
-            } else if distance > radius - 1.0 {
-                // Anti-aliasing edge
-                let alpha_factor = radius - distance;
+            } else {
                 let pixel = rgba_img.get_pixel_mut(x, y);
-                pixel[3] = (pixel[3] as f32 * alpha_factor) as u8;
+                pixel[3] = (pixel[3] as f32 * coverage).round() as u8;
             }
         }
     }
@@ -888,25 +4618,326 @@ fn apply_circular_mask(img: &DynamicImage) -> Result<DynamicImage> {
     Ok(DynamicImage::ImageRgba8(rgba_img))
 }
 
+/// Render a legacy (pre-Android 8.0) launcher icon on a material-style background shape
+/// Mirrors Android Studio's Image Asset output: the artwork is clipped to the shape,
+/// filled with the background color, and lifted off the canvas with a subtle shadow
+fn apply_legacy_shape(
+    img: &DynamicImage,
+    shape: &str,
+    bg_color: Rgba<u8>,
+    resampler: Resampler<'_>,
+) -> Result<DynamicImage> {
+    // Shape geometry on the 48dp legacy launcher grid: (offset, extent, corner radius)
+    let (offset_dp, extent_dp, radius_dp) = match shape {
+        "square" => (5.0, 38.0, 3.0),
+        "circle" => (2.0, 44.0, 22.0),
+        _ => anyhow::bail!(
+            "Unknown legacy shape: {}. Available shapes: none, square, circle",
+            shape
+        ),
+    };
+
+    let size = img.width().min(img.height());
+    let dp = size as f32 / 48.0;
+    let offset = offset_dp * dp;
+    let extent = extent_dp * dp;
+    let radius = radius_dp * dp;
+
+    // Place the artwork inside the shape bounds
+    let art_size = extent.round() as u32;
+    let artwork = resampler.resize(img, art_size, art_size);
+    let mut art_canvas = ImageBuffer::from_fn(size, size, |_, _| Rgba([0, 0, 0, 0]));
+    image::imageops::overlay(
+        &mut art_canvas,
+        &artwork,
+        offset.round() as i64,
+        offset.round() as i64,
+    );
+
+    // Soft drop shadow: the shape shifted down by 1dp and blurred
+    let shadow = ImageBuffer::from_fn(size, size, |x, y| {
+        let coverage =
+            rounded_rect_coverage(x as f32 + 0.5, y as f32 + 0.5 - dp, offset, extent, radius);
+        Rgba([0, 0, 0, (coverage * 0.3 * 255.0) as u8])
+    });
+    let mut output = image::imageops::blur(&shadow, dp.max(0.5));
+
+    for (x, y, pixel) in output.enumerate_pixels_mut() {
+        let coverage =
+            rounded_rect_coverage(x as f32 + 0.5, y as f32 + 0.5, offset, extent, radius);
+        if coverage <= 0.0 {
+            continue;
+        }
+
+        let mut shape_pixel = bg_color;
+        shape_pixel.blend(art_canvas.get_pixel(x, y));
+        shape_pixel[3] = (shape_pixel[3] as f32 * coverage) as u8;
+        pixel.blend(&shape_pixel);
+    }
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+/// Composite artwork into the macOS Big Sur icon template
+/// On the 1024pt grid the tile is an 824pt white rounded rectangle (185.4pt corners)
+/// inset by 100pt, casting a 28pt blurred shadow offset 12pt downward
+fn apply_big_sur_style(img: &DynamicImage, resampler: Resampler<'_>) -> DynamicImage {
+    let size = img.width().min(img.height());
+    let pt = size as f32 / 1024.0;
+    let offset = 100.0 * pt;
+    let extent = 824.0 * pt;
+    let radius = 185.4 * pt;
+
+    // The artwork fills the tile and is clipped by its corners
+    let art_size = extent.round() as u32;
+    let artwork = resampler.resize(img, art_size, art_size);
+    let mut art_canvas = ImageBuffer::from_fn(size, size, |_, _| Rgba([0, 0, 0, 0]));
+    image::imageops::overlay(
+        &mut art_canvas,
+        &artwork,
+        offset.round() as i64,
+        offset.round() as i64,
+    );
+
+    let shadow = ImageBuffer::from_fn(size, size, |x, y| {
+        let coverage = rounded_rect_coverage(
+            x as f32 + 0.5,
+            y as f32 + 0.5 - 12.0 * pt,
+            offset,
+            extent,
+            radius,
+        );
+        Rgba([0, 0, 0, (coverage * 0.5 * 255.0) as u8])
+    });
+    let mut output = image::imageops::blur(&shadow, (14.0 * pt).max(0.5));
+
+    for (x, y, pixel) in output.enumerate_pixels_mut() {
+        let coverage =
+            rounded_rect_coverage(x as f32 + 0.5, y as f32 + 0.5, offset, extent, radius);
+        if coverage <= 0.0 {
+            continue;
+        }
+
+        let mut tile_pixel = Rgba([255, 255, 255, 255]);
+        tile_pixel.blend(art_canvas.get_pixel(x, y));
+        tile_pixel[3] = (tile_pixel[3] as f32 * coverage) as u8;
+        pixel.blend(&tile_pixel);
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+/// Anti-aliased coverage (0.0–1.0) of a pixel center by a square with rounded corners
+/// The square starts at `offset` on both axes and spans `extent` pixels
+fn rounded_rect_coverage(px: f32, py: f32, offset: f32, extent: f32, radius: f32) -> f32 {
+    let half = extent / 2.0;
+    let center = offset + half;
+    let radius = radius.min(half);
+
+    // Signed distance from the point to the rounded rectangle edge
+    let qx = (px - center).abs() - (half - radius);
+    let qy = (py - center).abs() - (half - radius);
+    let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+    let inside = qx.max(qy).min(0.0);
+    let distance = outside + inside - radius;
+
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+/// Clip an image to Apple's continuous-corner icon shape
+/// The outline is a superellipse |x|^n + |y|^n = 1 spanning the whole canvas, whose
+/// curvature eases into the straight edges instead of meeting them at a tangent arc
+fn apply_squircle_mask(img: &DynamicImage) -> DynamicImage {
+    const EXPONENT: f32 = 5.0;
+
+    let half = img.width().min(img.height()) as f32 / 2.0;
+    let mut rgba_img = img.to_rgba8();
+
+    for (x, y, pixel) in rgba_img.enumerate_pixels_mut() {
+        let nx = ((x as f32 + 0.5 - half) / half).abs();
+        let ny = ((y as f32 + 0.5 - half) / half).abs();
+
+        // Radial distance to the outline in pixels, which is exact along the axes
+        // and close enough elsewhere for one pixel of anti-aliasing
+        let radius = (nx.powf(EXPONENT) + ny.powf(EXPONENT)).powf(1.0 / EXPONENT);
+        let distance = (radius - 1.0) * half;
+        let coverage = (0.5 - distance).clamp(0.0, 1.0);
+        pixel[3] = (pixel[3] as f32 * coverage) as u8;
+    }
+
+    DynamicImage::ImageRgba8(rgba_img)
+}
+
+/// Parse a `--radius` value, either pixels of the source image (`24`, `24px`)
+/// or a percentage of its side (`12%`), into source pixels
+fn parse_radius(radius: &str, size: u32) -> Result<f32> {
+    let (value, percent) = match radius.trim().strip_suffix('%') {
+        Some(value) => (value, true),
+        None => (radius.trim().trim_end_matches("px"), false),
+    };
+
+    let value: f32 = value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|value: &f32| value.is_finite() && *value >= 0.0)
+        .with_context(|| {
+            format!(
+                "Invalid radius: {radius}. Use pixels (e.g. 24 or 24px) or a percentage (e.g. 12%)"
+            )
+        })?;
+
+    if percent {
+        if value > 50.0 {
+            anyhow::bail!(
+                "Invalid radius: {}. Percentages must be between 0% and 50%",
+                radius
+            );
+        }
+        Ok(size as f32 * value / 100.0)
+    } else {
+        Ok(value)
+    }
+}
+
+/// Outline stroke parameters, with the width in source image pixels
+#[derive(Debug, Clone, Copy)]
+struct Border {
+    width: f32,
+    color: Rgba<u8>,
+}
+
+/// Parse a `--border` value of the form `<width>,<color>`
+/// Only the first comma separates the two, so colors like `rgb(0, 0, 0)` work
+fn parse_border(border: &str) -> Result<Border> {
+    let (width, color) = border.split_once(',').with_context(|| {
+        format!("Invalid border: {border}. Use <width>,<color>, e.g. 8,#ffffff")
+    })?;
+
+    let width = width
+        .trim()
+        .trim_end_matches("px")
+        .parse::<f32>()
+        .ok()
+        .filter(|width| width.is_finite() && *width > 0.0)
+        .with_context(|| format!("Invalid border width: {}", width.trim()))?;
+
+    let color = parse_color_checked("border color", color.trim())?;
+
+    Ok(Border { width, color })
+}
+
+/// Stroke the inside of the rounded-rectangle outline covering the whole canvas
+/// A radius of half the side draws a ring
+fn apply_border(img: &DynamicImage, border: Border, radius: f32) -> DynamicImage {
+    let size = img.width().min(img.height()) as f32;
+    let width = border.width.min(size / 2.0);
+    let inner_radius = (radius - width).max(0.0);
+    let mut rgba_img = img.to_rgba8();
+
+    for (x, y, pixel) in rgba_img.enumerate_pixels_mut() {
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+        let outer = rounded_rect_coverage(px, py, 0.0, size, radius);
+        let inner = rounded_rect_coverage(px, py, width, size - 2.0 * width, inner_radius);
+        let coverage = (outer - inner).max(0.0);
+        if coverage <= 0.0 {
+            continue;
+        }
+
+        let mut stroke = border.color;
+        stroke[3] = (stroke[3] as f32 * coverage) as u8;
+        pixel.blend(&stroke);
+    }
+
+    DynamicImage::ImageRgba8(rgba_img)
+}
+
+/// Drop shadow parameters, in source image pixels
+#[derive(Debug, Clone, Copy)]
+struct Shadow {
+    blur: f32,
+    offset: (i32, i32),
+    opacity: f32,
+}
+
+/// Validate the `--shadow-*` options
+fn parse_shadow(args: &Args) -> Result<Shadow> {
+    let offset = match args.shadow_offset[..] {
+        [x, y] => (x, y),
+        _ => anyhow::bail!("Shadow offset must be given as X,Y"),
+    };
+    if !(0.0..=1.0).contains(&args.shadow_opacity) {
+        anyhow::bail!(
+            "Invalid shadow opacity: {}. Opacity must be between 0 and 1",
+            args.shadow_opacity
+        );
+    }
+    if !args.shadow_blur.is_finite() || args.shadow_blur < 0.0 {
+        anyhow::bail!("Invalid shadow blur: {}", args.shadow_blur);
+    }
+
+    Ok(Shadow {
+        blur: args.shadow_blur,
+        offset,
+        opacity: args.shadow_opacity,
+    })
+}
+
+/// Render a soft shadow under the artwork
+/// The artwork is placed on a transparent canvas enlarged by enough margin for the
+/// blurred, offset shadow, and the result is scaled back to the original size
+fn apply_drop_shadow(img: &DynamicImage, shadow: Shadow, resampler: Resampler<'_>) -> DynamicImage {
+    let size = img.width();
+    let (dx, dy) = shadow.offset;
+    let margin = (shadow.blur * 1.5).ceil() as u32 + dx.unsigned_abs().max(dy.unsigned_abs());
+    let canvas_size = size + 2 * margin;
+
+    let rgba_img = img.to_rgba8();
+    let mut silhouette = ImageBuffer::from_pixel(canvas_size, canvas_size, Rgba([0, 0, 0, 0]));
+    for (x, y, pixel) in rgba_img.enumerate_pixels() {
+        let sx = (x + margin) as i64 + dx as i64;
+        let sy = (y + margin) as i64 + dy as i64;
+        let alpha = (pixel[3] as f32 * shadow.opacity) as u8;
+        silhouette.put_pixel(sx as u32, sy as u32, Rgba([0, 0, 0, alpha]));
+    }
+
+    // imageops::blur takes a gaussian sigma; the blur radius spans about two of them
+    let mut canvas = if shadow.blur > 0.0 {
+        image::imageops::blur(&silhouette, shadow.blur / 2.0)
+    } else {
+        silhouette
+    };
+    image::imageops::overlay(&mut canvas, &rgba_img, margin.into(), margin.into());
+
+    resampler.resize(&DynamicImage::ImageRgba8(canvas), size, size)
+}
+
+/// Clip an image to a rounded rectangle covering the whole canvas
+/// Radii larger than half the side are clamped, yielding a circle
+fn apply_rounded_corners(img: &DynamicImage, radius: f32) -> DynamicImage {
+    let size = img.width().min(img.height()) as f32;
+    let mut rgba_img = img.to_rgba8();
+
+    for (x, y, pixel) in rgba_img.enumerate_pixels_mut() {
+        let coverage = rounded_rect_coverage(x as f32 + 0.5, y as f32 + 0.5, 0.0, size, radius);
+        pixel[3] = (pixel[3] as f32 * coverage) as u8;
+    }
+
+    DynamicImage::ImageRgba8(rgba_img)
+}
+
 /// Generate Android adaptive icons with foreground and background layers
 fn generate_adaptive_icons(
     source: &DynamicImage,
     android_dir: &Path,
-    bg_color_str: &str,
-    dev_mode: bool,
-    dev_bug: &str,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    args: &Args,
+    output: &dyn OutputSink,
 ) -> Result<()> {
+    let encoding = args.png_encoding();
     // Parse background color
-    let bg_color = css_color::Srgb::from_str(bg_color_str)
-        .map(|color| {
-            Rgba([
-                (color.red * 255.) as u8,
-                (color.green * 255.) as u8,
-                (color.blue * 255.) as u8,
-                255,
-            ])
-        })
-        .unwrap_or(Rgba([255, 255, 255, 255]));
+    let bg_color = parse_color(&args.android_adaptive_bg);
 
     // Adaptive icon sizes (108dp with 72dp visible area)
     // The extra 36dp (18dp on each side) is for visual effects
@@ -918,48 +4949,133 @@ fn generate_adaptive_icons(
         ("xxxhdpi", 432),
     ];
 
-    // Generate foreground layers (the actual icon, scaled to fit in safe zone)
+    // Scale the icon to 66% of the adaptive icon size to fit in the safe zone
+    // This ensures the icon is fully visible in all shapes (circle, square, rounded square, etc.)
+    let foreground = fit_safe_zone(
+        Compositor::new(resampler, overlays),
+        source,
+        SafeZone::AdaptiveIcon,
+        &args.safe_zone,
+        0.66,
+    );
+
+    // Generate foreground layers (the actual icon, centered on a transparent canvas)
     for (density, size) in adaptive_densities {
         let mipmap_dir = android_dir.join(format!("mipmap-{density}"));
-        create_dir_all(&mipmap_dir)?;
-
-        // Scale the icon to 66% of the adaptive icon size to fit in the safe zone
-        // This ensures the icon is fully visible in all shapes (circle, square, rounded square, etc.)
-        let icon_size = (size as f32 * 0.66) as u32;
-        let padding = (size - icon_size) / 2;
-
-        let resized = source.resize_exact(icon_size, icon_size, FilterType::Lanczos3);
-
-        // Create a transparent canvas of the full adaptive size
-        let mut foreground = ImageBuffer::from_fn(size, size, |_, _| Rgba([0, 0, 0, 0]));
-
-        // Center the icon on the canvas
-        image::imageops::overlay(&mut foreground, &resized, padding.into(), padding.into());
 
-        let foreground_img = DynamicImage::ImageRgba8(foreground);
         let output_path = mipmap_dir.join("ic_launcher_foreground.png");
-        save_png(&foreground_img, &output_path, dev_mode, dev_bug)?;
-        println!("  ✓ Generated android/mipmap-{density}/ic_launcher_foreground.png");
+        save_png(
+            &foreground.render(source, size),
+            &output_path,
+            encoding,
+            output,
+        )?;
+        progress::file(format_args!(
+            "  ✓ Generated android/mipmap-{density}/ic_launcher_foreground.png"
+        ));
 
         // Generate background layer (solid color)
         let background = ImageBuffer::from_fn(size, size, |_, _| bg_color);
         let background_img = DynamicImage::ImageRgba8(background);
         let bg_output_path = mipmap_dir.join("ic_launcher_background.png");
-        save_png(&background_img, &bg_output_path, false, "")?; // Don't apply dev badge to background
-        println!("  ✓ Generated android/mipmap-{density}/ic_launcher_background.png");
+        save_png(&background_img, &bg_output_path, encoding, output)?;
+        progress::file(format_args!(
+            "  ✓ Generated android/mipmap-{density}/ic_launcher_background.png"
+        ));
     }
 
     // Generate XML configuration files for adaptive icons
-    generate_adaptive_icon_xml(&android_dir)?;
+    generate_adaptive_icon_xml(android_dir, output)?;
+
+    Ok(())
+}
+
+/// Generate Android 12 splash screen assets (android/drawable-*/splash_icon.png)
+/// Each drawable is a 288dp transparent canvas with the icon in the central 192dp,
+/// as expected by windowSplashScreenAnimatedIcon
+fn generate_android_splash(
+    source: &DynamicImage,
+    out_dir: &Path,
+    color: &str,
+    resampler: Resampler<'_>,
+    overlays: &Overlays,
+    encoding: PngEncoding,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let android_dir = out_dir.join("android");
+
+    progress::step("Generating Android splash screen icons...");
+    let splash_densities = [
+        ("mdpi", 288),
+        ("hdpi", 432),
+        ("xhdpi", 576),
+        ("xxhdpi", 864),
+        ("xxxhdpi", 1152),
+    ];
+
+    for (density, size) in splash_densities {
+        let drawable_dir = android_dir.join(format!("drawable-{density}"));
+
+        // 192dp of the 288dp canvas stays visible inside the system's circular mask
+        let icon_size = size * 2 / 3;
+        let padding = (size - icon_size) / 2;
+        let resized = resampler.resize(source, icon_size, icon_size);
+
+        let mut canvas = ImageBuffer::from_pixel(size, size, Rgba([0, 0, 0, 0]));
+        image::imageops::overlay(&mut canvas, &resized, padding.into(), padding.into());
+
+        let mut splash_icon = DynamicImage::ImageRgba8(canvas);
+        overlays.apply(&mut splash_icon);
+        save_png(
+            &splash_icon,
+            &drawable_dir.join("splash_icon.png"),
+            encoding,
+            output,
+        )?;
+        progress::file(format_args!(
+            "  ✓ Generated android/drawable-{density}/splash_icon.png"
+        ));
+    }
+
+    generate_android_splash_xml(&android_dir, parse_color(color), output)?;
+
+    Ok(())
+}
+
+/// Generate the values/splash.xml theme snippet for the androidx SplashScreen API
+fn generate_android_splash_xml(
+    android_dir: &Path,
+    bg_color: Rgba<u8>,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let values_dir = android_dir.join("values");
+
+    let splash_xml = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<resources>
+    <color name="splash_background">#{:02X}{:02X}{:02X}</color>
+
+    <!-- Set as the activity theme; requires androidx.core:core-splashscreen -->
+    <style name="Theme.App.Starting" parent="Theme.SplashScreen">
+        <item name="windowSplashScreenBackground">@color/splash_background</item>
+        <item name="windowSplashScreenAnimatedIcon">@drawable/splash_icon</item>
+        <item name="postSplashScreenTheme">@style/Theme.App</item>
+    </style>
+</resources>
+"#,
+        bg_color[0], bg_color[1], bg_color[2]
+    );
+
+    output.write(&values_dir.join("splash.xml"), splash_xml.as_bytes())?;
+    progress::file("  ✓ Generated android/values/splash.xml");
 
     Ok(())
 }
 
 /// Generate XML configuration files for Android adaptive icons
-fn generate_adaptive_icon_xml(android_dir: &Path) -> Result<()> {
+fn generate_adaptive_icon_xml(android_dir: &Path, output: &dyn OutputSink) -> Result<()> {
     // Create mipmap-anydpi-v26 directory for adaptive icon XML
     let anydpi_dir = android_dir.join("mipmap-anydpi-v26");
-    create_dir_all(&anydpi_dir)?;
 
     // ic_launcher.xml for adaptive square icon
     let ic_launcher_xml = r#"<?xml version="1.0" encoding="utf-8"?>
@@ -968,9 +5084,11 @@ fn generate_adaptive_icon_xml(android_dir: &Path) -> Result<()> {
     <foreground android:drawable="@mipmap/ic_launcher_foreground" />
 </adaptive-icon>"#;
 
-    std::fs::write(anydpi_dir.join("ic_launcher.xml"), ic_launcher_xml)
-        .context("Failed to write ic_launcher.xml")?;
-    println!("  ✓ Generated android/mipmap-anydpi-v26/ic_launcher.xml");
+    output.write(
+        &anydpi_dir.join("ic_launcher.xml"),
+        ic_launcher_xml.as_bytes(),
+    )?;
+    progress::file("  ✓ Generated android/mipmap-anydpi-v26/ic_launcher.xml");
 
     // ic_launcher_round.xml for adaptive round icon (same layers, system handles the shape)
     let ic_launcher_round_xml = r#"<?xml version="1.0" encoding="utf-8"?>
@@ -979,38 +5097,11 @@ fn generate_adaptive_icon_xml(android_dir: &Path) -> Result<()> {
     <foreground android:drawable="@mipmap/ic_launcher_foreground" />
 </adaptive-icon>"#;
 
-    std::fs::write(
-        anydpi_dir.join("ic_launcher_round.xml"),
-        ic_launcher_round_xml,
-    )
-    .context("Failed to write ic_launcher_round.xml")?;
-    println!("  ✓ Generated android/mipmap-anydpi-v26/ic_launcher_round.xml");
+    output.write(
+        &anydpi_dir.join("ic_launcher_round.xml"),
+        ic_launcher_round_xml.as_bytes(),
+    )?;
+    progress::file("  ✓ Generated android/mipmap-anydpi-v26/ic_launcher_round.xml");
 
     Ok(())
 }
-
-/// Resize the bug image to the given size, maintaining the aspect ratio
-fn resize_bug_with_aspect_ratio(bug_img: &DynamicImage, target_size: u32) -> DynamicImage {
-    let original_width = bug_img.width() as f32;
-    let original_height = bug_img.height() as f32;
-    let target_size_f32 = target_size as f32;
-
-    // Calculate aspect ratio
-    let aspect_ratio = original_width / original_height;
-
-    // Calculate new dimensions to fit within target_size while maintaining aspect ratio
-    let (new_width, new_height) = if aspect_ratio > 1.0 {
-        // Image is wider than tall
-        let width = target_size_f32;
-        let height = target_size_f32 / aspect_ratio;
-        (width as u32, height as u32)
-    } else {
-        // Image is taller than wide (or square)
-        let height = target_size_f32;
-        let width = target_size_f32 * aspect_ratio;
-        (width as u32, height as u32)
-    };
-
-    // Resize the image with the calculated dimensions
-    bug_img.resize_exact(new_width, new_height, FilterType::Lanczos3)
-}
@@ -0,0 +1,37 @@
+//! Stopping a run early, e.g. on Ctrl-C
+//!
+//! The library doesn't install signal handlers itself; the binary calls
+//! [`interrupt`] from its Ctrl-C handler, and embedders can do the same from
+//! wherever they cancel work. The run then stops before writing its next file
+//! and fails with [`IconGenError::Interrupted`]. Files are written to a
+//! temporary name and renamed into place, so an interrupted run leaves no
+//! partially written files behind, and the set it was working on isn't recorded
+//! in the generation cache, so the next run generates it again.
+
+use crate::error::{IconGenError, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Ask the running generation to stop; safe to call from a signal handler
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Whether [`interrupt`] was called since the last [`reset`]
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Let runs proceed again after an interruption
+pub fn reset() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+/// Fail with [`IconGenError::Interrupted`] once the run was asked to stop
+pub(crate) fn check() -> Result<()> {
+    if is_interrupted() {
+        return Err(IconGenError::Interrupted);
+    }
+    Ok(())
+}
@@ -0,0 +1,498 @@
+//! Batch job files (`icon-gen run jobs.yaml`)
+//!
+//! A job file lists icon sets to generate in one process, e.g. one per brand
+//! of a white-label product. Each job sets command line options by their long
+//! name, like `icon-gen.toml`, over the shared `defaults`:
+//!
+//! ```yaml
+//! defaults:
+//!   windows: true
+//!   ios: true
+//!   padding: 8
+//! jobs:
+//!   - name: acme
+//!     input: brands/acme.png
+//!     output: dist/acme
+//!     background: "#e53935"
+//!   - name: globex
+//!     input: brands/globex.png
+//!     output: dist/globex
+//!     background-for: [windows, ios]
+//! ```
+//!
+//! `name` labels a job in the log and in errors. Only the part of YAML that
+//! options need is understood: block mappings and sequences, flow sequences
+//! (`[a, b]`), quoted and plain scalars, and comments. Anchors, block scalars
+//! and flow mappings are not.
+
+use crate::config::{Entries, Value};
+use icon_gen::error::{IconGenError, Result};
+use std::path::Path;
+
+/// A parsed job file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobFile {
+    /// Options every job starts from
+    pub defaults: Entries,
+    pub jobs: Vec<Job>,
+}
+
+/// One icon set of a job file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Job {
+    pub name: Option<String>,
+    /// The job's own options, without `name`
+    pub options: Entries,
+}
+
+impl JobFile {
+    /// Read and parse a job file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|source| IconGenError::IoError {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&text).map_err(|error| match error {
+            IconGenError::InvalidInput(message) => IconGenError::InvalidInput(format!(
+                "Invalid job file {}: {message}",
+                path.display()
+            )),
+            error => error,
+        })
+    }
+
+    /// Parse a job file's contents
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = lines(text)?;
+        if lines.is_empty() {
+            return Err(IconGenError::InvalidInput(
+                "The job file has no jobs".into(),
+            ));
+        }
+        let mut pos = 0;
+        let root = block(&mut lines, &mut pos, 0)?;
+        if let Some(line) = lines.get(pos) {
+            return Err(line.error("Unexpected indentation"));
+        }
+
+        let Node::Mapping(root) = root else {
+            return Err(IconGenError::InvalidInput(
+                "A job file is a mapping with defaults and jobs".into(),
+            ));
+        };
+        let mut file = JobFile::default();
+        for (key, node) in root {
+            match key.as_str() {
+                "defaults" => file.defaults = entries(node, "defaults")?,
+                "jobs" => {
+                    let Node::Sequence(jobs) = node else {
+                        return Err(IconGenError::InvalidInput(
+                            "jobs has to be a list of jobs".into(),
+                        ));
+                    };
+                    for (index, node) in jobs.into_iter().enumerate() {
+                        let mut options = entries(node, &format!("Job {}", index + 1))?;
+                        let name = match options.iter().position(|(key, _)| key == "name") {
+                            Some(at) => match options.remove(at).1 {
+                                Value::String(name) => Some(name),
+                                value => Some(value.to_string()),
+                            },
+                            None => None,
+                        };
+                        file.jobs.push(Job { name, options });
+                    }
+                }
+                _ => {
+                    return Err(IconGenError::InvalidInput(format!(
+                        "Unknown key {}; a job file has defaults and jobs",
+                        key
+                    )))
+                }
+            }
+        }
+        if file.jobs.is_empty() {
+            return Err(IconGenError::InvalidInput(
+                "The job file has no jobs".into(),
+            ));
+        }
+        Ok(file)
+    }
+}
+
+impl Job {
+    /// The job's name, or its position for a job without one
+    pub fn label(&self, index: usize) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("job {}", index + 1),
+        }
+    }
+
+    /// The options in effect: the job's own, then the defaults it doesn't set
+    pub fn options(&self, defaults: &Entries) -> Entries {
+        let normalize = |key: &str| key.replace('_', "-");
+        let mut options = self.options.clone();
+        for (key, value) in defaults {
+            if !options
+                .iter()
+                .any(|(set, _)| normalize(set) == normalize(key))
+            {
+                options.push((key.clone(), value.clone()));
+            }
+        }
+        options
+    }
+}
+
+/// A mapping of options, for `defaults` or a job
+fn entries(node: Node, what: &str) -> Result<Entries> {
+    let Node::Mapping(mapping) = node else {
+        return Err(IconGenError::InvalidInput(format!(
+            "{what} has to be a mapping of options"
+        )));
+    };
+    mapping
+        .into_iter()
+        .map(|(key, node)| {
+            let value = match node {
+                Node::Scalar(value) => value,
+                Node::Sequence(items) => Value::Array(
+                    items
+                        .into_iter()
+                        .map(|item| match item {
+                            Node::Scalar(value) => Ok(value),
+                            _ => Err(IconGenError::InvalidInput(format!(
+                                "{key} in {what} can only list plain values"
+                            ))),
+                        })
+                        .collect::<Result<_>>()?,
+                ),
+                Node::Mapping(_) => {
+                    return Err(IconGenError::InvalidInput(format!(
+                        "{key} in {what} can't hold a mapping"
+                    )))
+                }
+            };
+            Ok((key, value))
+        })
+        .collect()
+}
+
+/// A YAML node
+#[derive(Debug)]
+enum Node {
+    Scalar(Value),
+    Sequence(Vec<Node>),
+    Mapping(Vec<(String, Node)>),
+}
+
+/// A line with content, without its comment
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+impl Line<'_> {
+    fn error(&self, message: &str) -> IconGenError {
+        IconGenError::InvalidInput(format!("{message} (line {})", self.number))
+    }
+
+    /// Whether the line is a sequence item, `- ...`
+    fn is_item(&self) -> bool {
+        self.text == "-" || self.text.starts_with("- ")
+    }
+}
+
+fn lines(text: &str) -> Result<Vec<Line<'_>>> {
+    let mut lines = Vec::new();
+    for (index, raw) in text.lines().enumerate() {
+        let number = index + 1;
+        let content = strip_comment(raw).trim_end();
+        let text = content.trim_start_matches(' ');
+        if text.is_empty() || (lines.is_empty() && text == "---") {
+            continue;
+        }
+        if text.starts_with('\t') {
+            return Err(IconGenError::InvalidInput(format!(
+                "Tabs can't indent YAML (line {number})"
+            )));
+        }
+        lines.push(Line {
+            number,
+            indent: content.len() - text.len(),
+            text,
+        });
+    }
+    Ok(lines)
+}
+
+/// `line` up to a `#` comment outside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (at, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '#') if previous.is_whitespace() => return &line[..at],
+            (None, '"' | '\'') if previous.is_whitespace() || matches!(previous, '[' | ',') => {
+                quote = Some(c)
+            }
+            (Some(open), c) if c == open && previous != '\\' => quote = None,
+            _ => {}
+        }
+        previous = c;
+    }
+    line
+}
+
+/// The block starting at `lines[*pos]`, indented by `indent`
+fn block(lines: &mut [Line<'_>], pos: &mut usize, indent: usize) -> Result<Node> {
+    if lines[*pos].is_item() {
+        sequence(lines, pos, indent)
+    } else {
+        mapping(lines, pos, indent)
+    }
+}
+
+fn sequence(lines: &mut [Line<'_>], pos: &mut usize, indent: usize) -> Result<Node> {
+    let mut items = Vec::new();
+    while *pos < lines.len() && lines[*pos].indent == indent && lines[*pos].is_item() {
+        let line = &lines[*pos];
+        let rest = line.text[1..].trim_start_matches(' ');
+        if rest.is_empty() {
+            *pos += 1;
+            match lines.get(*pos) {
+                Some(next) if next.indent > indent => {
+                    let indent = next.indent;
+                    items.push(block(lines, pos, indent)?);
+                }
+                _ => return Err(lines[*pos - 1].error("Empty sequence item")),
+            }
+        } else if key_value(rest).is_some() {
+            // `- key: value` starts a mapping indented like its first key
+            let item_indent = indent + (line.text.len() - rest.len());
+            let number = line.number;
+            lines[*pos] = Line {
+                number,
+                indent: item_indent,
+                text: rest,
+            };
+            items.push(mapping(lines, pos, item_indent)?);
+        } else {
+            items.push(Node::Scalar(scalar(rest, line)?));
+            *pos += 1;
+        }
+    }
+    Ok(Node::Sequence(items))
+}
+
+fn mapping(lines: &mut [Line<'_>], pos: &mut usize, indent: usize) -> Result<Node> {
+    let mut entries: Vec<(String, Node)> = Vec::new();
+    while *pos < lines.len() && lines[*pos].indent == indent {
+        let line = &lines[*pos];
+        let Some((key, rest)) = key_value(line.text) else {
+            return Err(line.error("Expected key: value"));
+        };
+        if entries.iter().any(|(existing, _)| existing == key) {
+            return Err(line.error(&format!("{key} is set twice")));
+        }
+        let key = key.to_string();
+        *pos += 1;
+
+        let value = if rest.is_empty() {
+            match lines.get(*pos) {
+                // Sequences may sit at the indentation of their key
+                Some(next) if next.indent > indent || (next.indent == indent && next.is_item()) => {
+                    let indent = next.indent;
+                    block(lines, pos, indent)?
+                }
+                _ => return Err(lines[*pos - 1].error(&format!("{key} has no value"))),
+            }
+        } else if rest.starts_with('[') {
+            flow_sequence(rest, &lines[*pos - 1])?
+        } else {
+            Node::Scalar(scalar(rest, &lines[*pos - 1])?)
+        };
+        entries.push((key, value));
+    }
+    if let Some(line) = lines.get(*pos).filter(|line| line.indent > indent) {
+        return Err(line.error("Unexpected indentation"));
+    }
+    Ok(Node::Mapping(entries))
+}
+
+/// The key and the rest of a `key: value` line
+fn key_value(text: &str) -> Option<(&str, &str)> {
+    if text.starts_with(['"', '\'', '[', '{', '-']) {
+        return None;
+    }
+    let colon = text
+        .char_indices()
+        .find(|&(at, c)| c == ':' && text[at + 1..].chars().next().is_none_or(|c| c == ' '))?
+        .0;
+    Some((text[..colon].trim_end(), text[colon + 1..].trim()))
+}
+
+/// `[a, "b", 3]`
+fn flow_sequence(text: &str, line: &Line<'_>) -> Result<Node> {
+    let Some(inner) = text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+    else {
+        return Err(line.error("Expected ] at the end of the list"));
+    };
+    let mut items = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (at, c) in inner.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (None, '[' | '{') => return Err(line.error("Nested collections are not supported")),
+            (None, ',') => {
+                items.push(&inner[start..at]);
+                start = at + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&inner[start..]);
+    if items.len() == 1 && items[0].trim().is_empty() {
+        return Ok(Node::Sequence(Vec::new()));
+    }
+    items
+        .into_iter()
+        .map(|item| scalar(item.trim(), line).map(Node::Scalar))
+        .collect::<Result<_>>()
+        .map(Node::Sequence)
+}
+
+fn scalar(text: &str, line: &Line<'_>) -> Result<Value> {
+    if let Some(quoted) = text.strip_prefix('"') {
+        let Some(quoted) = quoted.strip_suffix('"') else {
+            return Err(line.error("Unterminated string"));
+        };
+        let mut value = String::new();
+        let mut chars = quoted.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                value.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('"') => value.push('"'),
+                Some('\\') => value.push('\\'),
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                escape => {
+                    return Err(line.error(&format!(
+                        "Invalid escape \\{}",
+                        escape.map(String::from).unwrap_or_default()
+                    )))
+                }
+            }
+        }
+        return Ok(Value::String(value));
+    }
+    if let Some(quoted) = text.strip_prefix('\'') {
+        let Some(quoted) = quoted.strip_suffix('\'') else {
+            return Err(line.error("Unterminated string"));
+        };
+        return Ok(Value::String(quoted.replace("''", "'")));
+    }
+    if text.starts_with(['&', '*', '!', '|', '>', '{']) {
+        return Err(line.error(&format!("Unsupported YAML: {text}")));
+    }
+    Ok(match text {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        _ => {
+            if let Ok(integer) = text.parse::<i64>() {
+                Value::Integer(integer)
+            } else if let Ok(float) = text.parse::<f64>() {
+                Value::Float(float)
+            } else {
+                Value::String(text.to_string())
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_job_file() {
+        let file = JobFile::parse(
+            r##"
+# Brands
+defaults:
+  windows: true
+  padding: 8   # percent
+jobs:
+  - name: acme
+    input: brands/acme.png
+    background: "#e53935"
+    background-for: [windows, 'ios']
+  -
+    input: brands/globex.png
+    variant:
+      - grayscale
+"##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            file.defaults,
+            vec![
+                ("windows".to_string(), Value::Boolean(true)),
+                ("padding".to_string(), Value::Integer(8)),
+            ]
+        );
+        assert_eq!(file.jobs.len(), 2);
+        assert_eq!(file.jobs[0].label(0), "acme");
+        assert_eq!(file.jobs[1].label(1), "job 2");
+        assert_eq!(
+            file.jobs[0].options,
+            vec![
+                ("input".to_string(), Value::String("brands/acme.png".into())),
+                ("background".to_string(), Value::String("#e53935".into())),
+                (
+                    "background-for".to_string(),
+                    Value::Array(vec![
+                        Value::String("windows".into()),
+                        Value::String("ios".into())
+                    ])
+                ),
+            ]
+        );
+        assert_eq!(
+            file.jobs[1].options[1],
+            (
+                "variant".to_string(),
+                Value::Array(vec![Value::String("grayscale".into())])
+            )
+        );
+
+        let options = file.jobs[0].options(&file.defaults);
+        assert_eq!(options.len(), 5);
+        assert_eq!(options[3].0, "windows");
+    }
+
+    #[test]
+    fn test_invalid_job_files() {
+        let error = |text: &str| JobFile::parse(text).unwrap_err().to_string();
+
+        assert!(error("").contains("no jobs"));
+        assert!(error("jobs: []\n").contains("no jobs"));
+        assert!(error("job:\n  - input: a.png\n").contains("Unknown key job"));
+        assert!(error("jobs:\n  - input: a.png\n    input: b.png\n").contains("set twice (line 3)"));
+        assert!(
+            error("jobs:\n  - input: a.png\n      output: x\n").contains("indentation (line 3)")
+        );
+        assert!(error("jobs:\n  - input: \"a.png\n").contains("Unterminated string"));
+        assert!(error("jobs:\n  - input: *logo\n").contains("Unsupported YAML"));
+        assert!(error("jobs:\n  - overlay:\n      file: a.png\n").contains("can't hold a mapping"));
+    }
+}
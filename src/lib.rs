@@ -1,2 +1,61 @@
-pub mod contents_json;
-pub mod icon_gen;
+//! Generate icons for every major platform from a single source image
+//!
+//! The `icon-gen` binary is a thin command line front end over this library.
+//! Other tools, such as Tauri plugins and build scripts, can embed generation
+//! through [`IconGenerator`]:
+//!
+//! ```no_run
+//! use icon_gen::{IconGenerator, Platform};
+//!
+//! IconGenerator::new("logo.png")
+//!     .platforms([Platform::Windows, Platform::Macos])
+//!     .output("icons")
+//!     .run()?;
+//! # Ok::<(), icon_gen::IconGenError>(())
+//! ```
+
+mod archive;
+mod cache;
+mod compositor;
+mod contents_json;
+mod contrast;
+mod diff;
+pub mod error;
+pub mod ffi;
+mod generator;
+mod icon_gen;
+pub mod interrupt;
+mod manifest;
+mod output;
+mod overwrite;
+mod platform;
+mod png_metadata;
+mod png_passes;
+mod presets;
+mod progress;
+mod report;
+mod safe_zone;
+mod sha256;
+mod spec;
+mod splash;
+mod store_limits;
+mod validate;
+mod verify;
+mod web_manifest;
+mod zopfli;
+
+pub use archive::ArchiveFormat;
+pub use diff::{diff, Change, Diff, FileDiff};
+pub use error::IconGenError;
+pub use generator::{IconGenerator, Platform};
+pub use icon_gen::{
+    default_output_dir, generate_icons, generate_into, generate_to_memory, Args, CHANNEL_NAMES,
+};
+pub use output::{ArchiveSink, FileSystemSink, MemorySink, OutputSink};
+pub use platform::{
+    AndroidIcons, IosIcons, LinuxIcons, MacosIcons, PlatformContext, PlatformGenerator,
+    WindowsIcons,
+};
+pub use report::{ReportingSink, RunReport};
+pub use validate::{validate, Problem, Validation};
+pub use web_manifest::fix as fix_web_manifest;
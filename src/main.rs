@@ -1,9 +1,24 @@
-use anyhow::Result;
-use clap::Parser;
-use std::path::PathBuf;
+mod completions;
+mod config;
+mod jobs;
+mod man;
+mod wizard;
 
-mod contents_json;
-mod icon_gen;
+use anyhow::{Context, Result};
+use clap::{
+    builder::PossibleValuesParser, parser::ValueSource, ArgAction, ArgMatches, CommandFactory,
+    Parser, Subcommand, ValueHint,
+};
+use config::{Config, Entries, Value, CONFIG_FILE_NAME};
+use icon_gen::{interrupt, IconGenError, Platform};
+use jobs::JobFile;
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 #[derive(Debug, Parser)]
 #[clap(
@@ -11,21 +26,83 @@ mod icon_gen;
     version = env!("CARGO_PKG_VERSION"),
     about = "Generate various icons for all major platforms",
     long_about = "A cross-platform CLI tool for generating icons in various formats from a single source image. \
-Creates icons for Windows (ICO), macOS (ICNS), Linux (PNG), Android, iOS, and Tauri platforms."
+Creates icons for Windows (ICO), macOS (ICNS), Linux (PNG), Android, iOS, and Tauri platforms.",
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true
 )]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// Path to the source icon (squared PNG or SVG file with transparency).
-    #[clap(value_name = "INPUT")]
-    input: PathBuf,
+    #[clap(value_name = "INPUT", required_unless_present = "interactive")]
+    input: Option<PathBuf>,
 
     /// Output directory.
-    #[clap(short, long, value_name = "DIR")]
+    #[clap(short, long, value_name = "DIR", value_hint = ValueHint::DirPath)]
     output: Option<PathBuf>,
 
+    /// Write the generated files into this archive (.zip, .tar or .tar.gz) instead of the output directory
+    #[clap(long, value_name = "FILE")]
+    archive: Option<PathBuf>,
+
+    /// Print a report of every generated file (path, dimensions, format, bytes) instead of the log (json)
+    #[clap(long, value_name = "FORMAT")]
+    report: Option<String>,
+
+    /// Write icons.manifest.json with the SHA-256 of every generated file into the output directory
+    #[clap(long)]
+    manifest: bool,
+
+    /// Print the files that would be generated without decoding the source or writing anything
+    #[clap(long)]
+    dry_run: bool,
+
+    /// How progress is reported: a progress line or log (auto), or newline-delimited JSON events (json)
+    #[clap(long, value_name = "MODE", default_value = "auto")]
+    progress: String,
+
     /// Custom PNG icon sizes to generate. When set, only these sizes are generated.
     #[clap(short, long, value_delimiter = ',', value_name = "SIZES")]
     png: Option<Vec<u32>>,
 
+    /// Output formats for custom sizes (png, bmp, webp) - only effective with --png
+    #[clap(
+        long,
+        value_delimiter = ',',
+        default_value = "png",
+        value_name = "FORMATS"
+    )]
+    formats: Vec<String>,
+
+    /// Inset the artwork by this percentage of the side on every edge, for all targets
+    #[clap(long, value_name = "PERCENT")]
+    padding: Option<f32>,
+
+    /// Fill color for the --padding margin (CSS color format) [default: transparent]
+    #[clap(long, value_name = "COLOR")]
+    padding_color: Option<String>,
+
+    /// Parallel icon sets to generate next to the output directory, e.g. {output}_gray (grayscale)
+    #[clap(long, value_delimiter = ',', value_name = "VARIANTS")]
+    variant: Vec<String>,
+
+    /// Extra icon sets with rotated hues per named channel, e.g. dev=120,staging=60 ({output}_dev, ...)
+    #[clap(long, value_delimiter = ',', value_name = "NAME=DEGREES")]
+    hue_variants: Vec<String>,
+
+    /// Corrections applied to the source before resizing, e.g. brightness=1.1,contrast=1.2,saturation=0.9
+    #[clap(long, value_delimiter = ',', value_name = "NAME=FACTOR")]
+    adjust: Vec<String>,
+
+    /// Recolor the artwork with this color (CSS color format), keeping its alpha
+    #[clap(long, value_name = "COLOR")]
+    tint: Option<String>,
+
+    /// How --tint is applied (recolor, multiply)
+    #[clap(long, default_value = "recolor", value_name = "MODE")]
+    tint_mode: String,
+
     /// Generate only desktop platform icons (Windows, macOS, Linux)
     #[clap(long)]
     desktop_only: bool,
@@ -46,10 +123,22 @@ struct Args {
     #[clap(long)]
     macos: bool,
 
+    /// Icon treatment applied before building the ICNS (none, big-sur)
+    #[clap(long, default_value = "none", value_name = "STYLE")]
+    macos_style: String,
+
+    /// Members of the icon.icns files: default, modern (ic04/ic05 instead of the legacy is32/il32), retina (@2x members only) or a comma-separated list of OSTypes, e.g. ic04,ic05,ic07
+    #[clap(long, default_value = "default", value_name = "TYPES")]
+    icns_types: String,
+
     /// Generate icons for Linux/Desktop platform
     #[clap(long)]
     linux: bool,
 
+    /// Generate system tray / menu bar icons (16, 20, 22, 24, 32 px)
+    #[clap(long)]
+    tray: bool,
+
     /// Generate icons for Android platform
     #[clap(long)]
     android: bool,
@@ -66,13 +155,263 @@ struct Args {
     #[clap(long, default_value = "#ffffff")]
     android_adaptive_bg: String,
 
+    /// Background shape for legacy (pre-Android 8.0) launcher icons (none, square, circle)
+    #[clap(long, default_value = "none", value_name = "SHAPE")]
+    android_legacy_shape: String,
+
     /// Generate icons for iOS platform
     #[clap(long)]
     ios: bool,
 
-    /// The background color for iOS icons (CSS color format)
+    /// Background color used to flatten transparency (CSS color format) - iOS icons always use it
+    #[clap(
+        long,
+        alias = "ios-color",
+        default_value = "#ffffff",
+        value_name = "COLOR"
+    )]
+    background: String,
+
+    /// Platforms to flatten onto --background (windows, macos, linux, tray, android, ios)
+    #[clap(
+        long,
+        value_delimiter = ',',
+        default_value = "ios",
+        value_name = "PLATFORMS"
+    )]
+    background_for: Vec<String>,
+
+    /// Image composited under the icon instead of the --background color, cropped to fill
+    #[clap(long, value_name = "FILE")]
+    background_image: Option<PathBuf>,
+
+    /// Flatten the source onto --background for every target, leaving no transparency
+    #[clap(long)]
+    flatten: bool,
+
+    /// Write PNGs up to 64px as palette images with at most this many colors (2-256)
+    #[clap(
+        long,
+        num_args = 0..=1,
+        default_missing_value = "256",
+        value_name = "COLORS"
+    )]
+    quantize: Option<u16>,
+
+    /// Losslessly re-encode every generated PNG in its smallest form (slower)
+    #[clap(long)]
+    optimize: bool,
+
+    /// PNG deflate effort: best (zlib level 9, fast) or zopfli (a few percent smaller, but
+    /// many times slower - meant for release builds)
+    #[clap(long, default_value = "best", value_name = "MODE")]
+    compression: String,
+
+    /// PNG encoding effort while generating: fast (quick local iterations), balanced or best
+    #[clap(long, default_value = "best", value_name = "PROFILE")]
+    encode_profile: String,
+
+    /// Color-space and metadata chunks of the generated PNGs: keep, srgb (tag as sRGB, with gAMA and cHRM) or strip (every ancillary chunk but transparency)
+    #[clap(long, default_value = "keep", value_name = "MODE")]
+    png_metadata: String,
+
+    /// Resampling filter for every resize: lanczos3, catmullrom, triangle or nearest (pixel art)
+    #[clap(long, default_value = "lanczos3", value_name = "FILTER")]
+    filter: String,
+
+    /// Unsharp mask amount applied after resizing to 48px and below, e.g. 0.5
+    #[clap(long, value_name = "AMOUNT")]
+    sharpen: Option<f32>,
+
+    /// Render outputs of 32px and below at 4x first, then downscale with --filter
+    #[clap(long)]
+    supersample: bool,
+
+    /// Boost contrast and thicken strokes of outputs 32px and below, for tiny favicons and tray icons
+    #[clap(long)]
+    legibility: bool,
+
+    /// Number of worker threads resizing and encoding icons in parallel [default: number of CPUs]
+    #[clap(long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// How platform icons are resized: mipmap (from a halving chain of the source, faster) or direct (from the full source every time)
+    #[clap(long, default_value = "mipmap", value_name = "STRATEGY")]
+    resize_strategy: String,
+
+    /// Downscale larger sources to this side before generating; raised to the largest --png size
+    #[clap(long, default_value_t = 2048, value_name = "PX")]
+    max_working_size: u32,
+
+    /// Regenerate every set even when .icon-gen-cache.json shows it is up to date
+    #[clap(long)]
+    no_cache: bool,
+
+    /// Leave a set alone when its output directory already has files icon-gen generated, without checking them
+    #[clap(long)]
+    skip_existing: bool,
+
+    /// Regenerate a set only when an input file is newer than its oldest output
+    #[clap(long)]
+    if_newer: bool,
+
+    /// Replace existing files, including ones icon-gen didn't generate
+    #[clap(long)]
+    force: bool,
+
+    /// Replace existing files and remove the files of the output subfolders that the run doesn't generate
+    #[clap(long)]
+    clean: bool,
+
+    /// Refuse to replace any existing file
+    #[clap(long)]
+    no_clobber: bool,
+
+    /// Carry on with the other platforms when one fails, then list the failures; exits with 3 when only some failed
+    #[clap(long)]
+    keep_going: bool,
+
+    /// Decode icon.ico and icon.icns again after encoding and check their frames, sizes and pixels
+    #[clap(long)]
+    verify: bool,
+
+    /// File names of the --png, linux, tray and tauri-desktop icons, e.g. "{app}-{platform}-{size}.png"; variables: {app}, {platform}, {size}, {scale}, {density}
+    #[clap(long, value_name = "TEMPLATE")]
+    name_template: Option<String>,
+
+    /// Folder of the iOS app icons: flat (ios/), appiconset (ios/AppIcon.appiconset/) or xcassets (ios/Assets.xcassets/AppIcon.appiconset/)
+    #[clap(long, default_value = "flat", value_name = "LAYOUT")]
+    ios_layout: String,
+
+    /// Check the artwork against the iOS mask and the Android adaptive icon safe zone: off, warn, or fix (scale it in)
+    #[clap(long, default_value = "off", value_name = "MODE")]
+    safe_zone: String,
+
+    /// Replace existing asset catalog Contents.json files, or merge the generated entries into them: replace or merge
+    #[clap(long, default_value = "replace", value_name = "MODE")]
+    contents_json: String,
+
+    /// Warn when the edge of the artwork has too little contrast with the background fill or common launcher backgrounds
+    #[clap(long)]
+    contrast_check: bool,
+
+    /// Fail the run when it warns, e.g. about transparency, upscaling or safe zone violations
+    #[clap(long)]
+    strict: bool,
+
+    /// Also write squircle-masked iOS icon previews (ios-preview/) and web/apple-touch-icon.png
+    #[clap(long)]
+    ios_squircle: bool,
+
+    /// Generate the iMessage (Messages extension) app icon set with its Contents.json
+    #[clap(long)]
+    imessage: bool,
+
+    /// Generate the App Clip icon (app-clip/) with its appiconset Contents.json
+    #[clap(long)]
+    app_clip: bool,
+
+    /// Generate iOS launch images (ios-splash/LaunchImage.launchimage) for every device resolution
+    #[clap(long)]
+    ios_splash: bool,
+
+    /// Generate Android 12 splash screen icons (drawable-*/splash_icon.png) and values/splash.xml
+    #[clap(long)]
+    android_splash: bool,
+
+    /// Generate apple-touch-startup-image PWA splash screens (web/splash/) and their <link> tags
+    #[clap(long)]
+    pwa_startup: bool,
+
+    /// Background color for splash and launch screens (CSS color format)
     #[clap(long, default_value = "#ffffff")]
-    ios_color: String,
+    splash_bg: String,
+
+    /// Also write windows/app.rc and a winres build.rs fragment alongside icon.ico
+    #[clap(long)]
+    windows_rc: bool,
+
+    /// Generate a Windows cursor (windows/cursor.cur) at 32, 48 and 64 px
+    #[clap(long)]
+    cursor: bool,
+
+    /// Cursor hotspot as X,Y in source image pixels - only effective with --cursor
+    #[clap(long, value_delimiter = ',', default_value = "0,0", value_name = "X,Y")]
+    cursor_hotspot: Vec<u32>,
+
+    /// Extra frame images for an animated cursor (windows/cursor.ani), played after the source
+    #[clap(long, value_delimiter = ',', value_name = "FILES")]
+    cursor_frames: Option<Vec<PathBuf>>,
+
+    /// Delay between animated cursor frames in milliseconds
+    #[clap(long, default_value = "100", value_name = "MS")]
+    cursor_delay: u32,
+
+    /// Generate an animated 32x32 favicon (web/favicon.gif, web/favicon.apng) from a GIF/APNG source
+    #[clap(long)]
+    animated_favicon: bool,
+
+    /// Frame rate for the animated favicon, overriding the source frame delays
+    #[clap(long, value_name = "FPS")]
+    favicon_fps: Option<u32>,
+
+    /// Store/engine presets to generate, each into its own folder (steam, itch, unity, godot, github-social, play-store)
+    #[clap(long, value_delimiter = ',', value_name = "PRESETS")]
+    preset: Vec<String>,
+
+    /// Background color for non-square preset artwork such as capsules and covers (CSS color format)
+    #[clap(long, default_value = "#000000")]
+    preset_bg: String,
+
+    /// JSON file describing custom targets (size, filename template, format, background)
+    #[clap(long, value_name = "FILE")]
+    spec: Option<PathBuf>,
+
+    /// Project name shown on social preview cards (default: the input file name)
+    #[clap(long, value_name = "NAME")]
+    project_name: Option<String>,
+
+    /// Round the icon corners with a radius in source pixels (24, 24px) or percent of the side (12%)
+    #[clap(long, value_name = "PX|%")]
+    radius: Option<String>,
+
+    /// Platforms to apply --radius to (windows, macos, linux, tray, android, ios) [default: all]
+    #[clap(long, value_delimiter = ',', value_name = "PLATFORMS")]
+    radius_for: Vec<String>,
+
+    /// Draw an outline of the given width (source pixels) and CSS color around the icon, e.g. 8,#ffffff
+    #[clap(long, value_name = "WIDTH,COLOR")]
+    border: Option<String>,
+
+    /// Platforms to apply --border to (windows, macos, linux, tray, android, ios) [default: all]
+    #[clap(long, value_delimiter = ',', value_name = "PLATFORMS")]
+    border_for: Vec<String>,
+
+    /// Render a soft drop shadow under the artwork, shrinking it to make room
+    #[clap(long)]
+    shadow: bool,
+
+    /// Shadow blur radius in source pixels - only effective with --shadow
+    #[clap(long, default_value = "20", value_name = "PX")]
+    shadow_blur: f32,
+
+    /// Shadow offset as X,Y in source pixels - only effective with --shadow
+    #[clap(
+        long,
+        value_delimiter = ',',
+        default_value = "0,10",
+        allow_hyphen_values = true,
+        value_name = "X,Y"
+    )]
+    shadow_offset: Vec<i32>,
+
+    /// Shadow opacity from 0 to 1 - only effective with --shadow
+    #[clap(long, default_value = "0.4", value_name = "OPACITY")]
+    shadow_opacity: f32,
+
+    /// Platforms to apply --shadow to (windows, macos, linux, tray, android, ios) [default: all]
+    #[clap(long, value_delimiter = ',', value_name = "PLATFORMS")]
+    shadow_for: Vec<String>,
 
     /// Add a development/debug badge to all generated icons
     #[clap(long, alias = "debug")]
@@ -81,40 +420,624 @@ struct Args {
     /// Bug type to use for dev badge (cockroach, ladybug, moth, spider, caterpillar) - only effective with --dev-mode
     #[clap(long, default_value = "moth", value_name = "BUG")]
     dev_bug: String,
+
+    /// Custom image to use as the dev badge instead of a bug, e.g. a "DEV" mark or mascot - only effective with --dev-mode
+    #[clap(long, value_name = "FILE")]
+    dev_badge_file: Option<PathBuf>,
+
+    /// Where the dev badge sits: center, top-left, top-right, bottom-left, bottom-right (also top, bottom) - only effective with --dev-mode
+    #[clap(long, default_value = "center", value_name = "POSITION")]
+    badge_position: String,
+
+    /// Dev badge size as a fraction of the icon, from 0.1 to 1 - only effective with --dev-mode
+    #[clap(long, default_value = "0.25", value_name = "SCALE")]
+    badge_scale: f32,
+
+    /// Rotate the moth badge by an angle derived from this seed; the same seed gives byte-identical icons - only effective with --dev-mode
+    #[clap(long, value_name = "SEED")]
+    badge_seed: Option<u64>,
+
+    /// Image composited onto every icon, as file[:position[:scale]] (repeatable), e.g. mark.png:top-left:0.3
+    #[clap(long, value_name = "FILE[:POSITION[:SCALE]]")]
+    overlay: Vec<String>,
+
+    /// Order of the layers drawn over the artwork, bottom to top (overlays, badge, ribbon, stamp); layers left out are not drawn
+    #[clap(
+        long,
+        value_delimiter = ',',
+        default_value = "overlays,badge,ribbon,stamp",
+        value_name = "LAYERS"
+    )]
+    layers: Vec<String>,
+
+    /// Treatment for outputs below a size (repeatable), as <SIZE:key=value,... with keys source, padding, shadow, radius, border, e.g. "<48:source=small.png,shadow=off,padding=0"
+    #[clap(long, value_name = "<SIZE:SETTINGS")]
+    size_rule: Vec<String>,
+
+    /// Text stamped onto every icon of 32px and up, e.g. a version for QA builds
+    #[clap(long, value_name = "TEXT")]
+    stamp_text: Option<String>,
+
+    /// TrueType/OpenType font for --stamp-text [default: embedded DejaVu Sans Bold]
+    #[clap(long, value_name = "FILE")]
+    stamp_font: Option<PathBuf>,
+
+    /// CSS color of the --stamp-text, drawn on a contrasting plate
+    #[clap(long, default_value = "#ffffff", value_name = "COLOR")]
+    stamp_color: String,
+
+    /// Where --stamp-text sits: center, top, bottom, top-left, top-right, bottom-left, bottom-right
+    #[clap(long, default_value = "bottom", value_name = "POSITION")]
+    stamp_position: String,
+
+    /// Diagonal top-right corner ribbon as TEXT[:COLOR], e.g. BETA:#e53935
+    #[clap(long, value_name = "TEXT[:COLOR]")]
+    badge_ribbon: Option<String>,
+
+    /// Release channel badge preset (dev, alpha, beta, rc, nightly): a colored corner ribbon, plus the dev bug for dev
+    #[clap(long, value_name = "CHANNEL")]
+    channel: Option<String>,
+
+    /// Also multiply-tint the whole icon with the --channel color
+    #[clap(long)]
+    channel_tint: bool,
+
+    /// Read options from this file instead of icon-gen.toml in the working directory
+    #[clap(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Ask for the source image, platforms, colors and badge, then print the equivalent command and run it
+    #[clap(long)]
+    interactive: bool,
+
+    /// Ignore icon-gen.toml in the working directory
+    #[clap(long, conflicts_with_all = ["config", "profile"])]
+    no_config: bool,
+
+    /// Apply the [profile.<NAME>] settings of the config file over its shared ones
+    #[clap(long, value_name = "NAME")]
+    profile: Option<String>,
+}
+
+/// Commands other than generating icons
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print the tab-completion script for a shell
+    Completions {
+        #[clap(value_name = "SHELL", value_parser = PossibleValuesParser::new(completions::SHELLS))]
+        shell: String,
+    },
+    /// Compare two output directories and list the icons that look different,
+    /// e.g. to review regenerated assets
+    Diff {
+        #[clap(value_name = "BEFORE", value_hint = ValueHint::DirPath)]
+        before: PathBuf,
+        #[clap(value_name = "AFTER", value_hint = ValueHint::DirPath)]
+        after: PathBuf,
+    },
+    /// Print the man page (roff)
+    Man,
+    /// Generate every job of a YAML job file, e.g. one icon set per brand
+    Run {
+        #[clap(value_name = "FILE", value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+    },
+    /// Check an existing output directory or PWA manifest for missing or mismatched icons, e.g. in CI
+    Validate {
+        #[clap(value_name = "PATH", value_hint = ValueHint::AnyPath)]
+        path: PathBuf,
+        /// Regenerate the icons a PWA manifest declares that are missing or mismatched, from SOURCE
+        #[clap(long, value_name = "SOURCE", value_hint = ValueHint::FilePath)]
+        fix: Option<PathBuf>,
+    },
 }
 
+/// Options that only make sense on the command line
+const COMMAND_LINE_ONLY: &[&str] = &["config", "no_config", "profile", "interactive"];
+
+/// Exit status of a run stopped with Ctrl-C, as shells report for SIGINT
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Exit status of a `--keep-going` run where every platform failed, like any other failure
+const FAILURE_EXIT_CODE: i32 = 1;
+
+/// Exit status of a `--keep-going` run where some platforms failed and the others
+/// were generated; 2 is taken by clap for usage errors
+const PARTIAL_FAILURE_EXIT_CODE: i32 = 3;
+
 fn main() -> Result<()> {
-    let args = Args::parse();
+    install_interrupt_handler();
+    let mut args = parse_args(std::env::args_os().collect())?;
+    if args.interactive {
+        args = parse_args(interactive_command()?)?;
+    }
+    if let Some(command) = args.command {
+        return run_command(command);
+    }
+    generate(icon_args(args))
+}
+
+/// Generate icons, exiting with the status of Ctrl-C when interrupted, and
+/// listing the failed platforms of a `--keep-going` run
+fn generate(args: icon_gen::Args) -> Result<()> {
+    match icon_gen::IconGenerator::from_args(args).run() {
+        Err(IconGenError::Interrupted) => {
+            eprintln!(
+                "Interrupted. Files written so far are complete; the set that was cut short is generated again on the next run"
+            );
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
+        Err(IconGenError::PlatformsFailed {
+            failures,
+            generated,
+        }) => {
+            eprintln!(
+                "✗ {} of {} platforms failed:",
+                failures.len(),
+                failures.len() + generated
+            );
+            for failure in failures {
+                eprintln!(
+                    "  {}: {}",
+                    failure.set.join(&failure.platform).display(),
+                    failure.message
+                );
+            }
+            std::process::exit(match generated {
+                0 => FAILURE_EXIT_CODE,
+                _ => PARTIAL_FAILURE_EXIT_CODE,
+            });
+        }
+        result => Ok(result?),
+    }
+}
+
+/// The library options for a parsed command line
+fn icon_args(args: Args) -> icon_gen::Args {
+    let input = args
+        .input
+        .expect("clap requires INPUT without a command or --interactive");
 
     // Compute default output path from input filename if not provided
-    let output = args.output.unwrap_or_else(|| {
-        let source_stem = args.input
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("icon");
-        PathBuf::from(format!("icon-generator-{}", source_stem))
-    });
-
-    // Convert to icon_gen::Args
-    let icon_args = icon_gen::Args {
-        input: args.input,
+    let output = args
+        .output
+        .unwrap_or_else(|| icon_gen::default_output_dir(&input));
+
+    icon_gen::Args {
+        input,
+        input_bytes: None,
         output,
+        archive: args.archive,
+        report: args.report,
+        manifest: args.manifest,
+        dry_run: args.dry_run,
+        progress: args.progress,
         png: args.png,
+        formats: args.formats,
+        padding: args.padding,
+        padding_color: args.padding_color,
+        variant: args.variant,
+        hue_variants: args.hue_variants,
+        adjust: args.adjust,
+        tint: args.tint,
+        tint_mode: args.tint_mode,
         desktop_only: args.desktop_only,
         mobile_only: args.mobile_only,
         tauri_desktop: args.tauri_desktop,
         windows: args.windows,
         macos: args.macos,
+        macos_style: args.macos_style,
+        icns_types: args.icns_types,
         linux: args.linux,
+        tray: args.tray,
         android: args.android,
-        android_round: args.android_round || args.android, // Enable round by default with android
+        android_round: args.android_round,
         android_adaptive: args.android_adaptive,
         android_adaptive_bg: args.android_adaptive_bg,
+        android_legacy_shape: args.android_legacy_shape,
         ios: args.ios,
-        ios_color: args.ios_color,
+        background: args.background,
+        background_for: args.background_for,
+        background_image: args.background_image,
+        flatten: args.flatten,
+        quantize: args.quantize,
+        optimize: args.optimize,
+        compression: args.compression,
+        encode_profile: args.encode_profile,
+        png_metadata: args.png_metadata,
+        filter: args.filter,
+        sharpen: args.sharpen,
+        supersample: args.supersample,
+        legibility: args.legibility,
+        jobs: args.jobs,
+        resize_strategy: args.resize_strategy,
+        max_working_size: args.max_working_size,
+        no_cache: args.no_cache,
+        skip_existing: args.skip_existing,
+        if_newer: args.if_newer,
+        force: args.force,
+        clean: args.clean,
+        no_clobber: args.no_clobber,
+        keep_going: args.keep_going,
+        verify: args.verify,
+        name_template: args.name_template,
+        ios_layout: args.ios_layout,
+        contents_json: args.contents_json,
+        safe_zone: args.safe_zone,
+        contrast_check: args.contrast_check,
+        strict: args.strict,
+        ios_squircle: args.ios_squircle,
+        imessage: args.imessage,
+        app_clip: args.app_clip,
+        ios_splash: args.ios_splash,
+        android_splash: args.android_splash,
+        pwa_startup: args.pwa_startup,
+        splash_bg: args.splash_bg,
+        windows_rc: args.windows_rc,
+        cursor: args.cursor,
+        cursor_hotspot: args.cursor_hotspot,
+        cursor_frames: args.cursor_frames,
+        cursor_delay: args.cursor_delay,
+        animated_favicon: args.animated_favicon,
+        favicon_fps: args.favicon_fps,
+        preset: args.preset,
+        preset_bg: args.preset_bg,
+        spec: args.spec,
+        project_name: args.project_name,
+        radius: args.radius,
+        radius_for: args.radius_for,
+        border: args.border,
+        border_for: args.border_for,
+        shadow: args.shadow,
+        shadow_blur: args.shadow_blur,
+        shadow_offset: args.shadow_offset,
+        shadow_opacity: args.shadow_opacity,
+        shadow_for: args.shadow_for,
         dev_mode: args.dev_mode,
         dev_bug: args.dev_bug,
+        dev_badge_file: args.dev_badge_file,
+        badge_position: args.badge_position,
+        badge_scale: args.badge_scale,
+        badge_seed: args.badge_seed,
+        overlay: args.overlay,
+        layers: args.layers,
+        size_rule: args.size_rule,
+        stamp_text: args.stamp_text,
+        stamp_font: args.stamp_font,
+        stamp_color: args.stamp_color,
+        stamp_position: args.stamp_position,
+        badge_ribbon: args.badge_ribbon,
+        channel: args.channel,
+        channel_tint: args.channel_tint,
+        custom_platforms: Vec::new(),
+    }
+}
+
+/// Print what a command other than generation asks for
+fn run_command(command: Command) -> Result<()> {
+    let mut cli = Args::command();
+    let text = match command {
+        Command::Completions { shell } => completions::generate(&shell, &mut cli)?,
+        Command::Diff { before, after } => return diff_output(&before, &after),
+        Command::Man => man::render(&mut cli),
+        Command::Run { file } => return run_jobs(&file),
+        Command::Validate { path, fix } => return validate_output(&path, fix.as_deref()),
     };
+    print!("{text}");
+    Ok(())
+}
+
+/// List the problems `validate` finds in an output directory or PWA manifest,
+/// after regenerating the manifest's icons from `fix`, failing when there are any
+fn validate_output(dir: &Path, fix: Option<&Path>) -> Result<()> {
+    if let Some(source) = fix {
+        if !dir.is_file() {
+            anyhow::bail!(
+                "--fix only applies to a PWA manifest, not {}",
+                dir.display()
+            );
+        }
+        for path in icon_gen::fix_web_manifest(dir, source)? {
+            println!("✓ Regenerated {}", path.display());
+        }
+    }
+    let validation = icon_gen::validate(dir)?;
+    let checked = validation.checked.join(", ");
+    if validation.problems.is_empty() {
+        println!("✓ No problems in the {checked} icons of {}", dir.display());
+        return Ok(());
+    }
+    for problem in &validation.problems {
+        match problem.fix {
+            Some(fix) => println!(
+                "✗ {}: {} (regenerate with {fix})",
+                problem.path.display(),
+                problem.message
+            ),
+            None => println!("✗ {}: {}", problem.path.display(), problem.message),
+        }
+    }
+    anyhow::bail!(
+        "{} problems in the {checked} icons of {}",
+        validation.problems.len(),
+        dir.display()
+    )
+}
+
+/// List the files that differ between two output directories, failing when there are any
+fn diff_output(before: &Path, after: &Path) -> Result<()> {
+    let diff = icon_gen::diff(before, after)?;
+    if diff.changes.is_empty() {
+        println!(
+            "✓ No differences between the {} files of {} and {}",
+            diff.compared,
+            before.display(),
+            after.display()
+        );
+        return Ok(());
+    }
+    for file in &diff.changes {
+        let path = file.path.display();
+        match file.change {
+            icon_gen::Change::Added => println!("+ {path}"),
+            icon_gen::Change::Removed => println!("- {path}"),
+            icon_gen::Change::Resized {
+                before: (before_width, before_height),
+                after: (after_width, after_height),
+            } => {
+                println!("~ {path}: {before_width}x{before_height} → {after_width}x{after_height}")
+            }
+            icon_gen::Change::Pixels {
+                width,
+                height,
+                ssim,
+                delta_e,
+            } => println!("~ {path}: {width}x{height}, SSIM {ssim:.4}, ΔE {delta_e:.2}"),
+            icon_gen::Change::Contents => println!("~ {path}: contents changed"),
+        }
+    }
+    anyhow::bail!(
+        "{} of {} files differ between {} and {}",
+        diff.changes.len(),
+        diff.compared,
+        before.display(),
+        after.display()
+    )
+}
+
+/// Generate the jobs of a job file in order, stopping at the first that fails
+fn run_jobs(path: &Path) -> Result<()> {
+    let file = JobFile::load(path)?;
+    // Each job stands for a command line of its own; the config file doesn't apply
+    let given = Args::command()
+        .ignore_errors(true)
+        .get_matches_from(["icon-gen"]);
+
+    let mut jobs = Vec::new();
+    let mut outputs = HashMap::<PathBuf, String>::new();
+    for (index, job) in file.jobs.iter().enumerate() {
+        let label = job.label(index);
+        let options = job.options(&file.defaults);
+        if !options.iter().any(|(key, _)| key == "input") {
+            anyhow::bail!("{} has no input", label);
+        }
+        let mut cli = vec![OsString::from("icon-gen")];
+        cli.extend(
+            config_args(&options, &given)
+                .with_context(|| format!("Invalid options for {label}"))?,
+        );
+        let args = Args::try_parse_from(cli).map_err(|error| {
+            // The first line says what is wrong; the usage that follows is the command line's
+            let message = error.to_string();
+            let message = message.lines().next().unwrap_or_default();
+            anyhow::anyhow!(
+                "Invalid options for {}: {}",
+                label,
+                message.trim_start_matches("error: ")
+            )
+        })?;
+        let args = icon_args(args);
+        let target = args.archive.clone().unwrap_or_else(|| args.output.clone());
+        if let Some(other) = outputs.insert(target.clone(), label.clone()) {
+            anyhow::bail!("{} and {} both write to {}", other, label, target.display());
+        }
+        jobs.push((label, args));
+    }
+
+    // Jobs sharing a source, such as brand colors over one logo, read it once
+    let mut sources = HashMap::<PathBuf, Arc<[u8]>>::new();
+    let count = jobs.len();
+    for (index, (label, mut args)) in jobs.into_iter().enumerate() {
+        eprintln!("==> [{}/{}] {}", index + 1, count, label);
+        let source = match sources.get(&args.input) {
+            Some(source) => source.clone(),
+            None => {
+                let source = Arc::<[u8]>::from(std::fs::read(&args.input).with_context(|| {
+                    format!(
+                        "{label} failed: Failed to load image {}",
+                        args.input.display()
+                    )
+                })?);
+                sources.insert(args.input.clone(), source.clone());
+                source
+            }
+        };
+        args.input_bytes = Some(source);
+        generate(args).with_context(|| format!("{label} failed"))?;
+    }
+    eprintln!("✓ Finished {count} jobs");
+    Ok(())
+}
+
+/// Stop the run at the next file on Ctrl-C; a second Ctrl-C exits at once
+#[cfg(unix)]
+fn install_interrupt_handler() {
+    extern "C" fn on_interrupt(_signal: libc::c_int) {
+        if interrupt::is_interrupted() {
+            // SAFETY: _exit is async-signal-safe
+            unsafe { libc::_exit(INTERRUPTED_EXIT_CODE) };
+        }
+        interrupt::interrupt();
+    }
+    // SAFETY: the handler only touches an atomic flag or exits
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+}
+
+/// Stop the run at the next file on Ctrl-C; a second Ctrl-C exits at once
+#[cfg(windows)]
+fn install_interrupt_handler() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetConsoleCtrlHandler(
+            handler: Option<unsafe extern "system" fn(u32) -> i32>,
+            add: i32,
+        ) -> i32;
+    }
+
+    // Runs on a thread of its own, so it can exit like any other thread
+    unsafe extern "system" fn on_interrupt(_event: u32) -> i32 {
+        if interrupt::is_interrupted() {
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
+        interrupt::interrupt();
+        1
+    }
+    // SAFETY: the handler only touches an atomic flag or exits
+    unsafe {
+        SetConsoleCtrlHandler(Some(on_interrupt), 1);
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn install_interrupt_handler() {}
+
+/// The command `--interactive` stands for: the command line with the wizard's answers in
+/// place of the flag
+fn interactive_command() -> Result<Vec<OsString>> {
+    let mut cli = std::env::args_os().collect::<Vec<_>>();
+    let given = Args::command().ignore_errors(true).get_matches_from(&cli);
+    let on_command_line = |id: &str| given.value_source(id) == Some(ValueSource::CommandLine);
+    let platform_ids = Platform::ALL
+        .map(|platform| platform.name().replace('-', "_"))
+        .into_iter()
+        .chain(["desktop_only".to_string(), "mobile_only".to_string()]);
+    let settled = wizard::Given {
+        source: given.get_one::<PathBuf>("input").cloned(),
+        output: on_command_line("output") || on_command_line("archive"),
+        platforms: platform_ids.into_iter().any(|id| on_command_line(&id)),
+        ios: ["ios", "app_clip", "imessage", "mobile_only"]
+            .into_iter()
+            .any(on_command_line),
+        background: on_command_line("background"),
+        badge: ["channel", "dev_mode", "badge_ribbon"]
+            .into_iter()
+            .any(on_command_line),
+    };
+    let answers = wizard::ask(&mut io::stdin().lock(), &mut io::stderr(), &settled)?;
+
+    cli.retain(|arg| arg != "--interactive");
+    let start = cli.len().min(1);
+    cli.splice(start..start, answers.into_iter().map(OsString::from));
+    let args = cli[start..]
+        .iter()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    eprintln!(
+        "\nEquivalent command:\n  {}\n",
+        wizard::command_line("icon-gen", &args)
+    );
+    Ok(cli)
+}
+
+/// Parse the command line, taking the options it leaves out from the config file
+fn parse_args(cli: Vec<OsString>) -> Result<Args> {
+    // A lenient first pass finds the config file and the options given on the command line
+    let given = Args::command().ignore_errors(true).get_matches_from(&cli);
+    // Commands don't generate anything, so the config file doesn't apply
+    if given.subcommand().is_some() {
+        return Ok(Args::parse_from(cli));
+    }
+    let config_path = match given.get_one::<PathBuf>("config") {
+        Some(path) => Some(path.clone()),
+        None if given.get_flag("no_config") => None,
+        None => Config::discover(Path::new(".")),
+    };
+    let profile = given.get_one::<String>("profile");
+    let Some(config_path) = config_path else {
+        if let Some(profile) = profile {
+            anyhow::bail!(
+                "--profile {} needs a config file, but there is no {} in the working directory",
+                profile,
+                CONFIG_FILE_NAME
+            );
+        }
+        return Ok(Args::parse_from(cli));
+    };
+
+    let config = Config::load(&config_path)?;
+    let from_config = config
+        .options(profile.map(String::as_str))
+        .map_err(anyhow::Error::from)
+        .and_then(|options| config_args(&options, &given))
+        .with_context(|| format!("Invalid config file {}", config_path.display()))?;
+    let mut args = cli;
+    if args.is_empty() {
+        args.push(OsString::from("icon-gen"));
+    }
+    args.splice(1..1, from_config);
+    Ok(Args::parse_from(args))
+}
+
+/// Command line arguments for the config entries whose options weren't given on the command line
+fn config_args(entries: &Entries, given: &ArgMatches) -> Result<Vec<OsString>> {
+    let command = Args::command();
+    let mut args = Vec::new();
+    for (key, value) in entries {
+        let name = key.replace('_', "-");
+        let arg = command
+            .get_arguments()
+            .filter(|arg| !COMMAND_LINE_ONLY.contains(&arg.get_id().as_str()))
+            .find(|arg| match arg.get_long() {
+                Some(long) => long == name,
+                None => arg.get_id() == name.as_str(),
+            })
+            .with_context(|| format!("Unknown option: {}", key))?;
+        if given.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+            continue;
+        }
 
-    icon_gen::generate_icons(icon_args)
+        let takes_values = arg.get_action().takes_values();
+        let value_optional = arg
+            .get_num_args()
+            .is_some_and(|range| range.min_values() == 0);
+        match value {
+            Value::Boolean(set) if !takes_values || value_optional => {
+                if *set {
+                    args.push(OsString::from(format!("--{name}")));
+                }
+            }
+            _ if !takes_values => {
+                anyhow::bail!("{} is a switch and has to be true or false", key)
+            }
+            Value::Array(values) if !matches!(arg.get_action(), ArgAction::Append) => {
+                anyhow::bail!("{} takes a single value, not {} values", key, values.len())
+            }
+            Value::Array(values) => {
+                for value in values {
+                    if matches!(value, Value::Array(_)) {
+                        anyhow::bail!("{} can't contain nested arrays", key);
+                    }
+                    args.push(OsString::from(format!("--{name}={value}")));
+                }
+            }
+            value if arg.is_positional() => args.push(OsString::from(value.to_string())),
+            value => args.push(OsString::from(format!("--{name}={value}"))),
+        }
+    }
+    Ok(args)
 }
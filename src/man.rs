@@ -0,0 +1,172 @@
+//! The man page of the command line (`icon-gen man`)
+//!
+//! Rendered as roff from the clap definition of the binary, so it lists the
+//! same options as `--help`. There is no date in the header, which keeps the
+//! page the same for every build of a version.
+
+use clap::{Arg, Command};
+use std::fmt::Write;
+
+/// The man page of `command`, in section 1
+pub fn render(command: &mut Command) -> String {
+    // Adds --help, --version and the help command
+    command.build();
+    let name = command.get_name();
+    let version = command.get_version().unwrap_or_default();
+
+    let mut page = format!(
+        ".TH {} 1 \"\" \"{} {}\"\n",
+        escape(&name.to_uppercase()),
+        escape(name),
+        escape(version)
+    );
+    page.push_str(".SH NAME\n");
+    match command.get_about() {
+        Some(about) => {
+            let _ = writeln!(page, "{} \\- {}", escape(name), escape(&about.to_string()));
+        }
+        None => page.push_str(&format!("{}\n", escape(name))),
+    }
+
+    page.push_str(".SH SYNOPSIS\n");
+    let _ = write!(page, "\\fB{}\\fR [\\fIOPTIONS\\fR]", escape(name));
+    for positional in command.get_positionals() {
+        let _ = write!(page, " {}", value_name(positional));
+    }
+    page.push('\n');
+    for subcommand in command
+        .get_subcommands()
+        .filter(|command| !command.is_hide_set())
+    {
+        let _ = write!(
+            page,
+            ".br\n\\fB{} {}\\fR",
+            escape(name),
+            escape(subcommand.get_name())
+        );
+        for positional in subcommand.get_positionals() {
+            let _ = write!(page, " {}", value_name(positional));
+        }
+        page.push('\n');
+    }
+
+    if let Some(about) = command.get_long_about().or(command.get_about()) {
+        page.push_str(".SH DESCRIPTION\n");
+        page.push_str(&paragraphs(&about.to_string()));
+    }
+
+    page.push_str(".SH OPTIONS\n");
+    let arguments = command
+        .get_positionals()
+        .chain(command.get_arguments().filter(|arg| !arg.is_positional()));
+    for arg in arguments.filter(|arg| !arg.is_hide_set()) {
+        let _ = writeln!(page, ".TP\n{}", synopsis(arg));
+        let mut help = arg
+            .get_long_help()
+            .or(arg.get_help())
+            .map(|help| help.to_string())
+            .unwrap_or_default();
+        let defaults = arg
+            .get_default_values()
+            .iter()
+            .map(|value| value.to_string_lossy())
+            .collect::<Vec<_>>();
+        if !defaults.is_empty() && arg.get_action().takes_values() {
+            let _ = write!(help, " [default: {}]", defaults.join(","));
+        }
+        let values = arg
+            .get_possible_values()
+            .iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name().to_string())
+            .collect::<Vec<_>>();
+        if !values.is_empty() && arg.get_action().takes_values() {
+            let _ = write!(help, " [possible values: {}]", values.join(", "));
+        }
+        page.push_str(&paragraphs(help.trim()));
+    }
+
+    let subcommands = command
+        .get_subcommands()
+        .filter(|command| !command.is_hide_set())
+        .collect::<Vec<_>>();
+    if !subcommands.is_empty() {
+        page.push_str(".SH COMMANDS\n");
+        for subcommand in subcommands {
+            let _ = write!(page, ".TP\n\\fB{}\\fR", escape(subcommand.get_name()));
+            for positional in subcommand.get_positionals() {
+                let _ = write!(page, " {}", value_name(positional));
+            }
+            page.push('\n');
+            if let Some(about) = subcommand.get_long_about().or(subcommand.get_about()) {
+                page.push_str(&paragraphs(&about.to_string()));
+            }
+        }
+    }
+
+    if !version.is_empty() {
+        let _ = writeln!(page, ".SH VERSION\nv{}", escape(version));
+    }
+    page
+}
+
+/// How an option is spelled, e.g. `-o, --output DIR`
+fn synopsis(arg: &Arg) -> String {
+    if arg.is_positional() {
+        return value_name(arg);
+    }
+    let mut spellings = Vec::new();
+    if let Some(short) = arg.get_short() {
+        spellings.push(format!("\\fB\\-{}\\fR", escape(&short.to_string())));
+    }
+    if let Some(long) = arg.get_long() {
+        spellings.push(format!("\\fB\\-\\-{}\\fR", escape(long)));
+    }
+    let mut synopsis = spellings.join(", ");
+    if arg.get_action().takes_values() {
+        let optional = arg
+            .get_num_args()
+            .is_some_and(|range| range.min_values() == 0);
+        let _ = match optional {
+            true => write!(synopsis, " [{}]", value_name(arg)),
+            false => write!(synopsis, " {}", value_name(arg)),
+        };
+    }
+    synopsis
+}
+
+/// The italic value name of `arg`, e.g. `DIR`
+fn value_name(arg: &Arg) -> String {
+    let name = arg
+        .get_value_names()
+        .and_then(|names| names.first())
+        .map_or_else(
+            || arg.get_id().as_str().to_uppercase(),
+            |name| name.to_string(),
+        );
+    format!("\\fI{}\\fR", escape(&name))
+}
+
+/// `text` as roff, with a paragraph break for every blank line
+fn paragraphs(text: &str) -> String {
+    let mut roff = String::new();
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            roff.push_str(".sp\n");
+        } else {
+            roff.push_str(&escape(line));
+            roff.push('\n');
+        }
+    }
+    roff
+}
+
+/// Escape text for roff, which would read `\`, `-` and a leading `.` or `'`
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    if text.starts_with(['.', '\'']) {
+        format!("\\&{text}")
+    } else {
+        text
+    }
+}
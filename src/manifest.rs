@@ -0,0 +1,138 @@
+//! Output manifest with checksums (`--manifest`)
+//!
+//! Every generated set gets an `icons.manifest.json` in its directory listing each
+//! of its files with the hex SHA-256 of the contents, for integrity checks and CI
+//! cache keys:
+//!
+//! ```json
+//! {
+//!   "algorithm": "sha256",
+//!   "files": [
+//!     { "path": "windows/icon.ico", "bytes": 90518, "sha256": "9f86d0…" }
+//!   ]
+//! }
+//! ```
+//!
+//! Paths are relative to the set directory and use `/` on every platform. The
+//! manifest doesn't list itself or the generation cache.
+
+use crate::{output::OutputSink, progress, report::RunReport};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+};
+
+/// Name of the manifest written into each set directory
+pub const MANIFEST_FILE_NAME: &str = "icons.manifest.json";
+
+/// The manifest of a set directory
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    /// Always `sha256`
+    pub algorithm: String,
+    /// Every file of the set, in path order
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// The path relative to the set directory, e.g. `windows/icon.ico`
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+/// Write a manifest into every set directory of a run: the output directory and
+/// its variant siblings, e.g. `icons/` and `icons_gray/`
+pub(crate) fn write_manifests(
+    output_dir: &Path,
+    report: &RunReport,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let root = output_dir.parent().unwrap_or(Path::new(""));
+    let mut sets = BTreeMap::<PathBuf, Vec<ManifestEntry>>::new();
+    for file in &report.files {
+        let Ok(relative) = file.path.strip_prefix(root) else {
+            continue;
+        };
+        let mut components = relative.components();
+        let Some(Component::Normal(set)) = components.next() else {
+            continue;
+        };
+        let path = components
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if path.is_empty() || path == MANIFEST_FILE_NAME {
+            continue;
+        }
+        sets.entry(root.join(set)).or_default().push(ManifestEntry {
+            path,
+            bytes: file.bytes,
+            sha256: file.sha256.clone(),
+        });
+    }
+
+    for (dir, mut files) in sets {
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let manifest = Manifest {
+            algorithm: "sha256".to_string(),
+            files,
+        };
+        let path = dir.join(MANIFEST_FILE_NAME);
+        let json = serde_json::to_string_pretty(&manifest)
+            .context("Failed to serialize the output manifest")?;
+        output.write(&path, json.as_bytes())?;
+        progress::file(format_args!("✓ Generated {}", path.display()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{output::MemorySink, report::ReportingSink};
+
+    #[test]
+    fn test_manifest_per_set() {
+        let memory = MemorySink::new();
+        let sink = ReportingSink::new(&memory);
+        sink.write(Path::new("out/icons/windows/icon.ico"), b"ico")
+            .unwrap();
+        sink.write(Path::new("out/icons/32x32.png"), b"png")
+            .unwrap();
+        sink.write(Path::new("out/icons_gray/32x32.png"), b"gray")
+            .unwrap();
+        // A manifest kept from an earlier run doesn't list itself
+        sink.write(Path::new("out/icons/icons.manifest.json"), b"{}")
+            .unwrap();
+        write_manifests(Path::new("out/icons"), &sink.into_report(), &memory).unwrap();
+
+        let files = memory.into_files();
+        let manifest =
+            |path: &str| serde_json::from_slice::<Manifest>(&files[Path::new(path)]).unwrap();
+        assert_eq!(
+            manifest("out/icons/icons.manifest.json"),
+            Manifest {
+                algorithm: "sha256".to_string(),
+                files: vec![
+                    ManifestEntry {
+                        path: "32x32.png".to_string(),
+                        bytes: 3,
+                        sha256: crate::sha256::sha256_hex(b"png"),
+                    },
+                    ManifestEntry {
+                        path: "windows/icon.ico".to_string(),
+                        bytes: 3,
+                        sha256: crate::sha256::sha256_hex(b"ico"),
+                    },
+                ],
+            }
+        );
+        let gray = manifest("out/icons_gray/icons.manifest.json");
+        assert_eq!(gray.files.len(), 1);
+        assert_eq!(gray.files[0].path, "32x32.png");
+    }
+}
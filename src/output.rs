@@ -0,0 +1,246 @@
+//! Where generated files go
+//!
+//! Every file of a run is encoded in memory and handed to an [`OutputSink`],
+//! addressed by the path it has on disk under the run's output directory, e.g.
+//! `icons/windows/icon.ico`. The passes over the written PNGs (`--quantize`,
+//! `--optimize`, `--compression zopfli`) read files back through the same sink.
+//!
+//! - [`FileSystemSink`] writes to disk, leaving files whose contents are unchanged alone
+//! - [`MemorySink`] keeps the files in a map, for `generate_to_memory`
+//! - [`ArchiveSink`] collects the files into a zip or tar archive
+
+use crate::archive::ArchiveFormat;
+use crate::error::{IconGenError, Result};
+use crate::icon_gen::write_output;
+use anyhow::Context;
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+
+/// The destination of the files generated by a run
+/// Files are written from the worker threads, so sinks have to be `Sync`
+pub trait OutputSink: Sync {
+    /// Store `bytes` as the file at `path`, replacing any earlier contents
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()>;
+
+    /// The contents of a file written earlier in the run
+    fn read(&self, path: &Path) -> Result<Vec<u8>>;
+
+    /// Whether there is a file at `path`
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Every file under `dir`, in path order
+    fn files(&self, dir: &Path) -> Result<Vec<PathBuf>>;
+
+    /// Whether files outlive the run in the output directory, so that the
+    /// generation cache, `--skip-existing` and `--if-newer` can compare against them
+    fn is_persistent(&self) -> bool {
+        false
+    }
+
+    /// Note a file kept from an earlier run instead of being written again, e.g.
+    /// one of a set the generation cache found up to date
+    fn keep(&self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    /// Remove a file an earlier run wrote (`--clean`), along with the folders
+    /// between it and `dir` that it leaves empty
+    fn remove(&self, path: &Path, _dir: &Path) -> Result<()> {
+        Err(IconGenError::InvalidInput(format!(
+            "Can't remove {} from this output",
+            path.display()
+        )))
+    }
+}
+
+/// Writes files to disk, creating directories as needed
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FileSystemSink;
+
+impl OutputSink for FileSystemSink {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent).map_err(|source| IconGenError::io(parent, source))?;
+        }
+        write_output(path, bytes)
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        std::fs::read(path).map_err(|source| IconGenError::io(path, source))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn files(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        collect_files(dir, &mut paths)?;
+        Ok(paths)
+    }
+
+    fn is_persistent(&self) -> bool {
+        true
+    }
+
+    fn remove(&self, path: &Path, dir: &Path) -> Result<()> {
+        std::fs::remove_file(path).map_err(|source| IconGenError::io(path, source))?;
+        // Folders left empty go as well, e.g. a density no longer generated
+        for parent in path.ancestors().skip(1).take_while(|parent| *parent != dir) {
+            if std::fs::remove_dir(parent).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Every file under `dir`, in path order
+pub(crate) fn collect_files(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<Vec<_>>>()
+        })
+        .map_err(|source| IconGenError::io(dir, source))?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collect_files(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Keeps files in memory, leaving the filesystem untouched
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The files written so far, keyed by path
+    pub fn into_files(self) -> BTreeMap<PathBuf, Vec<u8>> {
+        self.files.into_inner().unwrap()
+    }
+}
+
+impl OutputSink for MemorySink {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.files
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| {
+                let message = format!("{} was not generated", path.display());
+                IconGenError::io(
+                    path,
+                    std::io::Error::new(std::io::ErrorKind::NotFound, message),
+                )
+            })
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.lock().unwrap().contains_key(path)
+    }
+
+    fn files(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .files
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|path| path.starts_with(dir))
+            .cloned()
+            .collect())
+    }
+}
+
+/// Collects files into an archive, named by their path relative to `root`
+/// Files are kept in memory until `finish`, as the PNG passes rewrite them in place
+#[derive(Debug)]
+pub struct ArchiveSink {
+    root: PathBuf,
+    format: ArchiveFormat,
+    files: MemorySink,
+}
+
+impl ArchiveSink {
+    /// Collect files from under `root`, e.g. the parent of the output directory
+    /// to keep the output and variant directories apart in the archive
+    pub fn new(root: impl Into<PathBuf>, format: ArchiveFormat) -> Self {
+        Self {
+            root: root.into(),
+            format,
+            files: MemorySink::new(),
+        }
+    }
+
+    /// The archive of every file written, in path order
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let files = self.files.into_files();
+        files
+            .iter()
+            .map(|(path, bytes)| Ok((entry_name(path, &self.root)?, bytes.as_slice())))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .and_then(|entries| self.format.encode(&entries))
+            .map_err(IconGenError::from_anyhow)
+    }
+}
+
+/// The `/`-separated archive name of `path`, which has to be under `root`
+fn entry_name(path: &Path, root: &Path) -> anyhow::Result<String> {
+    let relative = path
+        .strip_prefix(root)
+        .with_context(|| format!("{} is outside {}", path.display(), root.display()))?;
+    relative
+        .components()
+        .map(|component| match component {
+            Component::Normal(part) => Ok(Some(part.to_string_lossy())),
+            Component::CurDir => Ok(None),
+            _ => anyhow::bail!("{} can't be stored in an archive", path.display()),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map(|parts| parts.into_iter().flatten().collect::<Vec<_>>().join("/"))
+}
+
+impl OutputSink for ArchiveSink {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        self.files.write(path, bytes)
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.files.read(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.exists(path)
+    }
+
+    fn files(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        self.files.files(dir)
+    }
+}
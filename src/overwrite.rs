@@ -0,0 +1,269 @@
+//! What happens to files already in the output directory (`--force`,
+//! `--clean`, `--no-clobber`)
+//!
+//! By default a set only replaces the files an earlier run generated, as
+//! recorded in the generation cache; a file of the same name put there by
+//! anything else fails the run instead of being lost. `--no-clobber` refuses to
+//! replace any existing file, and `--force` replaces whatever is there.
+//! `--clean` also replaces anything, then removes the files earlier runs
+//! generated that this one didn't write, e.g. the `android/mipmap-xxxhdpi/`
+//! icons of a density that is no longer generated. Files the tool never
+//! generated are never removed, nor rewritten by the passes over the PNGs of a
+//! set (`--quantize`, `--optimize`, ...), so a set can be written into an existing
+//! `.xcassets` catalog or Android `res/` tree and regenerated safely. Writing a
+//! file with the contents it already has never counts as replacing it. With `--contents-json merge`,
+//! asset catalog `Contents.json` files are merged into rather than replaced, so
+//! they aren't protected. When a set directory holds protected files, the set
+//! is generated into memory first and only written once none of them would be
+//! replaced, so a refused run leaves the directory as it was.
+
+use crate::error::{IconGenError, Result};
+use crate::icon_gen::Args;
+use crate::output::OutputSink;
+use crate::progress;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Files held back with their contents, in the order they were first written
+#[derive(Default)]
+struct Staged {
+    files: Vec<(PathBuf, Vec<u8>)>,
+    /// The position of each file in `files`
+    index: HashMap<PathBuf, usize>,
+}
+
+impl Staged {
+    fn insert(&mut self, path: &Path, bytes: &[u8]) {
+        match self.index.get(path) {
+            Some(&position) => self.files[position].1 = bytes.to_vec(),
+            None => {
+                self.index.insert(path.to_path_buf(), self.files.len());
+                self.files.push((path.to_path_buf(), bytes.to_vec()));
+            }
+        }
+    }
+
+    fn get(&self, path: &Path) -> Option<&[u8]> {
+        let &position = self.index.get(path)?;
+        Some(&self.files[position].1)
+    }
+}
+
+/// Passes the files of a set on to another sink, refusing to replace the
+/// existing files the overwrite policy protects
+pub(crate) struct OverwriteGuard<'a> {
+    inner: &'a dyn OutputSink,
+    /// Existing files this run may not replace with different contents
+    protected: HashSet<PathBuf>,
+    no_clobber: bool,
+    /// The files of the set, held back until `commit` when any file is protected
+    staged: Option<Mutex<Staged>>,
+    /// Existing files earlier runs generated, the only ones `--clean` removes
+    owned: HashSet<PathBuf>,
+    /// Every file written, for `--clean` and the passes over the PNGs of the set
+    written: Mutex<HashSet<PathBuf>>,
+}
+
+impl<'a> OverwriteGuard<'a> {
+    /// Guard a set whose directory holds `existing`, of which `generated` were
+    /// written by earlier runs
+    pub(crate) fn new(
+        inner: &'a dyn OutputSink,
+        args: &Args,
+        existing: &[PathBuf],
+        generated: &HashSet<PathBuf>,
+    ) -> Self {
+        let protected = if args.force || args.clean {
+            HashSet::new()
+        } else {
+            // A merged Contents.json keeps what was in the file
+            let merged = |path: &Path| {
+                args.contents_json == "merge"
+                    && path.file_name().is_some_and(|name| name == "Contents.json")
+            };
+            existing
+                .iter()
+                .filter(|path| args.no_clobber || !generated.contains(*path))
+                .filter(|path| !merged(path))
+                .cloned()
+                .collect()
+        };
+        Self {
+            inner,
+            staged: (!protected.is_empty()).then(Mutex::default),
+            protected,
+            no_clobber: args.no_clobber,
+            owned: existing
+                .iter()
+                .filter(|path| generated.contains(*path))
+                .cloned()
+                .collect(),
+            written: Mutex::default(),
+        }
+    }
+
+    /// Fail when a file held back would replace a protected file, before
+    /// `commit` writes any of them
+    pub(crate) fn check(&self) -> Result<()> {
+        let Some(staged) = &self.staged else {
+            return Ok(());
+        };
+        for (path, bytes) in staged.lock().unwrap().files.iter() {
+            if !self.protected.contains(path)
+                || self
+                    .inner
+                    .read(path)
+                    .is_ok_and(|existing| existing == *bytes)
+            {
+                continue;
+            }
+            return Err(IconGenError::InvalidInput(if self.no_clobber {
+                format!("Refusing to overwrite {} (--no-clobber)", path.display())
+            } else {
+                format!(
+                    "Refusing to overwrite {}, which icon-gen didn't generate. Pass --force to replace it, or --clean to also remove stale files",
+                    path.display()
+                )
+            }));
+        }
+        Ok(())
+    }
+
+    /// Write the files held back, once `check` passed
+    pub(crate) fn commit(&self) -> Result<()> {
+        let Some(staged) = &self.staged else {
+            return Ok(());
+        };
+        for (path, bytes) in std::mem::take(&mut *staged.lock().unwrap()).files {
+            self.inner.write(&path, &bytes)?;
+            self.written.lock().unwrap().insert(path);
+        }
+        Ok(())
+    }
+
+    /// Write every file straight through from now on, once `check` passed for
+    /// the files the set is generated again with
+    pub(crate) fn write_through(&mut self) {
+        self.staged = None;
+    }
+
+    /// Remove the files earlier runs generated that this run didn't write from
+    /// the subfolders of the set directory `dir` it wrote into (`--clean`)
+    pub(crate) fn remove_stale(&self, dir: &Path) -> Result<()> {
+        let written = self.written.lock().unwrap();
+        let subfolders = written
+            .iter()
+            .filter_map(|path| {
+                let mut components = path.strip_prefix(dir).ok()?.components();
+                let first = components.next()?;
+                // Files directly in the set directory aren't in a subfolder
+                components.next()?;
+                Some(dir.join(first))
+            })
+            .collect::<HashSet<_>>();
+
+        let mut removed = 0;
+        for subfolder in subfolders {
+            for path in self.inner.files(&subfolder)? {
+                if written.contains(&path) || !self.owned.contains(&path) {
+                    continue;
+                }
+                self.inner.remove(&path, dir)?;
+                removed += 1;
+            }
+        }
+        if removed > 0 {
+            progress::note(format_args!(
+                "✓ Removed {removed} stale files from {}",
+                dir.display()
+            ));
+        }
+        Ok(())
+    }
+
+    /// The files the set owns after this run: those it wrote, and those earlier
+    /// runs generated that are still in place
+    pub(crate) fn owned_files(&self) -> Vec<PathBuf> {
+        let written = self.written.lock().unwrap();
+        let mut files = written
+            .iter()
+            .chain(self.owned.iter().filter(|path| !written.contains(*path)))
+            .filter(|path| self.inner.exists(path))
+            .cloned()
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+}
+
+impl OutputSink for OverwriteGuard<'_> {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        if let Some(staged) = &self.staged {
+            staged.lock().unwrap().insert(path, bytes);
+            return Ok(());
+        }
+        self.inner.write(path, bytes)?;
+        self.written.lock().unwrap().insert(path.to_path_buf());
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        if let Some(bytes) = self
+            .staged
+            .as_ref()
+            .and_then(|staged| Some(staged.lock().unwrap().get(path)?.to_vec()))
+        {
+            return Ok(bytes);
+        }
+        self.inner.read(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.staged
+            .as_ref()
+            .is_some_and(|staged| staged.lock().unwrap().index.contains_key(path))
+            || self.inner.exists(path)
+    }
+
+    /// Only the files this run wrote, so the passes over the PNGs of a set
+    /// never read or rewrite files the tool didn't generate
+    fn files(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = self
+            .written
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|path| path.starts_with(dir))
+            .cloned()
+            .collect::<Vec<_>>();
+        if let Some(staged) = &self.staged {
+            let staged = staged.lock().unwrap();
+            files.extend(
+                staged
+                    .files
+                    .iter()
+                    .map(|(path, _)| path)
+                    .filter(|path| path.starts_with(dir))
+                    .cloned(),
+            );
+        }
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    fn is_persistent(&self) -> bool {
+        self.inner.is_persistent()
+    }
+
+    fn keep(&self, path: &Path) -> Result<()> {
+        self.inner.keep(path)
+    }
+
+    fn remove(&self, path: &Path, dir: &Path) -> Result<()> {
+        self.inner.remove(path, dir)
+    }
+}
@@ -0,0 +1,100 @@
+//! Platforms as pluggable generators
+//!
+//! Each platform (windows, macos, linux, android, ios) is a [`PlatformGenerator`]
+//! that writes its files through a [`PlatformContext`]. Library users can register
+//! generators of their own with `IconGenerator::register`. They run after the
+//! built-in platforms, from the untreated source unless named after a built-in
+//! platform, and their PNGs go through the same `--quantize`, `--optimize` and
+//! `--compression` passes.
+
+pub use crate::icon_gen::{AndroidIcons, IosIcons, LinuxIcons, MacosIcons, WindowsIcons};
+
+use crate::compositor::Overlays;
+use crate::error::{IconGenError, Result};
+use crate::icon_gen::{save_png, Args, PlatformSources, Resampler};
+use crate::output::OutputSink;
+use crate::progress;
+use image::DynamicImage;
+use std::{fmt, path::Path};
+
+/// A platform whose icons are generated from the source image
+pub trait PlatformGenerator: fmt::Debug + Send + Sync {
+    /// The platform name, e.g. `windows`
+    fn name(&self) -> &str;
+
+    /// The pixel sizes of the square icons the platform gets
+    fn sizes(&self) -> Vec<u32>;
+
+    /// Write the platform's files
+    fn emit(&self, ctx: &PlatformContext<'_>) -> Result<()>;
+}
+
+/// What a generator renders from and writes to
+pub struct PlatformContext<'a> {
+    pub(crate) sources: &'a PlatformSources<'a>,
+    /// The name the platform's source treatments are selected by, empty for custom platforms
+    pub(crate) platform: &'static str,
+    pub(crate) args: &'a Args,
+    pub(crate) overlays: &'a Overlays,
+    pub(crate) output: &'a dyn OutputSink,
+}
+
+impl<'a> PlatformContext<'a> {
+    /// The source image as seen by this platform
+    pub fn source(&self) -> &DynamicImage {
+        self.sources.get(self.platform)
+    }
+
+    /// The options of the run
+    pub fn args(&self) -> &Args {
+        self.args
+    }
+
+    /// The directory the run writes to; platforms write into a subdirectory of it
+    pub fn output_dir(&self) -> &Path {
+        &self.args.output
+    }
+
+    /// The source resized to `size` x `size` with the run's filter, sharpening and
+    /// overlays (`--dev-mode`, `--overlay`, `--stamp-text`, ...)
+    pub fn render(&self, size: u32) -> DynamicImage {
+        let mut image = self.resampler().resize(self.source(), size, size);
+        self.overlays.apply(&mut image);
+        image
+    }
+
+    /// Write `bytes` to `relative`, a path inside the output directory
+    pub fn write(&self, relative: impl AsRef<Path>, bytes: &[u8]) -> Result<()> {
+        let relative = relative.as_ref();
+        self.output.write(&self.args.output.join(relative), bytes)?;
+        progress::file(format_args!("  ✓ Generated {}", relative.display()));
+        Ok(())
+    }
+
+    /// Write `image` as a PNG to `relative`, a path inside the output directory
+    pub fn write_png(&self, relative: impl AsRef<Path>, image: &DynamicImage) -> Result<()> {
+        let relative = relative.as_ref();
+        save_png(
+            image,
+            &self.args.output.join(relative),
+            self.args.png_encoding(),
+            self.output,
+        )
+        .map_err(IconGenError::from_anyhow)?;
+        progress::file(format_args!("  ✓ Generated {}", relative.display()));
+        Ok(())
+    }
+
+    pub(crate) fn resampler(&self) -> Resampler<'a> {
+        self.sources.resampler(self.platform)
+    }
+}
+
+impl fmt::Debug for PlatformContext<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PlatformContext")
+            .field("platform", &self.platform)
+            .field("output", &self.args.output)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,177 @@
+//! Color-space and metadata chunks of the generated PNGs (`--png-metadata`)
+//!
+//! The encoders write nothing but the image data, so a generated PNG doesn't
+//! say which color space its pixels are in and every viewer or validator
+//! assumes its own. Some pipelines insist that every icon of a set is tagged
+//! the same way. `srgb` tags each PNG as sRGB, together with the gAMA and cHRM
+//! chunks the PNG specification recommends for decoders that don't know sRGB;
+//! `strip` removes every ancillary chunk. The tRNS transparency and the APNG
+//! animation chunks are kept either way, since they change what the image is.
+
+use anyhow::{bail, Result};
+
+/// Modes of `--png-metadata`
+pub const PNG_METADATA_MODES: &[&str] = &["keep", "srgb", "strip"];
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Ancillary chunks that are part of the image rather than metadata
+const IMAGE_CHUNKS: &[&[u8; 4]] = &[b"tRNS", b"acTL", b"fcTL", b"fdAT"];
+
+/// Chunks that describe the color space, replaced by the sRGB tags
+const COLOR_CHUNKS: &[&[u8; 4]] = &[
+    b"iCCP", b"sRGB", b"gAMA", b"cHRM", b"cICP", b"mDCv", b"cLLi",
+];
+
+/// gAMA of sRGB: 1 / 2.2, times 100000
+const SRGB_GAMMA: u32 = 45455;
+
+/// cHRM of sRGB: the white point and the red, green and blue primaries (x, y),
+/// times 100000
+const SRGB_CHROMATICITIES: [u32; 8] = [31270, 32900, 64000, 33000, 30000, 60000, 15000, 6000];
+
+/// A chunk of a PNG file: its type and data
+pub(crate) type Chunk<'a> = ([u8; 4], &'a [u8]);
+
+/// The chunks of the PNG file `png`, in order
+pub(crate) fn chunks(png: &[u8]) -> Result<Vec<Chunk<'_>>> {
+    let Some(mut rest) = png.strip_prefix(SIGNATURE) else {
+        bail!("Not a PNG file");
+    };
+    let mut chunks = Vec::new();
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes(rest[..4].try_into()?) as usize;
+        if rest.len() < length + 12 {
+            bail!("Truncated PNG chunk");
+        }
+        chunks.push((rest[4..8].try_into()?, &rest[8..8 + length]));
+        rest = &rest[length + 12..];
+    }
+    Ok(chunks)
+}
+
+/// A PNG file of `chunks`, with their lengths and CRCs
+pub(crate) fn assemble(chunks: &[Chunk<'_>]) -> Vec<u8> {
+    let mut png = SIGNATURE.to_vec();
+    for (chunk_type, data) in chunks {
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(chunk_type);
+        crc.update(data);
+
+        png.extend((data.len() as u32).to_be_bytes());
+        png.extend(chunk_type);
+        png.extend(*data);
+        png.extend(crc.finalize().to_be_bytes());
+    }
+    png
+}
+
+/// The PNG file `png` with its metadata chunks set as `mode` asks
+pub(crate) fn set_metadata(png: &[u8], mode: &str) -> Result<Vec<u8>> {
+    let chunks = chunks(png)?;
+    let srgb_tags = [
+        // Perceptual rendering intent
+        (*b"sRGB", vec![0]),
+        (*b"gAMA", SRGB_GAMMA.to_be_bytes().to_vec()),
+        (
+            *b"cHRM",
+            SRGB_CHROMATICITIES
+                .iter()
+                .flat_map(|value| value.to_be_bytes())
+                .collect(),
+        ),
+    ];
+
+    let mut kept: Vec<Chunk<'_>> = Vec::new();
+    for (chunk_type, data) in chunks {
+        // The case of the first letter tells critical chunks (IHDR, PLTE, IDAT, IEND) apart
+        let ancillary = chunk_type[0].is_ascii_lowercase();
+        let keep = match mode {
+            "keep" => true,
+            "srgb" => !COLOR_CHUNKS.contains(&&chunk_type),
+            "strip" => !ancillary || IMAGE_CHUNKS.contains(&&chunk_type),
+            _ => bail!("Unknown PNG metadata mode: {mode}"),
+        };
+        if keep {
+            kept.push((chunk_type, data));
+        }
+        // The color space has to come before PLTE and IDAT, so right after IHDR
+        if mode == "srgb" && &chunk_type == b"IHDR" {
+            kept.extend(srgb_tags.iter().map(|(tag, data)| (*tag, data.as_slice())));
+        }
+    }
+    Ok(assemble(&kept))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    fn png() -> Vec<u8> {
+        let image = RgbaImage::from_fn(8, 8, |x, y| Rgba([x as u8 * 30, y as u8 * 30, 90, 200]));
+        let mut bytes = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(image)
+            .write_to(&mut bytes, ImageFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    /// `png` with a tEXt and a gAMA chunk after its IHDR
+    fn png_with_metadata() -> Vec<u8> {
+        let original = png();
+        let mut chunks = chunks(&original).unwrap();
+        chunks.insert(1, (*b"tEXt", b"Software\0paint"));
+        chunks.insert(2, (*b"gAMA", &[0, 0, 0xb1, 0x8f]));
+        assemble(&chunks)
+    }
+
+    fn types(png: &[u8]) -> Vec<String> {
+        chunks(png)
+            .unwrap()
+            .iter()
+            .map(|(chunk_type, _)| String::from_utf8_lossy(chunk_type).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let png = png_with_metadata();
+        assert_eq!(assemble(&chunks(&png).unwrap()), png);
+        assert_eq!(set_metadata(&png, "keep").unwrap(), png);
+        assert!(chunks(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn test_srgb() {
+        let tagged = set_metadata(&png_with_metadata(), "srgb").unwrap();
+        assert_eq!(
+            types(&tagged),
+            ["IHDR", "sRGB", "gAMA", "cHRM", "tEXt", "IDAT", "IEND"]
+        );
+        let gamma = chunks(&tagged).unwrap()[2].1.to_vec();
+        assert_eq!(gamma, SRGB_GAMMA.to_be_bytes());
+        // Tagging twice changes nothing
+        assert_eq!(set_metadata(&tagged, "srgb").unwrap(), tagged);
+
+        let decoded = image::load_from_memory(&tagged).unwrap();
+        assert_eq!(decoded, image::load_from_memory(&png()).unwrap());
+    }
+
+    #[test]
+    fn test_strip() {
+        let tagged = set_metadata(&png_with_metadata(), "srgb").unwrap();
+        let stripped = set_metadata(&tagged, "strip").unwrap();
+        assert_eq!(types(&stripped), ["IHDR", "IDAT", "IEND"]);
+        assert_eq!(stripped, png());
+
+        let mut with_transparency = chunks(&stripped).unwrap();
+        with_transparency.insert(1, (*b"tRNS", &[0]));
+        let with_transparency = assemble(&with_transparency);
+        assert_eq!(
+            set_metadata(&with_transparency, "strip").unwrap(),
+            with_transparency
+        );
+    }
+}
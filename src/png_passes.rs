@@ -0,0 +1,424 @@
+//! Passes over the PNGs a run wrote (`--quantize`, `--optimize`,
+//! `--compression zopfli`, `--png-metadata`)
+//!
+//! They run once every platform of a set is written, reading the files back
+//! through the set's `OutputSink` and writing a file again only when it
+//! changes. The sink lists only the files the run wrote, so PNGs of the
+//! project a set is written into are left alone.
+
+use crate::output::OutputSink;
+use crate::png_metadata;
+use crate::progress;
+use crate::zopfli;
+use anyhow::{Context, Result};
+use image::{ColorType, Pixel, RgbaImage};
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Largest side, in pixels, of the PNGs that `--quantize` rewrites
+const QUANTIZE_MAX_SIZE: u32 = 64;
+
+/// Rewrite the small PNGs this run wrote under `dir` as palette images with at most `colors` entries
+/// Favicons and tray icons use few colors, so PLTE + tRNS is a fraction of the RGBA size
+pub(crate) fn quantize_small_pngs(dir: &Path, colors: u16, output: &dyn OutputSink) -> Result<()> {
+    progress::step(format_args!(
+        "Quantizing PNGs up to {QUANTIZE_MAX_SIZE}px to {colors} colors..."
+    ));
+
+    let paths = collect_pngs(dir, output)?;
+    paths.into_par_iter().try_for_each(|path| {
+        let image = image::load_from_memory(&output.read(&path)?)
+            .with_context(|| format!("Failed to load {}", path.display()))?;
+        if image.width().max(image.height()) > QUANTIZE_MAX_SIZE {
+            return Ok(());
+        }
+
+        save_indexed_png(&image.into_rgba8(), &path, colors, output)?;
+        let relative = path.strip_prefix(dir).unwrap_or(&path);
+        progress::detail(format_args!("  ✓ Quantized {}", relative.display()));
+        Ok(())
+    })
+}
+
+/// Losslessly re-encode the PNGs this run wrote under `dir`, keeping each file's smallest encoding
+/// Like oxipng, this reduces the color type and bit depth and tries every filter strategy
+pub(crate) fn optimize_pngs(dir: &Path, output: &dyn OutputSink) -> Result<()> {
+    progress::step("Optimizing PNGs...");
+
+    let paths = collect_pngs(dir, output)?;
+
+    let sizes = paths
+        .into_par_iter()
+        .map(|path| {
+            let original = output.read(&path)?;
+            let image = image::load_from_memory_with_format(&original, image::ImageFormat::Png)
+                .with_context(|| format!("Failed to load {}", path.display()))?;
+
+            // 16-bit images would lose precision through the 8-bit reductions
+            let optimized = match image.color() {
+                ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8 => {
+                    Some(encode_smallest_png(&image.into_rgba8())?)
+                }
+                _ => None,
+            }
+            .filter(|optimized| optimized.len() < original.len());
+
+            match optimized {
+                Some(optimized) => {
+                    output.write(&path, &optimized)?;
+                    Ok((original.len(), optimized.len()))
+                }
+                None => Ok((original.len(), original.len())),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let (before, after) = sum_sizes(&sizes);
+    progress::note(format_args!(
+        "  ✓ PNGs reduced from {before} to {after} bytes"
+    ));
+    Ok(())
+}
+
+/// Parse iterations for `--compression zopfli`, the same default as the zopfli tool
+const ZOPFLI_ITERATIONS: usize = 15;
+
+/// Recompress the image data of the PNGs this run wrote under `dir` with the Zopfli-style deflater
+/// Only the IDAT stream changes, so the pixels, filters and other chunks stay as written
+pub(crate) fn recompress_pngs_zopfli(dir: &Path, output: &dyn OutputSink) -> Result<()> {
+    progress::step("Recompressing PNGs with zopfli (this can take a while)...");
+
+    let paths = collect_pngs(dir, output)?;
+
+    let sizes = paths
+        .into_par_iter()
+        .map(|path| {
+            let original = output.read(&path)?;
+            let recompressed = recompress_png_zopfli(&original)
+                .with_context(|| format!("Failed to recompress {}", path.display()))?;
+
+            if recompressed.len() < original.len() {
+                output.write(&path, &recompressed)?;
+                Ok((original.len(), recompressed.len()))
+            } else {
+                Ok((original.len(), original.len()))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let (before, after) = sum_sizes(&sizes);
+    progress::note(format_args!(
+        "  ✓ PNGs reduced from {before} to {after} bytes"
+    ));
+    Ok(())
+}
+
+/// Total the (before, after) byte counts of the files rewritten by a PNG pass
+fn sum_sizes(sizes: &[(usize, usize)]) -> (usize, usize) {
+    sizes.iter().fold((0, 0), |(before, after), (old, new)| {
+        (before + old, after + new)
+    })
+}
+
+/// Rewrite a PNG file with its IDAT chunks merged into one, deflated by zopfli
+fn recompress_png_zopfli(png_data: &[u8]) -> Result<Vec<u8>> {
+    let chunks = png_metadata::chunks(png_data)?;
+    let image_data = chunks
+        .iter()
+        .filter(|(chunk_type, _)| chunk_type == b"IDAT")
+        .flat_map(|(_, data)| data.iter().copied())
+        .collect::<Vec<_>>();
+
+    let filtered = miniz_oxide::inflate::decompress_to_vec_zlib(&image_data)
+        .map_err(|error| anyhow::anyhow!("Invalid PNG image data: {error:?}"))?;
+    let compressed = zopfli::compress_zlib(&filtered, ZOPFLI_ITERATIONS);
+
+    // The merged IDAT takes the position of the first one
+    let mut merged = Vec::with_capacity(chunks.len());
+    for (chunk_type, data) in chunks {
+        if &chunk_type != b"IDAT" {
+            merged.push((chunk_type, data));
+        } else if !merged.iter().any(|(chunk_type, _)| chunk_type == b"IDAT") {
+            merged.push((chunk_type, &compressed[..]));
+        }
+    }
+    Ok(png_metadata::assemble(&merged))
+}
+
+/// Tag the PNGs this run wrote under `dir` as sRGB or strip their metadata chunks, as `mode` asks
+/// (`--png-metadata`); a file is only rewritten when its chunks change
+pub(crate) fn set_pngs_metadata(dir: &Path, mode: &str, output: &dyn OutputSink) -> Result<()> {
+    progress::step(match mode {
+        "srgb" => "Tagging PNGs as sRGB...",
+        _ => "Stripping PNG metadata...",
+    });
+
+    let paths = collect_pngs(dir, output)?;
+    paths.into_par_iter().try_for_each(|path| {
+        let original = output.read(&path)?;
+        let rewritten = png_metadata::set_metadata(&original, mode)
+            .with_context(|| format!("Failed to rewrite {}", path.display()))?;
+        if rewritten != original {
+            output.write(&path, &rewritten)?;
+        }
+        Ok(())
+    })
+}
+
+/// Pixel data in one of the PNG color types, ready for the encoder
+struct PngLayout {
+    color: png::ColorType,
+    depth: png::BitDepth,
+    data: Vec<u8>,
+    /// RGB palette and its tRNS alpha entries, for indexed layouts
+    palette: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Encode the image in every lossless layout and filter strategy, returning the smallest
+fn encode_smallest_png(image: &RgbaImage) -> Result<Vec<u8>> {
+    let opaque = image.pixels().all(|pixel| pixel[3] == 255);
+    let gray = image
+        .pixels()
+        .all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2]);
+
+    let (color, data) = match (gray, opaque) {
+        (true, true) => (
+            png::ColorType::Grayscale,
+            image.pixels().map(|pixel| pixel[0]).collect(),
+        ),
+        (true, false) => (
+            png::ColorType::GrayscaleAlpha,
+            image
+                .pixels()
+                .flat_map(|pixel| [pixel[0], pixel[3]])
+                .collect(),
+        ),
+        (false, true) => (
+            png::ColorType::Rgb,
+            image.pixels().flat_map(|pixel| pixel.to_rgb().0).collect(),
+        ),
+        (false, false) => (png::ColorType::Rgba, image.as_raw().clone()),
+    };
+
+    let mut layouts = vec![PngLayout {
+        color,
+        depth: png::BitDepth::Eight,
+        data,
+        palette: None,
+    }];
+    layouts.extend(indexed_layout(image));
+
+    // None stands for the encoder's per-row adaptive choice
+    let filters = [
+        Some(png::FilterType::NoFilter),
+        Some(png::FilterType::Sub),
+        Some(png::FilterType::Up),
+        Some(png::FilterType::Avg),
+        Some(png::FilterType::Paeth),
+        None,
+    ];
+
+    let mut smallest: Option<Vec<u8>> = None;
+    for layout in &layouts {
+        for filter in filters {
+            let encoded = encode_png_layout(image.width(), image.height(), layout, filter)?;
+            if smallest
+                .as_ref()
+                .is_none_or(|smallest| encoded.len() < smallest.len())
+            {
+                smallest = Some(encoded);
+            }
+        }
+    }
+    Ok(smallest.unwrap_or_default())
+}
+
+/// The palette layout of an image with at most 256 distinct colors, at the lowest bit depth
+fn indexed_layout(image: &RgbaImage) -> Option<PngLayout> {
+    let mut colors: Vec<[u8; 4]> = Vec::new();
+    for pixel in image.pixels() {
+        if !colors.contains(&pixel.0) {
+            if colors.len() == 256 {
+                return None;
+            }
+            colors.push(pixel.0);
+        }
+    }
+
+    // Translucent entries first, so tRNS can stop at the last of them
+    colors.sort_by_key(|color| color[3]);
+    let translucent = colors.iter().filter(|color| color[3] < 255).count();
+
+    let (depth, bits) = match colors.len() {
+        0..=2 => (png::BitDepth::One, 1),
+        3..=4 => (png::BitDepth::Two, 2),
+        5..=16 => (png::BitDepth::Four, 4),
+        _ => (png::BitDepth::Eight, 8),
+    };
+
+    // Rows are packed most significant bits first and padded to a whole byte
+    let width = image.width() as usize;
+    let mut data = Vec::new();
+    for row in image.as_raw().chunks(width * 4) {
+        for chunk in row.chunks(4 * 8 / bits) {
+            let mut byte = 0u8;
+            for (i, pixel) in chunk.chunks(4).enumerate() {
+                let index = colors.iter().position(|color| color == pixel).unwrap_or(0) as u8;
+                byte |= index << (8 - bits * (i + 1));
+            }
+            data.push(byte);
+        }
+    }
+
+    Some(PngLayout {
+        color: png::ColorType::Indexed,
+        depth,
+        data,
+        palette: Some((
+            colors
+                .iter()
+                .flat_map(|color| &color[..3])
+                .copied()
+                .collect(),
+            colors[..translucent].iter().map(|color| color[3]).collect(),
+        )),
+    })
+}
+
+/// Encode a PNG layout with the given filter at the best zlib compression
+fn encode_png_layout(
+    width: u32,
+    height: u32,
+    layout: &PngLayout,
+    filter: Option<png::FilterType>,
+) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, width, height);
+    encoder.set_color(layout.color);
+    encoder.set_depth(layout.depth);
+    if let Some((rgb, alpha)) = &layout.palette {
+        encoder.set_palette(rgb.as_slice());
+        if !alpha.is_empty() {
+            encoder.set_trns(alpha.as_slice());
+        }
+    }
+    encoder.set_compression(png::Compression::Best);
+    match filter {
+        Some(resampler) => encoder.set_filter(resampler),
+        None => encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive),
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&layout.data)?;
+    writer.finish()?;
+    Ok(bytes)
+}
+
+/// The `.png` files this run wrote under `dir`, in a stable order; the sink of
+/// a set on disk leaves out the files already there that the run didn't write
+fn collect_pngs(dir: &Path, output: &dyn OutputSink) -> Result<Vec<PathBuf>> {
+    let mut paths = output.files(dir)?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "png"));
+    Ok(paths)
+}
+
+/// Save an RGBA image as an 8-bit indexed PNG, keeping per-entry alpha in tRNS
+fn save_indexed_png(
+    image: &RgbaImage,
+    path: &Path,
+    colors: u16,
+    output: &dyn OutputSink,
+) -> Result<()> {
+    let quantizer = color_quant::NeuQuant::new(1, colors.into(), image.as_raw());
+    let mut palette = quantizer.color_map_rgba();
+    let entries = palette.len() / 4;
+
+    // NeuQuant only approximates alpha (opaque pixels can come back at 254), so fully clear
+    // and fully opaque pixels are only matched against entries of their own kind
+    let has_clear = image.pixels().any(|pixel| pixel[3] == 0);
+    let clear = (0..entries)
+        .min_by_key(|&i| palette[i * 4 + 3])
+        .filter(|_| has_clear);
+    let alpha_kind = |alpha: u8| match alpha {
+        0 => 0,
+        255 => 2,
+        _ => 1,
+    };
+    let candidates: Vec<Vec<usize>> = (0..3)
+        .map(|kind| {
+            let all: Vec<usize> = (0..entries).filter(|&i| Some(i) != clear).collect();
+            let matching: Vec<usize> = all
+                .iter()
+                .copied()
+                .filter(|&i| (palette[i * 4 + 3] >= 240) == (kind == 2))
+                .collect();
+            if matching.is_empty() {
+                all
+            } else {
+                matching
+            }
+        })
+        .collect();
+
+    let indices: Vec<u8> = image
+        .pixels()
+        .map(|pixel| {
+            let kind = alpha_kind(pixel[3]);
+            let nearest = match clear {
+                Some(clear) if kind == 0 => Some(clear),
+                _ => candidates[kind].iter().copied().min_by_key(|&i| {
+                    (0..4)
+                        .map(|channel| {
+                            let diff =
+                                i32::from(palette[i * 4 + channel]) - i32::from(pixel[channel]);
+                            diff * diff
+                        })
+                        .sum::<i32>()
+                }),
+            };
+            nearest.or(clear).unwrap_or(0) as u8
+        })
+        .collect();
+
+    // Replace each entry with the exact mean of its pixels, so opaque stays 255 and clear 0
+    let mut sums = vec![[0u64; 5]; entries];
+    for (&index, pixel) in indices.iter().zip(image.pixels()) {
+        let sum = &mut sums[usize::from(index)];
+        for channel in 0..4 {
+            sum[channel] += u64::from(pixel[channel]);
+        }
+        sum[4] += 1;
+    }
+    for (entry, sum) in palette.chunks_mut(4).zip(&sums) {
+        for channel in 0..4 {
+            if let Some(mean) = (sum[channel] + sum[4] / 2).checked_div(sum[4]) {
+                entry[channel] = mean as u8;
+            }
+        }
+    }
+
+    let rgb: Vec<u8> = palette
+        .chunks(4)
+        .flat_map(|entry| &entry[..3])
+        .copied()
+        .collect();
+    let alpha: Vec<u8> = palette.chunks(4).map(|entry| entry[3]).collect();
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, image.width(), image.height());
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(rgb);
+    // tRNS is left out of opaque images, so opaque icons stay without alpha
+    if image.pixels().any(|pixel| pixel[3] < 255) {
+        encoder.set_trns(alpha);
+    }
+    encoder.set_compression(png::Compression::Best);
+    let mut writer = encoder.write_header()?;
+    writer
+        .write_image_data(&indices)
+        .context("Failed to write indexed PNG")?;
+    writer.finish()?;
+    output.write(path, &png)?;
+    Ok(())
+}
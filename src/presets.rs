@@ -0,0 +1,373 @@
+//! Store and engine presets
+//!
+//! A preset is a named bundle of output files with the exact sizes and names a
+//! distribution channel or game engine expects. Each preset is written into its
+//! own directory under the output path (e.g. `steam/`, `godot/`); filenames may
+//! contain subdirectories.
+
+use crate::error::{IconGenError, Result};
+use crate::store_limits::{StoreLimit, APP_STORE_ICON, PLAY_STORE_ICON};
+
+/// A single file produced by a preset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresetAsset {
+    /// Square PNG resized from the source
+    Png { filename: String, size: u32 },
+
+    /// Square PNG flattened onto the preset background color (for targets that forbid alpha)
+    OpaquePng { filename: String, size: u32 },
+
+    /// Square PNG with a circular mask
+    RoundPng { filename: String, size: u32 },
+
+    /// Square PNG with the artwork scaled to `scale_percent` of the canvas and centered on
+    /// transparency (e.g. adaptive icon foreground layers)
+    PaddedPng {
+        filename: String,
+        size: u32,
+        scale_percent: u32,
+    },
+
+    /// Multi-resolution ICO file
+    Ico {
+        filename: String,
+        sizes: &'static [u32],
+    },
+
+    /// macOS ICNS file with the standard member set
+    Icns { filename: String },
+
+    /// Square opaque JPEG, flattened onto the preset background color
+    Jpeg { filename: String, size: u32 },
+
+    /// Non-square artwork: the icon centered on a canvas filled with the preset background color
+    Canvas {
+        filename: String,
+        width: u32,
+        height: u32,
+    },
+
+    /// Social card: the icon beside the project name on the preset background color
+    SocialCard {
+        filename: String,
+        width: u32,
+        height: u32,
+    },
+
+    /// Static text file such as a configuration snippet
+    Text {
+        filename: String,
+        contents: &'static str,
+    },
+}
+
+impl PresetAsset {
+    /// The path of the asset relative to the preset directory
+    pub fn filename(&self) -> &str {
+        match self {
+            PresetAsset::Png { filename, .. }
+            | PresetAsset::OpaquePng { filename, .. }
+            | PresetAsset::RoundPng { filename, .. }
+            | PresetAsset::PaddedPng { filename, .. }
+            | PresetAsset::Ico { filename, .. }
+            | PresetAsset::Icns { filename }
+            | PresetAsset::Jpeg { filename, .. }
+            | PresetAsset::Canvas { filename, .. }
+            | PresetAsset::SocialCard { filename, .. }
+            | PresetAsset::Text { filename, .. } => filename,
+        }
+    }
+}
+
+/// Names of all available presets
+pub const PRESET_NAMES: &[&str] = &[
+    "steam",
+    "itch",
+    "unity",
+    "godot",
+    "github-social",
+    "play-store",
+];
+
+/// Look up the assets for a preset by name
+///
+/// # Errors
+/// Returns an error listing the available presets if the name is unknown
+pub fn preset_assets(name: &str) -> Result<Vec<PresetAsset>> {
+    let assets = match name {
+        "steam" => steam_assets(),
+        "itch" => itch_assets(),
+        "unity" => unity_assets(),
+        "godot" => godot_assets(),
+        "github-social" => github_social_assets(),
+        "play-store" => play_store_assets(),
+        _ => {
+            return Err(IconGenError::InvalidInput(format!(
+                "Unknown preset: {}. Available presets: {}",
+                name,
+                PRESET_NAMES.join(", ")
+            )))
+        }
+    };
+
+    Ok(assets)
+}
+
+/// Steamworks: client icon, community icon and store/library capsules
+fn steam_assets() -> Vec<PresetAsset> {
+    let mut assets = vec![PresetAsset::Ico {
+        filename: "client_icon.ico".to_string(),
+        sizes: &[16, 32, 64],
+    }];
+
+    for size in [16, 32, 64, 184, 256] {
+        assets.push(PresetAsset::Png {
+            filename: format!("{size}x{size}.png"),
+            size,
+        });
+    }
+
+    assets.push(PresetAsset::Jpeg {
+        filename: "community_icon.jpg".to_string(),
+        size: 184,
+    });
+
+    for (name, width, height) in [
+        ("capsule_small", 231, 87),
+        ("capsule_header", 460, 215),
+        ("capsule_main", 616, 353),
+        ("capsule_vertical", 374, 448),
+        ("library_capsule", 600, 900),
+    ] {
+        assets.push(PresetAsset::Canvas {
+            filename: format!("{name}.png"),
+            width,
+            height,
+        });
+    }
+
+    assets
+}
+
+/// itch.io: game page cover images and the app icon
+fn itch_assets() -> Vec<PresetAsset> {
+    vec![
+        PresetAsset::Canvas {
+            filename: "cover.png".to_string(),
+            width: 630,
+            height: 500,
+        },
+        PresetAsset::Canvas {
+            filename: "cover_small.png".to_string(),
+            width: 315,
+            height: 250,
+        },
+        PresetAsset::Png {
+            filename: "icon.png".to_string(),
+            size: 512,
+        },
+    ]
+}
+
+/// Unity: one folder per PlayerSettings icon section, one file per slot size
+fn unity_assets() -> Vec<PresetAsset> {
+    let mut assets = Vec::new();
+
+    // Standalone (Windows/macOS/Linux) default and override icons
+    for size in [1024, 512, 256, 128, 48, 32, 16] {
+        assets.push(PresetAsset::Png {
+            filename: format!("Standalone/icon_{size}.png"),
+            size,
+        });
+    }
+
+    // iOS application, spotlight, settings, notification and marketing icons (must be opaque)
+    for size in [
+        1024, 180, 167, 152, 144, 120, 114, 87, 80, 76, 72, 60, 58, 57, 40, 29, 20,
+    ] {
+        assets.push(PresetAsset::OpaquePng {
+            filename: format!("iOS/icon_{size}.png"),
+            size,
+        });
+    }
+
+    // Android legacy, round and adaptive icons
+    for size in [192, 144, 96, 72, 48, 36] {
+        assets.push(PresetAsset::Png {
+            filename: format!("Android/Legacy/icon_{size}.png"),
+            size,
+        });
+        assets.push(PresetAsset::RoundPng {
+            filename: format!("Android/Round/icon_{size}.png"),
+            size,
+        });
+    }
+    for size in [432, 324, 216, 162, 108, 81] {
+        assets.push(PresetAsset::PaddedPng {
+            filename: format!("Android/Adaptive/foreground_{size}.png"),
+            size,
+            scale_percent: 66,
+        });
+    }
+
+    assets
+}
+
+/// Godot: project icon, export icons and the matching export_presets.cfg options
+fn godot_assets() -> Vec<PresetAsset> {
+    vec![
+        PresetAsset::Png {
+            filename: "icon.png".to_string(),
+            size: 256,
+        },
+        PresetAsset::Ico {
+            filename: "icons/icon.ico".to_string(),
+            sizes: &[16, 32, 48, 64, 128, 256],
+        },
+        PresetAsset::Icns {
+            filename: "icons/icon.icns".to_string(),
+        },
+        PresetAsset::Png {
+            filename: "icons/android_192x192.png".to_string(),
+            size: 192,
+        },
+        PresetAsset::PaddedPng {
+            filename: "icons/android_adaptive_foreground_432x432.png".to_string(),
+            size: 432,
+            scale_percent: 66,
+        },
+        PresetAsset::OpaquePng {
+            filename: "icons/ios_1024x1024.png".to_string(),
+            size: 1024,
+        },
+        PresetAsset::Text {
+            filename: "export_presets.cfg.snippet".to_string(),
+            contents: GODOT_EXPORT_PRESETS,
+        },
+    ]
+}
+
+/// GitHub: the repository social preview card (Settings > Social preview)
+fn github_social_assets() -> Vec<PresetAsset> {
+    vec![PresetAsset::SocialCard {
+        filename: "social-preview.png".to_string(),
+        width: 1280,
+        height: 640,
+    }]
+}
+
+/// Google Play: the store listing icon
+fn play_store_assets() -> Vec<PresetAsset> {
+    vec![PresetAsset::Png {
+        filename: "icon-512.png".to_string(),
+        size: 512,
+    }]
+}
+
+/// The assets of a preset that are uploaded to a store, with its limits
+pub(crate) fn store_icons(name: &str) -> Vec<(&'static str, &'static StoreLimit)> {
+    match name {
+        "unity" => vec![("iOS/icon_1024.png", &APP_STORE_ICON)],
+        "godot" => vec![("icons/ios_1024x1024.png", &APP_STORE_ICON)],
+        "play-store" => vec![("icon-512.png", &PLAY_STORE_ICON)],
+        _ => Vec::new(),
+    }
+}
+
+/// Export preset options pointing at the generated icons, for merging into export_presets.cfg
+const GODOT_EXPORT_PRESETS: &str = r#"; Generated by icon-gen
+; Copy icon.png and the icons/ folder into your project root, then merge the
+; options below into the matching [preset.N.options] sections of export_presets.cfg.
+; In project.godot set: config/icon="res://icon.png"
+
+; Windows Desktop
+application/icon="res://icons/icon.ico"
+
+; macOS
+application/icon="res://icons/icon.icns"
+
+; Android
+launcher_icons/main_192x192="res://icons/android_192x192.png"
+launcher_icons/adaptive_foreground_432x432="res://icons/android_adaptive_foreground_432x432.png"
+
+; iOS
+icons/app_store_1024x1024="res://icons/ios_1024x1024.png"
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_presets_resolve() {
+        for name in PRESET_NAMES {
+            let assets = preset_assets(name).unwrap();
+            assert!(!assets.is_empty(), "Preset {} has no assets", name);
+        }
+    }
+
+    #[test]
+    fn test_preset_filenames_are_unique() {
+        for name in PRESET_NAMES {
+            let assets = preset_assets(name).unwrap();
+            let mut filenames: Vec<_> = assets.iter().map(|a| a.filename()).collect();
+            filenames.sort();
+            filenames.dedup();
+            assert_eq!(filenames.len(), assets.len(), "Duplicate file in {}", name);
+        }
+    }
+
+    #[test]
+    fn test_store_icons_are_preset_assets() {
+        for name in PRESET_NAMES {
+            let assets = preset_assets(name).unwrap();
+            for (filename, limit) in store_icons(name) {
+                assert!(
+                    assets.iter().any(|asset| asset.filename() == filename),
+                    "{} has no {}",
+                    name,
+                    filename
+                );
+                assert!(assets.iter().any(|asset| matches!(
+                    asset,
+                    PresetAsset::Png { filename: f, size } | PresetAsset::OpaquePng { filename: f, size }
+                        if f == filename && *size == limit.width
+                )));
+            }
+        }
+    }
+
+    #[test]
+    fn test_steam_client_icon_sizes() {
+        let assets = preset_assets("steam").unwrap();
+        assert!(assets.contains(&PresetAsset::Png {
+            filename: "184x184.png".to_string(),
+            size: 184
+        }));
+        assert!(assets.iter().any(
+            |a| matches!(a, PresetAsset::Ico { filename, .. } if filename == "client_icon.ico")
+        ));
+    }
+
+    #[test]
+    fn test_godot_snippet_references_generated_files() {
+        let assets = preset_assets("godot").unwrap();
+        for asset in &assets {
+            if matches!(asset, PresetAsset::Text { .. }) || asset.filename() == "icon.png" {
+                continue;
+            }
+            let resource = format!("res://{}", asset.filename());
+            assert!(
+                GODOT_EXPORT_PRESETS.contains(&resource),
+                "export_presets snippet is missing {}",
+                resource
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_preset() {
+        let err = preset_assets("origin").unwrap_err();
+        assert!(err.to_string().contains("Unknown preset: origin"));
+    }
+}
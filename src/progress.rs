@@ -0,0 +1,439 @@
+//! Progress reporting
+//!
+//! On a terminal, a run is summarized on a single progress line: a bar over the
+//! sets being generated (the main icons and each variant), the current platform
+//! step and the number of files written so far. When stdout isn't a terminal,
+//! e.g. in CI logs or when piped, every step and file is logged on its own line.
+//! A quiet run (`--report json`, which owns stdout) logs nothing but warnings.
+//!
+//! With `--progress json` every line on stdout is instead a JSON event for GUI
+//! wrappers and editor extensions, tagged by its `event` field:
+//!
+//! - `started`: `sets`, the number of sets the run generates
+//! - `set_started`: `set`, the directory of the set
+//! - `file_written`: `path` and `bytes` of each file, once per file
+//! - `platform_done`: `set`, `step` (e.g. `Generating Android icons`), the top-level
+//!   `platforms` folders written and the number of `files`, after each step that wrote files
+//! - `note` and `warning`: `message`, e.g. a set skipped as up to date
+//! - `finished`: `files`, the number of files written
+//!
+//! Each run reports through its own [`Run`], which the thread that started it
+//! and the workers of its thread pool enter, so that runs of the library at the
+//! same time keep their progress and warnings apart.
+
+use crate::error::Result;
+use crate::output::OutputSink;
+use serde::Serialize;
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashSet},
+    fmt::Display,
+    io::{IsTerminal, Write},
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, OnceLock,
+    },
+};
+
+/// Values accepted by `--progress`: a progress line or log (`auto`), or JSON events
+pub const PROGRESS_MODES: &[&str] = &["auto", "json"];
+
+/// How a run reports its progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    /// A progress line on a terminal, a log line per step and file otherwise
+    Auto,
+    /// Nothing but warnings, e.g. while stdout holds a report
+    Quiet,
+    /// A JSON event per line (`--progress json`)
+    Json,
+}
+
+/// Width of the bar, in characters
+const BAR_WIDTH: usize = 24;
+
+/// Longest step label shown on the progress line, so that it fits on one terminal line
+const LABEL_WIDTH: usize = 40;
+
+const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// The reporting of one run
+#[derive(Debug, Default)]
+pub(crate) struct Run {
+    /// The progress line, present only while the run is drawing to a terminal
+    bar: Mutex<Option<Bar>>,
+    /// Whether the run only reports warnings
+    quiet: AtomicBool,
+    /// Warnings of the run, which fail it with `--strict`
+    warnings: AtomicUsize,
+    /// The event stream, present only while the run reports JSON events
+    events: Mutex<Option<Events>>,
+}
+
+thread_local! {
+    /// The run the thread reports for
+    static CURRENT: RefCell<Option<Arc<Run>>> = const { RefCell::new(None) };
+}
+
+impl Run {
+    /// Report for this run on the current thread until the guard is dropped
+    pub(crate) fn enter(self: &Arc<Self>) -> Entered {
+        Entered(CURRENT.with(|current| current.replace(Some(Arc::clone(self)))))
+    }
+
+    /// Report for this run on a worker thread that only ever works for it
+    pub(crate) fn adopt(self: &Arc<Self>) {
+        CURRENT.with(|current| *current.borrow_mut() = Some(Arc::clone(self)));
+    }
+
+    /// The number of warnings since the run started
+    pub(crate) fn warnings(&self) -> usize {
+        self.warnings.load(Ordering::Relaxed)
+    }
+
+    /// The reporter stays usable after a panic on another worker thread
+    fn bar(&self) -> MutexGuard<'_, Option<Bar>> {
+        self.bar
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn events(&self) -> MutexGuard<'_, Option<Events>> {
+        self.events
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The thread reporting for a run, until it is dropped
+#[must_use]
+pub(crate) struct Entered(Option<Arc<Run>>);
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+/// The run of the current thread; reporting outside of a run just logs
+fn run() -> Arc<Run> {
+    static OUTSIDE: OnceLock<Arc<Run>> = OnceLock::new();
+    CURRENT
+        .with(|current| current.borrow().clone())
+        .unwrap_or_else(|| Arc::clone(OUTSIDE.get_or_init(Arc::default)))
+}
+
+#[derive(Debug, Default)]
+struct Events {
+    set: PathBuf,
+    step: String,
+    /// Top-level folders written by the step in progress
+    platforms: BTreeSet<String>,
+    step_files: usize,
+    files: usize,
+    /// Files rewritten by a later pass, e.g. `--optimize`, are only reported once
+    written: HashSet<PathBuf>,
+}
+
+impl Events {
+    /// Report the step in progress as done if it wrote anything
+    fn end_step(&mut self) {
+        if self.step_files > 0 {
+            emit(&Event::PlatformDone {
+                set: &self.set,
+                step: &self.step,
+                platforms: std::mem::take(&mut self.platforms).into_iter().collect(),
+                files: self.step_files,
+            });
+        }
+        self.step.clear();
+        self.platforms.clear();
+        self.step_files = 0;
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    Started {
+        sets: usize,
+    },
+    SetStarted {
+        set: &'a Path,
+    },
+    FileWritten {
+        path: &'a Path,
+        bytes: usize,
+    },
+    PlatformDone {
+        set: &'a Path,
+        step: &'a str,
+        platforms: Vec<String>,
+        files: usize,
+    },
+    Note {
+        message: &'a str,
+    },
+    Warning {
+        message: &'a str,
+    },
+    Finished {
+        files: usize,
+    },
+}
+
+fn emit(event: &Event<'_>) {
+    let json = serde_json::to_string(event).expect("Failed to serialize a progress event");
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{json}");
+    let _ = stdout.flush();
+}
+
+#[derive(Debug, Default)]
+struct Bar {
+    sets: usize,
+    /// Sets started so far, including the one in progress
+    started: usize,
+    set_name: String,
+    step: String,
+    files: usize,
+    frame: usize,
+}
+
+impl Bar {
+    fn draw(&mut self) {
+        let done = self.started.saturating_sub(1);
+        let filled = BAR_WIDTH * done / self.sets.max(1);
+        let mut step = self.step.trim().trim_end_matches("...").to_string();
+        if step.chars().count() > LABEL_WIDTH {
+            step = step.chars().take(LABEL_WIDTH - 1).collect::<String>() + "…";
+        }
+        self.frame = (self.frame + 1) % SPINNER.len();
+
+        let mut stdout = std::io::stdout().lock();
+        let _ = write!(
+            stdout,
+            "\r\x1b[2K{} [{}{}] {}/{} {} · {} · {} files",
+            SPINNER[self.frame],
+            "=".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled),
+            self.started.min(self.sets),
+            self.sets,
+            self.set_name,
+            step,
+            self.files
+        );
+        let _ = stdout.flush();
+    }
+
+    fn clear(&self) {
+        let mut stdout = std::io::stdout().lock();
+        let _ = write!(stdout, "\r\x1b[2K");
+        let _ = stdout.flush();
+    }
+}
+
+/// Start reporting a run of `sets` sets, drawing a progress line if stdout is a terminal
+/// A quiet run prints nothing but warnings
+pub(crate) fn start(sets: usize, mode: Mode) {
+    let run = run();
+    run.quiet.store(mode != Mode::Auto, Ordering::Relaxed);
+    run.warnings.store(0, Ordering::Relaxed);
+    let bar = (mode == Mode::Auto && std::io::stdout().is_terminal()).then(|| Bar {
+        sets,
+        ..Bar::default()
+    });
+    *run.bar() = bar;
+
+    let mut events = run.events();
+    *events = (mode == Mode::Json).then(Events::default);
+    if events.is_some() {
+        emit(&Event::Started { sets });
+    }
+}
+
+/// Start the next set, named after its output directory
+pub(crate) fn begin_set(dir: &Path) {
+    let run = run();
+    if let Some(events) = run.events().as_mut() {
+        events.end_step();
+        events.set = dir.to_path_buf();
+        emit(&Event::SetStarted { set: dir });
+    }
+    let mut bar = run.bar();
+    if let Some(bar) = bar.as_mut() {
+        bar.started += 1;
+        bar.set_name = dir.display().to_string();
+        bar.step.clear();
+        bar.draw();
+    }
+}
+
+/// A platform or pass starting, e.g. `Generating Android icons...`
+pub(crate) fn step(message: impl Display) {
+    let run = run();
+    if let Some(events) = run.events().as_mut() {
+        events.end_step();
+        events.step = message
+            .to_string()
+            .trim()
+            .trim_end_matches("...")
+            .to_string();
+    }
+    let mut bar = run.bar();
+    match bar.as_mut() {
+        Some(bar) => {
+            bar.step = message.to_string();
+            bar.draw();
+        }
+        None => log(message),
+    }
+}
+
+/// A file written by the current step, e.g. `  ✓ Generated linux/32x32.png`
+pub(crate) fn file(message: impl Display) {
+    let run = run();
+    let mut bar = run.bar();
+    match bar.as_mut() {
+        Some(bar) => {
+            bar.files += 1;
+            bar.draw();
+        }
+        None => log(message),
+    }
+}
+
+/// Per-file detail of a pass over the written files, only logged without a terminal
+pub(crate) fn detail(message: impl Display) {
+    let run = run();
+    let mut bar = run.bar();
+    match bar.as_mut() {
+        Some(bar) => bar.draw(),
+        None => log(message),
+    }
+}
+
+/// A file as it is written, reported once per path as a JSON event
+fn written(path: &Path, bytes: usize) {
+    let run = run();
+    let mut events = run.events();
+    let Some(events) = events.as_mut() else {
+        return;
+    };
+    if !events.written.insert(path.to_path_buf()) {
+        return;
+    }
+    events.files += 1;
+    events.step_files += 1;
+    // The platform is the folder the file went into, e.g. android/ for android/mipmap-mdpi/
+    if let Ok(relative) = path.strip_prefix(&events.set) {
+        let mut components = relative.components();
+        if let (Some(Component::Normal(folder)), Some(_)) = (components.next(), components.next()) {
+            events
+                .platforms
+                .insert(folder.to_string_lossy().into_owned());
+        }
+    }
+    emit(&Event::FileWritten { path, bytes });
+}
+
+/// A message shown in full on either output, printed above the progress line
+pub(crate) fn note(message: impl Display) {
+    let run = run();
+    if run.events().is_some() {
+        emit(&Event::Note {
+            message: message.to_string().trim().trim_start_matches("✓ "),
+        });
+        return;
+    }
+    let mut bar = run.bar();
+    match bar.as_mut() {
+        Some(bar) => {
+            bar.clear();
+            println!("{message}");
+            bar.draw();
+        }
+        None => log(message),
+    }
+}
+
+/// A warning on stderr, printed above the progress line
+pub(crate) fn warn(message: impl Display) {
+    let run = run();
+    run.warnings.fetch_add(1, Ordering::Relaxed);
+    if run.events().is_some() {
+        emit(&Event::Warning {
+            message: message.to_string().trim(),
+        });
+    }
+    let mut bar = run.bar();
+    match bar.as_mut() {
+        Some(bar) => {
+            bar.clear();
+            eprintln!("{message}");
+            bar.draw();
+        }
+        None => eprintln!("{message}"),
+    }
+}
+
+/// Remove the progress line and print a summary of the files written
+pub(crate) fn finish() {
+    let run = run();
+    if let Some(mut events) = run.events().take() {
+        events.end_step();
+        emit(&Event::Finished {
+            files: events.files,
+        });
+    }
+    let bar = run.bar().take();
+    if let Some(bar) = bar {
+        bar.clear();
+        println!("✓ Generated {} files", bar.files);
+    }
+}
+
+/// A line of the log written when there is no progress line
+fn log(message: impl Display) {
+    if !run().quiet.load(Ordering::Relaxed) {
+        println!("{message}");
+    }
+}
+
+/// Passes files on to another sink, reporting each as it is written
+/// Writing fails once the run is interrupted, which stops every worker
+pub(crate) struct ProgressSink<'a>(pub(crate) &'a dyn OutputSink);
+
+impl OutputSink for ProgressSink<'_> {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        crate::interrupt::check()?;
+        self.0.write(path, bytes)?;
+        written(path, bytes.len());
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.0.read(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.0.exists(path)
+    }
+
+    fn files(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        self.0.files(dir)
+    }
+
+    fn is_persistent(&self) -> bool {
+        self.0.is_persistent()
+    }
+
+    fn keep(&self, path: &Path) -> Result<()> {
+        self.0.keep(path)
+    }
+
+    fn remove(&self, path: &Path, dir: &Path) -> Result<()> {
+        self.0.remove(path, dir)
+    }
+}
@@ -0,0 +1,273 @@
+//! Machine-readable run report (`--report json`)
+//!
+//! [`ReportingSink`] wraps the sink a run writes through and describes every
+//! file that passes through it, so build systems can pick up the outputs without
+//! scraping the log:
+//!
+//! ```json
+//! {
+//!   "files": [
+//!     { "path": "icons/windows/icon.ico", "format": "ico", "width": 256, "height": 256,
+//!       "bytes": 90518, "sha256": "9f86d0…", "reused": false }
+//!   ],
+//!   "total_bytes": 90518
+//! }
+//! ```
+//!
+//! `format` is the file extension, and `width`/`height` are the pixel size of
+//! images (the largest member of ICO, CUR and ICNS containers), `null` otherwise.
+//! `sha256` is the hex SHA-256 of the contents, as printed by `sha256sum`.
+//! Files of sets skipped as up to date are listed with `reused: true`.
+
+use crate::{error::Result, output::OutputSink, sha256::sha256_hex};
+use icns::IconFamily;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Report formats accepted by `--report`
+pub const REPORT_FORMATS: &[&str] = &["json"];
+
+/// A generated file
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReportEntry {
+    pub path: PathBuf,
+    /// The file extension, e.g. `png` or `json`
+    pub format: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub bytes: u64,
+    pub sha256: String,
+    /// Kept from an earlier run rather than written by this one
+    pub reused: bool,
+}
+
+/// Every file of a run, in path order
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunReport {
+    pub files: Vec<ReportEntry>,
+    pub total_bytes: u64,
+}
+
+impl RunReport {
+    /// The report as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Failed to serialize the run report")
+    }
+
+    /// The files as JSON without their size, checksum and reuse, which only a real
+    /// run knows (`--dry-run --report json`)
+    pub fn to_plan_json(&self) -> String {
+        let files = self
+            .files
+            .iter()
+            .map(|file| PlannedFile {
+                path: &file.path,
+                format: &file.format,
+                width: file.width,
+                height: file.height,
+            })
+            .collect();
+        serde_json::to_string_pretty(&Plan { files }).expect("Failed to serialize the run plan")
+    }
+}
+
+/// A file `--dry-run` would generate
+#[derive(Serialize)]
+struct PlannedFile<'a> {
+    path: &'a Path,
+    format: &'a str,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct Plan<'a> {
+    files: Vec<PlannedFile<'a>>,
+}
+
+/// Passes files on to another sink, recording each for the run report
+/// A file written more than once, e.g. by `--optimize`, is described as last written
+pub struct ReportingSink<'a> {
+    inner: &'a dyn OutputSink,
+    files: Mutex<BTreeMap<PathBuf, ReportEntry>>,
+}
+
+impl<'a> ReportingSink<'a> {
+    pub fn new(inner: &'a dyn OutputSink) -> Self {
+        Self {
+            inner,
+            files: Mutex::default(),
+        }
+    }
+
+    /// The report of every file written or kept so far
+    pub fn into_report(self) -> RunReport {
+        let files = self
+            .files
+            .into_inner()
+            .unwrap()
+            .into_values()
+            .collect::<Vec<_>>();
+        RunReport {
+            total_bytes: files.iter().map(|file| file.bytes).sum(),
+            files,
+        }
+    }
+
+    fn record(&self, path: &Path, bytes: &[u8], reused: bool) {
+        let entry = describe(path, bytes, reused);
+        self.files.lock().unwrap().insert(path.to_path_buf(), entry);
+    }
+}
+
+impl OutputSink for ReportingSink<'_> {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        self.inner.write(path, bytes)?;
+        self.record(path, bytes, false);
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.inner.read(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn files(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        self.inner.files(dir)
+    }
+
+    fn is_persistent(&self) -> bool {
+        self.inner.is_persistent()
+    }
+
+    fn keep(&self, path: &Path) -> Result<()> {
+        self.inner.keep(path)?;
+        let bytes = self
+            .inner
+            .read(path)
+            .map_err(|error| error.context(format!("Failed to describe {}", path.display())))?;
+        self.record(path, &bytes, true);
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path, dir: &Path) -> Result<()> {
+        self.inner.remove(path, dir)
+    }
+}
+
+impl std::fmt::Debug for ReportingSink<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReportingSink")
+            .field("files", &self.files)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Describe a file from its name and contents
+pub(crate) fn describe(path: &Path, bytes: &[u8], reused: bool) -> ReportEntry {
+    let format = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let dimensions = image_dimensions(bytes);
+    ReportEntry {
+        path: path.to_path_buf(),
+        format,
+        width: dimensions.map(|(width, _)| width),
+        height: dimensions.map(|(_, height)| height),
+        bytes: bytes.len() as u64,
+        sha256: sha256_hex(bytes),
+        reused,
+    }
+}
+
+/// The pixel size of an encoded image, read from its header
+fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] if bytes.len() >= 24 => Some((
+            u32::from_be_bytes(bytes[16..20].try_into().unwrap()),
+            u32::from_be_bytes(bytes[20..24].try_into().unwrap()),
+        )),
+        // ICO and CUR share a directory of entries, 0 standing for 256 pixels
+        [0, 0, 1 | 2, 0, ..] => {
+            let count = u16::from_le_bytes([*bytes.get(4)?, *bytes.get(5)?]) as usize;
+            (0..count)
+                .filter_map(|index| {
+                    let entry = bytes.get(6 + index * 16..6 + index * 16 + 2)?;
+                    let side = |byte: u8| if byte == 0 { 256 } else { byte as u32 };
+                    Some((side(entry[0]), side(entry[1])))
+                })
+                .max()
+        }
+        [b'i', b'c', b'n', b's', ..] => IconFamily::read(Cursor::new(bytes))
+            .ok()?
+            .available_icons()
+            .into_iter()
+            .map(|icon_type| (icon_type.pixel_width(), icon_type.pixel_height()))
+            .max(),
+        _ => image::io::Reader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .ok()?
+            .into_dimensions()
+            .ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageOutputFormat};
+
+    fn encode(width: u32, height: u32, format: ImageOutputFormat) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        DynamicImage::new_rgba8(width, height)
+            .write_to(&mut bytes, format)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_describe_images() {
+        let png = encode(48, 32, ImageOutputFormat::Png);
+        assert_eq!(
+            describe(Path::new("icons/a.PNG"), &png, false),
+            ReportEntry {
+                path: PathBuf::from("icons/a.PNG"),
+                format: "png".to_string(),
+                width: Some(48),
+                height: Some(32),
+                bytes: png.len() as u64,
+                sha256: sha256_hex(&png),
+                reused: false,
+            }
+        );
+
+        let bmp = encode(20, 10, ImageOutputFormat::Bmp);
+        let entry = describe(Path::new("a.bmp"), &bmp, true);
+        assert_eq!(
+            (entry.width, entry.height, entry.reused),
+            (Some(20), Some(10), true)
+        );
+
+        // A directory of a 16px and a 256px member
+        let mut ico = vec![0, 0, 1, 0, 2, 0];
+        ico.extend([16, 16].iter().chain(&[0; 14]));
+        ico.extend([0, 0].iter().chain(&[0; 14]));
+        let entry = describe(Path::new("icon.ico"), &ico, false);
+        assert_eq!((entry.width, entry.height), (Some(256), Some(256)));
+
+        let entry = describe(Path::new("Contents.json"), b"{}", false);
+        assert_eq!(
+            (entry.format.as_str(), entry.width, entry.bytes),
+            ("json", None, 2)
+        );
+    }
+}
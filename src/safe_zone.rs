@@ -0,0 +1,212 @@
+//! Safe zone analysis of the source artwork (`--safe-zone`)
+//!
+//! iOS draws app icons through a rounded-square mask, and Android launchers cut
+//! adaptive icons to a shape of their choosing that is only guaranteed to show
+//! the central 66dp circle of the 108dp layer. Artwork reaching past those zones
+//! gets clipped on device. [`measure`] tells how much of the artwork lies
+//! outside a zone, and by how much it has to shrink to fit.
+
+use image::{DynamicImage, Rgba};
+
+/// Modes of `--safe-zone`
+pub const SAFE_ZONE_MODES: &[&str] = &["off", "warn", "fix"];
+
+/// Side of the copy of the source that is measured
+const SAMPLE_SIZE: u32 = 256;
+
+/// Largest channel difference from the background that still counts as background
+const BACKGROUND_TOLERANCE: u8 = 24;
+
+/// Share of the artwork outside a zone that is put down to anti-aliasing
+pub(crate) const OUTSIDE_TOLERANCE: f32 = 0.005;
+
+/// Exponent of the superellipse of the iOS mask, the shape `--ios-squircle` draws
+const SQUIRCLE_EXPONENT: f32 = 5.0;
+
+/// Radius of the adaptive icon safe zone (33dp) relative to half the artwork,
+/// which spans 66% of the 108dp layer
+const ADAPTIVE_RADIUS: f32 = 33.0 / (0.66 * 108.0 / 2.0);
+
+/// The part of an icon that a platform's mask always shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SafeZone {
+    /// The rounded square iOS masks app icons with, inscribed in the icon
+    IosMask,
+    /// The central 66dp circle of the adaptive icon foreground
+    AdaptiveIcon,
+}
+
+impl SafeZone {
+    /// The zone as named in warnings
+    pub(crate) fn describe(self) -> &'static str {
+        match self {
+            SafeZone::IosMask => "iOS app icon mask",
+            SafeZone::AdaptiveIcon => "Android adaptive icon safe zone (the central 66dp circle)",
+        }
+    }
+
+    /// Distance of a point from the center, where 1 is the edge of the zone;
+    /// `x` and `y` are relative to half the side of the artwork
+    fn distance(self, x: f32, y: f32) -> f32 {
+        let (x, y) = (x.abs(), y.abs());
+        match self {
+            SafeZone::IosMask => (x.powf(SQUIRCLE_EXPONENT) + y.powf(SQUIRCLE_EXPONENT))
+                .powf(1.0 / SQUIRCLE_EXPONENT),
+            SafeZone::AdaptiveIcon => x.hypot(y) / ADAPTIVE_RADIUS,
+        }
+    }
+}
+
+/// How the artwork sits in a zone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Fit {
+    /// Share of the artwork, weighted by coverage, that lies outside the zone
+    pub outside: f32,
+    /// Scale, at most 1, that brings all of the artwork inside the zone
+    pub scale: f32,
+}
+
+/// The color around the artwork: transparent when the corners are, otherwise
+/// the color the opaque corners share
+/// None when the corners differ, e.g. for a photo or a gradient
+pub(crate) fn background(image: &DynamicImage) -> Option<Rgba<u8>> {
+    let (width, height) = (image.width(), image.height());
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let image = image.to_rgba8();
+    let corners = [
+        *image.get_pixel(0, 0),
+        *image.get_pixel(width - 1, 0),
+        *image.get_pixel(0, height - 1),
+        *image.get_pixel(width - 1, height - 1),
+    ];
+    if corners.iter().all(|corner| corner[3] == 0) {
+        return Some(Rgba([0, 0, 0, 0]));
+    }
+    let first = corners[0];
+    corners
+        .iter()
+        .all(|corner| corner[3] == 255 && is_background(corner, &first))
+        .then_some(first)
+}
+
+fn is_background(pixel: &Rgba<u8>, background: &Rgba<u8>) -> bool {
+    (0..4).all(|channel| pixel[channel].abs_diff(background[channel]) <= BACKGROUND_TOLERANCE)
+}
+
+/// Measure the artwork of `image` against `zone`
+/// None when the artwork can't be told from the background, or there is none
+pub(crate) fn measure(image: &DynamicImage, zone: SafeZone) -> Option<Fit> {
+    let background = background(image)?;
+    let sample = image
+        .resize_exact(
+            SAMPLE_SIZE,
+            SAMPLE_SIZE,
+            image::imageops::FilterType::Triangle,
+        )
+        .into_rgba8();
+
+    let half = SAMPLE_SIZE as f32 / 2.0;
+    let (mut total, mut outside, mut farthest) = (0.0, 0.0, 0.0f32);
+    for (x, y, pixel) in sample.enumerate_pixels() {
+        // Coverage against a transparent background, presence against an opaque one
+        let weight = if background[3] == 0 {
+            pixel[3] as f32 / 255.0
+        } else if is_background(pixel, &background) {
+            0.0
+        } else {
+            1.0
+        };
+        if weight == 0.0 {
+            continue;
+        }
+        let distance = zone.distance(
+            (x as f32 + 0.5 - half) / half,
+            (y as f32 + 0.5 - half) / half,
+        );
+        total += weight;
+        if distance > 1.0 {
+            outside += weight;
+        }
+        if weight >= 0.5 {
+            farthest = farthest.max(distance);
+        }
+    }
+
+    (total > 0.0).then(|| Fit {
+        outside: outside / total,
+        scale: (1.0 / farthest).min(1.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    /// A disc of the given radius, relative to half the side, on `background`
+    fn disc(radius: f32, background: Rgba<u8>) -> DynamicImage {
+        let size = 128;
+        let half = size as f32 / 2.0;
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(size, size, |x, y| {
+            let distance = (x as f32 + 0.5 - half).hypot(y as f32 + 0.5 - half) / half;
+            if distance <= radius {
+                Rgba([200, 40, 40, 255])
+            } else {
+                background
+            }
+        }))
+    }
+
+    #[test]
+    fn test_background() {
+        let clear = Rgba([0, 0, 0, 0]);
+        let white = Rgba([255, 255, 255, 255]);
+        assert_eq!(background(&disc(0.5, clear)), Some(clear));
+        assert_eq!(background(&disc(0.5, white)), Some(white));
+
+        let mut gradient = disc(0.5, white).into_rgba8();
+        gradient.put_pixel(127, 127, Rgba([0, 0, 255, 255]));
+        assert_eq!(background(&DynamicImage::ImageRgba8(gradient)), None);
+    }
+
+    #[test]
+    fn test_measure() {
+        let clear = Rgba([0, 0, 0, 0]);
+
+        // A small disc is inside both zones
+        let fit = measure(&disc(0.5, clear), SafeZone::AdaptiveIcon).unwrap();
+        assert_eq!(fit.outside, 0.0);
+        assert_eq!(fit.scale, 1.0);
+
+        // A full-bleed disc touches the edges of the square, so it passes the
+        // iOS mask but not the smaller adaptive circle
+        let full = disc(1.0, Rgba([255, 255, 255, 255]));
+        let ios = measure(&full, SafeZone::IosMask).unwrap();
+        assert!(ios.outside < OUTSIDE_TOLERANCE, "{ios:?}");
+        let adaptive = measure(&full, SafeZone::AdaptiveIcon).unwrap();
+        assert!(adaptive.outside > 0.1, "{adaptive:?}");
+        assert!(
+            (adaptive.scale - ADAPTIVE_RADIUS).abs() < 0.02,
+            "{adaptive:?}"
+        );
+
+        // A plain square is all background, with no artwork to measure
+        let square = DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 64, Rgba([9, 9, 9, 255])));
+        assert_eq!(measure(&square, SafeZone::IosMask), None);
+
+        // A square tile reaches into the corners the iOS mask cuts
+        let mut framed = RgbaImage::from_pixel(64, 64, clear);
+        for (x, y, pixel) in framed.enumerate_pixels_mut() {
+            if x > 0 && y > 0 && x < 63 && y < 63 {
+                *pixel = Rgba([9, 9, 9, 255]);
+            }
+        }
+        let fit = measure(&DynamicImage::ImageRgba8(framed), SafeZone::IosMask).unwrap();
+        assert!(fit.outside > OUTSIDE_TOLERANCE, "{fit:?}");
+        assert!(fit.scale < 0.95, "{fit:?}");
+
+        assert_eq!(measure(&disc(0.0, clear), SafeZone::IosMask), None);
+    }
+}
@@ -0,0 +1,272 @@
+//! Custom size-spec files (`--spec`)
+//!
+//! A spec file lists arbitrary targets for hardware and platforms the built-in
+//! sets don't cover, such as kiosks and smart TVs:
+//!
+//! ```json
+//! {
+//!   "targets": [
+//!     { "size": 96, "filename": "kiosk/icon-{size}.png" },
+//!     { "size": "400x240", "filename": "tv/banner.png", "background": "#101820" },
+//!     { "size": 64, "filename": "legacy/icon.bmp" }
+//!   ]
+//! }
+//! ```
+//!
+//! `size` is a side in pixels or `WIDTHxHEIGHT`; non-square targets get the icon
+//! centered at 80% of the shorter side. `format` defaults to the filename
+//! extension, and `background` flattens the target onto a CSS color.
+
+use crate::error::IconGenError;
+use crate::icon_gen::parse_color_checked;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Component, Path};
+
+/// Formats a spec target can be written in
+pub const SPEC_FORMATS: &[&str] = &["png", "bmp", "webp", "jpeg"];
+
+/// A single file described by a spec
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecTarget {
+    /// The path of the file relative to the output directory, with the template expanded
+    pub filename: String,
+    pub width: u32,
+    pub height: u32,
+    /// One of `SPEC_FORMATS`
+    pub format: &'static str,
+    /// CSS color the target is flattened onto, transparent when unset
+    pub background: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpecFile {
+    targets: Vec<RawTarget>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTarget {
+    size: RawSize,
+    filename: String,
+    format: Option<String>,
+    background: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSize {
+    Side(u32),
+    Dimensions(String),
+}
+
+/// Read and validate the targets of a spec file
+pub fn load_spec(path: &Path) -> crate::error::Result<Vec<SpecTarget>> {
+    let json = std::fs::read_to_string(path).map_err(|source| IconGenError::io(path, source))?;
+    parse_spec(&json)
+        .map_err(|error| error.context(format!("Invalid spec file {}", path.display())))
+}
+
+/// Parse and validate the targets of a spec given as JSON
+pub fn parse_spec(json: &str) -> crate::error::Result<Vec<SpecTarget>> {
+    parse_targets(json).map_err(IconGenError::from_anyhow)
+}
+
+fn parse_targets(json: &str) -> Result<Vec<SpecTarget>> {
+    let spec: SpecFile = serde_json::from_str(json)?;
+    if spec.targets.is_empty() {
+        anyhow::bail!("The spec has no targets");
+    }
+
+    let mut targets = Vec::<SpecTarget>::new();
+    for raw in spec.targets {
+        let target = parse_target(raw)?;
+        if targets.iter().any(|t| t.filename == target.filename) {
+            anyhow::bail!("{} is targeted more than once", target.filename);
+        }
+        targets.push(target);
+    }
+    Ok(targets)
+}
+
+fn parse_target(raw: RawTarget) -> Result<SpecTarget> {
+    let (width, height) = match raw.size {
+        RawSize::Side(side) => (side, side),
+        RawSize::Dimensions(text) => parse_dimensions(&text)?,
+    };
+    if width == 0 || height == 0 {
+        anyhow::bail!("Target sizes must be at least 1 pixel: {}", raw.filename);
+    }
+
+    let extension = Path::new(&raw.filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let format = raw
+        .format
+        .or(extension)
+        .unwrap_or_else(|| "png".to_string());
+    let format = match format.as_str() {
+        "jpg" => "jpeg",
+        format => SPEC_FORMATS
+            .iter()
+            .copied()
+            .find(|&known| known == format)
+            .ok_or_else(|| {
+                IconGenError::UnsupportedFormat(format!(
+                    "Unknown format {} for {}. Available formats: {}",
+                    format,
+                    raw.filename,
+                    SPEC_FORMATS.join(", ")
+                ))
+            })?,
+    };
+
+    if let Some(background) = &raw.background {
+        parse_color_checked(&format!("background for {}", raw.filename), background)?;
+    }
+
+    let filename = expand_template(&raw.filename, width, height, format)?;
+    let is_relative = Path::new(&filename)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if filename.is_empty() || !is_relative {
+        anyhow::bail!(
+            "Target filenames must be relative paths inside the output directory: {}",
+            filename
+        );
+    }
+
+    Ok(SpecTarget {
+        filename,
+        width,
+        height,
+        format,
+        background: raw.background,
+    })
+}
+
+/// Parse `WIDTHxHEIGHT`, or a bare side given as a string
+fn parse_dimensions(text: &str) -> Result<(u32, u32)> {
+    let parse = |value: &str| value.trim().parse::<u32>();
+    match text.split_once(['x', 'X']) {
+        Some((width, height)) => Ok((parse(width)?, parse(height)?)),
+        None => parse(text).map(|side| (side, side)),
+    }
+    .with_context(|| format!("Invalid target size, expected e.g. 96 or 400x240: {}", text))
+}
+
+/// Expand `{size}`, `{width}`, `{height}` and `{format}` in a filename template
+/// `{size}` is the side of square targets and `WIDTHxHEIGHT` otherwise
+fn expand_template(template: &str, width: u32, height: u32, format: &str) -> Result<String> {
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .with_context(|| format!("Unclosed {{ in filename template: {}", template))?;
+        let value = match &rest[start + 1..start + end] {
+            "size" if width == height => width.to_string(),
+            "size" => format!("{width}x{height}"),
+            "width" => width.to_string(),
+            "height" => height.to_string(),
+            "format" => format.to_string(),
+            name => anyhow::bail!(
+                "Unknown variable {{{}}} in filename template: {}. Available variables: \
+                 {{size}}, {{width}}, {{height}}, {{format}}",
+                name,
+                template
+            ),
+        };
+        expanded.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_targets() {
+        let targets = parse_spec(
+            r##"{ "targets": [
+                { "size": 96, "filename": "kiosk/icon-{size}.png" },
+                { "size": "400x240", "filename": "tv/banner-{size}.{format}", "format": "webp",
+                  "background": "#101820" },
+                { "size": "64", "filename": "legacy/icon.JPG" }
+            ] }"##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            targets,
+            [
+                SpecTarget {
+                    filename: "kiosk/icon-96.png".to_string(),
+                    width: 96,
+                    height: 96,
+                    format: "png",
+                    background: None,
+                },
+                SpecTarget {
+                    filename: "tv/banner-400x240.webp".to_string(),
+                    width: 400,
+                    height: 240,
+                    format: "webp",
+                    background: Some("#101820".to_string()),
+                },
+                SpecTarget {
+                    filename: "legacy/icon.JPG".to_string(),
+                    width: 64,
+                    height: 64,
+                    format: "jpeg",
+                    background: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_specs() {
+        let error = |json: &str| format!("{:#}", parse_spec(json).unwrap_err());
+
+        assert!(error(r#"{ "targets": [] }"#).contains("no targets"));
+        assert!(
+            error(r#"{ "targets": [{ "size": 0, "filename": "a.png" }] }"#)
+                .contains("at least 1 pixel")
+        );
+        assert!(
+            error(r#"{ "targets": [{ "size": "big", "filename": "a.png" }] }"#)
+                .contains("Invalid target size")
+        );
+        assert!(
+            error(r#"{ "targets": [{ "size": 16, "filename": "a.tga" }] }"#)
+                .contains("Unknown format tga")
+        );
+        assert!(error(
+            r##"{ "targets": [{ "size": 16, "filename": "a.png", "background": "#10182" }] }"##
+        )
+        .contains("Invalid background for a.png: #10182"));
+        assert!(
+            error(r#"{ "targets": [{ "size": 16, "filename": "{dpi}.png" }] }"#)
+                .contains("Unknown variable {dpi}")
+        );
+        assert!(
+            error(r#"{ "targets": [{ "size": 16, "filename": "../a.png" }] }"#)
+                .contains("relative paths")
+        );
+        assert!(error(
+            r#"{ "targets": [{ "size": 16, "filename": "a.png" }, { "size": 32, "filename": "a.png" }] }"#
+        )
+        .contains("more than once"));
+        assert!(
+            error(r#"{ "targets": [{ "size": 16, "filename": "a.png", "dpi": 2 }] }"#)
+                .contains("unknown field")
+        );
+    }
+}
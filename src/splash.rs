@@ -0,0 +1,199 @@
+//! Splash and launch screen device tables
+//!
+//! Launch and startup images are full-screen bitmaps, so every entry describes
+//! one device resolution. The compositing itself lives in `icon_gen`.
+
+/// A slot in a legacy `LaunchImage.launchimage` asset catalog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LaunchImage {
+    /// Device idiom ("iphone" or "ipad")
+    pub idiom: &'static str,
+
+    /// Screen height subtype used by Xcode to pick the image (e.g. "2436h")
+    pub subtype: Option<&'static str>,
+
+    /// "portrait" or "landscape"
+    pub orientation: &'static str,
+
+    /// Width in pixels
+    pub width: u32,
+
+    /// Height in pixels
+    pub height: u32,
+
+    /// Scale factor (1, 2 or 3)
+    pub scale: u32,
+
+    /// First iOS version the slot applies to
+    pub minimum_system_version: &'static str,
+}
+
+impl LaunchImage {
+    /// The filename for the slot inside the asset catalog
+    pub fn filename(&self) -> String {
+        format!(
+            "LaunchImage-{}x{}-{}.png",
+            self.width, self.height, self.orientation
+        )
+    }
+}
+
+const fn launch(
+    idiom: &'static str,
+    subtype: Option<&'static str>,
+    orientation: &'static str,
+    size: (u32, u32),
+    scale: u32,
+    minimum_system_version: &'static str,
+) -> LaunchImage {
+    LaunchImage {
+        idiom,
+        subtype,
+        orientation,
+        width: size.0,
+        height: size.1,
+        scale,
+        minimum_system_version,
+    }
+}
+
+/// Every full-screen slot Xcode offers in a LaunchImage asset catalog
+#[rustfmt::skip]
+pub const IOS_LAUNCH_IMAGES: &[LaunchImage] = &[
+    // iPhone XS Max / 11 Pro Max
+    launch("iphone", Some("2688h"), "portrait", (1242, 2688), 3, "12.0"),
+    launch("iphone", Some("2688h"), "landscape", (2688, 1242), 3, "12.0"),
+    // iPhone XR / 11
+    launch("iphone", Some("1792h"), "portrait", (828, 1792), 2, "12.0"),
+    launch("iphone", Some("1792h"), "landscape", (1792, 828), 2, "12.0"),
+    // iPhone X / XS / 11 Pro
+    launch("iphone", Some("2436h"), "portrait", (1125, 2436), 3, "11.0"),
+    launch("iphone", Some("2436h"), "landscape", (2436, 1125), 3, "11.0"),
+    // iPhone 6/7/8 Plus
+    launch("iphone", Some("736h"), "portrait", (1242, 2208), 3, "8.0"),
+    launch("iphone", Some("736h"), "landscape", (2208, 1242), 3, "8.0"),
+    // iPhone 6/7/8
+    launch("iphone", Some("667h"), "portrait", (750, 1334), 2, "8.0"),
+    // iPhone 5 / SE
+    launch("iphone", Some("retina4"), "portrait", (640, 1136), 2, "7.0"),
+    // iPhone 4s
+    launch("iphone", None, "portrait", (640, 960), 2, "7.0"),
+    // iPad
+    launch("ipad", None, "portrait", (768, 1024), 1, "7.0"),
+    launch("ipad", None, "landscape", (1024, 768), 1, "7.0"),
+    launch("ipad", None, "portrait", (1536, 2048), 2, "7.0"),
+    launch("ipad", None, "landscape", (2048, 1536), 2, "7.0"),
+];
+
+/// A device targeted by an `apple-touch-startup-image` link, in portrait CSS pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StartupDevice {
+    /// Device width in CSS pixels
+    pub device_width: u32,
+
+    /// Device height in CSS pixels
+    pub device_height: u32,
+
+    /// Value of `-webkit-device-pixel-ratio`
+    pub pixel_ratio: u32,
+}
+
+impl StartupDevice {
+    /// Pixel size of the startup image in the given orientation
+    pub fn pixel_size(&self, landscape: bool) -> (u32, u32) {
+        let width = self.device_width * self.pixel_ratio;
+        let height = self.device_height * self.pixel_ratio;
+        if landscape {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// The media query matching this device in the given orientation
+    pub fn media_query(&self, landscape: bool) -> String {
+        format!(
+            "screen and (device-width: {}px) and (device-height: {}px) and (-webkit-device-pixel-ratio: {}) and (orientation: {})",
+            self.device_width,
+            self.device_height,
+            self.pixel_ratio,
+            if landscape { "landscape" } else { "portrait" }
+        )
+    }
+}
+
+const fn startup(device_width: u32, device_height: u32, pixel_ratio: u32) -> StartupDevice {
+    StartupDevice {
+        device_width,
+        device_height,
+        pixel_ratio,
+    }
+}
+
+/// iPhone and iPad screens that Safari matches startup images against
+pub const APPLE_STARTUP_DEVICES: &[StartupDevice] = &[
+    startup(1024, 1366, 2), // iPad Pro 12.9"
+    startup(834, 1194, 2),  // iPad Pro 11"
+    startup(820, 1180, 2),  // iPad Air 10.9"
+    startup(834, 1112, 2),  // iPad Air 10.5"
+    startup(810, 1080, 2),  // iPad 10.2"
+    startup(768, 1024, 2),  // iPad 9.7", iPad mini 7.9"
+    startup(744, 1133, 2),  // iPad mini 8.3"
+    startup(440, 956, 3),   // iPhone 16 Pro Max
+    startup(402, 874, 3),   // iPhone 16 Pro
+    startup(430, 932, 3),   // iPhone 14/15 Pro Max, 15/16 Plus
+    startup(393, 852, 3),   // iPhone 14/15 Pro, 15/16
+    startup(428, 926, 3),   // iPhone 12/13 Pro Max, 14 Plus
+    startup(390, 844, 3),   // iPhone 12/13/14, 12/13 Pro
+    startup(375, 812, 3),   // iPhone X/XS/11 Pro, 12/13 mini
+    startup(414, 896, 3),   // iPhone XS Max, 11 Pro Max
+    startup(414, 896, 2),   // iPhone XR, 11
+    startup(414, 736, 3),   // iPhone 6/7/8 Plus
+    startup(375, 667, 2),   // iPhone 6/7/8, SE 2nd/3rd gen
+    startup(320, 568, 2),   // iPhone 5, SE 1st gen
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch_image_filenames_are_unique() {
+        let mut filenames: Vec<_> = IOS_LAUNCH_IMAGES.iter().map(|l| l.filename()).collect();
+        filenames.sort();
+        filenames.dedup();
+        assert_eq!(filenames.len(), IOS_LAUNCH_IMAGES.len());
+    }
+
+    #[test]
+    fn test_startup_image_sizes_are_unique() {
+        let mut sizes: Vec<_> = APPLE_STARTUP_DEVICES
+            .iter()
+            .flat_map(|d| [d.pixel_size(false), d.pixel_size(true)])
+            .collect();
+        sizes.sort();
+        sizes.dedup();
+        assert_eq!(sizes.len(), APPLE_STARTUP_DEVICES.len() * 2);
+    }
+
+    #[test]
+    fn test_startup_media_query() {
+        let device = startup(390, 844, 3);
+        assert_eq!(device.pixel_size(true), (2532, 1170));
+        assert_eq!(
+            device.media_query(false),
+            "screen and (device-width: 390px) and (device-height: 844px) and (-webkit-device-pixel-ratio: 3) and (orientation: portrait)"
+        );
+    }
+
+    #[test]
+    fn test_launch_image_orientation_matches_dimensions() {
+        for slot in IOS_LAUNCH_IMAGES {
+            match slot.orientation {
+                "portrait" => assert!(slot.height > slot.width, "{:?}", slot),
+                "landscape" => assert!(slot.width > slot.height, "{:?}", slot),
+                other => panic!("Unexpected orientation {}", other),
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Limits that stores put on uploaded icons
+//!
+//! A store only checks an icon once it is uploaded: the Play Store refuses a
+//! listing icon over 1024 KB, and App Store Connect one that isn't a 1024x1024
+//! PNG without an alpha channel. After a set is written, the icons it made for a
+//! store are checked against these limits, so that a run warns instead.
+
+use image::ImageFormat;
+
+/// What a store accepts for an icon
+#[derive(Debug)]
+pub(crate) struct StoreLimit {
+    /// The store, as named in warnings
+    pub(crate) store: &'static str,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// Largest file accepted, in bytes
+    pub(crate) max_bytes: Option<usize>,
+    /// Whether an alpha channel is rejected, even a fully opaque one
+    pub(crate) opaque: bool,
+}
+
+/// The Play Store listing icon: a 512x512 PNG of at most 1024 KB
+pub(crate) const PLAY_STORE_ICON: StoreLimit = StoreLimit {
+    store: "Play Store",
+    width: 512,
+    height: 512,
+    max_bytes: Some(1024 * 1024),
+    opaque: false,
+};
+
+/// The App Store icon: a 1024x1024 PNG without an alpha channel
+pub(crate) const APP_STORE_ICON: StoreLimit = StoreLimit {
+    store: "App Store",
+    width: 1024,
+    height: 1024,
+    max_bytes: None,
+    opaque: true,
+};
+
+/// How the icon file `bytes` breaks `limit`, one message per problem; empty
+/// when the store accepts it
+pub(crate) fn violations(bytes: &[u8], limit: &StoreLimit) -> Vec<String> {
+    let store = limit.store;
+    let mut violations = Vec::new();
+    if let Some(max_bytes) = limit.max_bytes.filter(|&max| bytes.len() > max) {
+        violations.push(format!(
+            "is {} KB, over the {store} limit of {} KB",
+            bytes.len().div_ceil(1024),
+            max_bytes / 1024
+        ));
+    }
+    if image::guess_format(bytes).ok() != Some(ImageFormat::Png) {
+        violations.push(format!("isn't a PNG, which the {store} requires"));
+        return violations;
+    }
+    let Ok(image) = image::load_from_memory_with_format(bytes, ImageFormat::Png) else {
+        violations.push("can't be read".to_string());
+        return violations;
+    };
+    if (image.width(), image.height()) != (limit.width, limit.height) {
+        violations.push(format!(
+            "is {}x{}, the {store} expects {}x{}",
+            image.width(),
+            image.height(),
+            limit.width,
+            limit.height
+        ));
+    }
+    if limit.opaque && image.color().has_alpha() {
+        violations.push(format!("has an alpha channel, which the {store} rejects"));
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgb, RgbImage, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    fn png(image: DynamicImage) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_accepted_icons() {
+        let opaque = png(DynamicImage::ImageRgb8(RgbImage::from_pixel(
+            1024,
+            1024,
+            Rgb([20, 40, 60]),
+        )));
+        assert!(violations(&opaque, &APP_STORE_ICON).is_empty());
+
+        let transparent = png(DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            512,
+            512,
+            Rgba([20, 40, 60, 0]),
+        )));
+        assert!(violations(&transparent, &PLAY_STORE_ICON).is_empty());
+    }
+
+    #[test]
+    fn test_violations() {
+        let small = png(DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            512,
+            512,
+            Rgba([20, 40, 60, 255]),
+        )));
+        assert_eq!(
+            violations(&small, &APP_STORE_ICON),
+            [
+                "is 512x512, the App Store expects 1024x1024",
+                "has an alpha channel, which the App Store rejects",
+            ]
+        );
+
+        // Noise doesn't compress, so it takes more than the raw 1024 KB
+        let mut seed = 1u32;
+        let noise = RgbaImage::from_fn(512, 512, |_, _| {
+            Rgba([0; 4].map(|_: u8| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (seed >> 24) as u8
+            }))
+        });
+        let oversized = png(DynamicImage::ImageRgba8(noise));
+        let message = &violations(&oversized, &PLAY_STORE_ICON)[0];
+        assert!(
+            message.ends_with("KB, over the Play Store limit of 1024 KB"),
+            "{message}"
+        );
+
+        assert_eq!(
+            violations(b"GIF89a", &PLAY_STORE_ICON),
+            ["isn't a PNG, which the Play Store requires"]
+        );
+    }
+}
@@ -0,0 +1,457 @@
+//! Checks of an existing output tree (`icon-gen validate`)
+//!
+//! Icon folders are often committed and then edited by hand, so they drift
+//! from what was generated: a PNG replaced at the wrong size, a density left
+//! out, an ICO re-exported without its small frames. [`validate`] inspects the
+//! platform folders it finds and lists every missing or mismatched asset:
+//!
+//! - `ios/`: every file named by the app icon `Contents.json` exists at its
+//!   point size times its scale, and every PNG is listed; the icons can also be
+//!   in `ios/AppIcon.appiconset/` or `ios/Assets.xcassets/AppIcon.appiconset/`
+//! - `android/`, when it has launcher icons, or the directory itself when it is
+//!   an Android `res/` folder: every `mipmap-<density>` folder exists and has
+//!   the same icons, including the layers the adaptive icon XML refers to, at
+//!   the density's size; round icons come with their square counterparts, and
+//!   adaptive layers with the `mipmap-anydpi-v26` XML that uses them
+//! - `windows/icon.ico`: the directory is well formed and has a frame of every
+//!   size icon-gen writes
+//!
+//! Given a web app manifest instead of a directory, it checks the icons the
+//! manifest declares (see [`crate::web_manifest`]).
+//!
+//! Nothing is written, so it can run in CI against the committed folders.
+
+use crate::contents_json::ContentsFile;
+use crate::error::{IconGenError, Result};
+use crate::icon_gen::{ANDROID_DENSITIES, ICO_SIZES};
+use std::{
+    collections::BTreeSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// A missing or mismatched asset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// The file or folder, relative to the checked directory, e.g. `ios/AppIcon-60x60@2x.png`
+    pub path: PathBuf,
+    /// What is wrong with it, e.g. `is 100x100, Contents.json expects 120x120`
+    pub message: String,
+    /// The icon-gen option that regenerates the asset, e.g. `--android-round`
+    pub fix: Option<&'static str>,
+}
+
+/// What [`validate`] found
+#[derive(Debug, Default)]
+pub struct Validation {
+    /// The platform folders that were checked, e.g. `["ios", "windows"]`, or `["pwa"]` for a manifest
+    pub checked: Vec<&'static str>,
+    pub problems: Vec<Problem>,
+}
+
+/// Check the platform folders of the output directory `dir`, or the icons of a
+/// web app manifest when `dir` is a file
+/// Fails when `dir` has none of the folders that can be checked
+pub fn validate(dir: &Path) -> Result<Validation> {
+    if dir.is_file() {
+        return crate::web_manifest::check(dir);
+    }
+    if !dir.is_dir() {
+        return Err(IconGenError::io(
+            dir,
+            io::Error::new(io::ErrorKind::NotFound, "Not a directory"),
+        ));
+    }
+    let mut validation = Validation::default();
+    if dir.join("ios").is_dir() {
+        validation.checked.push("ios");
+        check_ios(dir, &mut validation.problems)?;
+    }
+    // android/ can hold nothing but the splash screen drawables
+    let mut mipmaps = ANDROID_DENSITIES
+        .iter()
+        .map(|(density, _)| format!("mipmap-{density}"))
+        .chain(["mipmap-anydpi-v26".to_string()]);
+    if mipmaps
+        .clone()
+        .any(|folder| dir.join("android").join(&folder).is_dir())
+    {
+        validation.checked.push("android");
+        check_android(dir, Path::new("android"), &mut validation.problems)?;
+    } else if mipmaps.any(|folder| dir.join(folder).is_dir()) {
+        // An Android project's res/ folder, e.g. app/src/main/res
+        validation.checked.push("android");
+        check_android(dir, Path::new(""), &mut validation.problems)?;
+    }
+    if dir.join("windows").is_dir() {
+        validation.checked.push("windows");
+        check_ico(dir, Path::new("windows/icon.ico"), &mut validation.problems)?;
+    }
+    if validation.checked.is_empty() {
+        return Err(IconGenError::InvalidInput(format!(
+            "No ios, android or windows icons in {}",
+            dir.display()
+        )));
+    }
+    Ok(validation)
+}
+
+pub(crate) fn problem(
+    problems: &mut Vec<Problem>,
+    path: impl Into<PathBuf>,
+    message: impl Into<String>,
+) {
+    fixable_problem(problems, path, message, None);
+}
+
+/// Add a problem that generating with the option `fix` would solve, if any
+pub(crate) fn fixable_problem(
+    problems: &mut Vec<Problem>,
+    path: impl Into<PathBuf>,
+    message: impl Into<String>,
+    fix: Option<&'static str>,
+) {
+    problems.push(Problem {
+        path: path.into(),
+        message: message.into(),
+        fix,
+    });
+}
+
+/// The file names in `dir` with the extension `extension`, sorted
+fn files_with_extension(dir: &Path, extension: &str) -> Result<BTreeSet<String>> {
+    let entries = std::fs::read_dir(dir).map_err(|error| IconGenError::io(dir, error))?;
+    let mut names = BTreeSet::new();
+    for entry in entries {
+        let path = entry.map_err(|error| IconGenError::io(dir, error))?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == extension) {
+            if let Some(name) = path.file_name() {
+                names.insert(name.to_string_lossy().into_owned());
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Compare the size of the image at `dir/path` with `expected` pixels on each side
+/// Returns whether the file exists
+fn check_size(
+    dir: &Path,
+    path: &Path,
+    expected: Option<u32>,
+    expected_by: &str,
+    fix: Option<&'static str>,
+    problems: &mut Vec<Problem>,
+) -> bool {
+    let full_path = dir.join(path);
+    if !full_path.is_file() {
+        return false;
+    }
+    match image::image_dimensions(&full_path) {
+        Err(error) => problem(problems, path, format!("can't be read: {error}")),
+        Ok((width, height)) => match expected {
+            Some(expected) if (width, height) != (expected, expected) => fixable_problem(
+                problems,
+                path,
+                format!("is {width}x{height}, {expected_by} expects {expected}x{expected}"),
+                fix,
+            ),
+            _ => {}
+        },
+    }
+    true
+}
+
+/// The folders of the iOS app icons for each `--ios-layout`, nested first
+const IOS_ICON_DIRS: &[&str] = &[
+    "ios/Assets.xcassets/AppIcon.appiconset",
+    "ios/AppIcon.appiconset",
+    "ios",
+];
+
+fn check_ios(dir: &Path, problems: &mut Vec<Problem>) -> Result<()> {
+    let ios = IOS_ICON_DIRS
+        .iter()
+        .map(Path::new)
+        .find(|folder| dir.join(folder).join("Contents.json").is_file())
+        .unwrap_or(Path::new("ios"));
+    let contents_path = ios.join("Contents.json");
+    let contents = match std::fs::read(dir.join(&contents_path)) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            problem(problems, contents_path, "is missing");
+            return Ok(());
+        }
+        Err(error) => return Err(IconGenError::io(dir.join(contents_path), error)),
+    };
+    let contents = match serde_json::from_slice::<ContentsFile>(&contents) {
+        Ok(contents) => contents,
+        Err(error) => {
+            problem(problems, contents_path, format!("can't be parsed: {error}"));
+            return Ok(());
+        }
+    };
+
+    let mut listed = BTreeSet::new();
+    for entry in &contents.images {
+        // Slots without a file are left empty on purpose
+        let Some(filename) = &entry.filename else {
+            continue;
+        };
+        listed.insert(filename.clone());
+        let expected = entry
+            .size
+            .as_deref()
+            .zip(entry.scale.as_deref())
+            .and_then(|(size, scale)| pixel_size(size, scale));
+        let path = ios.join(filename);
+        if !check_size(dir, &path, expected, "Contents.json", None, problems) {
+            problem(problems, path, "is listed in Contents.json but missing");
+        }
+    }
+    for name in files_with_extension(&dir.join(ios), "png")? {
+        if !listed.contains(&name) {
+            problem(problems, ios.join(name), "isn't listed in Contents.json");
+        }
+    }
+    Ok(())
+}
+
+/// The pixel side of an appiconset entry, e.g. 167 for `83.5x83.5` at `2x`
+fn pixel_size(size: &str, scale: &str) -> Option<u32> {
+    let (width, height) = size.split_once('x')?;
+    if width != height {
+        return None;
+    }
+    let points = width.parse::<f32>().ok()?;
+    let scale = scale.strip_suffix('x')?.parse::<f32>().ok()?;
+    Some((points * scale).round() as u32)
+}
+
+/// Side of the launcher icons in density-independent pixels, which is the
+/// mdpi size in pixels
+const LAUNCHER_DP: u32 = 48;
+
+/// The size in dp of the icons icon-gen writes to the mipmap folders; other
+/// icons are expected to keep the proportions of their first density
+fn known_dp(name: &str) -> Option<u32> {
+    match name {
+        "ic_launcher.png" | "ic_launcher_round.png" => Some(LAUNCHER_DP),
+        // Adaptive icon layers are 108dp, with the 72dp inner part visible
+        "ic_launcher_foreground.png" | "ic_launcher_background.png" => Some(108),
+        _ => None,
+    }
+}
+
+/// The option that generates an icon of the mipmap folders
+fn android_fix(name: &str) -> Option<&'static str> {
+    match name {
+        "ic_launcher.png" => Some("--android"),
+        "ic_launcher_round.png" => Some("--android-round"),
+        "ic_launcher_foreground.png"
+        | "ic_launcher_background.png"
+        | "ic_launcher.xml"
+        | "ic_launcher_round.xml" => Some("--android-adaptive"),
+        _ => None,
+    }
+}
+
+/// Check the launcher icons of the Android resource folder `android`, which is
+/// `android/` in an output directory and empty for a `res/` folder
+fn check_android(dir: &Path, android: &Path, problems: &mut Vec<Problem>) -> Result<()> {
+    let mut names = BTreeSet::new();
+    let mut present = Vec::new();
+    for (density, _) in ANDROID_DENSITIES {
+        let folder = android.join(format!("mipmap-{density}"));
+        if dir.join(&folder).is_dir() {
+            names.extend(files_with_extension(&dir.join(&folder), "png")?);
+            present.push(density);
+        } else {
+            fixable_problem(problems, folder, "is missing", Some("--android"));
+        }
+    }
+
+    // The adaptive icons name their layers, e.g. @mipmap/ic_launcher_foreground
+    let anydpi = android.join("mipmap-anydpi-v26");
+    let mut adaptive = BTreeSet::new();
+    if dir.join(&anydpi).is_dir() {
+        adaptive = files_with_extension(&dir.join(&anydpi), "xml")?;
+        for xml in &adaptive {
+            let path = anydpi.join(xml);
+            let text = std::fs::read_to_string(dir.join(&path))
+                .map_err(|error| IconGenError::io(dir.join(&path), error))?;
+            for reference in text.split("@mipmap/").skip(1) {
+                let name = reference
+                    .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .next()
+                    .unwrap_or_default();
+                names.insert(format!("{name}.png"));
+            }
+        }
+    }
+
+    // Adaptive layers are only shown through the XML that stacks them
+    for name in &names {
+        if let Some(icon) = name.strip_suffix("_foreground.png") {
+            let xml = format!("{icon}.xml");
+            if !adaptive.contains(&xml) {
+                let message = format!("is missing, so nothing uses the {name} layer");
+                fixable_problem(problems, anydpi.join(&xml), message, android_fix(&xml));
+            }
+        }
+    }
+
+    // android:roundIcon and android:icon come in pairs, and Android 8+ picks the
+    // adaptive version of whichever the launcher asks for
+    for xml in &adaptive {
+        let Some(icon) = xml.strip_suffix(".xml") else {
+            continue;
+        };
+        let (counterpart, round_png) = match icon.strip_suffix("_round") {
+            Some(square) => (format!("{square}.xml"), None),
+            None => (
+                format!("{icon}_round.xml"),
+                Some(format!("{icon}_round.png")),
+            ),
+        };
+        // Without round icons at all, the round XML isn't needed
+        if adaptive.contains(&counterpart)
+            || round_png.is_some_and(|round_png| !names.contains(&round_png))
+        {
+            continue;
+        }
+        let message = format!("is missing, but {xml} is there");
+        fixable_problem(
+            problems,
+            anydpi.join(&counterpart),
+            message,
+            android_fix(&counterpart),
+        );
+    }
+    for name in &names {
+        let Some(square) = name
+            .strip_suffix("_round.png")
+            .map(|icon| format!("{icon}.png"))
+        else {
+            continue;
+        };
+        if names.contains(&square) {
+            continue;
+        }
+        for density in &present {
+            let path = android.join(format!("mipmap-{density}")).join(&square);
+            let message = format!("is missing, but {name} is there");
+            fixable_problem(problems, path, message, android_fix(&square));
+        }
+    }
+
+    for name in &names {
+        let mut dp = known_dp(name);
+        for (density, size) in ANDROID_DENSITIES {
+            if !present.contains(&density) {
+                continue;
+            }
+            let path = android.join(format!("mipmap-{density}")).join(name);
+            let expected = dp.map(|dp| dp * size / LAUNCHER_DP);
+            let expected_by = format!("mipmap-{density}");
+            let fix = android_fix(name);
+            if !check_size(dir, &path, expected, &expected_by, fix, problems) {
+                let message = "is missing, but other densities have it";
+                fixable_problem(problems, path, message, fix);
+                continue;
+            }
+            if dp.is_none() {
+                dp = image::image_dimensions(dir.join(&path))
+                    .ok()
+                    .map(|(width, _)| width * LAUNCHER_DP / size);
+            }
+        }
+    }
+    Ok(())
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn check_ico(dir: &Path, path: &Path, problems: &mut Vec<Problem>) -> Result<()> {
+    let data = match std::fs::read(dir.join(path)) {
+        Ok(data) => data,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            problem(problems, path, "is missing");
+            return Ok(());
+        }
+        Err(error) => return Err(IconGenError::io(dir.join(path), error)),
+    };
+    let u16_at = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+    let u32_at = |offset: usize| {
+        u32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ])
+    };
+    if data.len() < 6 || u16_at(0) != 0 || u16_at(2) != 1 {
+        problem(problems, path, "isn't an ICO file");
+        return Ok(());
+    }
+    let count = u16_at(4) as usize;
+    if data.len() < 6 + 16 * count {
+        problem(problems, path, "is truncated");
+        return Ok(());
+    }
+
+    let mut sizes = BTreeSet::new();
+    for index in 0..count {
+        let entry = 6 + 16 * index;
+        // 0 stands for 256
+        let width = if data[entry] == 0 {
+            256
+        } else {
+            data[entry] as u32
+        };
+        let height = if data[entry + 1] == 0 {
+            256
+        } else {
+            data[entry + 1] as u32
+        };
+        let (length, offset) = (u32_at(entry + 8) as usize, u32_at(entry + 12) as usize);
+        if offset
+            .checked_add(length)
+            .is_none_or(|end| end > data.len())
+        {
+            problem(
+                problems,
+                path,
+                format!("frame {width}x{height} lies outside the file"),
+            );
+            continue;
+        }
+        if width != height {
+            problem(
+                problems,
+                path,
+                format!("has a non-square {width}x{height} frame"),
+            );
+        }
+        // PNG frames carry their own size, which has to agree with the directory
+        let frame = &data[offset..offset + length];
+        if frame.len() >= 24 && frame.starts_with(PNG_SIGNATURE) {
+            let png_width = u32::from_be_bytes([frame[16], frame[17], frame[18], frame[19]]);
+            let png_height = u32::from_be_bytes([frame[20], frame[21], frame[22], frame[23]]);
+            if (png_width, png_height) != (width, height) {
+                problem(
+                    problems,
+                    path,
+                    format!("frame {width}x{height} holds a {png_width}x{png_height} PNG"),
+                );
+            }
+        }
+        sizes.insert(width);
+    }
+    for &size in ICO_SIZES {
+        if !sizes.contains(&size) {
+            problem(problems, path, format!("has no {size}x{size} frame"));
+        }
+    }
+    Ok(())
+}
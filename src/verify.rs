@@ -0,0 +1,246 @@
+//! Round-trip checks of the icon containers (`--verify`)
+//!
+//! A broken `icon.ico` or `icon.icns` usually goes unnoticed until an installer
+//! or Finder shows a blank icon. With `--verify` each container is decoded again
+//! from the bytes about to be written and compared with the frames that went
+//! in: the number of frames, the size of each, and every pixel. The frames are
+//! stored losslessly (PNG, or RLE-compressed RGB with an alpha mask for the
+//! small ICNS members), so anything short of an exact match is an encoder bug.
+//! The ic04/ic05 ICNS members, which the icns crate doesn't know, are decoded
+//! as the PNGs they hold.
+
+use crate::error::IconGenError;
+use anyhow::Result;
+use icns::{IconFamily, IconType, OSType, PixelFormat};
+use image::{ImageFormat, RgbaImage};
+
+/// Check that the ICO file `data` decodes to exactly `frames`, in order
+pub(crate) fn verify_ico(data: &[u8], frames: &[RgbaImage]) -> Result<()> {
+    check_ico(data, frames).map_err(|message| failure("ICO", message))
+}
+
+/// Check that the ICNS file `data` has exactly the icons `members`, each
+/// decoding (with its mask) to the image it was encoded from
+pub(crate) fn verify_icns(data: &[u8], members: &[(OSType, RgbaImage)]) -> Result<()> {
+    check_icns(data, members).map_err(|message| failure("ICNS", message))
+}
+
+fn failure(format: &str, message: String) -> anyhow::Error {
+    IconGenError::EncodeError(format!("{format} round trip failed: {message}")).into()
+}
+
+fn check_ico(data: &[u8], frames: &[RgbaImage]) -> Result<(), String> {
+    if data.len() < 6 {
+        return Err("the file is truncated".to_string());
+    }
+    let count = u16::from_le_bytes([data[4], data[5]]) as usize;
+    if count != frames.len() {
+        return Err(format!("{count} frames, expected {}", frames.len()));
+    }
+    if data.len() < 6 + 16 * count {
+        return Err("the frame directory is truncated".to_string());
+    }
+
+    for (index, expected) in frames.iter().enumerate() {
+        // Each frame is decoded on its own, as an ICO of just its directory entry
+        let entry = &data[6 + 16 * index..6 + 16 * (index + 1)];
+        let length = u32::from_le_bytes([entry[8], entry[9], entry[10], entry[11]]) as usize;
+        let offset = u32::from_le_bytes([entry[12], entry[13], entry[14], entry[15]]) as usize;
+        let Some(frame) = offset
+            .checked_add(length)
+            .and_then(|end| data.get(offset..end))
+        else {
+            return Err(format!("frame {} lies outside the file", index + 1));
+        };
+
+        let mut single = Vec::with_capacity(22 + frame.len());
+        single.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+        single.extend_from_slice(&entry[..12]);
+        single.extend_from_slice(&22u32.to_le_bytes());
+        single.extend_from_slice(frame);
+        let name = format!("frame {}", index + 1);
+        match image::load_from_memory_with_format(&single, ImageFormat::Ico) {
+            Ok(decoded) => compare(&name, &decoded.into_rgba8(), expected)?,
+            Err(error) => return Err(format!("{name} can't be decoded: {error}")),
+        }
+    }
+    Ok(())
+}
+
+fn check_icns(data: &[u8], members: &[(OSType, RgbaImage)]) -> Result<(), String> {
+    let family = match IconFamily::read(data) {
+        Ok(family) => family,
+        Err(error) => return Err(format!("the icon family can't be read: {error}")),
+    };
+    // The icons the crate decodes, and the PNG members it doesn't know
+    let unknown = family
+        .elements
+        .iter()
+        .filter(|element| IconType::from_ostype(element.ostype).is_none())
+        .count();
+    let count = family.available_icons().len() + unknown;
+    if count != members.len() {
+        return Err(format!("{count} icons, expected {}", members.len()));
+    }
+
+    for (ostype, expected) in members {
+        let name = ostype.to_string();
+        let Some(icon_type) = IconType::from_ostype(*ostype) else {
+            let Some(element) = family
+                .elements
+                .iter()
+                .find(|element| element.ostype == *ostype)
+            else {
+                return Err(format!("{name} is missing"));
+            };
+            match image::load_from_memory_with_format(&element.data, ImageFormat::Png) {
+                Ok(decoded) => compare(&name, &decoded.into_rgba8(), expected)?,
+                Err(error) => return Err(format!("{name} can't be decoded: {error}")),
+            }
+            continue;
+        };
+        let image = match family.get_icon_with_type(icon_type) {
+            Ok(image) => image.convert_to(PixelFormat::RGBA),
+            Err(error) => return Err(format!("{name} can't be decoded: {error}")),
+        };
+        let (width, height) = (image.width(), image.height());
+        let Some(decoded) = RgbaImage::from_raw(width, height, image.into_data().into()) else {
+            return Err(format!(
+                "{name} decodes to a truncated {width}x{height} image"
+            ));
+        };
+        compare(&name, &decoded, expected)?;
+    }
+    Ok(())
+}
+
+/// Compare a decoded frame with the image it was encoded from
+fn compare(name: &str, decoded: &RgbaImage, expected: &RgbaImage) -> Result<(), String> {
+    if decoded.dimensions() != expected.dimensions() {
+        let (width, height) = decoded.dimensions();
+        let (expected_width, expected_height) = expected.dimensions();
+        return Err(format!(
+            "{name} is {width}x{height}, expected {expected_width}x{expected_height}"
+        ));
+    }
+    let differing = decoded
+        .pixels()
+        .zip(expected.pixels())
+        .filter(|(decoded, expected)| decoded != expected)
+        .count();
+    if differing > 0 {
+        let (width, height) = expected.dimensions();
+        return Err(format!(
+            "{differing} of the {} pixels of {name} ({width}x{height}) changed",
+            width * height
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icns::IconElement;
+    use image::codecs::ico::{IcoEncoder, IcoFrame};
+    use image::{ColorType, Rgba};
+
+    fn gradient(size: u32) -> RgbaImage {
+        RgbaImage::from_fn(size, size, |x, y| {
+            Rgba([(x * 16) as u8, (y * 16) as u8, 200, (x * 8 + y * 8) as u8])
+        })
+    }
+
+    fn encode_ico(frames: &[RgbaImage]) -> Vec<u8> {
+        let frames = frames
+            .iter()
+            .map(|frame| {
+                IcoFrame::as_png(
+                    frame.as_raw(),
+                    frame.width(),
+                    frame.height(),
+                    ColorType::Rgba8,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let mut data = Vec::new();
+        IcoEncoder::new(&mut data).encode_images(&frames).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_verify_ico() {
+        let frames = [gradient(16), gradient(32)];
+        let data = encode_ico(&frames);
+        verify_ico(&data, &frames).unwrap();
+
+        let error = verify_ico(&data, &frames[..1]).unwrap_err().to_string();
+        assert_eq!(error, "ICO round trip failed: 2 frames, expected 1");
+
+        let mut changed = frames.clone();
+        changed[1].put_pixel(3, 4, Rgba([0, 0, 0, 0]));
+        let error = verify_ico(&data, &changed).unwrap_err().to_string();
+        assert!(error.contains("1 of the 1024 pixels of frame 2 (32x32) changed"));
+
+        let error = verify_ico(&data, &[gradient(16), gradient(48)]).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("frame 2 is 32x32, expected 48x48"));
+
+        assert!(verify_ico(&data[..data.len() - 10], &frames).is_err());
+    }
+
+    #[test]
+    fn test_verify_icns() {
+        let image = gradient(16);
+        let icon = icns::Image::from_data(PixelFormat::RGBA, 16, 16, image.to_vec()).unwrap();
+        let mut family = IconFamily::new();
+        family
+            .add_icon_with_type(&icon, IconType::RGB24_16x16)
+            .unwrap();
+        let mut data = Vec::new();
+        family.write(&mut data).unwrap();
+
+        let members = [(IconType::RGB24_16x16.ostype(), image.clone())];
+        verify_icns(&data, &members).unwrap();
+
+        let mut changed = image.clone();
+        changed.put_pixel(0, 0, Rgba([1, 2, 3, 4]));
+        let error = verify_icns(&data, &[(IconType::RGB24_16x16.ostype(), changed)]).unwrap_err();
+        assert!(error.to_string().contains("pixels of is32 (16x16) changed"));
+
+        // The mask makes up a complete icon only together with its image
+        family
+            .elements
+            .retain(|element| element.ostype != IconType::RGB24_16x16.ostype());
+        family
+            .elements
+            .push(IconElement::new(IconType::RGB24_16x16.ostype(), vec![0; 3]));
+        let mut data = Vec::new();
+        family.write(&mut data).unwrap();
+        assert!(verify_icns(&data, &members).is_err());
+    }
+
+    #[test]
+    fn test_verify_icns_png_member() {
+        // ic04 holds a PNG, and isn't one of the types the icns crate knows
+        let image = gradient(16);
+        let mut png = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut png, ImageFormat::Png).unwrap();
+        let ic04: OSType = "ic04".parse().unwrap();
+        let mut family = IconFamily::new();
+        family
+            .elements
+            .push(IconElement::new(ic04, png.into_inner()));
+        let mut data = Vec::new();
+        family.write(&mut data).unwrap();
+
+        verify_icns(&data, &[(ic04, image.clone())]).unwrap();
+        let error = verify_icns(&data, &[]).unwrap_err();
+        assert!(error.to_string().contains("1 icons, expected 0"));
+        let ic05: OSType = "ic05".parse().unwrap();
+        let error = verify_icns(&data, &[(ic05, image)]).unwrap_err();
+        assert!(error.to_string().contains("ic05 is missing"));
+    }
+}
@@ -0,0 +1,276 @@
+//! Checks of the icons a web app manifest declares (`icon-gen validate manifest.json`)
+//!
+//! A PWA manifest lists its icons by URL, size and type, and nothing keeps the
+//! list in step with the files: an icon is renamed, re-exported at another size
+//! or converted to WebP while the manifest still says PNG. [`check`] resolves
+//! every declared icon next to the manifest and compares the file with its
+//! declaration, and looks for what browsers need to install the app: a maskable
+//! icon and the 192px and 512px sizes. [`fix`] regenerates the declared files
+//! that are missing or don't match from a source image.
+//!
+//! URLs are resolved against the folder of the manifest, root-relative ones
+//! (`/icons/icon-192.png`) included, so the manifest is expected at the site
+//! root. Absolute `http(s):` and `data:` URLs aren't checked.
+
+use crate::error::{IconGenError, Result};
+use crate::icon_gen::{load_image, save_png, save_webp, PngEncoding};
+use crate::output::FileSystemSink;
+use crate::safe_zone;
+use crate::validate::{fixable_problem, problem, Problem, Validation};
+use image::{imageops::FilterType, DynamicImage, ImageFormat, Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+
+/// Hint for the problems [`fix`] solves
+const FIX: &str = "--fix <SOURCE>";
+
+/// Sizes browsers need to offer installing the app
+const INSTALL_SIZES: &[u32] = &[192, 512];
+
+/// Share of a maskable icon taken by the artwork; the safe zone is the central
+/// circle of 40% radius
+const MASKABLE_ARTWORK: f32 = 0.8;
+
+/// An entry of the manifest's `icons`
+struct Icon {
+    /// The file, relative to the manifest's folder
+    path: PathBuf,
+    /// The declared `sizes`, without `any`
+    sizes: Vec<(u32, u32)>,
+    /// The declared `type`, e.g. `image/png`
+    mime: Option<String>,
+    maskable: bool,
+}
+
+/// Check the icons the web app manifest at `manifest` declares
+pub fn check(manifest: &Path) -> Result<Validation> {
+    let (icons, mut problems) = read_icons(manifest)?;
+    let dir = manifest.parent().unwrap_or(Path::new(""));
+    let name = manifest_name(manifest);
+
+    for icon in &icons {
+        if let Some(message) = mismatch(dir, icon, &name)? {
+            let fix = (!icon.sizes.is_empty() && target_format(icon).is_some()).then_some(FIX);
+            fixable_problem(&mut problems, &icon.path, message, fix);
+        }
+    }
+
+    if !icons.iter().any(|icon| icon.maskable) {
+        problem(
+            &mut problems,
+            &name,
+            "declares no maskable icon (\"purpose\": \"maskable\"), so Android crops the others",
+        );
+    }
+    for &size in INSTALL_SIZES {
+        if !icons.iter().any(|icon| icon.sizes.contains(&(size, size))) {
+            problem(
+                &mut problems,
+                &name,
+                format!("declares no {size}x{size} icon, which browsers need to install the app"),
+            );
+        }
+    }
+    Ok(Validation {
+        checked: vec!["pwa"],
+        problems,
+    })
+}
+
+/// Regenerate the declared icons of the manifest at `manifest` that are missing
+/// or don't match their declaration from the image at `source`
+/// Returns the files written, relative to the manifest's folder
+pub fn fix(manifest: &Path, source: &Path) -> Result<Vec<PathBuf>> {
+    let (icons, _) = read_icons(manifest)?;
+    let dir = manifest.parent().unwrap_or(Path::new(""));
+    let name = manifest_name(manifest);
+    let mut source_image = None;
+
+    let mut fixed = Vec::new();
+    for icon in &icons {
+        if mismatch(dir, icon, &name)?.is_none() {
+            continue;
+        }
+        let Some(&(width, height)) = icon.sizes.first() else {
+            continue;
+        };
+        let Some(format) = target_format(icon) else {
+            continue;
+        };
+        if source_image.is_none() {
+            source_image = Some(load_image(source).map_err(IconGenError::from_anyhow)?);
+        }
+        let source = source_image.as_ref().expect("loaded above");
+        let image = if icon.maskable {
+            maskable(source, width, height)
+        } else {
+            source.resize_exact(width, height, FilterType::Lanczos3)
+        };
+        let path = dir.join(&icon.path);
+        let saved = match format {
+            ImageFormat::WebP => save_webp(&image, &path, &FileSystemSink),
+            _ => save_png(&image, &path, PngEncoding::default(), &FileSystemSink),
+        };
+        saved.map_err(IconGenError::from_anyhow)?;
+        fixed.push(icon.path.clone());
+    }
+    Ok(fixed)
+}
+
+/// The file name of the manifest, which problems with its declarations are filed under
+fn manifest_name(manifest: &Path) -> PathBuf {
+    manifest
+        .file_name()
+        .map_or_else(|| manifest.to_path_buf(), PathBuf::from)
+}
+
+/// The icons the manifest declares, with the problems of entries that can't be checked
+fn read_icons(manifest: &Path) -> Result<(Vec<Icon>, Vec<Problem>)> {
+    let name = manifest_name(manifest);
+    let text = std::fs::read(manifest).map_err(|error| IconGenError::io(manifest, error))?;
+    let json = serde_json::from_slice::<serde_json::Value>(&text).map_err(|error| {
+        IconGenError::InvalidInput(format!("Failed to parse {}: {error}", manifest.display()))
+    })?;
+
+    let mut problems = Vec::new();
+    let Some(entries) = json.get("icons").and_then(|icons| icons.as_array()) else {
+        problem(&mut problems, &name, "has no icons");
+        return Ok((Vec::new(), problems));
+    };
+
+    let mut icons = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let field = |key: &str| entry.get(key).and_then(|value| value.as_str());
+        let Some(src) = field("src") else {
+            problem(&mut problems, &name, format!("icons[{index}] has no src"));
+            continue;
+        };
+        if src.starts_with("http:") || src.starts_with("https:") || src.starts_with("data:") {
+            continue;
+        }
+        let src = src.split(['?', '#']).next().unwrap_or_default();
+        let path = PathBuf::from(src.trim_start_matches('/'));
+
+        let mut sizes = Vec::new();
+        for size in field("sizes").unwrap_or_default().split_whitespace() {
+            if size.eq_ignore_ascii_case("any") {
+                continue;
+            }
+            match size
+                .to_ascii_lowercase()
+                .split_once('x')
+                .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+            {
+                Some(size) => sizes.push(size),
+                None => problem(
+                    &mut problems,
+                    &path,
+                    format!("has an invalid size \"{size}\" in {}", name.display()),
+                ),
+            }
+        }
+        let maskable = field("purpose")
+            .unwrap_or_default()
+            .split_whitespace()
+            .any(|purpose| purpose == "maskable");
+        icons.push(Icon {
+            path,
+            sizes,
+            mime: field("type").map(str::to_string),
+            maskable,
+        });
+    }
+    Ok((icons, problems))
+}
+
+/// How the file of `icon` differs from its declaration, None when it matches
+fn mismatch(dir: &Path, icon: &Icon, manifest: &Path) -> Result<Option<String>> {
+    let manifest = manifest.display();
+    let path = dir.join(&icon.path);
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Some(format!("is declared in {manifest} but missing")));
+        }
+        Err(error) => return Err(IconGenError::io(path, error)),
+    };
+    // Vector icons scale to any declared size
+    if icon.mime.as_deref() == Some("image/svg+xml")
+        || icon.path.extension().is_some_and(|ext| ext == "svg")
+    {
+        return Ok(None);
+    }
+
+    let Ok(format) = image::guess_format(&bytes) else {
+        return Ok(Some("isn't an image".to_string()));
+    };
+    if let Some(declared) = &icon.mime {
+        let actual = mime_type(format);
+        if !actual.contains(&declared.as_str()) {
+            return Ok(Some(format!(
+                "is {}, {manifest} declares {declared}",
+                actual[0]
+            )));
+        }
+    }
+    let (width, height) = match image::load_from_memory_with_format(&bytes, format) {
+        Ok(image) => (image.width(), image.height()),
+        Err(error) => return Ok(Some(format!("can't be read: {error}"))),
+    };
+    if !icon.sizes.is_empty() && !icon.sizes.contains(&(width, height)) {
+        let declared = icon
+            .sizes
+            .iter()
+            .map(|(width, height)| format!("{width}x{height}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        return Ok(Some(format!(
+            "is {width}x{height}, {manifest} declares {declared}"
+        )));
+    }
+    Ok(None)
+}
+
+/// The MIME types a manifest may declare for an image format, the usual one first
+fn mime_type(format: ImageFormat) -> &'static [&'static str] {
+    match format {
+        ImageFormat::Png => &["image/png"],
+        ImageFormat::Jpeg => &["image/jpeg", "image/jpg"],
+        ImageFormat::WebP => &["image/webp"],
+        ImageFormat::Gif => &["image/gif"],
+        ImageFormat::Bmp => &["image/bmp"],
+        ImageFormat::Ico => &["image/x-icon", "image/vnd.microsoft.icon"],
+        _ => &["application/octet-stream"],
+    }
+}
+
+/// The format [`fix`] writes an icon in, from its declared type or else its
+/// extension; None for formats it doesn't write
+fn target_format(icon: &Icon) -> Option<ImageFormat> {
+    let format = match &icon.mime {
+        Some(mime) => match mime.as_str() {
+            "image/png" => ImageFormat::Png,
+            "image/webp" => ImageFormat::WebP,
+            _ => return None,
+        },
+        None => ImageFormat::from_path(&icon.path).ok()?,
+    };
+    matches!(format, ImageFormat::Png | ImageFormat::WebP).then_some(format)
+}
+
+/// The source shrunk into the maskable safe zone, on its own background when it
+/// has an opaque one and on white otherwise, since maskable icons fill the mask
+fn maskable(source: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+    let background = safe_zone::background(source)
+        .filter(|color| color[3] == 255)
+        .unwrap_or(Rgba([255, 255, 255, 255]));
+    let side = ((width.min(height) as f32 * MASKABLE_ARTWORK).round() as u32).max(1);
+    let artwork = source.resize_exact(side, side, FilterType::Lanczos3);
+    let mut canvas = RgbaImage::from_pixel(width, height, background);
+    image::imageops::overlay(
+        &mut canvas,
+        &artwork.to_rgba8(),
+        ((width - side) / 2) as i64,
+        ((height - side) / 2) as i64,
+    );
+    DynamicImage::ImageRgba8(canvas)
+}
@@ -0,0 +1,183 @@
+//! Questions asked by `icon-gen --interactive`
+//!
+//! The wizard asks for the source image, platforms, output directory, iOS
+//! background color and release badge, offering the command line default for
+//! each, and turns the answers into command line arguments. The binary prints
+//! them as the equivalent command and runs it, so first-time users learn the
+//! flags along the way. Answers are read a line at a time; an empty line takes
+//! the default, and an invalid one is asked again.
+
+use icon_gen::{default_output_dir, Platform, CHANNEL_NAMES};
+use std::{
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// What the command line already settles, so the wizard doesn't ask for it
+#[derive(Debug, Default)]
+pub struct Given {
+    /// The source image, used instead of asking for one
+    pub source: Option<PathBuf>,
+    pub output: bool,
+    pub platforms: bool,
+    /// Whether the platforms given generate iOS icons
+    pub ios: bool,
+    pub background: bool,
+    pub badge: bool,
+}
+
+/// Ask for everything `given` leaves open, prompting on `output`
+/// The arguments start with the source image when it was asked for
+pub fn ask(
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+    given: &Given,
+) -> io::Result<Vec<String>> {
+    let mut wizard = Wizard { input, output };
+    let mut args = Vec::new();
+
+    let source = match &given.source {
+        Some(source) => source.clone(),
+        None => {
+            let source = wizard.ask("Source image (a square PNG)", None, |answer| {
+                let path = PathBuf::from(answer);
+                match path.is_file() {
+                    true => Ok(path),
+                    false => Err(format!("No such file: {answer}")),
+                }
+            })?;
+            args.push(source.to_string_lossy().into_owned());
+            source
+        }
+    };
+
+    let mut ios = given.ios;
+    if !given.platforms {
+        let flags = wizard.ask(
+            "Platforms: all, desktop, mobile, or a list such as windows,macos,ios",
+            Some("all"),
+            platform_flags,
+        )?;
+        ios = flags.is_empty()
+            || flags.iter().any(|flag| {
+                ["--mobile-only", "--ios", "--app-clip", "--imessage"].contains(&flag.as_str())
+            });
+        args.extend(flags);
+    }
+
+    if !given.output {
+        let default = default_output_dir(&source);
+        let directory = wizard.ask(
+            "Output directory",
+            Some(&default.to_string_lossy()),
+            |answer| Ok(PathBuf::from(answer)),
+        )?;
+        if directory != default {
+            args.push("--output".to_string());
+            args.push(directory.to_string_lossy().into_owned());
+        }
+    }
+
+    if ios && !given.background {
+        let color = wizard.ask(
+            "Background color iOS icons are flattened onto (CSS color)",
+            Some("#ffffff"),
+            |answer| match css_color::Srgb::from_str(answer) {
+                Ok(_) => Ok(answer.to_string()),
+                Err(_) => Err(format!("Not a CSS color: {answer}")),
+            },
+        )?;
+        if color != "#ffffff" {
+            args.push(format!("--background={color}"));
+        }
+    }
+
+    if !given.badge {
+        let question = format!("Release badge: none, {}", CHANNEL_NAMES.join(", "));
+        let channel = wizard.ask(&question, Some("none"), |answer| match answer {
+            "none" => Ok(None),
+            channel if CHANNEL_NAMES.contains(&channel) => Ok(Some(channel.to_string())),
+            _ => Err(format!("Unknown badge: {answer}")),
+        })?;
+        if let Some(channel) = channel {
+            args.push(format!("--channel={channel}"));
+        }
+    }
+    Ok(args)
+}
+
+/// The platform flags for an answer to the platforms question
+fn platform_flags(answer: &str) -> Result<Vec<String>, String> {
+    match answer {
+        "all" => Ok(Vec::new()),
+        "desktop" => Ok(vec!["--desktop-only".to_string()]),
+        "mobile" => Ok(vec!["--mobile-only".to_string()]),
+        list => list
+            .split(',')
+            .map(|name| {
+                Platform::from_str(name.trim())
+                    .map(|platform| format!("--{}", platform.name()))
+                    .map_err(|error| error.to_string())
+            })
+            .collect(),
+    }
+}
+
+struct Wizard<'a> {
+    input: &'a mut dyn BufRead,
+    output: &'a mut dyn Write,
+}
+
+impl Wizard<'_> {
+    /// Ask `question` until `parse` accepts the answer; an empty answer takes `default`
+    fn ask<T>(
+        &mut self,
+        question: &str,
+        default: Option<&str>,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> io::Result<T> {
+        loop {
+            match default {
+                Some(default) => write!(self.output, "{question} [{default}]: ")?,
+                None => write!(self.output, "{question}: ")?,
+            }
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("No answer to \"{question}\""),
+                ));
+            }
+            let answer = match (line.trim(), default) {
+                ("", Some(default)) => default,
+                ("", None) => continue,
+                (answer, _) => answer,
+            };
+            match parse(answer) {
+                Ok(value) => return Ok(value),
+                Err(message) => writeln!(self.output, "  {message}")?,
+            }
+        }
+    }
+}
+
+/// `args` as one shell command line, quoting the arguments that need it
+pub fn command_line(program: &str, args: &[String]) -> String {
+    let mut line = program.to_string();
+    for arg in args {
+        line.push(' ');
+        let plain = !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c));
+        if plain {
+            line.push_str(arg);
+        } else {
+            line.push_str(&format!("'{}'", arg.replace('\'', "'\\''")));
+        }
+    }
+    line
+}
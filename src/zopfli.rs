@@ -0,0 +1,661 @@
+//! Zopfli-style DEFLATE compression
+//!
+//! Trades a lot of CPU time for a smaller stream than zlib's best level: instead of
+//! greedy or lazy matching, the LZ77 parse is a shortest path over every possible
+//! match length, priced with a cost model taken from the previous pass' symbol
+//! statistics and repeated for a number of iterations. The output is an ordinary
+//! zlib stream that any inflater can read.
+
+/// Largest distance a DEFLATE match can reach back
+const WINDOW_SIZE: usize = 32768;
+
+/// Shortest and longest DEFLATE match lengths
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+/// Hash chain entries visited per position before giving up on longer matches
+const MAX_CHAIN_HITS: usize = 4096;
+
+const HASH_BITS: u32 = 16;
+
+/// Base match length and extra bits for length symbols 257..=285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distance and extra bits for distance symbols 0..=29
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Transmission order of the code length code lengths
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const END_OF_BLOCK: usize = 256;
+
+/// Compress data into a zlib stream (RFC 1950), running the given number of parse iterations
+pub fn compress_zlib(data: &[u8], iterations: usize) -> Vec<u8> {
+    // Deflate with a 32K window, flagged as maximum compression
+    let mut stream = vec![0x78, 0xda];
+    stream.extend(deflate(data, iterations));
+    stream.extend(adler32(data).to_be_bytes());
+    stream
+}
+
+/// Compress data into a raw DEFLATE stream (RFC 1951) made of a single final block
+pub fn deflate(data: &[u8], iterations: usize) -> Vec<u8> {
+    let matches = Matches::find(data);
+    let mut model = CostModel::fixed();
+    let mut best: Option<Vec<u8>> = None;
+
+    for _ in 0..iterations.max(1) {
+        let symbols = optimal_parse(data, &matches, &model);
+        for encoded in [encode_dynamic(&symbols), encode_fixed(&symbols)] {
+            if best.as_ref().is_none_or(|best| encoded.len() < best.len()) {
+                best = Some(encoded);
+            }
+        }
+
+        // The next pass prices symbols by how often this parse used them
+        model = CostModel::from_symbols(&symbols);
+    }
+
+    best.unwrap_or_default()
+}
+
+/// A parsed LZ77 symbol: a literal byte, or a match of `length` bytes `distance` back
+#[derive(Debug, Clone, Copy)]
+enum Symbol {
+    Literal(u8),
+    Match { length: u16, distance: u16 },
+}
+
+/// Every match available at each position, as runs of (longest length, distance)
+/// with both increasing: each length is reachable at the distance of the first run
+/// that covers it, which is the shortest distance offering that length
+struct Matches {
+    offsets: Vec<u32>,
+    runs: Vec<(u16, u16)>,
+}
+
+impl Matches {
+    fn find(data: &[u8]) -> Self {
+        let mut head = vec![u32::MAX; 1 << HASH_BITS];
+        let mut prev = vec![u32::MAX; data.len()];
+        let mut offsets = Vec::with_capacity(data.len() + 1);
+        let mut runs = Vec::new();
+
+        for i in 0..data.len() {
+            offsets.push(runs.len() as u32);
+            if i + MIN_MATCH > data.len() {
+                continue;
+            }
+
+            let hash = hash3(&data[i..]);
+            let limit = (data.len() - i).min(MAX_MATCH);
+            let mut longest = MIN_MATCH - 1;
+            let mut candidate = head[hash];
+            let mut hits = 0;
+
+            // The chain runs from the closest position outwards, so the first match of
+            // each length also has the shortest distance
+            while candidate != u32::MAX && hits < MAX_CHAIN_HITS {
+                let position = candidate as usize;
+                let distance = i - position;
+                if distance > WINDOW_SIZE {
+                    break;
+                }
+
+                if data[position + longest] == data[i + longest] {
+                    let length = data[position..]
+                        .iter()
+                        .zip(&data[i..i + limit])
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                    if length > longest {
+                        runs.push((length as u16, distance as u16));
+                        longest = length;
+                        if longest == limit {
+                            break;
+                        }
+                    }
+                }
+
+                candidate = prev[position];
+                hits += 1;
+            }
+
+            prev[i] = head[hash];
+            head[hash] = i as u32;
+        }
+        offsets.push(runs.len() as u32);
+
+        Self { offsets, runs }
+    }
+
+    fn at(&self, position: usize) -> &[(u16, u16)] {
+        &self.runs[self.offsets[position] as usize..self.offsets[position + 1] as usize]
+    }
+}
+
+fn hash3(bytes: &[u8]) -> usize {
+    let key = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+    (key.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+/// Estimated bit cost of each literal/length and distance symbol
+struct CostModel {
+    litlen: [f64; 286],
+    dist: [f64; 30],
+}
+
+impl CostModel {
+    /// Costs of the fixed Huffman codes, used before any statistics exist
+    fn fixed() -> Self {
+        let mut litlen = [0.0; 286];
+        for (symbol, cost) in litlen.iter_mut().enumerate() {
+            *cost = match symbol {
+                0..=143 => 8.0,
+                144..=255 => 9.0,
+                256..=279 => 7.0,
+                _ => 8.0,
+            };
+        }
+        Self {
+            litlen,
+            dist: [5.0; 30],
+        }
+    }
+
+    /// Costs as the entropy of each symbol in a previous parse
+    fn from_symbols(symbols: &[Symbol]) -> Self {
+        let (litlen_counts, dist_counts) = histogram(symbols);
+        let entropy = |counts: &[u32], costs: &mut [f64]| {
+            let total = counts.iter().map(|&count| f64::from(count)).sum::<f64>();
+            let log_total = total.max(1.0).log2();
+            for (cost, &count) in costs.iter_mut().zip(counts) {
+                // Unused symbols are priced as if they occurred once
+                *cost = log_total - f64::from(count.max(1)).log2();
+            }
+        };
+
+        let mut model = Self {
+            litlen: [0.0; 286],
+            dist: [0.0; 30],
+        };
+        entropy(&litlen_counts, &mut model.litlen);
+        entropy(&dist_counts, &mut model.dist);
+        model
+    }
+
+    fn literal(&self, byte: u8) -> f64 {
+        self.litlen[usize::from(byte)]
+    }
+
+    fn length_match(&self, length: usize, distance: usize) -> f64 {
+        let length_code = length_code(length);
+        let dist_code = dist_code(distance);
+        self.litlen[257 + length_code]
+            + f64::from(LENGTH_EXTRA[length_code])
+            + self.dist[dist_code]
+            + f64::from(DIST_EXTRA[dist_code])
+    }
+}
+
+/// Index into LENGTH_BASE for a match length
+fn length_code(length: usize) -> usize {
+    LENGTH_BASE.partition_point(|&base| usize::from(base) <= length) - 1
+}
+
+/// Index into DIST_BASE for a match distance
+fn dist_code(distance: usize) -> usize {
+    DIST_BASE.partition_point(|&base| usize::from(base) <= distance) - 1
+}
+
+/// Find the cheapest sequence of literals and matches under the cost model
+fn optimal_parse(data: &[u8], matches: &Matches, model: &CostModel) -> Vec<Symbol> {
+    let mut costs = vec![f64::INFINITY; data.len() + 1];
+    let mut steps = vec![(0u16, 0u16); data.len() + 1];
+    costs[0] = 0.0;
+
+    for i in 0..data.len() {
+        let cost = costs[i] + model.literal(data[i]);
+        if cost < costs[i + 1] {
+            costs[i + 1] = cost;
+            steps[i + 1] = (1, 0);
+        }
+
+        let mut relax = |length: usize, distance: u16| {
+            let cost = costs[i] + model.length_match(length, usize::from(distance));
+            if cost < costs[i + length] {
+                costs[i + length] = cost;
+                steps[i + length] = (length as u16, distance);
+            }
+        };
+
+        let runs = matches.at(i);
+        match runs.last() {
+            // Inside long repetitions every length is available, so only the longest is
+            // worth the time
+            Some(&(longest, distance)) if usize::from(longest) == MAX_MATCH => {
+                relax(MAX_MATCH, distance);
+            }
+            _ => {
+                let mut length = MIN_MATCH;
+                for &(longest, distance) in runs {
+                    while length <= usize::from(longest) {
+                        relax(length, distance);
+                        length += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut symbols = Vec::new();
+    let mut position = data.len();
+    while position > 0 {
+        let (length, distance) = steps[position];
+        if distance == 0 {
+            symbols.push(Symbol::Literal(data[position - 1]));
+            position -= 1;
+        } else {
+            symbols.push(Symbol::Match { length, distance });
+            position -= usize::from(length);
+        }
+    }
+    symbols.reverse();
+    symbols
+}
+
+/// Symbol counts for the literal/length and distance alphabets, including end-of-block
+fn histogram(symbols: &[Symbol]) -> ([u32; 286], [u32; 30]) {
+    let mut litlen = [0; 286];
+    let mut dist = [0; 30];
+    for symbol in symbols {
+        match *symbol {
+            Symbol::Literal(byte) => litlen[usize::from(byte)] += 1,
+            Symbol::Match { length, distance } => {
+                litlen[257 + length_code(usize::from(length))] += 1;
+                dist[dist_code(usize::from(distance))] += 1;
+            }
+        }
+    }
+    litlen[END_OF_BLOCK] += 1;
+    (litlen, dist)
+}
+
+/// Encode the symbols as a final block with the fixed Huffman codes
+fn encode_fixed(symbols: &[Symbol]) -> Vec<u8> {
+    let mut litlen_lengths = [0u8; 288];
+    for (symbol, length) in litlen_lengths.iter_mut().enumerate() {
+        *length = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+
+    let mut writer = BitWriter::default();
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+    write_symbols(
+        &mut writer,
+        symbols,
+        &canonical_codes(&litlen_lengths),
+        &canonical_codes(&[5; 30]),
+    );
+    writer.finish()
+}
+
+/// Encode the symbols as a final block with Huffman codes built from their statistics
+fn encode_dynamic(symbols: &[Symbol]) -> Vec<u8> {
+    let (litlen_counts, dist_counts) = histogram(symbols);
+    let litlen_lengths = huffman_lengths(&litlen_counts, 15);
+    let mut dist_lengths = huffman_lengths(&dist_counts, 15);
+
+    // Some decoders reject distance trees with fewer than two codes
+    if dist_lengths.iter().filter(|&&length| length > 0).count() < 2 {
+        dist_lengths[0] = 1;
+        dist_lengths[1] = 1;
+    }
+
+    let hlit = 257.max(last_used(&litlen_lengths));
+    let hdist = 1.max(last_used(&dist_lengths));
+
+    // Run-length encode the code lengths of both alphabets together
+    let lengths: Vec<u8> = litlen_lengths[..hlit]
+        .iter()
+        .chain(&dist_lengths[..hdist])
+        .copied()
+        .collect();
+    let mut runs: Vec<(usize, u32)> = Vec::new();
+    let mut i = 0;
+    while i < lengths.len() {
+        let value = lengths[i];
+        let count = lengths[i..]
+            .iter()
+            .take_while(|&&length| length == value)
+            .count();
+
+        if value == 0 && count >= 11 {
+            let count = count.min(138);
+            runs.push((18, (count - 11) as u32));
+            i += count;
+        } else if value == 0 && count >= 3 {
+            runs.push((17, (count - 3) as u32));
+            i += count;
+        } else if value != 0 && count >= 4 {
+            let count = count.min(7);
+            runs.push((usize::from(value), 0));
+            runs.push((16, (count - 4) as u32));
+            i += count;
+        } else {
+            runs.push((usize::from(value), 0));
+            i += 1;
+        }
+    }
+
+    let mut code_length_counts = [0u32; 19];
+    for &(symbol, _) in &runs {
+        code_length_counts[symbol] += 1;
+    }
+    let code_length_lengths = huffman_lengths(&code_length_counts, 7);
+    let code_length_codes = canonical_codes(&code_length_lengths);
+    let hclen = 4.max(
+        CODE_LENGTH_ORDER
+            .iter()
+            .rposition(|&symbol| code_length_lengths[symbol] > 0)
+            .map_or(0, |position| position + 1),
+    );
+
+    let mut writer = BitWriter::default();
+    writer.write_bits(1, 1);
+    writer.write_bits(2, 2);
+    writer.write_bits((hlit - 257) as u32, 5);
+    writer.write_bits((hdist - 1) as u32, 5);
+    writer.write_bits((hclen - 4) as u32, 4);
+    for &symbol in &CODE_LENGTH_ORDER[..hclen] {
+        writer.write_bits(u32::from(code_length_lengths[symbol]), 3);
+    }
+    for &(symbol, extra) in &runs {
+        writer.write_code(code_length_codes[symbol]);
+        match symbol {
+            16 => writer.write_bits(extra, 2),
+            17 => writer.write_bits(extra, 3),
+            18 => writer.write_bits(extra, 7),
+            _ => {}
+        }
+    }
+
+    write_symbols(
+        &mut writer,
+        symbols,
+        &canonical_codes(&litlen_lengths),
+        &canonical_codes(&dist_lengths),
+    );
+    writer.finish()
+}
+
+/// Number of entries up to and including the last nonzero length
+fn last_used(lengths: &[u8]) -> usize {
+    lengths
+        .iter()
+        .rposition(|&length| length > 0)
+        .map_or(0, |position| position + 1)
+}
+
+/// Write the compressed symbols and the end-of-block code
+fn write_symbols(
+    writer: &mut BitWriter,
+    symbols: &[Symbol],
+    litlen_codes: &[(u32, u32)],
+    dist_codes: &[(u32, u32)],
+) {
+    for symbol in symbols {
+        match *symbol {
+            Symbol::Literal(byte) => writer.write_code(litlen_codes[usize::from(byte)]),
+            Symbol::Match { length, distance } => {
+                let length = usize::from(length);
+                let length_code = length_code(length);
+                writer.write_code(litlen_codes[257 + length_code]);
+                writer.write_bits(
+                    (length - usize::from(LENGTH_BASE[length_code])) as u32,
+                    u32::from(LENGTH_EXTRA[length_code]),
+                );
+
+                let distance = usize::from(distance);
+                let dist_code = dist_code(distance);
+                writer.write_code(dist_codes[dist_code]);
+                writer.write_bits(
+                    (distance - usize::from(DIST_BASE[dist_code])) as u32,
+                    u32::from(DIST_EXTRA[dist_code]),
+                );
+            }
+        }
+    }
+    writer.write_code(litlen_codes[END_OF_BLOCK]);
+}
+
+/// Optimal code lengths of at most `max_bits` for the symbol counts, by package-merge
+/// The resulting code is always complete, as strict decoders such as zlib require
+fn huffman_lengths(counts: &[u32], max_bits: usize) -> Vec<u8> {
+    let mut lengths = vec![0u8; counts.len()];
+    let mut leaves: Vec<(u64, usize)> = counts
+        .iter()
+        .enumerate()
+        .filter(|&(_, &count)| count > 0)
+        .map(|(symbol, &count)| (u64::from(count), symbol))
+        .collect();
+
+    match leaves.len() {
+        0 => return lengths,
+        1 => {
+            // A lone code would be incomplete, so pair it with an unused symbol
+            let symbol = leaves[0].1;
+            lengths[symbol] = 1;
+            lengths[if symbol == 0 { 1 } else { 0 }] = 1;
+            return lengths;
+        }
+        _ => {}
+    }
+    leaves.sort_unstable();
+
+    let leaf_items: Vec<(u64, Vec<usize>)> = leaves
+        .iter()
+        .map(|&(weight, symbol)| (weight, vec![symbol]))
+        .collect();
+    let mut items = leaf_items.clone();
+    for _ in 1..max_bits {
+        let packages = items.chunks_exact(2).map(|pair| {
+            let symbols = pair[0].1.iter().chain(&pair[1].1).copied().collect();
+            (pair[0].0 + pair[1].0, symbols)
+        });
+
+        // Merge by weight, leaves first on ties
+        let mut merged = Vec::with_capacity(leaf_items.len() * 2);
+        let mut leaves = leaf_items.iter().cloned().peekable();
+        let mut packages = packages.peekable();
+        loop {
+            let take_leaf = match (leaves.peek(), packages.peek()) {
+                (Some(leaf), Some(package)) => leaf.0 <= package.0,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            if take_leaf {
+                merged.extend(leaves.next());
+            } else {
+                merged.extend(packages.next());
+            }
+        }
+        items = merged;
+    }
+
+    for (_, symbols) in items.iter().take(2 * leaves.len() - 2) {
+        for &symbol in symbols {
+            lengths[symbol] += 1;
+        }
+    }
+    lengths
+}
+
+/// Canonical Huffman codes for the code lengths, as (bit-reversed code, length) pairs
+/// ready to be written least significant bit first
+fn canonical_codes(lengths: &[u8]) -> Vec<(u32, u32)> {
+    let mut length_counts = [0u32; 16];
+    for &length in lengths {
+        length_counts[usize::from(length)] += 1;
+    }
+    length_counts[0] = 0;
+
+    let mut next_code = [0u32; 16];
+    let mut code = 0;
+    for bits in 1..16 {
+        code = (code + length_counts[bits - 1]) << 1;
+        next_code[bits] = code;
+    }
+
+    lengths
+        .iter()
+        .map(|&length| {
+            let length = u32::from(length);
+            if length == 0 {
+                return (0, 0);
+            }
+            let code = next_code[length as usize];
+            next_code[length as usize] += 1;
+            (code.reverse_bits() >> (32 - length), length)
+        })
+        .collect()
+}
+
+/// Writes bits least significant first, as DEFLATE packs them
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    pending: u64,
+    pending_bits: u32,
+}
+
+impl BitWriter {
+    fn write_bits(&mut self, value: u32, bits: u32) {
+        self.pending |= u64::from(value) << self.pending_bits;
+        self.pending_bits += bits;
+        while self.pending_bits >= 8 {
+            self.bytes.push(self.pending as u8);
+            self.pending >>= 8;
+            self.pending_bits -= 8;
+        }
+    }
+
+    fn write_code(&mut self, (code, bits): (u32, u32)) {
+        self.write_bits(code, bits);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.pending_bits > 0 {
+            self.bytes.push(self.pending as u8);
+        }
+        self.bytes
+    }
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the most bytes that can be summed before b could overflow
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(data: &[u8]) -> Vec<u8> {
+        let compressed = compress_zlib(data, 5);
+        let decompressed = miniz_oxide::inflate::decompress_to_vec_zlib(&compressed)
+            .expect("Stream should inflate");
+        assert_eq!(decompressed, data);
+        compressed
+    }
+
+    /// Deterministic bytes with some structure, like filtered image rows
+    fn sample_data(len: usize) -> Vec<u8> {
+        let mut state = 12345u32;
+        (0..len)
+            .map(|i| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                if i % 7 < 4 {
+                    (i / 64) as u8
+                } else {
+                    (state >> 24) as u8 & 0x1f
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trips() {
+        round_trip(&[]);
+        round_trip(&[42]);
+        round_trip(b"abcabcabcabcabcabc");
+        round_trip(&[0; 100_000]);
+        round_trip(&sample_data(50_000));
+        round_trip(&(0..=255).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_not_larger_than_zlib_best() {
+        let data = sample_data(50_000);
+        let zopfli = round_trip(&data);
+        let zlib = miniz_oxide::deflate::compress_to_vec_zlib(&data, 9);
+        assert!(
+            zopfli.len() <= zlib.len(),
+            "zopfli {} bytes vs zlib {} bytes",
+            zopfli.len(),
+            zlib.len()
+        );
+    }
+
+    #[test]
+    fn test_huffman_lengths_are_limited_and_complete() {
+        // Fibonacci counts would need codes longer than 7 bits without the limit
+        let mut counts = vec![1u32, 1];
+        while counts.len() < 19 {
+            counts.push(counts[counts.len() - 1] + counts[counts.len() - 2]);
+        }
+
+        let lengths = huffman_lengths(&counts, 7);
+        assert!(lengths.iter().all(|&length| (1..=7).contains(&length)));
+        let kraft: f64 = lengths
+            .iter()
+            .map(|&length| 0.5f64.powi(length.into()))
+            .sum();
+        assert!((kraft - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+}
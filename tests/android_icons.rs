@@ -0,0 +1,366 @@
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Test that `--android-legacy-shape square` renders ic_launcher.png on a rounded square
+/// with transparent corners, while the center keeps the opaque artwork.
+#[test]
+fn test_android_legacy_square_shape() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 512, 512);
+
+    let output_dir = temp_path.join("test_output");
+    let binary_path = get_icon_gen_binary_path();
+
+    let output = Command::new(&binary_path)
+        .arg(&source_path)
+        .arg("--android")
+        .arg("--android-legacy-shape")
+        .arg("square")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --android-legacy-shape failed");
+    }
+
+    let icon_path = output_dir
+        .join("android")
+        .join("mipmap-xxxhdpi")
+        .join("ic_launcher.png");
+    let icon = image::open(&icon_path)
+        .expect("Failed to load legacy icon")
+        .to_rgba8();
+
+    assert_eq!(icon.width(), 192);
+
+    // The corners sit outside the 38dp shape and must stay transparent
+    assert_eq!(
+        icon.get_pixel(0, 0)[3],
+        0,
+        "Top-left corner should be transparent"
+    );
+    assert_eq!(
+        icon.get_pixel(191, 0)[3],
+        0,
+        "Top-right corner should be transparent"
+    );
+
+    // The center holds the opaque artwork
+    assert_eq!(icon.get_pixel(96, 96)[3], 255, "Center should be opaque");
+
+    // Round icons use the circle shape, so a point just inside the square corner is clear
+    let round_path = output_dir
+        .join("android")
+        .join("mipmap-xxxhdpi")
+        .join("ic_launcher_round.png");
+    let round = image::open(&round_path)
+        .expect("Failed to load round icon")
+        .to_rgba8();
+    assert!(
+        round.get_pixel(24, 24)[3] < 128,
+        "Round legacy icon should be clipped to a circle"
+    );
+}
+
+/// Test that round icons get a symmetric, anti-aliased circular edge
+#[test]
+fn test_android_round_icon_antialiasing() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 512, 512);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--android-round")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(
+        output.status.success(),
+        "icon-gen --android-round failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // mdpi is 48px, the size where a rough edge shows the most
+    let round = image::open(
+        output_dir
+            .join("android")
+            .join("mipmap-mdpi")
+            .join("ic_launcher_round.png"),
+    )
+    .expect("Failed to load round icon")
+    .to_rgba8();
+    assert_eq!(round.width(), 48);
+
+    assert_eq!(
+        round.get_pixel(0, 0)[3],
+        0,
+        "Corners are outside the circle"
+    );
+    assert_eq!(round.get_pixel(24, 24)[3], 255, "Center is opaque");
+
+    // The mask is centered, so it mirrors across both axes
+    for y in 0..48 {
+        for x in 0..48 {
+            let alpha = round.get_pixel(x, y)[3];
+            assert_eq!(
+                alpha,
+                round.get_pixel(47 - x, y)[3],
+                "Asymmetric at {x},{y}"
+            );
+            assert_eq!(
+                alpha,
+                round.get_pixel(x, 47 - y)[3],
+                "Asymmetric at {x},{y}"
+            );
+        }
+    }
+
+    // Edge pixels carry partial coverage in many steps rather than a hard cut
+    let partial_alphas: std::collections::BTreeSet<u8> = round
+        .pixels()
+        .map(|pixel| pixel[3])
+        .filter(|&alpha| alpha > 0 && alpha < 255)
+        .collect();
+    assert!(
+        partial_alphas.len() >= 6,
+        "Expected a graded edge, found alphas {partial_alphas:?}"
+    );
+
+    // The total coverage matches the area of the circle
+    let area: f32 = round.pixels().map(|pixel| pixel[3] as f32 / 255.0).sum();
+    let expected = std::f32::consts::PI * 24.0 * 24.0;
+    assert!(
+        (area - expected).abs() < expected * 0.01,
+        "Coverage {area} should be close to the circle area {expected}"
+    );
+}
+
+/// Test that an unknown legacy shape is rejected
+#[test]
+fn test_android_legacy_shape_rejects_unknown() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 64, 64);
+
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--android")
+        .arg("--android-legacy-shape")
+        .arg("hexagon")
+        .arg("-o")
+        .arg(temp_path.join("test_output"))
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    assert!(!output.status.success(), "Unknown shape should fail");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown legacy shape"));
+}
+
+/// Test that an invalid adaptive icon background fails instead of filling with white
+#[test]
+fn test_android_adaptive_bg_rejects_invalid() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 64, 64);
+
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--android", "--android-adaptive"])
+        .args(["--android-adaptive-bg", "#12345g"])
+        .arg("-o")
+        .arg(temp_path.join("test_output"))
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    assert!(!output.status.success(), "Invalid color should fail");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Invalid --android-adaptive-bg: #12345g")
+    );
+}
+
+/// Test that `--android-splash` writes 288dp splash drawables with the icon in the
+/// 192dp safe zone and a theme snippet using the splash background color.
+#[test]
+fn test_android_splash_screen() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--android-splash")
+        .arg("--splash-bg")
+        .arg("#336699")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --android-splash failed");
+    }
+
+    let android_dir = output_dir.join("android");
+    for (density, size) in [("mdpi", 288), ("xhdpi", 576), ("xxxhdpi", 1152)] {
+        let icon = image::open(
+            android_dir
+                .join(format!("drawable-{density}"))
+                .join("splash_icon.png"),
+        )
+        .expect("Splash icon should exist")
+        .to_rgba8();
+        assert_eq!(icon.dimensions(), (size, size));
+
+        // Outside the safe zone is transparent, the center holds the artwork
+        assert_eq!(icon.get_pixel(size / 8, size / 2)[3], 0);
+        assert_eq!(icon.get_pixel(size / 2, size / 2)[3], 255);
+    }
+
+    let splash_xml = std::fs::read_to_string(android_dir.join("values").join("splash.xml"))
+        .expect("values/splash.xml should exist");
+    assert!(splash_xml.contains("#336699"));
+    assert!(splash_xml.contains("windowSplashScreenAnimatedIcon"));
+    assert!(splash_xml.contains("@drawable/splash_icon"));
+
+    // A typo fails instead of writing #FFFFFF into colors.xml
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--android-splash", "--splash-bg", "#33669"])
+        .arg("-o")
+        .arg(temp_path.join("invalid_output"))
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid --splash-bg: #33669"));
+}
+
+/// Creates an opaque test image with a gradient
+/// Test that `--safe-zone warn` reports artwork reaching past the adaptive icon
+/// safe zone, and that `fix` scales it into the central 66dp circle
+#[test]
+fn test_adaptive_icon_safe_zone() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    // A tile that nearly fills the canvas, with a transparent margin
+    let source_path = temp_path.join("test_source.png");
+    let source = RgbaImage::from_fn(256, 256, |x, y| {
+        if (8..248).contains(&x) && (8..248).contains(&y) {
+            Rgba([30, 90, 200, 255])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    });
+    source
+        .save(&source_path)
+        .expect("Failed to save test image");
+
+    let run = |mode: &str| {
+        let output_dir = temp_path.join(mode);
+        let output = Command::new(get_icon_gen_binary_path())
+            .arg(&source_path)
+            .args(["--android", "--android-adaptive", "--safe-zone", mode])
+            .arg("-o")
+            .arg(&output_dir)
+            .output()
+            .expect("Failed to run icon-gen command");
+        assert!(
+            output.status.success(),
+            "icon-gen --safe-zone {mode} failed"
+        );
+        let foreground = image::open(
+            output_dir
+                .join("android")
+                .join("mipmap-mdpi")
+                .join("ic_launcher_foreground.png"),
+        )
+        .unwrap()
+        .to_rgba8();
+        (
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+            foreground,
+        )
+    };
+
+    let (stderr, warned) = run("warn");
+    assert!(
+        stderr.contains("of the artwork lies outside the Android adaptive icon safe zone"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("--safe-zone fix"));
+
+    // The 108px layer has a 33px safe zone radius around its center
+    let outside_circle = |icon: &RgbaImage| {
+        icon.enumerate_pixels()
+            .filter(|(x, y, pixel)| {
+                let (dx, dy) = (*x as f32 + 0.5 - 54.0, *y as f32 + 0.5 - 54.0);
+                pixel[3] > 128 && dx.hypot(dy) > 34.0
+            })
+            .count()
+    };
+    assert!(outside_circle(&warned) > 0);
+
+    let (stderr, fixed) = run("fix");
+    assert!(!stderr.contains("Warning"), "{stderr}");
+    assert_eq!(outside_circle(&fixed), 0);
+    assert_eq!(fixed.get_pixel(54, 54)[3], 255);
+}
+
+fn create_test_image(path: &Path, width: u32, height: u32) {
+    let mut image = RgbaImage::new(width, height);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let red = (255.0 * x as f32 / width as f32) as u8;
+        let green = (255.0 * y as f32 / height as f32) as u8;
+        *pixel = Rgba([red, green, 128, 255]);
+    }
+
+    image.save(path).expect("Failed to save test image");
+}
+
+/// Gets the path to the icon-gen binary
+fn get_icon_gen_binary_path() -> std::path::PathBuf {
+    // First try to find in target/debug
+    let debug_path = std::path::Path::new("target/debug/icon-gen");
+    if debug_path.exists() {
+        return debug_path.to_path_buf();
+    }
+
+    // If not found, build it first
+    let build_output = Command::new("cargo")
+        .args(["build", "--bin", "icon-gen"])
+        .output()
+        .expect("Failed to run cargo build");
+
+    if !build_output.status.success() {
+        panic!(
+            "Failed to build icon-gen binary: {}",
+            String::from_utf8_lossy(&build_output.stderr)
+        );
+    }
+
+    debug_path.to_path_buf()
+}
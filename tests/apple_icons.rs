@@ -0,0 +1,725 @@
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Test that `--imessage` writes every Messages extension slot at its exact
+/// non-square pixel size, and that Contents.json lists each file.
+#[test]
+fn test_imessage_icon_set() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 512, 512);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--imessage")
+        .arg("--ios-color")
+        .arg("#ff0000")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --imessage failed");
+    }
+
+    let imessage_dir = output_dir.join("imessage");
+    for (filename, width, height) in [
+        ("iMessage-27x20@2x.png", 54, 40),
+        ("iMessage-32x24@3x.png", 96, 72),
+        ("iMessage-60x45@3x.png", 180, 135),
+        ("iMessage-67x50@2x.png", 134, 100),
+        ("iMessage-74x55@2x.png", 148, 110),
+        ("iMessage-1024x768.png", 1024, 768),
+        ("iMessage-1024x1024.png", 1024, 1024),
+    ] {
+        let icon = image::open(imessage_dir.join(filename))
+            .unwrap_or_else(|_| panic!("{} should exist", filename));
+        assert_eq!(
+            (icon.width(), icon.height()),
+            (width, height),
+            "{}",
+            filename
+        );
+    }
+
+    // The sides of a 4:3 slot are filled with the iOS background color
+    let wide = image::open(imessage_dir.join("iMessage-1024x768.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(*wide.get_pixel(0, 384), Rgba([255, 0, 0, 255]));
+
+    let contents: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(imessage_dir.join("Contents.json"))
+            .expect("imessage/Contents.json should exist"),
+    )
+    .expect("Contents.json should be valid JSON");
+
+    let images = contents["images"].as_array().unwrap();
+    for image in images {
+        let filename = image["filename"].as_str().unwrap();
+        assert!(
+            imessage_dir.join(filename).exists(),
+            "{} is missing",
+            filename
+        );
+    }
+    assert!(images
+        .iter()
+        .any(|i| i["size"] == "27x20" && i["idiom"] == "universal" && i["platform"] == "ios"));
+    assert!(images
+        .iter()
+        .any(|i| i["size"] == "1024x768" && i["idiom"] == "ios-marketing"));
+
+    // Messages icons alone do not trigger the full set
+    assert!(!output_dir.join("ios").exists());
+}
+
+/// Test that `--app-clip` writes an opaque 1024 icon and a single universal
+/// appiconset entry, with transparent source pixels filled by `--ios-color`.
+#[test]
+fn test_app_clip_icon() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    let mut source = RgbaImage::new(256, 256);
+    source.put_pixel(128, 128, Rgba([0, 0, 255, 255]));
+    source.save(&source_path).unwrap();
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--app-clip")
+        .arg("--ios-color")
+        .arg("#00ff00")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --app-clip failed");
+    }
+
+    let app_clip_dir = output_dir.join("app-clip");
+    let icon = image::open(app_clip_dir.join("AppClipIcon-1024x1024.png"))
+        .expect("App Clip icon should exist")
+        .to_rgba8();
+    assert_eq!(icon.dimensions(), (1024, 1024));
+    assert_eq!(*icon.get_pixel(0, 0), Rgba([0, 255, 0, 255]));
+
+    let contents: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(app_clip_dir.join("Contents.json"))
+            .expect("app-clip/Contents.json should exist"),
+    )
+    .expect("Contents.json should be valid JSON");
+    let images = contents["images"].as_array().unwrap();
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0]["filename"], "AppClipIcon-1024x1024.png");
+    assert_eq!(images[0]["idiom"], "universal");
+    assert_eq!(images[0]["platform"], "ios");
+    assert_eq!(images[0]["size"], "1024x1024");
+}
+
+/// Test that `--ios-splash` renders each launch slot at its device resolution
+/// with the icon centered on `--splash-bg`.
+#[test]
+fn test_ios_splash_launch_images() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--ios-splash")
+        .arg("--splash-bg")
+        .arg("#102030")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --ios-splash failed");
+    }
+
+    let launch_dir = output_dir
+        .join("ios-splash")
+        .join("LaunchImage.launchimage");
+    let splash = image::open(launch_dir.join("LaunchImage-1125x2436-portrait.png"))
+        .expect("iPhone X launch image should exist")
+        .to_rgba8();
+    assert_eq!(splash.dimensions(), (1125, 2436));
+    assert_eq!(*splash.get_pixel(0, 0), Rgba([16, 32, 48, 255]));
+    assert_ne!(*splash.get_pixel(562, 1218), Rgba([16, 32, 48, 255]));
+
+    let contents: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(launch_dir.join("Contents.json"))
+            .expect("LaunchImage Contents.json should exist"),
+    )
+    .expect("Contents.json should be valid JSON");
+    let images = contents["images"].as_array().unwrap();
+    for image in images {
+        assert_eq!(image["extent"], "full-screen");
+        let filename = image["filename"].as_str().unwrap();
+        assert!(
+            launch_dir.join(filename).exists(),
+            "{} is missing",
+            filename
+        );
+    }
+    assert!(images.iter().any(|i| i["subtype"] == "2436h"
+        && i["orientation"] == "landscape"
+        && i["minimum-system-version"] == "11.0"));
+
+    // A typo fails instead of filling the launch images with white
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--ios-splash", "--splash-bg", "navyblue"])
+        .arg("-o")
+        .arg(temp_path.join("invalid_output"))
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid --splash-bg: navyblue"));
+}
+
+/// Test that `--ios-squircle` writes masked previews and an apple-touch-icon
+/// alongside the iOS icon set, with the corners cut away by the superellipse.
+#[test]
+fn test_ios_squircle_previews() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--ios")
+        .arg("--ios-squircle")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --ios-squircle failed");
+    }
+
+    for size in [120, 180, 512, 1024] {
+        let path = output_dir
+            .join("ios-preview")
+            .join(format!("AppIcon-{size}x{size}.png"));
+        let preview = image::open(&path)
+            .expect("Squircle preview should exist")
+            .to_rgba8();
+        assert_eq!(preview.dimensions(), (size, size));
+        assert_eq!(preview.get_pixel(0, 0)[3], 0, "Corner should be masked");
+        assert_eq!(
+            preview.get_pixel(size / 2, 1)[3],
+            255,
+            "Edge should stay opaque"
+        );
+        assert_eq!(preview.get_pixel(size / 2, size / 2)[3], 255);
+    }
+
+    // Along the diagonal the outline sits about 13% in from the corner
+    let preview = image::open(output_dir.join("ios-preview").join("AppIcon-1024x1024.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(preview.get_pixel(40, 40)[3], 0);
+    assert_eq!(preview.get_pixel(80, 80)[3], 255);
+
+    let touch_icon = image::open(output_dir.join("web").join("apple-touch-icon.png"))
+        .expect("apple-touch-icon.png should exist");
+    assert_eq!(touch_icon.width(), 180);
+
+    // The asset catalog icons themselves stay square and opaque
+    let app_icon = image::open(output_dir.join("ios").join("AppIcon-1024x1024.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(app_icon.get_pixel(0, 0)[3], 255);
+}
+
+#[test]
+fn test_opaque_targets_flatten_transparency() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    // Transparent left half, at full size so that nothing is upscaled and only
+    // the transparency is warned about
+    let source_path = temp_path.join("test_source.png");
+    let source = RgbaImage::from_fn(1024, 1024, |x, _| {
+        if x < 512 {
+            Rgba([0, 0, 0, 0])
+        } else {
+            Rgba([200, 100, 64, 255])
+        }
+    });
+    source
+        .save(&source_path)
+        .expect("Failed to save test image");
+
+    // Without iOS in --background-for the transparency reaches the opaque-only targets
+    let output_dir = temp_path.join("warned");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--ios", "--app-clip", "--background", "#00ff00"])
+        .args(["--background-for", "windows"])
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(output.status.success(), "icon-gen --ios --app-clip failed");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Warning"), "Expected a warning: {stderr}");
+    assert!(stderr.contains("iOS app icons"));
+    assert!(stderr.contains("App Clip icons"));
+
+    for path in [
+        output_dir.join("ios").join("AppIcon-1024x1024.png"),
+        output_dir
+            .join("app-clip")
+            .join("AppClipIcon-1024x1024.png"),
+    ] {
+        let icon = image::open(&path).unwrap().to_rgba8();
+        assert_eq!(*icon.get_pixel(10, 512), Rgba([0, 255, 0, 255]));
+        assert!(icon.pixels().all(|pixel| pixel[3] == 255));
+    }
+
+    // --flatten removes the transparency up front, for every target
+    let output_dir = temp_path.join("flattened");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args([
+            "--ios",
+            "--app-clip",
+            "--flatten",
+            "--background",
+            "#00ff00",
+        ])
+        .args(["--linux", "-p", "64"])
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(output.status.success(), "icon-gen --flatten failed");
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Warning"));
+
+    let icon = image::open(output_dir.join("64x64.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(*icon.get_pixel(5, 32), Rgba([0, 255, 0, 255]));
+}
+
+/// Creates an opaque test image with a gradient
+/// Test that `--ios-layout xcassets` nests the app icons in an asset catalog
+/// that Xcode can take as is, with every Contents.json file name present.
+#[test]
+fn test_ios_layout_asset_catalog() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 512, 512);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--ios")
+        .arg("--ios-layout")
+        .arg("xcassets")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --ios-layout failed");
+    }
+
+    let catalog = output_dir.join("ios").join("Assets.xcassets");
+    let appiconset = catalog.join("AppIcon.appiconset");
+    assert!(!output_dir.join("ios").join("Contents.json").exists());
+
+    // The catalog root has nothing but its info
+    let root: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(catalog.join("Contents.json"))
+            .expect("Assets.xcassets/Contents.json should exist"),
+    )
+    .unwrap();
+    assert!(root.get("images").is_none());
+    assert_eq!(root["info"]["version"], 1);
+
+    let contents: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(appiconset.join("Contents.json"))
+            .expect("AppIcon.appiconset/Contents.json should exist"),
+    )
+    .unwrap();
+    let mut listed = contents["images"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|image| image["filename"].as_str())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    let mut written = std::fs::read_dir(&appiconset)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".png"))
+        .collect::<Vec<_>>();
+    listed.sort();
+    listed.dedup();
+    written.sort();
+    assert_eq!(listed, written);
+    assert!(written.contains(&"AppIcon-1024x1024.png".to_string()));
+
+    // An unknown layout fails before anything is written
+    let rejected = temp_path.join("rejected");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--ios")
+        .arg("--ios-layout")
+        .arg("nested")
+        .arg("-o")
+        .arg(&rejected)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Unknown iOS layout: nested. Available layouts: flat, appiconset, xcassets"));
+    assert!(!rejected.join("ios").exists());
+}
+
+/// Test that the iOS Contents.json has every iPhone and iPad slot Xcode shows,
+/// each naming a file of its exact pixel size
+#[test]
+fn test_ios_idiom_matrix() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 1024, 1024);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--ios")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(output.status.success(), "icon-gen --ios failed");
+
+    let ios_dir = output_dir.join("ios");
+    let contents: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(ios_dir.join("Contents.json")).unwrap())
+            .unwrap();
+    let mut slots = contents["images"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|image| {
+            let size = image["size"].as_str().unwrap();
+            let scale = image["scale"].as_str().unwrap();
+            let points = size.split('x').next().unwrap().parse::<f32>().unwrap();
+            let expected = (points * scale.trim_end_matches('x').parse::<f32>().unwrap()) as u32;
+            let filename = image["filename"].as_str().unwrap();
+            let icon = image::open(ios_dir.join(filename))
+                .unwrap_or_else(|_| panic!("{filename} should exist"));
+            assert_eq!(icon.width(), expected, "{filename}");
+            format!("{} {size}@{scale}", image["idiom"].as_str().unwrap())
+        })
+        .collect::<Vec<_>>();
+    slots.sort();
+
+    let mut expected = [
+        "iphone 20x20@2x",
+        "iphone 20x20@3x",
+        "iphone 29x29@2x",
+        "iphone 29x29@3x",
+        "iphone 40x40@2x",
+        "iphone 40x40@3x",
+        "iphone 60x60@2x",
+        "iphone 60x60@3x",
+        "ipad 20x20@1x",
+        "ipad 20x20@2x",
+        "ipad 29x29@1x",
+        "ipad 29x29@2x",
+        "ipad 40x40@1x",
+        "ipad 40x40@2x",
+        "ipad 76x76@1x",
+        "ipad 76x76@2x",
+        "ipad 83.5x83.5@2x",
+        "ios-marketing 1024x1024@1x",
+    ];
+    expected.sort();
+    assert_eq!(slots, expected);
+
+    // The iPad Pro icon is named after its 83.5pt size, not a rounded one
+    let ipad_pro = image::open(ios_dir.join("AppIcon-83.5x83.5@2x.png")).unwrap();
+    assert_eq!(ipad_pro.width(), 167);
+    assert!(!ios_dir.join("AppIcon-83x83@2x.png").exists());
+
+    // A file that iPhone and iPad slots share is written once, leaving no temporary files
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.matches("AppIcon-29x29@2x.png").count(),
+        1,
+        "{stdout}"
+    );
+    for entry in std::fs::read_dir(&ios_dir).unwrap() {
+        let name = entry.unwrap().file_name();
+        assert!(
+            !name.to_string_lossy().ends_with(".icon-gen-tmp"),
+            "{name:?}"
+        );
+    }
+}
+
+/// Test that `--contents-json merge` updates the entries of an existing
+/// Contents.json and keeps what the tool doesn't generate
+#[test]
+fn test_contents_json_merge() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 1024, 1024);
+
+    let ios_dir = temp_path.join("test_output").join("ios");
+    std::fs::create_dir_all(&ios_dir).unwrap();
+    std::fs::write(
+        ios_dir.join("Contents.json"),
+        r#"{
+  "images": [
+    {"filename": "old-60.png", "idiom": "iphone", "scale": "2x", "size": "60x60"},
+    {"appearances": [{"appearance": "luminosity", "value": "dark"}],
+     "filename": "dark.png", "idiom": "universal", "platform": "ios", "size": "1024x1024"}
+  ],
+  "info": {"version": 1, "author": "xcode"},
+  "properties": {"pre-rendered": true}
+}"#,
+    )
+    .unwrap();
+
+    let run = |mode: &str| {
+        Command::new(get_icon_gen_binary_path())
+            .arg(&source_path)
+            .args(["--ios", "--contents-json", mode])
+            .arg("-o")
+            .arg(temp_path.join("test_output"))
+            .output()
+            .expect("Failed to run icon-gen command")
+    };
+
+    // Replacing a Contents.json the tool didn't write is refused
+    let output = run("replace");
+    assert!(!output.status.success());
+
+    let output = run("merge");
+    if !output.status.success() {
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --contents-json merge failed");
+    }
+    let contents: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(ios_dir.join("Contents.json")).unwrap())
+            .unwrap();
+    let images = contents["images"].as_array().unwrap();
+    assert_eq!(images[0]["filename"], "AppIcon-60x60@2x.png");
+    assert_eq!(images[1]["filename"], "dark.png");
+    assert_eq!(images[1]["appearances"][0]["value"], "dark");
+    assert!(images.len() > 2);
+    assert_eq!(contents["info"]["author"], "xcode");
+    assert_eq!(contents["properties"]["pre-rendered"], true);
+
+    let output = run("combine");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Unknown Contents.json mode: combine. Available modes: replace, merge"));
+}
+
+/// Test that regenerating into an existing asset catalog, even with --clean,
+/// keeps the files icon-gen didn't generate and leaves them out of its cache
+#[test]
+fn test_existing_asset_catalog_files_kept() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 1024, 1024);
+
+    let output_dir = temp_path.join("MyApp");
+    let catalog = output_dir.join("ios").join("Assets.xcassets");
+    let dark_icon = catalog.join("AppIcon.appiconset").join("AppIcon-Dark.png");
+    let logo = catalog.join("Logo.imageset").join("Logo.png");
+    for path in [&dark_icon, &logo] {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"from the project").unwrap();
+    }
+
+    for extra in [&[][..], &["--clean"][..]] {
+        let output = Command::new(get_icon_gen_binary_path())
+            .arg(&source_path)
+            .args(["--ios", "--ios-layout", "xcassets"])
+            .args(["--contents-json", "merge"])
+            .args(extra)
+            .arg("-o")
+            .arg(&output_dir)
+            .output()
+            .expect("Failed to run icon-gen command");
+        if !output.status.success() {
+            eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+            panic!("icon-gen command into an asset catalog failed");
+        }
+        assert!(!String::from_utf8_lossy(&output.stdout).contains("stale"));
+    }
+
+    assert_eq!(std::fs::read(&dark_icon).unwrap(), b"from the project");
+    assert_eq!(std::fs::read(&logo).unwrap(), b"from the project");
+    let cache = std::fs::read_to_string(output_dir.join(".icon-gen-cache.json")).unwrap();
+    assert!(cache.contains("AppIcon-60x60@2x.png"));
+    assert!(!cache.contains("AppIcon-Dark.png"));
+    assert!(!cache.contains("Logo.png"));
+}
+
+/// Test that the PNG passes over a set written into an existing asset catalog
+/// leave the catalog's own PNGs as they were
+#[test]
+fn test_existing_asset_catalog_pngs_not_rewritten() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 1024, 1024);
+
+    let output_dir = temp_path.join("MyApp");
+    let appiconset = output_dir.join("ios/Assets.xcassets/AppIcon.appiconset");
+    let dark_icon = appiconset.join("AppIcon-Dark.png");
+    std::fs::create_dir_all(&appiconset).unwrap();
+    create_test_image(&dark_icon, 32, 32);
+    let dark = std::fs::read(&dark_icon).unwrap();
+    let broken = appiconset.join("AppIcon-Tinted.png");
+    std::fs::write(&broken, b"from the project").unwrap();
+
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--ios", "--ios-layout", "xcassets"])
+        .args(["--contents-json", "merge"])
+        .args(["--quantize", "16", "--optimize"])
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    if !output.status.success() {
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen --quantize --optimize into an asset catalog failed");
+    }
+
+    assert_eq!(std::fs::read(&dark_icon).unwrap(), dark);
+    assert_eq!(std::fs::read(&broken).unwrap(), b"from the project");
+}
+
+/// Test that the icons App Store validation checks are written without an alpha
+/// channel, including the palette PNGs of `--quantize`
+#[test]
+fn test_apple_icons_without_alpha() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    // Transparent corners around an opaque disc
+    let source_path = temp_path.join("test_source.png");
+    let source = RgbaImage::from_fn(256, 256, |x, y| {
+        let (dx, dy) = (x as i32 - 128, y as i32 - 128);
+        if dx * dx + dy * dy < 100 * 100 {
+            Rgba([30, 90, 200, 255])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    });
+    source
+        .save(&source_path)
+        .expect("Failed to save test image");
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--ios", "--app-clip", "--imessage", "--quantize", "64"])
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(output.status.success(), "icon-gen --ios --imessage failed");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("iMessage app icons"));
+
+    let mut checked = 0;
+    for folder in ["ios", "app-clip", "imessage"] {
+        for entry in std::fs::read_dir(output_dir.join(folder)).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "png") {
+                let icon = image::open(&path).unwrap();
+                assert!(!icon.color().has_alpha(), "{} has alpha", path.display());
+                checked += 1;
+            }
+        }
+    }
+    assert_eq!(checked, 15 + 1 + 12);
+}
+
+fn create_test_image(path: &Path, width: u32, height: u32) {
+    let mut image = RgbaImage::new(width, height);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let red = (255.0 * x as f32 / width as f32) as u8;
+        let green = (255.0 * y as f32 / height as f32) as u8;
+        *pixel = Rgba([red, green, 128, 255]);
+    }
+
+    image.save(path).expect("Failed to save test image");
+}
+
+/// Gets the path to the icon-gen binary
+fn get_icon_gen_binary_path() -> std::path::PathBuf {
+    // First try to find in target/debug
+    let debug_path = std::path::Path::new("target/debug/icon-gen");
+    if debug_path.exists() {
+        return debug_path.to_path_buf();
+    }
+
+    // If not found, build it first
+    let build_output = Command::new("cargo")
+        .args(["build", "--bin", "icon-gen"])
+        .output()
+        .expect("Failed to run cargo build");
+
+    if !build_output.status.success() {
+        panic!(
+            "Failed to build icon-gen binary: {}",
+            String::from_utf8_lossy(&build_output.stderr)
+        );
+    }
+
+    debug_path.to_path_buf()
+}
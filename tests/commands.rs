@@ -0,0 +1,463 @@
+use image::{Rgba, RgbaImage};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+
+/// Test that `icon-gen completions` prints a script covering the options and commands for every shell
+#[test]
+fn test_completions() {
+    for shell in ["bash", "zsh", "fish", "powershell"] {
+        let output = run_icon_gen(&["completions", shell]);
+        assert!(output.status.success(), "completions {shell} failed");
+        let script = String::from_utf8_lossy(&output.stdout);
+        assert!(script.contains("name-template"), "{shell}: {script}");
+        assert!(script.contains("completions"), "{shell}: {script}");
+        assert!(script.contains("powershell"), "{shell}: {script}");
+    }
+
+    let bash = run_icon_gen(&["completions", "bash"]);
+    let bash = String::from_utf8_lossy(&bash.stdout);
+    assert!(bash.contains("complete -F _icon_gen"));
+    assert!(bash.contains("-o|--output)\n            COMPREPLY=($(compgen -d"));
+
+    let output = run_icon_gen(&["completions", "tcsh"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("possible values: bash"));
+}
+
+/// Test that `icon-gen man` prints a roff man page listing every option
+#[test]
+fn test_man_page() {
+    let output = run_icon_gen(&["man"]);
+    assert!(output.status.success());
+    let page = String::from_utf8_lossy(&output.stdout);
+    assert!(page.starts_with(".TH ICON\\-GEN 1"), "{page}");
+    for section in [".SH NAME", ".SH SYNOPSIS", ".SH OPTIONS", ".SH COMMANDS"] {
+        assert!(page.contains(section), "missing {section}");
+    }
+    assert!(page.contains("\\fB\\-o\\fR, \\fB\\-\\-output\\fR \\fIDIR\\fR"));
+    assert!(page.contains("\\fB\\-\\-quantize\\fR [\\fICOLORS\\fR]"));
+}
+
+/// Test that `--interactive` asks again after invalid answers, prints the equivalent command and runs it
+#[test]
+fn test_interactive() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let source_path = temp_dir.path().join("logo.png");
+    RgbaImage::from_pixel(64, 64, Rgba([30, 90, 200, 255]))
+        .save(&source_path)
+        .unwrap();
+
+    let mut child = Command::new(get_icon_gen_binary_path())
+        .args(["--interactive", "--no-config"])
+        .current_dir(temp_dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start icon-gen");
+    // Source, platforms (one unknown), output, iOS background (one invalid), badge
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(
+            b"missing.png\nlogo.png\nwindows,bogus\nwindows,ios\nicons\nnot-a-color\n#102030\n\n",
+        )
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success(), "{stderr}");
+    assert!(stderr.contains("No such file: missing.png"));
+    assert!(stderr.contains("Unknown platform: bogus"));
+    assert!(stderr.contains("Not a CSS color: not-a-color"));
+    assert!(stderr.contains(
+        "icon-gen logo.png --windows --ios --output icons '--background=#102030' --no-config"
+    ));
+    let output_dir = temp_dir.path().join("icons");
+    assert!(output_dir.join("windows/icon.ico").exists());
+    assert!(output_dir.join("ios/Contents.json").exists());
+    assert!(!output_dir.join("android").exists());
+
+    // Without answers the wizard stops instead of guessing
+    let output = Command::new(get_icon_gen_binary_path())
+        .args(["--interactive", "--no-config", "logo.png"])
+        .current_dir(temp_dir.path())
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No answer"));
+}
+
+/// Test that `icon-gen run` generates every job of a job file over its defaults
+#[test]
+fn test_run_jobs() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    RgbaImage::from_pixel(64, 64, Rgba([0, 0, 0, 0]))
+        .save(temp_path.join("logo.png"))
+        .unwrap();
+    let jobs_path = temp_path.join("jobs.yaml");
+    std::fs::write(
+        &jobs_path,
+        r##"# One set per brand
+defaults:
+  png: [32]
+  background-for: [linux]
+jobs:
+  - name: acme
+    input: logo.png
+    output: acme
+    background: "#ff0000"
+  - input: logo.png
+    output: globex
+    background: '#0000ff'
+"##,
+    )
+    .unwrap();
+
+    let run = || {
+        Command::new(get_icon_gen_binary_path())
+            .args(["run", "jobs.yaml"])
+            .current_dir(temp_path)
+            .output()
+            .unwrap()
+    };
+    let output = run();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(stderr.contains("[1/2] acme"));
+    assert!(stderr.contains("[2/2] job 2"));
+    let pixel = |set: &str| {
+        image::open(temp_path.join(set).join("32x32.png"))
+            .unwrap()
+            .to_rgba8()
+            .get_pixel(16, 16)
+            .0
+    };
+    assert_eq!(pixel("acme"), [255, 0, 0, 255]);
+    assert_eq!(pixel("globex"), [0, 0, 255, 255]);
+
+    // Each set keeps its own generation cache
+    let output = run();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("acme is up to date"));
+
+    std::fs::write(
+        &jobs_path,
+        "jobs:\n  - input: logo.png\n  - name: again\n    input: logo.png\n",
+    )
+    .unwrap();
+    let output = run();
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("job 1 and again both write to icon-generator-logo"));
+    assert!(!temp_path.join("icon-generator-logo").exists());
+}
+
+/// Test that `icon-gen validate` passes a generated tree and lists hand-made drift
+#[test]
+fn test_validate() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("logo.png");
+    RgbaImage::from_pixel(256, 256, Rgba([30, 90, 200, 255]))
+        .save(&source_path)
+        .unwrap();
+    let output_dir = temp_path.join("icons");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args([
+            "--windows",
+            "--android",
+            "--ios",
+            "--android-adaptive",
+            "-o",
+        ])
+        .arg(&output_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let validate = || {
+        Command::new(get_icon_gen_binary_path())
+            .arg("validate")
+            .arg(&output_dir)
+            .output()
+            .unwrap()
+    };
+    let output = validate();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("No problems in the ios, android, windows icons"));
+
+    let small = RgbaImage::from_pixel(16, 16, Rgba([0, 0, 0, 255]));
+    small
+        .save(output_dir.join("ios/AppIcon-60x60@2x.png"))
+        .unwrap();
+    small.save(output_dir.join("ios/stray.png")).unwrap();
+    std::fs::remove_file(output_dir.join("ios/AppIcon-76x76@1x.png")).unwrap();
+    std::fs::remove_file(output_dir.join("android/mipmap-hdpi/ic_launcher_foreground.png"))
+        .unwrap();
+    std::fs::remove_dir_all(output_dir.join("android/mipmap-xxhdpi")).unwrap();
+    // Dropping the last directory entry leaves the ICO without its 256px frame
+    let ico_path = output_dir.join("windows/icon.ico");
+    let mut ico = std::fs::read(&ico_path).unwrap();
+    let count = u16::from_le_bytes([ico[4], ico[5]]);
+    ico[4..6].copy_from_slice(&(count - 1).to_le_bytes());
+    std::fs::write(&ico_path, &ico).unwrap();
+
+    let output = validate();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    for problem in [
+        "ios/AppIcon-60x60@2x.png: is 16x16, Contents.json expects 120x120",
+        "ios/AppIcon-76x76@1x.png: is listed in Contents.json but missing",
+        "ios/stray.png: isn't listed in Contents.json",
+        "android/mipmap-xxhdpi: is missing",
+        "android/mipmap-hdpi/ic_launcher_foreground.png: is missing",
+        "windows/icon.ico: has no 256x256 frame",
+    ] {
+        assert!(stdout.contains(problem), "missing {problem}: {stdout}");
+    }
+    assert!(String::from_utf8_lossy(&output.stderr).contains("6 problems"));
+}
+
+/// Test that `icon-gen validate` checks an Android res/ folder and points at the option that fixes each problem
+#[test]
+fn test_validate_android_res() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("logo.png");
+    RgbaImage::from_pixel(256, 256, Rgba([30, 90, 200, 255]))
+        .save(&source_path)
+        .unwrap();
+    let output_dir = temp_path.join("icons");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--android", "--android-adaptive", "-o"])
+        .arg(&output_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // Laid out like app/src/main/res, with other resources next to the mipmaps
+    let res = temp_path.join("res");
+    std::fs::rename(output_dir.join("android"), &res).unwrap();
+    std::fs::create_dir(res.join("values")).unwrap();
+    let validate = || {
+        Command::new(get_icon_gen_binary_path())
+            .arg("validate")
+            .arg(&res)
+            .output()
+            .unwrap()
+    };
+    let output = validate();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("No problems in the android icons"));
+
+    std::fs::remove_file(res.join("mipmap-anydpi-v26/ic_launcher_round.xml")).unwrap();
+    for entry in std::fs::read_dir(&res).unwrap() {
+        let launcher = entry.unwrap().path().join("ic_launcher.png");
+        if launcher.exists() {
+            std::fs::remove_file(launcher).unwrap();
+        }
+    }
+    RgbaImage::from_pixel(48, 48, Rgba([0, 0, 0, 255]))
+        .save(res.join("mipmap-xhdpi/ic_launcher_round.png"))
+        .unwrap();
+
+    let output = validate();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    for problem in [
+        "mipmap-anydpi-v26/ic_launcher_round.xml: is missing, but ic_launcher.xml is there (regenerate with --android-adaptive)",
+        "mipmap-mdpi/ic_launcher.png: is missing, but ic_launcher_round.png is there (regenerate with --android)",
+        "mipmap-xxxhdpi/ic_launcher.png: is missing, but ic_launcher_round.png is there (regenerate with --android)",
+        "mipmap-xhdpi/ic_launcher_round.png: is 48x48, mipmap-xhdpi expects 96x96 (regenerate with --android-round)",
+    ] {
+        assert!(stdout.contains(problem), "missing {problem}: {stdout}");
+    }
+
+    std::fs::remove_dir_all(res.join("mipmap-anydpi-v26")).unwrap();
+    let stdout = String::from_utf8_lossy(&validate().stdout).into_owned();
+    assert!(
+        stdout.contains("mipmap-anydpi-v26/ic_launcher.xml: is missing, so nothing uses the ic_launcher_foreground.png layer"),
+        "{stdout}"
+    );
+}
+
+/// Test that `icon-gen validate` checks the icons a PWA manifest declares, and `--fix` regenerates them
+#[test]
+fn test_validate_web_manifest() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let site = temp_dir.path();
+    let source_path = site.join("logo.png");
+    RgbaImage::from_pixel(256, 256, Rgba([30, 90, 200, 255]))
+        .save(&source_path)
+        .unwrap();
+    std::fs::create_dir(site.join("icons")).unwrap();
+    RgbaImage::from_pixel(100, 100, Rgba([30, 90, 200, 255]))
+        .save(site.join("icons/icon-192.png"))
+        .unwrap();
+    let manifest = site.join("manifest.json");
+    std::fs::write(
+        &manifest,
+        r#"{
+  "name": "App",
+  "icons": [
+    { "src": "/icons/icon-192.png", "sizes": "192x192", "type": "image/png" },
+    { "src": "icons/icon-512.png?v=2", "sizes": "512x512", "type": "image/png" },
+    { "src": "icons/maskable.png", "sizes": "512x512", "type": "image/png", "purpose": "maskable" },
+    { "src": "https://cdn.example.com/icon.png", "sizes": "48x48" }
+  ]
+}"#,
+    )
+    .unwrap();
+    let validate = |fix: bool| {
+        let mut command = Command::new(get_icon_gen_binary_path());
+        command.arg("validate").arg(&manifest);
+        if fix {
+            command.arg("--fix").arg(&source_path);
+        }
+        command.output().unwrap()
+    };
+
+    let output = validate(false);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    for problem in [
+        "icons/icon-192.png: is 100x100, manifest.json declares 192x192 (regenerate with --fix <SOURCE>)",
+        "icons/icon-512.png: is declared in manifest.json but missing",
+        "icons/maskable.png: is declared in manifest.json but missing",
+    ] {
+        assert!(stdout.contains(problem), "missing {problem}: {stdout}");
+    }
+    assert!(!stdout.contains("cdn.example.com"), "{stdout}");
+    assert!(!stdout.contains("maskable icon"), "{stdout}");
+
+    let output = validate(true);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(
+        stdout.contains("Regenerated icons/icon-512.png"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("No problems in the pwa icons"), "{stdout}");
+    assert_eq!(
+        image::image_dimensions(site.join("icons/icon-192.png")).unwrap(),
+        (192, 192)
+    );
+    // The maskable icon keeps its artwork inside the safe zone, on the source's background
+    let maskable = image::open(site.join("icons/maskable.png"))
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(maskable.dimensions(), (512, 512));
+    assert_eq!(*maskable.get_pixel(0, 0), Rgba([30, 90, 200, 255]));
+
+    // Without a maskable icon or the install sizes, the manifest needs editing
+    std::fs::write(
+        &manifest,
+        r#"{ "icons": [{ "src": "icons/icon-192.png", "sizes": "192x192" }] }"#,
+    )
+    .unwrap();
+    let stdout = String::from_utf8_lossy(&validate(false).stdout).into_owned();
+    assert!(
+        stdout.contains("manifest.json: declares no maskable icon"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("manifest.json: declares no 512x512 icon"),
+        "{stdout}"
+    );
+    assert!(!stdout.contains("declares no 192x192"), "{stdout}");
+}
+
+/// Test that `icon-gen diff` lists the icons that look different between two output directories
+#[test]
+fn test_diff() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("logo.png");
+    RgbaImage::from_fn(256, 256, |x, y| Rgba([x as u8, y as u8, 120, 255]))
+        .save(&source_path)
+        .unwrap();
+    let generate = |dir: &str| {
+        let output = Command::new(get_icon_gen_binary_path())
+            .arg(&source_path)
+            .args(["--windows", "--ios", "-o", dir])
+            .current_dir(temp_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+    };
+    generate("before");
+    generate("after");
+    let diff = || {
+        Command::new(get_icon_gen_binary_path())
+            .args(["diff", "before", "after"])
+            .current_dir(temp_path)
+            .output()
+            .unwrap()
+    };
+
+    let output = diff();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("No differences between the"), "{stdout}");
+
+    let after = temp_path.join("after");
+    let mut icon = image::open(after.join("ios/AppIcon-60x60@2x.png"))
+        .unwrap()
+        .into_rgb8();
+    image::imageops::invert(&mut icon);
+    icon.save(after.join("ios/AppIcon-60x60@2x.png")).unwrap();
+    RgbaImage::from_pixel(16, 16, Rgba([0, 0, 0, 255]))
+        .save(after.join("ios/AppIcon-1024x1024.png"))
+        .unwrap();
+    std::fs::remove_file(after.join("windows/icon.ico")).unwrap();
+    std::fs::write(after.join("ios/notes.txt"), "new").unwrap();
+
+    let output = diff();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(
+        stdout.contains("~ ios/AppIcon-60x60@2x.png: 120x120, SSIM 0."),
+        "{stdout}"
+    );
+    for change in [
+        "~ ios/AppIcon-1024x1024.png: 1024x1024 → 16x16",
+        "- windows/icon.ico",
+        "+ ios/notes.txt",
+    ] {
+        assert!(stdout.contains(change), "missing {change}: {stdout}");
+    }
+    assert_eq!(stdout.lines().count(), 4, "{stdout}");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("4 of"));
+}
+
+fn run_icon_gen(args: &[&str]) -> std::process::Output {
+    Command::new(get_icon_gen_binary_path())
+        .args(args)
+        .output()
+        .expect("Failed to run icon-gen command")
+}
+
+/// Gets the path to the icon-gen binary
+fn get_icon_gen_binary_path() -> std::path::PathBuf {
+    let debug_path = std::path::Path::new("target/debug/icon-gen");
+    if !debug_path.exists() {
+        let build_output = Command::new("cargo")
+            .args(["build", "--bin", "icon-gen"])
+            .output()
+            .expect("Failed to run cargo build");
+        assert!(
+            build_output.status.success(),
+            "Failed to build icon-gen binary: {}",
+            String::from_utf8_lossy(&build_output.stderr)
+        );
+    }
+    // Absolute, so the binary can run in another working directory
+    debug_path.canonicalize().unwrap()
+}
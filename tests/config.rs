@@ -0,0 +1,179 @@
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// Test that icon-gen.toml in the working directory supplies the options left off the command line
+#[test]
+fn test_config_file_discovery() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    create_test_image(&temp_path.join("logo.png"), 64, 64);
+    std::fs::write(
+        temp_path.join("icon-gen.toml"),
+        r#"
+# Shared icon settings
+input = "logo.png"
+output = "icons"
+png = [16, 32]
+padding = 10
+"#,
+    )
+    .unwrap();
+
+    let output = run_icon_gen_in(temp_path, &[]);
+    assert!(
+        output.status.success(),
+        "icon-gen with a config file failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(temp_path.join("icons/16x16.png").exists());
+    assert!(temp_path.join("icons/32x32.png").exists());
+    // --padding 10 leaves the corner transparent
+    let icon = image::open(temp_path.join("icons/32x32.png")).unwrap();
+    assert_eq!(icon.to_rgba8().get_pixel(0, 0)[3], 0);
+
+    // Command line options win over the file
+    let output = run_icon_gen_in(temp_path, &["--png", "48", "-o", "cli"]);
+    assert!(output.status.success());
+    assert!(temp_path.join("cli/48x48.png").exists());
+    assert!(!temp_path.join("cli/16x16.png").exists());
+
+    // --no-config ignores the file, so the input has to be given again
+    let output = run_icon_gen_in(temp_path, &["--no-config", "--png", "24"]);
+    assert!(!output.status.success(), "input should be required");
+    let output = run_icon_gen_in(
+        temp_path,
+        &["--no-config", "logo.png", "--png", "24", "-o", "bare"],
+    );
+    assert!(output.status.success());
+    let icon = image::open(temp_path.join("bare/24x24.png")).unwrap();
+    assert_eq!(icon.to_rgba8().get_pixel(0, 0)[3], 255);
+}
+
+/// Test that --config reads another file and that mistakes in it are reported
+#[test]
+fn test_config_file_errors() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    create_test_image(&temp_path.join("logo.png"), 64, 64);
+
+    let failure = |config: &str| {
+        std::fs::write(temp_path.join("ci.toml"), config).unwrap();
+        let output = run_icon_gen_in(temp_path, &["--config", "ci.toml", "logo.png"]);
+        assert!(!output.status.success(), "{config} should fail");
+        String::from_utf8_lossy(&output.stderr).to_string()
+    };
+
+    assert!(failure("colour = \"red\"").contains("Unknown option: colour"));
+    assert!(failure("windows = \"yes\"").contains("windows is a switch"));
+    assert!(failure("tint = [\"red\", \"blue\"]").contains("tint takes a single value"));
+    assert!(failure("output = icons").contains("Strings need quotes (line 1)"));
+    assert!(failure("config = \"other.toml\"").contains("Unknown option: config"));
+
+    std::fs::write(temp_path.join("ci.toml"), "png = [20]\nwindows = false\n").unwrap();
+    let output = run_icon_gen_in(temp_path, &["--config", "ci.toml", "logo.png", "-o", "ci"]);
+    assert!(output.status.success());
+    assert!(temp_path.join("ci/20x20.png").exists());
+}
+
+/// Test that --profile layers a named profile between the command line and the shared keys
+#[test]
+fn test_config_profiles() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    create_test_image(&temp_path.join("logo.png"), 64, 64);
+    std::fs::write(
+        temp_path.join("icon-gen.toml"),
+        r#"
+input = "logo.png"
+output = "icons"
+png = [16]
+
+[profile.dev]
+output = "icons-dev"
+png = [32]
+
+[profile.release]
+"#,
+    )
+    .unwrap();
+
+    let output = run_icon_gen_in(temp_path, &[]);
+    assert!(output.status.success());
+    assert!(temp_path.join("icons/16x16.png").exists());
+
+    let output = run_icon_gen_in(temp_path, &["--profile", "dev"]);
+    assert!(output.status.success());
+    assert!(temp_path.join("icons-dev/32x32.png").exists());
+    assert!(!temp_path.join("icons-dev/16x16.png").exists());
+
+    // The command line wins over the profile
+    let output = run_icon_gen_in(temp_path, &["--profile", "dev", "--png", "48"]);
+    assert!(output.status.success());
+    assert!(temp_path.join("icons-dev/48x48.png").exists());
+
+    // An empty profile keeps the shared settings
+    let output = run_icon_gen_in(temp_path, &["--profile", "release", "-o", "release"]);
+    assert!(output.status.success());
+    assert!(temp_path.join("release/16x16.png").exists());
+
+    let output = run_icon_gen_in(temp_path, &["--profile", "qa"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Unknown profile: qa. Available profiles: dev, release"));
+
+    std::fs::remove_file(temp_path.join("icon-gen.toml")).unwrap();
+    let output = run_icon_gen_in(temp_path, &["logo.png", "--profile", "dev"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("needs a config file"));
+}
+
+/// Runs icon-gen in `dir` with the given arguments
+fn run_icon_gen_in(dir: &Path, args: &[&str]) -> Output {
+    let binary = get_icon_gen_binary_path()
+        .canonicalize()
+        .expect("Failed to resolve the icon-gen binary");
+    Command::new(binary)
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("Failed to run icon-gen command")
+}
+
+/// Creates an opaque test image with a gradient pattern
+fn create_test_image(path: &Path, width: u32, height: u32) {
+    let mut image = RgbaImage::new(width, height);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let red = (255.0 * x as f32 / width as f32) as u8;
+        let green = (255.0 * y as f32 / height as f32) as u8;
+        *pixel = Rgba([red, green, 128, 255]);
+    }
+
+    image.save(path).expect("Failed to save test image");
+}
+
+/// Gets the path to the icon-gen binary
+fn get_icon_gen_binary_path() -> std::path::PathBuf {
+    // First try to find in target/debug
+    let debug_path = std::path::Path::new("target/debug/icon-gen");
+    if debug_path.exists() {
+        return debug_path.to_path_buf();
+    }
+
+    // If not found, build it first
+    let build_output = Command::new("cargo")
+        .args(["build", "--bin", "icon-gen"])
+        .output()
+        .expect("Failed to run cargo build");
+
+    if !build_output.status.success() {
+        panic!(
+            "Failed to build icon-gen binary: {}",
+            String::from_utf8_lossy(&build_output.stderr)
+        );
+    }
+
+    debug_path.to_path_buf()
+}
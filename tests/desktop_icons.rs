@@ -0,0 +1,723 @@
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Test that `--tray` on its own generates only the tray icon set
+#[test]
+fn test_tray_icon_generation() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--tray")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --tray failed");
+    }
+
+    let tray_dir = output_dir.join("tray");
+    for size in [16, 20, 22, 24, 32] {
+        let path = tray_dir.join(format!("{size}x{size}.png"));
+        let icon = image::open(&path).expect("Tray icon should exist");
+        assert_eq!(icon.width(), size);
+        assert_eq!(icon.height(), size);
+    }
+
+    // Tray is an additive target, not a trigger for the full set
+    assert!(!output_dir.join("windows").exists());
+    assert!(!output_dir.join("linux").exists());
+}
+
+/// Test that `--radius` rounds the corners of only the platforms picked with `--radius-for`
+#[test]
+fn test_rounded_corners_per_platform() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--linux")
+        .arg("--tray")
+        .arg("--radius")
+        .arg("25%")
+        .arg("--radius-for")
+        .arg("linux")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --radius failed");
+    }
+
+    let linux_icon = image::open(output_dir.join("linux").join("icon.png"))
+        .expect("Linux icon should exist")
+        .to_rgba8();
+    assert_eq!(linux_icon.get_pixel(0, 0)[3], 0, "Corner should be masked");
+    assert_eq!(
+        linux_icon.get_pixel(256, 2)[3],
+        255,
+        "Edge center should stay opaque"
+    );
+    assert_eq!(linux_icon.get_pixel(256, 256)[3], 255);
+
+    let tray_icon = image::open(output_dir.join("tray").join("32x32.png"))
+        .expect("Tray icon should exist")
+        .to_rgba8();
+    assert_eq!(tray_icon.get_pixel(0, 0)[3], 255, "Tray was not selected");
+
+    // Out-of-range percentages are rejected
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--linux")
+        .arg("--radius")
+        .arg("80%")
+        .arg("-o")
+        .arg(temp_path.join("invalid_output"))
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid radius"));
+}
+
+/// Test that `--macos-style big-sur` places the artwork on the inset macOS tile
+/// with a drop shadow before it is packed into the ICNS
+#[test]
+fn test_macos_big_sur_style() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 512, 512);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--macos")
+        .arg("--macos-style")
+        .arg("big-sur")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --macos-style failed");
+    }
+
+    let file = std::fs::File::open(output_dir.join("macos").join("icon.icns"))
+        .expect("icon.icns should exist");
+    let family = icns::IconFamily::read(std::io::BufReader::new(file)).unwrap();
+    let icon = family
+        .get_icon_with_type(icns::IconType::RGBA32_512x512)
+        .expect("ICNS should contain the 512x512 member");
+    let alpha_at = |x: u32, y: u32| icon.data()[((y * 512 + x) * 4 + 3) as usize];
+
+    // 100pt of the 1024pt grid is left as margin around the tile
+    assert_eq!(alpha_at(20, 256), 0, "Margin should be transparent");
+    assert_eq!(alpha_at(256, 256), 255, "Tile should be opaque");
+    assert_eq!(alpha_at(60, 60), 0, "Tile corners should be rounded");
+
+    // The shadow falls below the tile
+    let shadow = alpha_at(256, 466);
+    assert!(
+        shadow > 0 && shadow < 255,
+        "Expected a soft shadow, got alpha {shadow}"
+    );
+}
+
+/// Test that the ICNS members encoded in parallel are all present, with the
+/// legacy RGB members paired with their masks, and assembled in a stable order
+#[test]
+fn test_icns_members() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 512, 512);
+
+    let generate = |name: &str| {
+        let output_dir = temp_path.join(name);
+        let output = Command::new(get_icon_gen_binary_path())
+            .arg(&source_path)
+            .arg("--macos")
+            .arg("-o")
+            .arg(&output_dir)
+            .output()
+            .expect("Failed to run icon-gen command");
+        assert!(output.status.success(), "icon-gen --macos failed");
+        std::fs::read(output_dir.join("macos").join("icon.icns")).expect("icon.icns should exist")
+    };
+    let first = generate("first");
+    assert_eq!(
+        first,
+        generate("second"),
+        "ICNS output should be deterministic"
+    );
+
+    let family = icns::IconFamily::read(std::io::Cursor::new(&first)).unwrap();
+    let mut types = family.available_icons();
+    types.sort_by_key(|icon_type| icon_type.pixel_width());
+    assert_eq!(types.len(), 10, "Expected every ICNS member, got {types:?}");
+    for icon_type in [
+        icns::IconType::RGB24_16x16,
+        icns::IconType::RGB24_32x32,
+        icns::IconType::RGBA32_16x16_2x,
+        icns::IconType::RGBA32_512x512_2x,
+    ] {
+        assert!(
+            family.has_icon_with_type(icon_type),
+            "Missing {icon_type:?}"
+        );
+    }
+
+    // The legacy member is decoded from its RLE data and mask
+    let legacy = family
+        .get_icon_with_type(icns::IconType::RGB24_32x32)
+        .unwrap();
+    let pixel_at = |x: u32, y: u32| {
+        let offset = ((y * 32 + x) * 4) as usize;
+        &legacy.data()[offset..offset + 4]
+    };
+    assert_eq!(
+        pixel_at(16, 16)[3],
+        255,
+        "Mask should keep the artwork opaque"
+    );
+    assert!(
+        pixel_at(4, 16)[0] < pixel_at(28, 16)[0],
+        "The red gradient should run from left to right"
+    );
+}
+
+/// Test that `--icns-types` picks the ICNS members, including the PNG ic04/ic05
+/// types, while the asset catalog keeps all of its PNGs
+#[test]
+fn test_icns_types() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 512, 512);
+
+    let generate = |name: &str, types: &str| {
+        let output_dir = temp_path.join(name);
+        let output = Command::new(get_icon_gen_binary_path())
+            .arg(&source_path)
+            .arg("--macos")
+            .arg("--verify")
+            .arg("--icns-types")
+            .arg(types)
+            .arg("-o")
+            .arg(&output_dir)
+            .output()
+            .expect("Failed to run icon-gen command");
+        assert!(
+            output.status.success(),
+            "icon-gen --icns-types {types} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let file = std::fs::File::open(output_dir.join("macos").join("icon.icns")).unwrap();
+        let family = icns::IconFamily::read(std::io::BufReader::new(file)).unwrap();
+        let ostypes = family
+            .elements
+            .iter()
+            .map(|element| element.ostype.to_string())
+            .collect::<Vec<_>>();
+        (output_dir.join("macos"), family, ostypes)
+    };
+
+    let (macos_dir, _, ostypes) = generate("retina", "retina");
+    assert_eq!(ostypes, ["ic11", "ic12", "ic13", "ic14", "ic10"]);
+    assert_eq!(
+        image::open(macos_dir.join("icon_16x16.png"))
+            .unwrap()
+            .width(),
+        16,
+        "The catalog keeps the 1x PNGs"
+    );
+
+    // A list is written in the order of the sizes, and ic04 holds the catalog's 16px PNG
+    let (macos_dir, family, ostypes) = generate("list", "ic10, ic04,ic05");
+    assert_eq!(ostypes, ["ic04", "ic05", "ic10"]);
+    assert_eq!(
+        family.elements[0].data,
+        std::fs::read(macos_dir.join("icon_16x16.png")).unwrap()
+    );
+
+    let (_, _, ostypes) = generate("modern", "modern");
+    assert!(!ostypes.iter().any(|ostype| ostype.ends_with("mk")));
+    assert_eq!(ostypes.len(), 10);
+
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--macos")
+        .arg("--icns-types")
+        .arg("ic04,ic06")
+        .arg("-o")
+        .arg(temp_path.join("unknown"))
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown ICNS type: ic06"));
+}
+
+/// Test that `--background-for` flattens only the chosen platforms onto `--background`,
+/// and that a `--radius` mask then cuts the colored tile
+#[test]
+fn test_background_for_platforms() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    // Transparent source with a small opaque square in the middle
+    let source_path = temp_path.join("test_source.png");
+    let mut source = RgbaImage::new(256, 256);
+    for x in 96..160 {
+        for y in 96..160 {
+            source.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        }
+    }
+    source.save(&source_path).unwrap();
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--linux")
+        .arg("--tray")
+        .arg("--background")
+        .arg("#00ff00")
+        .arg("--background-for")
+        .arg("linux")
+        .arg("--radius")
+        .arg("10%")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --background-for failed");
+    }
+
+    let linux_icon = image::open(output_dir.join("linux").join("icon.png"))
+        .expect("Linux icon should exist")
+        .to_rgba8();
+    assert_eq!(*linux_icon.get_pixel(256, 20), Rgba([0, 255, 0, 255]));
+    assert_eq!(*linux_icon.get_pixel(256, 256), Rgba([255, 0, 0, 255]));
+    assert_eq!(
+        linux_icon.get_pixel(0, 0)[3],
+        0,
+        "Radius should cut the tile"
+    );
+
+    let tray_icon = image::open(output_dir.join("tray").join("32x32.png"))
+        .expect("Tray icon should exist")
+        .to_rgba8();
+    assert_eq!(tray_icon.get_pixel(16, 2)[3], 0, "Tray was not flattened");
+
+    // A typo fails instead of flattening onto white
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args([
+            "--linux",
+            "--background",
+            "#00gg00",
+            "--background-for",
+            "linux",
+        ])
+        .arg("-o")
+        .arg(temp_path.join("invalid_output"))
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid --background: #00gg00"));
+}
+
+/// Test that `--background-image` is cropped to fill the square and composited
+/// under the artwork of the `--background-for` platforms
+#[test]
+fn test_background_image() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    let mut source = RgbaImage::new(256, 256);
+    for x in 96..160 {
+        for y in 96..160 {
+            source.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        }
+    }
+    source.save(&source_path).unwrap();
+
+    // A wide backdrop, blue on the left half and green on the right
+    let backdrop_path = temp_path.join("backdrop.png");
+    let backdrop = RgbaImage::from_fn(300, 200, |x, _| {
+        if x < 150 {
+            Rgba([0, 0, 255, 255])
+        } else {
+            Rgba([0, 255, 0, 255])
+        }
+    });
+    backdrop.save(&backdrop_path).unwrap();
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--linux")
+        .arg("--background-image")
+        .arg(&backdrop_path)
+        .arg("--background-for")
+        .arg("linux")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --background-image failed");
+    }
+
+    let icon = image::open(output_dir.join("linux").join("icon.png"))
+        .expect("Linux icon should exist")
+        .to_rgba8();
+    assert_eq!(*icon.get_pixel(20, 256), Rgba([0, 0, 255, 255]));
+    assert_eq!(*icon.get_pixel(490, 256), Rgba([0, 255, 0, 255]));
+    assert_eq!(*icon.get_pixel(256, 256), Rgba([255, 0, 0, 255]));
+}
+
+/// Test that `--shadow` shrinks the artwork onto an enlarged canvas and renders a
+/// soft shadow below it, on the selected platforms only
+#[test]
+fn test_drop_shadow() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--linux")
+        .arg("--tray")
+        .arg("--shadow")
+        .arg("--shadow-blur")
+        .arg("8")
+        .arg("--shadow-offset")
+        .arg("-2,12")
+        .arg("--shadow-opacity")
+        .arg("0.5")
+        .arg("--shadow-for")
+        .arg("linux")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --shadow failed");
+    }
+
+    let icon = image::open(output_dir.join("linux").join("icon.png"))
+        .expect("Linux icon should exist")
+        .to_rgba8();
+    assert_eq!(icon.dimensions(), (512, 512));
+    assert_eq!(
+        icon.get_pixel(256, 10)[3],
+        0,
+        "Margin above the artwork is clear"
+    );
+    assert_eq!(icon.get_pixel(256, 256)[3], 255);
+
+    let shadow = icon.get_pixel(256, 485);
+    assert_eq!(&shadow.0[..3], &[0, 0, 0]);
+    assert!(
+        shadow[3] > 0 && shadow[3] < 128,
+        "Expected a soft shadow below the artwork, got alpha {}",
+        shadow[3]
+    );
+
+    let tray_icon = image::open(output_dir.join("tray").join("32x32.png"))
+        .expect("Tray icon should exist")
+        .to_rgba8();
+    assert_eq!(tray_icon.get_pixel(16, 0)[3], 255, "Tray was not selected");
+}
+
+/// Test that `--border` strokes the outline left by `--radius`, drawing a ring at 50%
+#[test]
+fn test_border_ring() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--linux")
+        .arg("--radius")
+        .arg("50%")
+        .arg("--border")
+        .arg("16,rgb(255, 255, 255)")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --border failed");
+    }
+
+    let icon = image::open(output_dir.join("linux").join("icon.png"))
+        .expect("Linux icon should exist")
+        .to_rgba8();
+    let white = Rgba([255, 255, 255, 255]);
+    assert_eq!(
+        *icon.get_pixel(256, 12),
+        white,
+        "Ring should be drawn at the top"
+    );
+    assert_eq!(
+        *icon.get_pixel(500, 256),
+        white,
+        "Ring should be drawn at the side"
+    );
+    assert_ne!(
+        *icon.get_pixel(256, 256),
+        white,
+        "Artwork should show inside the ring"
+    );
+    assert_eq!(
+        icon.get_pixel(0, 0)[3],
+        0,
+        "Outside the ring stays transparent"
+    );
+
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--linux")
+        .arg("--border")
+        .arg("8")
+        .arg("-o")
+        .arg(temp_path.join("invalid_output"))
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid border"));
+}
+
+/// Creates an opaque test image with a gradient
+/// Test that every PNG the macOS Contents.json names is written next to it at
+/// the entry's size times its scale, matching the ICNS member of that size
+#[test]
+fn test_macos_catalog_pngs() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 512, 512);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--macos")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(output.status.success(), "icon-gen --macos failed");
+
+    let macos_dir = output_dir.join("macos");
+    let contents: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(macos_dir.join("Contents.json"))
+            .expect("macos/Contents.json should exist"),
+    )
+    .unwrap();
+    let images = contents["images"].as_array().unwrap();
+    assert_eq!(images.len(), 10);
+    for image in images {
+        let filename = image["filename"].as_str().unwrap();
+        let points = image["size"].as_str().unwrap().split('x').next().unwrap();
+        let scale = image["scale"].as_str().unwrap().trim_end_matches('x');
+        let expected = points.parse::<u32>().unwrap() * scale.parse::<u32>().unwrap();
+        let png = image::open(macos_dir.join(filename))
+            .unwrap_or_else(|_| panic!("{filename} should exist"));
+        assert_eq!(
+            (png.width(), png.height()),
+            (expected, expected),
+            "{filename}"
+        );
+        assert_eq!(image["idiom"], "mac");
+    }
+    assert!(macos_dir.join("icon_16x16@2x.png").exists());
+    assert!(macos_dir.join("icon_512x512@2x.png").exists());
+
+    // The PNG-backed members are stored byte for byte in the ICNS
+    let file = std::fs::File::open(macos_dir.join("icon.icns")).unwrap();
+    let family = icns::IconFamily::read(std::io::BufReader::new(file)).unwrap();
+    let member = family
+        .elements
+        .iter()
+        .find(|element| element.ostype == icns::IconType::RGBA32_256x256.ostype())
+        .unwrap();
+    assert_eq!(
+        member.data,
+        std::fs::read(macos_dir.join("icon_256x256.png")).unwrap()
+    );
+}
+
+/// Test that `--contrast-check` warns about a white logo that vanishes on the
+/// white iOS fill and on light launcher backgrounds, but not once both are fixed
+#[test]
+fn test_contrast_check() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    // A white disc on a transparent canvas
+    let source_path = temp_path.join("test_source.png");
+    let source = RgbaImage::from_fn(256, 256, |x, y| {
+        let (dx, dy) = (x as i32 - 128, y as i32 - 128);
+        if dx * dx + dy * dy < 90 * 90 {
+            Rgba([255, 255, 255, 255])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    });
+    source
+        .save(&source_path)
+        .expect("Failed to save test image");
+
+    let run = |name: &str, extra: &[&str]| {
+        let output = Command::new(get_icon_gen_binary_path())
+            .arg(&source_path)
+            .args(["--ios", "--windows", "--contrast-check"])
+            .args(extra)
+            .arg("-o")
+            .arg(temp_path.join(name))
+            .output()
+            .expect("Failed to run icon-gen command");
+        assert!(output.status.success(), "icon-gen --contrast-check failed");
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    let stderr = run("white", &[]);
+    assert!(
+        stderr.contains("1.0:1 contrast with the #ffffff background of the ios icons"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("transparent windows icons has too little contrast on white (1.0:1)"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("black"), "{stderr}");
+
+    let stderr = run(
+        "fixed",
+        &["--background", "#1e88e5", "--background-for", "ios,windows"],
+    );
+    assert!(!stderr.contains("contrast"), "{stderr}");
+}
+
+/// Test that `--strict` fails a run that warns, here about an upscaled source,
+/// on every run rather than only until the set is cached
+#[test]
+fn test_strict() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+    let output_dir = temp_path.join("icons");
+
+    let run = |platform: &str| {
+        Command::new(get_icon_gen_binary_path())
+            .arg(&source_path)
+            .args([platform, "--strict", "-o"])
+            .arg(&output_dir)
+            .output()
+            .expect("Failed to run icon-gen command")
+    };
+
+    // The 256px frame of the ICO is as large as the source
+    let output = run("--windows");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(!stderr.contains("Warning"), "{stderr}");
+
+    for _ in 0..2 {
+        let output = run("--ios");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "{stderr}");
+        assert!(
+            stderr.contains("the source is 256px, smaller than the 1024px ios icons"),
+            "{stderr}"
+        );
+        assert!(stderr.contains("1 warning with --strict"), "{stderr}");
+        assert!(output_dir.join("ios/AppIcon-1024x1024.png").exists());
+    }
+}
+
+fn create_test_image(path: &Path, width: u32, height: u32) {
+    let mut image = RgbaImage::new(width, height);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let red = (255.0 * x as f32 / width as f32) as u8;
+        let green = (255.0 * y as f32 / height as f32) as u8;
+        *pixel = Rgba([red, green, 128, 255]);
+    }
+
+    image.save(path).expect("Failed to save test image");
+}
+
+/// Gets the path to the icon-gen binary
+fn get_icon_gen_binary_path() -> std::path::PathBuf {
+    // First try to find in target/debug
+    let debug_path = std::path::Path::new("target/debug/icon-gen");
+    if debug_path.exists() {
+        return debug_path.to_path_buf();
+    }
+
+    // If not found, build it first
+    let build_output = Command::new("cargo")
+        .args(["build", "--bin", "icon-gen"])
+        .output()
+        .expect("Failed to run cargo build");
+
+    if !build_output.status.success() {
+        panic!(
+            "Failed to build icon-gen binary: {}",
+            String::from_utf8_lossy(&build_output.stderr)
+        );
+    }
+
+    debug_path.to_path_buf()
+}
@@ -95,8 +95,8 @@ fn verify_dev_badge_applied(img: &DynamicImage) {
 
     for i in 0..samples_to_check {
         // Sample pixels in a grid around the center
-        let grid_x = (i % 5) as i32 - 2;
-        let grid_y = (i / 5) as i32 - 2;
+        let grid_x = (i % 5) - 2;
+        let grid_y = (i / 5) - 2;
         let x_offset = grid_x * (bug_size as i32 / 6);
         let y_offset = grid_y * (bug_size as i32 / 6);
 
@@ -182,7 +182,7 @@ fn test_dev_mode_multiple_sizes() {
         );
 
         let generated_icon = image::open(&output_icon_path)
-            .expect(&format!("Failed to load {}x{} icon", size, size));
+            .unwrap_or_else(|_| panic!("Failed to load {}x{} icon", size, size));
 
         assert_eq!(generated_icon.width(), *size);
         assert_eq!(generated_icon.height(), *size);
@@ -198,6 +198,522 @@ fn test_dev_mode_multiple_sizes() {
     println!("  - All icons have dev badge applied");
 }
 
+/// Test that --overlay places images by position and scale, and rejects bad scales
+#[test]
+fn test_overlay_placement() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    // A solid magenta mark that stands out from the gradient
+    let mark_path = temp_path.join("mark.png");
+    RgbaImage::from_pixel(40, 40, Rgba([255, 0, 255, 255]))
+        .save(&mark_path)
+        .expect("Failed to save overlay image");
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--png")
+        .arg("128")
+        .arg("--overlay")
+        .arg(&mark_path)
+        .arg("--overlay")
+        .arg(format!("{}:top-left:0.5", mark_path.display()))
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(
+        output.status.success(),
+        "icon-gen with --overlay failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let icon = image::open(output_dir.join("128x128.png"))
+        .expect("Failed to load generated icon")
+        .to_rgba8();
+    let magenta = Rgba([255, 0, 255, 255]);
+
+    // Default: a quarter of the icon in the bottom-right corner, inset by the margin
+    assert_eq!(*icon.get_pixel(100, 100), magenta);
+    assert_ne!(*icon.get_pixel(125, 125), magenta);
+    // top-left at half the icon
+    assert_eq!(*icon.get_pixel(30, 30), magenta);
+    assert_eq!(*icon.get_pixel(65, 65), magenta);
+    assert_ne!(*icon.get_pixel(64, 110), magenta);
+
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--png")
+        .arg("128")
+        .arg("--overlay")
+        .arg(format!("{}:center:2", mark_path.display()))
+        .arg("-o")
+        .arg(temp_path.join("rejected"))
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(
+        !output.status.success(),
+        "Scales above 1 should be rejected"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Overlay scale"));
+}
+
+/// Test that --stamp-text draws the text where asked and skips tiny icons
+#[test]
+fn test_stamp_text() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--png")
+        .arg("16,128")
+        .arg("--stamp-text")
+        .arg("v1.4.2")
+        .arg("--stamp-color")
+        .arg("#ff0000")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(
+        output.status.success(),
+        "icon-gen with --stamp-text failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // The gradient never gets this red, so red pixels are text
+    let red_rows = |name: &str| {
+        let icon = image::open(output_dir.join(name))
+            .expect("Failed to load generated icon")
+            .to_rgba8();
+        icon.enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel[0] > 200 && pixel[1] < 80 && pixel[2] < 80)
+            .map(|(_, y, _)| y)
+            .collect::<Vec<_>>()
+    };
+
+    let rows = red_rows("128x128.png");
+    assert!(rows.len() > 50, "Stamp text should be drawn on 128x128");
+    assert!(
+        rows.iter().all(|&y| y >= 64),
+        "Stamp text defaults to the bottom of the icon"
+    );
+    assert!(
+        red_rows("16x16.png").is_empty(),
+        "Icons below 32px are not stamped"
+    );
+
+    // A typo fails instead of stamping in white
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--png", "128", "--stamp-text", "v1.4.2"])
+        .args(["--stamp-color", "#ff00zz", "-o"])
+        .arg(temp_path.join("test_output_invalid"))
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid stamp color: #ff00zz"));
+}
+
+/// Test that --badge-ribbon draws a band across the top-right corner only
+#[test]
+fn test_badge_ribbon() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let plain_dir = temp_path.join("plain");
+    let ribbon_dir = temp_path.join("ribbon");
+    for (output_dir, ribbon) in [(&plain_dir, None), (&ribbon_dir, Some("BETA:#e53935"))] {
+        let mut command = Command::new(get_icon_gen_binary_path());
+        command.arg(&source_path).arg("--png").arg("128");
+        if let Some(ribbon) = ribbon {
+            command.arg("--badge-ribbon").arg(ribbon);
+        }
+        let output = command
+            .arg("-o")
+            .arg(output_dir)
+            .output()
+            .expect("Failed to run icon-gen command");
+        assert!(
+            output.status.success(),
+            "icon-gen with --badge-ribbon failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let load = |dir: &Path| {
+        image::open(dir.join("128x128.png"))
+            .expect("Failed to load generated icon")
+            .to_rgba8()
+    };
+    let plain = load(&plain_dir);
+    let ribbon = load(&ribbon_dir);
+
+    // On the band, clear of the label
+    assert_eq!(*ribbon.get_pixel(88, 2), Rgba([229, 57, 53, 255]));
+    // The very corner, and everything away from it, is untouched
+    assert_eq!(ribbon.get_pixel(127, 0), plain.get_pixel(127, 0));
+    assert_eq!(ribbon.get_pixel(20, 100), plain.get_pixel(20, 100));
+    assert_eq!(ribbon.get_pixel(64, 64), plain.get_pixel(64, 64));
+
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--png")
+        .arg("128")
+        .arg("--badge-ribbon")
+        .arg("BETA:notacolor")
+        .arg("-o")
+        .arg(temp_path.join("rejected"))
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(
+        !output.status.success(),
+        "Invalid ribbon colors should be rejected"
+    );
+}
+
+/// Test that --badge-position moves the dev badge into a corner
+#[test]
+fn test_badge_position() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let plain_dir = temp_path.join("plain");
+    let badge_dir = temp_path.join("badge");
+    for (output_dir, dev_mode) in [(&plain_dir, false), (&badge_dir, true)] {
+        let mut command = Command::new(get_icon_gen_binary_path());
+        command.arg(&source_path).arg("--png").arg("128");
+        if dev_mode {
+            command.args(["--dev-mode", "--badge-position", "top-left"]);
+        }
+        let output = command
+            .arg("-o")
+            .arg(output_dir)
+            .output()
+            .expect("Failed to run icon-gen command");
+        assert!(
+            output.status.success(),
+            "icon-gen with --badge-position failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let load = |dir: &Path| {
+        image::open(dir.join("128x128.png"))
+            .expect("Failed to load generated icon")
+            .to_rgba8()
+    };
+    let plain = load(&plain_dir);
+    let badge = load(&badge_dir);
+
+    // The 32px badge sits inside the margin in the top-left corner
+    let changed = |x_range: std::ops::Range<u32>, y_range: std::ops::Range<u32>| {
+        x_range
+            .flat_map(|x| y_range.clone().map(move |y| (x, y)))
+            .filter(|&(x, y)| badge.get_pixel(x, y) != plain.get_pixel(x, y))
+            .count()
+    };
+    assert!(
+        changed(6..38, 6..38) > 100,
+        "Badge should be in the top-left corner"
+    );
+    assert_eq!(changed(0..6, 0..128), 0, "Badge should keep the margin");
+    assert_eq!(changed(40..128, 40..128), 0, "Badge should not be centered");
+}
+
+/// Test that --badge-scale resizes the dev badge and rejects out-of-range scales
+#[test]
+fn test_badge_scale() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let generate = |name: &str, scale: Option<&str>| {
+        let output_dir = temp_path.join(name);
+        let mut command = Command::new(get_icon_gen_binary_path());
+        command.arg(&source_path).arg("--png").arg("128");
+        if let Some(scale) = scale {
+            command.args(["--dev-mode", "--badge-scale", scale]);
+        }
+        let output = command
+            .arg("-o")
+            .arg(&output_dir)
+            .output()
+            .expect("Failed to run icon-gen command");
+        (output, output_dir.join("128x128.png"))
+    };
+
+    let (_, plain_path) = generate("plain", None);
+    let plain = image::open(plain_path).unwrap().to_rgba8();
+    let changed_pixels = |scale: &str| {
+        let (output, path) = generate(scale, Some(scale));
+        assert!(
+            output.status.success(),
+            "icon-gen --badge-scale {scale} failed"
+        );
+        let badge = image::open(path).unwrap().to_rgba8();
+        badge
+            .pixels()
+            .zip(plain.pixels())
+            .filter(|(a, b)| a != b)
+            .count()
+    };
+
+    let subtle = changed_pixels("0.1");
+    let prominent = changed_pixels("0.5");
+    assert!(subtle > 0, "A small badge is still drawn");
+    assert!(
+        prominent > subtle * 10,
+        "A 0.5 badge covers far more than a 0.1 badge ({prominent} vs {subtle} pixels)"
+    );
+
+    let (output, _) = generate("too_big", Some("1.5"));
+    assert!(
+        !output.status.success(),
+        "Scales above 1 should be rejected"
+    );
+}
+
+/// Test that --dev-badge-file replaces the bug with custom artwork
+#[test]
+fn test_dev_badge_file() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let badge_path = temp_path.join("dev.png");
+    RgbaImage::from_pixel(40, 40, Rgba([0, 255, 0, 255]))
+        .save(&badge_path)
+        .expect("Failed to save badge image");
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--png")
+        .arg("128")
+        .arg("--dev-mode")
+        .arg("--dev-badge-file")
+        .arg(&badge_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(
+        output.status.success(),
+        "icon-gen with --dev-badge-file failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // The custom badge is centered at a quarter of the icon, like the bugs
+    let icon = image::open(output_dir.join("128x128.png"))
+        .expect("Failed to load generated icon")
+        .to_rgba8();
+    let green = Rgba([0, 255, 0, 255]);
+    assert_eq!(*icon.get_pixel(64, 64), green);
+    assert_eq!(*icon.get_pixel(49, 49), green);
+    assert_ne!(*icon.get_pixel(44, 44), green);
+
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--png")
+        .arg("128")
+        .arg("--dev-mode")
+        .arg("--dev-badge-file")
+        .arg(temp_path.join("missing.png"))
+        .arg("-o")
+        .arg(temp_path.join("rejected"))
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(
+        !output.status.success(),
+        "A missing badge file should be an error"
+    );
+}
+
+/// Test that --badge-seed rotates the moth reproducibly
+#[test]
+fn test_badge_seed_is_reproducible() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let generate = |name: &str, seed: Option<&str>| {
+        let output_dir = temp_path.join(name);
+        let mut command = Command::new(get_icon_gen_binary_path());
+        command
+            .arg(&source_path)
+            .args(["--png", "128", "--dev-mode"]);
+        if let Some(seed) = seed {
+            command.args(["--badge-seed", seed]);
+        }
+        let output = command
+            .arg("-o")
+            .arg(&output_dir)
+            .output()
+            .expect("Failed to run icon-gen command");
+        assert!(
+            output.status.success(),
+            "icon-gen --badge-seed {seed:?} failed"
+        );
+        std::fs::read(output_dir.join("128x128.png")).expect("Failed to read generated icon")
+    };
+
+    let unrotated = generate("unrotated", None);
+    let first = generate("first", Some("42"));
+    let second = generate("second", Some("42"));
+    let other = generate("other", Some("7"));
+
+    assert_eq!(first, second, "The same seed gives byte-identical icons");
+    assert_ne!(first, unrotated, "A seed rotates the moth");
+    assert_ne!(first, other, "Different seeds give different angles");
+    assert_eq!(
+        unrotated,
+        generate("unrotated_again", None),
+        "Without a seed the badge is not rotated"
+    );
+}
+
+/// Test that --channel maps to its ribbon preset, and that explicit options win
+#[test]
+fn test_channel_presets() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let generate = |name: &str, args: &[&str]| {
+        let output_dir = temp_path.join(name);
+        let output = Command::new(get_icon_gen_binary_path())
+            .arg(&source_path)
+            .args(["--png", "128"])
+            .args(args)
+            .arg("-o")
+            .arg(&output_dir)
+            .output()
+            .expect("Failed to run icon-gen command");
+        assert!(
+            output.status.success(),
+            "icon-gen {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        std::fs::read(output_dir.join("128x128.png")).expect("Failed to read generated icon")
+    };
+
+    // A channel is the same as spelling out its badge options
+    assert_eq!(
+        generate("beta", &["--channel", "beta"]),
+        generate("beta_ribbon", &["--badge-ribbon", "BETA:#e53935"])
+    );
+    assert_eq!(
+        generate("dev", &["--channel", "dev"]),
+        generate(
+            "dev_flags",
+            &["--dev-mode", "--badge-ribbon", "DEV:#1e88e5"]
+        )
+    );
+    assert_eq!(
+        generate(
+            "rc_override",
+            &["--channel", "rc", "--badge-ribbon", "RC1:#000000"]
+        ),
+        generate("rc1_ribbon", &["--badge-ribbon", "RC1:#000000"]),
+        "An explicit --badge-ribbon overrides the channel preset"
+    );
+    assert_ne!(
+        generate("alpha_tint", &["--channel", "alpha", "--channel-tint"]),
+        generate("alpha", &["--channel", "alpha"]),
+        "--channel-tint tints the icon"
+    );
+
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--png", "128", "--channel", "gamma", "-o"])
+        .arg(temp_path.join("rejected"))
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(
+        !output.status.success(),
+        "Unknown channels should be rejected"
+    );
+}
+
+/// Test that --layers orders the decoration layers and leaves out the ones not listed
+#[test]
+fn test_layer_order() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    // A mark over the whole top-right corner, where the ribbon runs
+    let mark_path = temp_path.join("mark.png");
+    RgbaImage::from_pixel(40, 40, Rgba([255, 0, 255, 255]))
+        .save(&mark_path)
+        .expect("Failed to save overlay image");
+    let mark = format!("{}:top-right:0.5", mark_path.display());
+
+    let generate = |name: &str, args: &[&str]| {
+        let output_dir = temp_path.join(name);
+        let output = Command::new(get_icon_gen_binary_path())
+            .arg(&source_path)
+            .args(["--png", "128", "--overlay", &mark])
+            .args(args)
+            .arg("-o")
+            .arg(&output_dir)
+            .output()
+            .expect("Failed to run icon-gen command");
+        assert!(
+            output.status.success(),
+            "icon-gen {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        image::open(output_dir.join("128x128.png"))
+            .expect("Failed to load generated icon")
+            .to_rgba8()
+    };
+
+    // (88, 8) is on the ribbon band, clear of its label, and inside the mark
+    let ribbon_red = Rgba([229, 57, 53, 255]);
+    let magenta = Rgba([255, 0, 255, 255]);
+    let default_order = generate("default", &["--badge-ribbon", "BETA"]);
+    assert_eq!(*default_order.get_pixel(88, 8), ribbon_red);
+
+    let ribbon_below = generate(
+        "ribbon_below",
+        &["--badge-ribbon", "BETA", "--layers", "ribbon,overlays"],
+    );
+    assert_eq!(*ribbon_below.get_pixel(88, 8), magenta);
+
+    let without_ribbon = generate(
+        "without_ribbon",
+        &["--badge-ribbon", "BETA", "--layers", "overlays"],
+    );
+    assert_eq!(without_ribbon, generate("mark_only", &[]));
+}
+
 /// Creates a test image with a blue gradient
 fn create_test_image(path: &Path, width: u32, height: u32) {
     let mut image = RgbaImage::new(width, height);
@@ -225,7 +741,7 @@ fn get_icon_gen_binary_path() -> std::path::PathBuf {
 
     // If not found, build it first
     let build_output = Command::new("cargo")
-        .args(&["build", "--bin", "icon-gen"])
+        .args(["build", "--bin", "icon-gen"])
         .output()
         .expect("Failed to run cargo build");
 
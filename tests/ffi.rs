@@ -0,0 +1,88 @@
+use icon_gen::ffi::{
+    icon_gen_buffer_free, icon_gen_generate_zip, icon_gen_last_error, IconGenBuffer,
+    ICON_GEN_ERROR, ICON_GEN_OK,
+};
+use image::{Rgba, RgbaImage};
+use std::ffi::CStr;
+use std::io::Cursor;
+use std::ptr;
+
+/// Test that the C API returns a zip of the selected platforms
+#[test]
+fn test_generate_zip() {
+    let source = test_image_png(128);
+    let mut out = IconGenBuffer {
+        data: ptr::null_mut(),
+        len: 0,
+    };
+
+    let code = unsafe {
+        icon_gen_generate_zip(
+            source.as_ptr(),
+            source.len(),
+            c"windows,linux".as_ptr(),
+            &mut out,
+        )
+    };
+    assert_eq!(code, ICON_GEN_OK);
+    assert!(icon_gen_last_error().is_null());
+
+    let archive = unsafe { std::slice::from_raw_parts(out.data, out.len) }.to_vec();
+    unsafe { icon_gen_buffer_free(out) };
+    assert_eq!(archive[..4], [0x50, 0x4b, 0x03, 0x04]);
+    let contains = |name: &str| {
+        archive
+            .windows(name.len())
+            .any(|window| window == name.as_bytes())
+    };
+    assert!(contains("windows/icon.ico"));
+    assert!(contains("linux/icon.png"));
+    assert!(!contains("macos/icon.icns"));
+}
+
+/// Test that failures are reported through the return code and the last error
+#[test]
+fn test_generate_zip_errors() {
+    let source = test_image_png(64);
+    let mut out = IconGenBuffer {
+        data: ptr::null_mut(),
+        len: 0,
+    };
+
+    let code = unsafe {
+        icon_gen_generate_zip(source.as_ptr(), source.len(), c"windoze".as_ptr(), &mut out)
+    };
+    assert_eq!(code, ICON_GEN_ERROR);
+    assert!(out.data.is_null());
+    let message = unsafe { CStr::from_ptr(icon_gen_last_error()) };
+    assert!(message
+        .to_string_lossy()
+        .contains("Unknown platform: windoze"));
+
+    let garbage = b"not an image";
+    let code =
+        unsafe { icon_gen_generate_zip(garbage.as_ptr(), garbage.len(), ptr::null(), &mut out) };
+    assert_eq!(code, ICON_GEN_ERROR);
+    let message = unsafe { CStr::from_ptr(icon_gen_last_error()) };
+    assert!(message.to_string_lossy().contains("Failed to load image"));
+
+    // Freeing an empty buffer is a no-op
+    unsafe { icon_gen_buffer_free(out) };
+}
+
+/// Encodes a square test image with a gradient pattern as PNG
+fn test_image_png(size: u32) -> Vec<u8> {
+    let mut image = RgbaImage::new(size, size);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let red = (255.0 * x as f32 / size as f32) as u8;
+        let green = (255.0 * y as f32 / size as f32) as u8;
+        *pixel = Rgba([red, green, 128, 255]);
+    }
+
+    let mut png = Cursor::new(Vec::new());
+    image
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .expect("Failed to encode test image");
+    png.into_inner()
+}
@@ -131,7 +131,7 @@ fn get_icon_gen_binary_path() -> std::path::PathBuf {
 
     // If not found, build it first
     let build_output = Command::new("cargo")
-        .args(&["build", "--bin", "icon-gen"])
+        .args(["build", "--bin", "icon-gen"])
         .output()
         .expect("Failed to run cargo build");
 
@@ -0,0 +1,156 @@
+use icon_gen::{interrupt, IconGenError, IconGenerator, PlatformContext, PlatformGenerator};
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// A platform that is interrupted between its two files
+#[derive(Debug)]
+struct Interrupted;
+
+impl PlatformGenerator for Interrupted {
+    fn name(&self) -> &str {
+        "interrupted"
+    }
+
+    fn sizes(&self) -> Vec<u32> {
+        vec![16]
+    }
+
+    fn emit(&self, ctx: &PlatformContext<'_>) -> icon_gen::error::Result<()> {
+        ctx.write_png("interrupted/first.png", &ctx.render(16))?;
+        interrupt::interrupt();
+        ctx.write_png("interrupted/second.png", &ctx.render(16))
+    }
+}
+
+/// Test that an interrupted run stops before its next file and can run again after a reset
+#[test]
+fn test_interrupted_run_stops() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 64, 64);
+    let output_dir = temp_path.join("icons");
+
+    let result = IconGenerator::new(&source_path)
+        .output(&output_dir)
+        .register(Interrupted)
+        .run();
+    assert!(
+        matches!(result, Err(IconGenError::Interrupted)),
+        "{result:?}"
+    );
+    assert!(output_dir.join("interrupted/first.png").exists());
+    assert!(!output_dir.join("interrupted/second.png").exists());
+    assert!(list_files(&output_dir)
+        .iter()
+        .all(|path| !path.to_string_lossy().ends_with(".icon-gen-tmp")));
+
+    // The interrupted set isn't cached, so the next run generates it again; as
+    // no set finished, there is no cache file at all
+    assert!(!output_dir.join(".icon-gen-cache.json").exists());
+
+    interrupt::reset();
+    let files = IconGenerator::new(&source_path)
+        .output(&output_dir)
+        .register(Interrupted)
+        .generate_to_memory();
+    assert!(matches!(files, Err(IconGenError::Interrupted)));
+    interrupt::reset();
+    IconGenerator::new(&source_path)
+        .output(&output_dir)
+        .png_sizes([16])
+        .run()
+        .expect("A run after the reset should succeed");
+}
+
+/// Test that Ctrl-C stops the command line run with exit status 130
+#[cfg(unix)]
+#[test]
+fn test_ctrl_c_exit_status() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+    let output_dir = temp_path.join("icons");
+
+    // zopfli recompresses every PNG once they are written, so the run is still busy
+    let mut child = std::process::Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args([
+            "--windows",
+            "--linux",
+            "--compression",
+            "zopfli",
+            "--no-config",
+        ])
+        .arg("--output")
+        .arg(&output_dir)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to start icon-gen");
+    while !output_dir.join("windows/icon.ico").exists() {
+        assert!(child.try_wait().unwrap().is_none(), "icon-gen exited early");
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGINT) };
+    let output = child
+        .wait_with_output()
+        .expect("Failed to wait for icon-gen");
+
+    assert_eq!(output.status.code(), Some(130));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Interrupted"));
+    assert!(list_files(&output_dir)
+        .iter()
+        .all(|path| !path.to_string_lossy().ends_with(".icon-gen-tmp")));
+}
+
+/// Every file under `dir`, relative to it
+fn list_files(dir: &Path) -> Vec<PathBuf> {
+    fn walk(dir: &Path, root: &Path, files: &mut Vec<PathBuf>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                walk(&path, root, files);
+            } else {
+                files.push(path.strip_prefix(root).unwrap().to_path_buf());
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    walk(dir, dir, &mut files);
+    files
+}
+
+/// Creates a square test image with a color gradient
+fn create_test_image(path: &Path, width: u32, height: u32) {
+    let mut image = RgbaImage::new(width, height);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let red = (255.0 * x as f32 / width as f32) as u8;
+        let green = (255.0 * y as f32 / height as f32) as u8;
+        *pixel = Rgba([red, green, 128, 255]);
+    }
+
+    image.save(path).expect("Failed to save test image");
+}
+
+/// Gets the path to the icon-gen binary
+#[cfg(unix)]
+fn get_icon_gen_binary_path() -> PathBuf {
+    let debug_path = Path::new("target/debug/icon-gen");
+    if !debug_path.exists() {
+        let build_output = std::process::Command::new("cargo")
+            .args(["build", "--bin", "icon-gen"])
+            .output()
+            .expect("Failed to run cargo build");
+        assert!(
+            build_output.status.success(),
+            "Failed to build icon-gen binary: {}",
+            String::from_utf8_lossy(&build_output.stderr)
+        );
+    }
+    debug_path.to_path_buf()
+}
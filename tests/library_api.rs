@@ -0,0 +1,626 @@
+use icon_gen::{
+    ArchiveFormat, ArchiveSink, IconGenError, IconGenerator, Platform, PlatformContext,
+    PlatformGenerator, WindowsIcons,
+};
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Test that the builder API writes the same files as the equivalent command line
+#[test]
+fn test_builder_matches_cli() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let library_dir = temp_path.join("library");
+    IconGenerator::new(&source_path)
+        .platforms([Platform::Windows, Platform::Linux])
+        .padding(10.0)
+        .output(&library_dir)
+        .run()
+        .expect("IconGenerator::run failed");
+
+    let cli_dir = temp_path.join("cli");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--windows", "--linux", "--padding", "10", "-o"])
+        .arg(&cli_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(output.status.success(), "icon-gen command failed");
+
+    let library_files = list_files(&library_dir);
+    assert!(library_files.contains(&PathBuf::from("windows/icon.ico")));
+    assert!(library_files.contains(&PathBuf::from("linux/icon.png")));
+    assert!(!library_files.contains(&PathBuf::from("macos/icon.icns")));
+    assert_eq!(library_files, list_files(&cli_dir));
+    for file in &library_files {
+        assert_eq!(
+            std::fs::read(library_dir.join(file)).unwrap(),
+            std::fs::read(cli_dir.join(file)).unwrap(),
+            "{} differs between the library and the command line",
+            file.display()
+        );
+    }
+}
+
+/// Test that the builder generates the round launcher icons `--android` enables by default
+#[test]
+fn test_builder_matches_cli_android() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 512, 512);
+
+    let library_dir = temp_path.join("library");
+    IconGenerator::new(&source_path)
+        .platforms([Platform::Android])
+        .output(&library_dir)
+        .run()
+        .expect("IconGenerator::run failed");
+
+    let cli_dir = temp_path.join("cli");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--android", "-o"])
+        .arg(&cli_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(output.status.success(), "icon-gen command failed");
+
+    let library_files = list_files(&library_dir);
+    assert!(library_files.contains(&PathBuf::from(
+        "android/mipmap-xxxhdpi/ic_launcher_round.png"
+    )));
+    assert_eq!(library_files, list_files(&cli_dir));
+}
+
+/// Test that generating into memory returns the files a run would write, without writing them
+#[test]
+fn test_generate_to_memory() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let generator = IconGenerator::new(&source_path)
+        .platforms([Platform::Windows, Platform::Ios])
+        .variant("grayscale")
+        .optimize(true);
+
+    let memory_dir = temp_path.join("memory");
+    let files = generator
+        .clone()
+        .output(&memory_dir)
+        .generate_to_memory()
+        .expect("generate_to_memory failed");
+    assert!(!memory_dir.exists());
+    assert!(!temp_path.join("memory_gray").exists());
+
+    let disk_dir = temp_path.join("disk");
+    generator
+        .output(&disk_dir)
+        .run()
+        .expect("IconGenerator::run failed");
+
+    let mut disk_files = list_files(&disk_dir)
+        .into_iter()
+        .map(|file| (memory_dir.join(&file), disk_dir.join(file)))
+        .collect::<Vec<_>>();
+    disk_files.extend(
+        list_files(&temp_path.join("disk_gray"))
+            .into_iter()
+            .map(|file| {
+                (
+                    temp_path.join("memory_gray").join(&file),
+                    temp_path.join("disk_gray").join(file),
+                )
+            }),
+    );
+    assert!(files.contains_key(&memory_dir.join("windows/icon.ico")));
+    assert!(files.contains_key(&temp_path.join("memory_gray/ios/Contents.json")));
+    assert_eq!(files.len(), disk_files.len());
+    for (memory_path, disk_path) in disk_files {
+        let bytes = files
+            .get(&memory_path)
+            .unwrap_or_else(|| panic!("{} was not generated", memory_path.display()));
+        assert_eq!(
+            *bytes,
+            std::fs::read(&disk_path).unwrap(),
+            "{} differs from the file written to disk",
+            memory_path.display()
+        );
+    }
+}
+
+/// Test that runs at the same time each encode with their own `encode_profile`
+#[test]
+fn test_concurrent_encode_profiles() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let generate = |profile: &str| {
+        let mut generator = IconGenerator::new(&source_path)
+            .platforms([Platform::Linux])
+            .output(temp_path.join(profile));
+        generator.args_mut().encode_profile = profile.to_string();
+        generator
+            .generate_to_memory()
+            .expect("generate_to_memory failed")
+    };
+    let fast = generate("fast");
+    let best = generate("best");
+    assert_ne!(
+        fast.values().next(),
+        best.values().next(),
+        "The profiles should encode differently"
+    );
+
+    for _ in 0..4 {
+        let (concurrent_fast, concurrent_best) = std::thread::scope(|scope| {
+            let fast = scope.spawn(|| generate("fast"));
+            let best = scope.spawn(|| generate("best"));
+            (fast.join().unwrap(), best.join().unwrap())
+        });
+        assert_eq!(concurrent_fast, fast);
+        assert_eq!(concurrent_best, best);
+    }
+}
+
+/// Test that a `strict` run only counts its own warnings, not those of a run
+/// at the same time
+#[test]
+fn test_concurrent_strict_runs() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    // Upscaled to every Linux size, which warns
+    let small_path = temp_path.join("small.png");
+    create_test_image(&small_path, 16, 16);
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 512, 512);
+
+    let warning = IconGenerator::new(&small_path).platforms([Platform::Linux]);
+    let error = warning
+        .clone()
+        .strict(true)
+        .generate_to_memory()
+        .expect_err("An upscaled source should fail a strict run");
+    assert!(matches!(error, IconGenError::Warnings { .. }), "{error:?}");
+
+    // Warning runs go on in the background for as long as the strict runs take
+    let done = std::sync::atomic::AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        let noisy = scope.spawn(|| {
+            while !done.load(std::sync::atomic::Ordering::Relaxed) {
+                warning
+                    .clone()
+                    .generate_to_memory()
+                    .expect("A run that isn't strict only warns");
+            }
+        });
+        let strict = (0..4)
+            .map(|_| {
+                IconGenerator::new(&source_path)
+                    .platforms([Platform::Linux, Platform::Windows])
+                    .strict(true)
+                    .generate_to_memory()
+            })
+            .collect::<Vec<_>>();
+        done.store(true, std::sync::atomic::Ordering::Relaxed);
+        noisy.join().unwrap();
+        for result in strict {
+            assert!(result.is_ok(), "{:?}", result.err());
+        }
+    });
+}
+
+/// Test that a source given as bytes generates the same files as one read from disk
+#[test]
+fn test_generate_from_bytes() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+    let bytes = std::fs::read(&source_path).unwrap();
+
+    let from_bytes = IconGenerator::from_bytes(bytes)
+        .platforms([Platform::Windows, Platform::Android])
+        .output("")
+        .generate_to_memory()
+        .expect("Generating from bytes failed");
+    let from_file = IconGenerator::new(&source_path)
+        .platforms([Platform::Windows, Platform::Android])
+        .output("")
+        .generate_to_memory()
+        .expect("Generating from a file failed");
+
+    assert!(from_bytes.contains_key(Path::new("windows/icon.ico")));
+    assert!(from_bytes.contains_key(Path::new("android/mipmap-hdpi/ic_launcher.png")));
+    assert_eq!(from_bytes, from_file);
+
+    let error = IconGenerator::from_bytes(&b"not an image"[..])
+        .generate_to_memory()
+        .expect_err("Undecodable bytes should fail");
+    assert!(error.to_string().contains("Failed to load image"));
+}
+
+/// Test that an animated favicon is decoded from a source given as bytes, never
+/// opening the placeholder input path
+#[test]
+fn test_animated_favicon_from_bytes() {
+    use image::{codecs::gif::GifEncoder, AnimationDecoder, Delay, Frame};
+
+    let mut gif = Vec::new();
+    let mut encoder = GifEncoder::new(&mut gif);
+    for red in [255, 0, 128] {
+        let frame = RgbaImage::from_pixel(64, 64, Rgba([red, 80, 160, 255]));
+        encoder
+            .encode_frame(Frame::from_parts(
+                frame,
+                0,
+                0,
+                Delay::from_numer_denom_ms(100, 1),
+            ))
+            .unwrap();
+    }
+    drop(encoder);
+
+    let mut generator = IconGenerator::from_bytes(gif).output("");
+    generator.args_mut().animated_favicon = true;
+    let files = generator
+        .generate_to_memory()
+        .expect("Generating an animated favicon from bytes failed");
+
+    let favicon = image::codecs::gif::GifDecoder::new(&files[Path::new("web/favicon.gif")][..])
+        .unwrap()
+        .into_frames()
+        .collect_frames()
+        .unwrap();
+    assert_eq!(favicon.len(), 3, "Every frame of the source should be kept");
+    assert_eq!(favicon[0].buffer().dimensions(), (32, 32));
+    assert!(files.contains_key(Path::new("web/favicon.apng")));
+}
+
+/// Test that a run through an archive sink archives every file relative to the sink root
+#[test]
+fn test_run_with_zip_sink() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let generator = IconGenerator::new(&source_path)
+        .platforms([Platform::Windows, Platform::Linux])
+        .output(temp_path.join("icons"));
+    let files = generator
+        .clone()
+        .generate_to_memory()
+        .expect("generate_to_memory failed");
+
+    let sink = ArchiveSink::new(temp_path, ArchiveFormat::Zip);
+    generator
+        .run_with(&sink)
+        .expect("IconGenerator::run_with failed");
+    let archive = sink.finish().expect("Failed to build the archive");
+    assert!(!temp_path.join("icons").exists());
+
+    // The end of central directory record holds the entry count
+    let end = archive.len() - 22;
+    assert_eq!(archive[end..end + 4], [0x50, 0x4b, 0x05, 0x06]);
+    assert_eq!(
+        u16::from_le_bytes([archive[end + 10], archive[end + 11]]) as usize,
+        files.len()
+    );
+    let contains = |name: &str| {
+        archive
+            .windows(name.len())
+            .any(|window| window == name.as_bytes())
+    };
+    assert!(contains("icons/windows/icon.ico"));
+    assert!(contains("icons/linux/icon.png"));
+}
+
+/// A custom platform writing one PNG per size and a listing of them
+#[derive(Debug)]
+struct Kiosk;
+
+impl PlatformGenerator for Kiosk {
+    fn name(&self) -> &str {
+        "kiosk"
+    }
+
+    fn sizes(&self) -> Vec<u32> {
+        vec![40, 80]
+    }
+
+    fn emit(&self, ctx: &PlatformContext<'_>) -> icon_gen::error::Result<()> {
+        let mut listing = String::new();
+        for size in self.sizes() {
+            let name = format!("kiosk/icon-{size}.png");
+            ctx.write_png(&name, &ctx.render(size))?;
+            listing.push_str(&name);
+            listing.push('\n');
+        }
+        ctx.write("kiosk/icons.txt", listing.as_bytes())
+    }
+}
+
+/// Test that a registered platform is generated alongside the selected built-in ones
+#[test]
+fn test_register_custom_platform() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let source_path = temp_dir.path().join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let files = IconGenerator::new(&source_path)
+        .platforms([Platform::Windows])
+        .register(Kiosk)
+        .output("out")
+        .generate_to_memory()
+        .expect("generate_to_memory failed");
+
+    assert!(files.contains_key(Path::new("out/windows/icon.ico")));
+    let kiosk_files = files
+        .keys()
+        .filter(|path| path.starts_with("out/kiosk"))
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kiosk_files,
+        [
+            "out/kiosk/icon-40.png",
+            "out/kiosk/icon-80.png",
+            "out/kiosk/icons.txt",
+        ]
+        .map(PathBuf::from)
+    );
+
+    let icon = image::load_from_memory(&files[Path::new("out/kiosk/icon-80.png")])
+        .expect("Failed to decode custom platform icon");
+    assert_eq!((icon.width(), icon.height()), (80, 80));
+    assert_eq!(
+        files[Path::new("out/kiosk/icons.txt")],
+        b"kiosk/icon-40.png\nkiosk/icon-80.png\n"
+    );
+
+    // Registering a platform on its own leaves out the built-in ones
+    let files = IconGenerator::new(&source_path)
+        .register(Kiosk)
+        .output("out")
+        .generate_to_memory()
+        .expect("generate_to_memory failed");
+    assert!(files.keys().all(|path| path.starts_with("out/kiosk")));
+    assert_eq!(WindowsIcons.sizes(), [16, 24, 32, 48, 64, 256]);
+}
+
+/// A custom platform whose encoder always fails
+#[derive(Debug)]
+struct Broken;
+
+impl PlatformGenerator for Broken {
+    fn name(&self) -> &str {
+        "broken"
+    }
+
+    fn sizes(&self) -> Vec<u32> {
+        vec![16]
+    }
+
+    fn emit(&self, _ctx: &PlatformContext<'_>) -> icon_gen::error::Result<()> {
+        Err(IconGenError::EncodeError(
+            "Failed to encode broken icon".to_string(),
+        ))
+    }
+}
+
+/// Test that keep_going generates the other platforms and lists the failed one
+#[test]
+fn test_keep_going() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let source_path = temp_dir.path().join("test_source.png");
+    create_test_image(&source_path, 64, 64);
+    let output_dir = temp_dir.path().join("icons");
+
+    let error = IconGenerator::new(&source_path)
+        .platforms([Platform::Windows])
+        .register(Broken)
+        .register(Kiosk)
+        .output(&output_dir)
+        .keep_going(true)
+        .run()
+        .expect_err("The broken platform should fail the run");
+    match error {
+        IconGenError::PlatformsFailed {
+            failures,
+            generated,
+        } => {
+            // windows, tauri-desktop and kiosk
+            assert_eq!(generated, 3);
+            assert_eq!(failures.len(), 1);
+            assert_eq!(failures[0].set, output_dir);
+            assert_eq!(failures[0].platform, "broken");
+            assert!(failures[0].message.contains("Failed to encode broken icon"));
+        }
+        error => panic!("Expected failed platforms, got {error:?}"),
+    }
+    assert!(output_dir.join("windows/icon.ico").exists());
+    assert!(output_dir.join("kiosk/icons.txt").exists());
+
+    // Without it the run stops at the failure
+    let error = IconGenerator::new(&source_path)
+        .register(Broken)
+        .register(Kiosk)
+        .generate_to_memory()
+        .expect_err("The broken platform should fail the run");
+    assert!(matches!(error, IconGenError::EncodeError(_)), "{error:?}");
+}
+
+/// Test that invalid options are reported as errors rather than panics
+#[test]
+fn test_builder_reports_errors() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 64, 64);
+
+    let error = IconGenerator::new(&source_path)
+        .png_sizes([16])
+        .filter("bicubic")
+        .output(temp_path.join("icons"))
+        .run()
+        .expect_err("An unknown filter should fail");
+    assert!(error.to_string().contains("Unknown filter: bicubic"));
+
+    assert_eq!(
+        icon_gen::default_output_dir(Path::new("assets/logo.png")),
+        PathBuf::from("icon-generator-logo")
+    );
+}
+
+/// Test that failures can be told apart by their `IconGenError` variant
+#[test]
+fn test_error_variants() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let missing = temp_path.join("missing.png");
+    match IconGenerator::new(&missing).generate_to_memory() {
+        Err(IconGenError::IoError { path, source }) => {
+            assert_eq!(path, missing);
+            assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+        }
+        result => panic!("Expected an I/O error, got {result:?}"),
+    }
+
+    let error = IconGenerator::from_bytes(&b"not an image"[..])
+        .generate_to_memory()
+        .expect_err("Undecodable bytes should fail");
+    assert!(
+        matches!(error, IconGenError::UnsupportedFormat(_)),
+        "{error:?}"
+    );
+
+    let source_path = temp_path.join("wide.png");
+    create_test_image(&source_path, 64, 32);
+    let error = IconGenerator::new(&source_path)
+        .generate_to_memory()
+        .expect_err("A non-square source should fail");
+    match error {
+        IconGenError::InvalidInput(message) => assert!(message.contains("must be square")),
+        error => panic!("Expected invalid input, got {error:?}"),
+    }
+
+    assert!(matches!(
+        "windoze".parse::<Platform>(),
+        Err(IconGenError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        ArchiveFormat::from_path(Path::new("icons.rar")),
+        Err(IconGenError::UnsupportedFormat(_))
+    ));
+}
+
+/// Test that an I/O error without a path keeps the context it was raised in,
+/// both in the library error and in the CLI message
+#[test]
+fn test_io_error_context() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 64, 64);
+    // A file where the output directory's parent should be
+    std::fs::write(temp_path.join("blocker"), b"").unwrap();
+    let output_dir = temp_path.join("blocker").join("sub");
+
+    let error = IconGenerator::new(&source_path)
+        .platforms([Platform::Linux])
+        .output(&output_dir)
+        .run()
+        .expect_err("The output directory can't be created");
+    assert!(matches!(error, IconGenError::IoError { .. }), "{error:?}");
+    assert_eq!(error.to_string(), "Can't create output directory");
+
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--no-config", "--linux", "-o"])
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.starts_with("Error: Can't create output directory\n\nCaused by:\n"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("I/O error"), "{stderr}");
+}
+
+/// Every file under `dir`, relative to it, apart from the generation cache
+fn list_files(dir: &Path) -> Vec<PathBuf> {
+    fn walk(dir: &Path, root: &Path, files: &mut Vec<PathBuf>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                walk(&path, root, files);
+            } else if path.file_name().unwrap() != ".icon-gen-cache.json" {
+                files.push(path.strip_prefix(root).unwrap().to_path_buf());
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    walk(dir, dir, &mut files);
+    files.sort();
+    files
+}
+
+/// Creates a test image with a gradient pattern
+fn create_test_image(path: &Path, width: u32, height: u32) {
+    let mut image = RgbaImage::new(width, height);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let red = (255.0 * x as f32 / width as f32) as u8;
+        let green = (255.0 * y as f32 / height as f32) as u8;
+        *pixel = Rgba([red, green, 128, 255]);
+    }
+
+    image.save(path).expect("Failed to save test image");
+}
+
+/// Gets the path to the icon-gen binary
+fn get_icon_gen_binary_path() -> std::path::PathBuf {
+    // First try to find in target/debug
+    let debug_path = std::path::Path::new("target/debug/icon-gen");
+    if debug_path.exists() {
+        return debug_path.to_path_buf();
+    }
+
+    // If not found, build it first
+    let build_output = Command::new("cargo")
+        .args(["build", "--bin", "icon-gen"])
+        .output()
+        .expect("Failed to run cargo build");
+
+    if !build_output.status.success() {
+        panic!(
+            "Failed to build icon-gen binary: {}",
+            String::from_utf8_lossy(&build_output.stderr)
+        );
+    }
+
+    debug_path.to_path_buf()
+}
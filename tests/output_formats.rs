@@ -0,0 +1,2084 @@
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+/// Test that `--formats png,bmp` writes both encodings for every custom size,
+/// with BMP output flattened to opaque 24-bit color.
+#[test]
+fn test_custom_sizes_bmp_output() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let output_dir = temp_path.join("test_output");
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "32,64".as_ref(),
+        "--formats".as_ref(),
+        "png,bmp".as_ref(),
+        "-o".as_ref(),
+        output_dir.as_os_str(),
+    ]);
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --formats failed");
+    }
+
+    for size in [32, 64] {
+        assert!(output_dir.join(format!("{size}x{size}.png")).exists());
+
+        let bmp_path = output_dir.join(format!("{size}x{size}.bmp"));
+        let bmp = image::open(&bmp_path).expect("Failed to load generated BMP");
+        assert_eq!(bmp.width(), size);
+        assert_eq!(bmp.color(), image::ColorType::Rgb8, "BMP should be 24-bit");
+
+        // The transparent left half of the source is flattened onto white
+        let pixel = bmp.to_rgba8().get_pixel(0, size / 2).0;
+        assert_eq!(pixel, [255, 255, 255, 255]);
+    }
+}
+
+/// Test that `--formats webp` writes lossless WebP identical in pixels to the PNG output
+#[test]
+fn test_custom_sizes_webp_output() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let output_dir = temp_path.join("test_output");
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "48".as_ref(),
+        "--formats".as_ref(),
+        "png,webp".as_ref(),
+        "-o".as_ref(),
+        output_dir.as_os_str(),
+    ]);
+
+    assert!(output.status.success(), "icon-gen --formats webp failed");
+
+    let png = image::open(output_dir.join("48x48.png"))
+        .expect("Failed to load generated PNG")
+        .to_rgba8();
+    let webp = image::open(output_dir.join("48x48.webp"))
+        .expect("Failed to load generated WebP")
+        .to_rgba8();
+
+    assert_eq!(webp.dimensions(), (48, 48));
+    assert_eq!(png, webp, "Lossless WebP should match the PNG pixels");
+}
+
+/// Test that `--padding` insets the artwork and fills the margin with `--padding-color`
+#[test]
+fn test_padding_with_color() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let output_dir = temp_path.join("test_output");
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "100".as_ref(),
+        "--padding".as_ref(),
+        "20".as_ref(),
+        "--padding-color".as_ref(),
+        "#0000ff".as_ref(),
+        "-o".as_ref(),
+        output_dir.as_os_str(),
+    ]);
+
+    assert!(output.status.success(), "icon-gen --padding failed");
+
+    let icon = image::open(output_dir.join("100x100.png"))
+        .expect("Failed to load generated PNG")
+        .to_rgba8();
+    assert_eq!(icon.dimensions(), (100, 100));
+
+    // The artwork now spans 20..80 on both axes
+    let blue = Rgba([0, 0, 255, 255]);
+    assert_eq!(*icon.get_pixel(10, 50), blue);
+    assert_eq!(*icon.get_pixel(90, 50), blue);
+    assert_eq!(*icon.get_pixel(50, 10), blue);
+    assert_eq!(
+        icon.get_pixel(70, 50)[0],
+        200,
+        "Artwork should be inside the margin"
+    );
+
+    // Padding of half the side or more would leave no room for the artwork
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "32".as_ref(),
+        "--padding".as_ref(),
+        "50".as_ref(),
+        "-o".as_ref(),
+        temp_path.join("invalid_output").as_os_str(),
+    ]);
+    assert!(!output.status.success(), "Padding of 50% should fail");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid padding"));
+
+    // A typo fails instead of filling the margin with white
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "32".as_ref(),
+        "--padding".as_ref(),
+        "20".as_ref(),
+        "--padding-color".as_ref(),
+        "nonsense".as_ref(),
+        "-o".as_ref(),
+        temp_path.join("invalid_color").as_os_str(),
+    ]);
+    assert!(
+        !output.status.success(),
+        "An invalid --padding-color should fail"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid --padding-color: nonsense"));
+}
+
+/// Test that `--variant grayscale` writes a desaturated parallel set into `{output}_gray`
+/// while the main set keeps its colors
+#[test]
+fn test_grayscale_variant() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let output_dir = temp_path.join("test_output");
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "64".as_ref(),
+        "--formats".as_ref(),
+        "png,webp".as_ref(),
+        "--variant".as_ref(),
+        "grayscale".as_ref(),
+        "-o".as_ref(),
+        output_dir.as_os_str(),
+    ]);
+
+    assert!(
+        output.status.success(),
+        "icon-gen --variant grayscale failed"
+    );
+
+    let color = image::open(output_dir.join("64x64.png"))
+        .expect("Failed to load generated PNG")
+        .to_rgba8();
+    assert_ne!(color.get_pixel(48, 32)[0], color.get_pixel(48, 32)[2]);
+
+    let gray_dir = temp_path.join("test_output_gray");
+    assert!(
+        gray_dir.join("64x64.webp").exists(),
+        "Variant should use every format"
+    );
+    let gray = image::open(gray_dir.join("64x64.png"))
+        .expect("Failed to load grayscale PNG")
+        .to_rgba8();
+    assert_eq!(gray.dimensions(), (64, 64));
+    for pixel in gray.pixels() {
+        assert!(
+            pixel[0] == pixel[1] && pixel[1] == pixel[2],
+            "Pixel should be gray"
+        );
+    }
+    assert_eq!(gray.get_pixel(0, 32)[3], 0, "Transparency should be kept");
+    assert_eq!(gray.get_pixel(48, 32)[3], 255);
+}
+
+/// Test that `--tint` recolors the artwork while keeping its alpha, and that
+/// `--tint-mode multiply` keeps the shading
+#[test]
+fn test_tint() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    for (mode, expected) in [
+        ("recolor", [255, 0, 0, 255]),
+        ("multiply", [200, 0, 0, 255]),
+    ] {
+        let output_dir = temp_path.join(format!("test_output_{mode}"));
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "-p".as_ref(),
+            "64".as_ref(),
+            "--tint".as_ref(),
+            "#ff0000".as_ref(),
+            "--tint-mode".as_ref(),
+            mode.as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ]);
+
+        assert!(
+            output.status.success(),
+            "icon-gen --tint-mode {mode} failed"
+        );
+
+        let icon = image::open(output_dir.join("64x64.png"))
+            .expect("Failed to load generated PNG")
+            .to_rgba8();
+        assert_eq!(icon.get_pixel(48, 32).0, expected, "{mode} tint");
+        assert_eq!(icon.get_pixel(0, 32)[3], 0, "Transparency should be kept");
+    }
+
+    // A typo fails instead of tinting white
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "64".as_ref(),
+        "--tint".as_ref(),
+        "#ff00zz".as_ref(),
+        "-o".as_ref(),
+        temp_path.join("test_output_invalid").as_os_str(),
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid --tint color: #ff00zz"));
+    assert!(!temp_path.join("test_output_invalid").exists());
+}
+
+/// Test that `--adjust` corrects the source before resizing and rejects unknown names
+#[test]
+fn test_adjustments() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    // Opaque solid color so resizing leaves the adjusted values untouched
+    let source_path = temp_path.join("test_source.png");
+    RgbaImage::from_pixel(128, 128, Rgba([200, 100, 40, 255]))
+        .save(&source_path)
+        .unwrap();
+
+    let cases = [
+        ("brightness=0.5", [100, 50, 20, 255]),
+        ("contrast=0", [128, 128, 128, 255]),
+        ("saturation=0", [123, 123, 123, 255]),
+    ];
+    for (adjustment, expected) in cases {
+        let output_dir = temp_path.join(adjustment.replace('=', "_"));
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "-p".as_ref(),
+            "32".as_ref(),
+            "--adjust".as_ref(),
+            adjustment.as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ]);
+
+        assert!(
+            output.status.success(),
+            "icon-gen --adjust {adjustment} failed"
+        );
+
+        let icon = image::open(output_dir.join("32x32.png"))
+            .expect("Failed to load generated PNG")
+            .to_rgba8();
+        assert_eq!(icon.get_pixel(16, 16).0, expected, "{adjustment}");
+    }
+
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "32".as_ref(),
+        "--adjust".as_ref(),
+        "gamma=2".as_ref(),
+        "-o".as_ref(),
+        temp_path.join("invalid_output").as_os_str(),
+    ]);
+    assert!(!output.status.success(), "Unknown adjustment should fail");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown adjustment"));
+}
+
+/// Test that `--hue-variants` writes one hue-rotated set per named channel next to
+/// the main output
+#[test]
+fn test_hue_variants() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    RgbaImage::from_pixel(128, 128, Rgba([220, 20, 20, 255]))
+        .save(&source_path)
+        .unwrap();
+
+    let output_dir = temp_path.join("icons");
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "32".as_ref(),
+        "--hue-variants".as_ref(),
+        "dev=120,staging=240".as_ref(),
+        "-o".as_ref(),
+        output_dir.as_os_str(),
+    ]);
+
+    assert!(output.status.success(), "icon-gen --hue-variants failed");
+
+    let dominant_channel = |dir: &str| {
+        let icon = image::open(temp_path.join(dir).join("32x32.png"))
+            .expect("Failed to load generated PNG")
+            .to_rgba8();
+        let pixel = icon.get_pixel(16, 16);
+        assert_eq!(pixel[3], 255);
+        (0..3).max_by_key(|&channel| pixel[channel]).unwrap()
+    };
+
+    assert_eq!(dominant_channel("icons"), 0, "Main set stays red");
+    assert_eq!(
+        dominant_channel("icons_dev"),
+        1,
+        "120 degrees turns red into green"
+    );
+    assert_eq!(
+        dominant_channel("icons_staging"),
+        2,
+        "240 degrees turns red into blue"
+    );
+}
+
+/// Test that `--quantize` rewrites small PNGs as palette images and leaves large ones alone
+#[test]
+fn test_quantize_small_pngs() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let output_dir = temp_path.join("test_output");
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "32,128".as_ref(),
+        "--quantize".as_ref(),
+        "16".as_ref(),
+        "-o".as_ref(),
+        output_dir.as_os_str(),
+    ]);
+
+    assert!(output.status.success(), "icon-gen --quantize failed");
+
+    let png_info = |name: &str| {
+        let file = std::fs::File::open(output_dir.join(name)).expect("PNG should exist");
+        let reader = png::Decoder::new(file).read_info().unwrap();
+        let info = reader.info();
+        (
+            info.color_type,
+            info.palette.as_ref().map(|palette| palette.len() / 3),
+        )
+    };
+
+    let (color_type, palette_len) = png_info("32x32.png");
+    assert_eq!(color_type, png::ColorType::Indexed);
+    assert!(palette_len.unwrap() <= 16);
+    assert_eq!(png_info("128x128.png").0, png::ColorType::Rgba);
+
+    // Transparency survives through the tRNS entries
+    let icon = image::open(output_dir.join("32x32.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(icon.get_pixel(4, 16)[3], 0);
+    assert_eq!(icon.get_pixel(28, 16)[3], 255);
+
+    // A palette needs at least two entries
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "32".as_ref(),
+        "--quantize".as_ref(),
+        "1".as_ref(),
+        "-o".as_ref(),
+        temp_path.join("rejected").as_os_str(),
+    ]);
+    assert!(!output.status.success(), "--quantize 1 should fail");
+}
+
+/// Test that `--quantize` leaves alone the PNGs in the output directory that
+/// icon-gen didn't write, even ones that aren't images
+#[test]
+fn test_quantize_skips_unmanaged_pngs() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+    let output_dir = temp_path.join("icons");
+    let logo_path = output_dir.join("linux/user-logo.png");
+    std::fs::create_dir_all(logo_path.parent().unwrap()).unwrap();
+    create_test_image(&logo_path, 32, 32);
+    let logo = std::fs::read(&logo_path).unwrap();
+    let broken_path = output_dir.join("linux/broken.png");
+    std::fs::write(&broken_path, b"not a png").unwrap();
+
+    for extra in [None, Some("--force")] {
+        let mut args = vec![
+            source_path.as_os_str(),
+            "--linux".as_ref(),
+            "--quantize".as_ref(),
+            "4".as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ];
+        args.extend(extra.map(std::ffi::OsStr::new));
+        let output = run_icon_gen(&args);
+        assert!(
+            output.status.success(),
+            "icon-gen --quantize {extra:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(std::fs::read(&logo_path).unwrap(), logo);
+        assert_eq!(std::fs::read(&broken_path).unwrap(), b"not a png");
+    }
+
+    let file = std::fs::File::open(output_dir.join("linux/32x32.png")).unwrap();
+    let reader = png::Decoder::new(file).read_info().unwrap();
+    assert_eq!(reader.info().color_type, png::ColorType::Indexed);
+}
+
+/// Test that `--optimize` shrinks PNGs without changing a single pixel
+#[test]
+fn test_optimize_is_lossless() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let plain_dir = temp_path.join("plain");
+    let optimized_dir = temp_path.join("optimized");
+    for (dir, extra) in [(&plain_dir, None), (&optimized_dir, Some("--optimize"))] {
+        let mut args = vec![
+            source_path.as_os_str(),
+            "-p".as_ref(),
+            "16,64".as_ref(),
+            "-o".as_ref(),
+            dir.as_os_str(),
+        ];
+        args.extend(extra.map(std::ffi::OsStr::new));
+        let output = run_icon_gen(&args);
+        assert!(
+            output.status.success(),
+            "icon-gen {extra:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    for name in ["16x16.png", "64x64.png"] {
+        let plain = image::open(plain_dir.join(name)).unwrap().to_rgba8();
+        let optimized = image::open(optimized_dir.join(name)).unwrap().to_rgba8();
+        assert_eq!(plain, optimized, "{name} pixels should be unchanged");
+
+        let size = |dir: &Path| std::fs::metadata(dir.join(name)).unwrap().len();
+        assert!(size(&optimized_dir) <= size(&plain_dir));
+    }
+
+    // A PNG put into the set by hand isn't re-encoded, even with --force
+    let user_path = optimized_dir.join("user.png");
+    create_test_image(&user_path, 64, 64);
+    let user = std::fs::read(&user_path).unwrap();
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "16,64".as_ref(),
+        "--optimize".as_ref(),
+        "--force".as_ref(),
+        "-o".as_ref(),
+        optimized_dir.as_os_str(),
+    ]);
+    assert!(
+        output.status.success(),
+        "icon-gen --optimize --force failed"
+    );
+    assert_eq!(std::fs::read(&user_path).unwrap(), user);
+
+    // Two flat colors fit a 1-bit palette
+    let flat_path = temp_path.join("flat.png");
+    RgbaImage::from_fn(64, 64, |x, _| {
+        if x < 32 {
+            Rgba([0, 0, 0, 0])
+        } else {
+            Rgba([30, 90, 200, 255])
+        }
+    })
+    .save(&flat_path)
+    .unwrap();
+
+    let flat_dir = temp_path.join("flat");
+    let output = run_icon_gen(&[
+        flat_path.as_os_str(),
+        "-p".as_ref(),
+        "64".as_ref(),
+        "--optimize".as_ref(),
+        "-o".as_ref(),
+        flat_dir.as_os_str(),
+    ]);
+    assert!(output.status.success(), "icon-gen --optimize failed");
+
+    let file = std::fs::File::open(flat_dir.join("64x64.png")).unwrap();
+    let reader = png::Decoder::new(file).read_info().unwrap();
+    assert_eq!(reader.info().color_type, png::ColorType::Indexed);
+    assert_eq!(reader.info().bit_depth, png::BitDepth::One);
+
+    let icon = image::open(flat_dir.join("64x64.png")).unwrap().to_rgba8();
+    assert_eq!(*icon.get_pixel(10, 10), Rgba([0, 0, 0, 0]));
+    assert_eq!(*icon.get_pixel(50, 10), Rgba([30, 90, 200, 255]));
+}
+
+/// Test that `--compression zopfli` produces smaller PNGs with the same pixels
+#[test]
+fn test_zopfli_compression() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let best_dir = temp_path.join("best");
+    let zopfli_dir = temp_path.join("zopfli");
+    for (dir, mode) in [(&best_dir, "best"), (&zopfli_dir, "zopfli")] {
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "-p".as_ref(),
+            "64".as_ref(),
+            "--compression".as_ref(),
+            mode.as_ref(),
+            "-o".as_ref(),
+            dir.as_os_str(),
+        ]);
+        assert!(
+            output.status.success(),
+            "icon-gen --compression {mode} failed"
+        );
+    }
+
+    let best = image::open(best_dir.join("64x64.png")).unwrap().to_rgba8();
+    let zopfli = image::open(zopfli_dir.join("64x64.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(best, zopfli, "Recompression must not change pixels");
+
+    let size = |dir: &Path| std::fs::metadata(dir.join("64x64.png")).unwrap().len();
+    assert!(size(&zopfli_dir) < size(&best_dir));
+
+    // A PNG put into the set by hand isn't recompressed
+    let user_path = zopfli_dir.join("user.png");
+    std::fs::copy(best_dir.join("64x64.png"), &user_path).unwrap();
+    let user = std::fs::read(&user_path).unwrap();
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "64".as_ref(),
+        "--compression".as_ref(),
+        "zopfli".as_ref(),
+        "-o".as_ref(),
+        zopfli_dir.as_os_str(),
+    ]);
+    assert!(
+        output.status.success(),
+        "icon-gen --compression zopfli failed"
+    );
+    assert_eq!(std::fs::read(&user_path).unwrap(), user);
+
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "64".as_ref(),
+        "--compression".as_ref(),
+        "gzip".as_ref(),
+        "-o".as_ref(),
+        temp_path.join("rejected").as_os_str(),
+    ]);
+    assert!(!output.status.success(), "Unknown compression should fail");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown compression"));
+}
+
+/// Test that `--filter nearest` keeps pixel art crisp where the default filter blends
+#[test]
+fn test_resize_filter() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    // 8x8 checkerboard of 16px cells
+    let source_path = temp_path.join("pixel_art.png");
+    RgbaImage::from_fn(128, 128, |x, y| {
+        if (x / 16 + y / 16) % 2 == 0 {
+            Rgba([255, 0, 0, 255])
+        } else {
+            Rgba([0, 0, 255, 255])
+        }
+    })
+    .save(&source_path)
+    .unwrap();
+
+    let colors = |filter: &str| {
+        let output_dir = temp_path.join(filter);
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "-p".as_ref(),
+            "24".as_ref(),
+            "--filter".as_ref(),
+            filter.as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ]);
+        assert!(output.status.success(), "icon-gen --filter {filter} failed");
+
+        let icon = image::open(output_dir.join("24x24.png"))
+            .unwrap()
+            .to_rgba8();
+        let mut colors: Vec<_> = icon.pixels().map(|pixel| pixel.0).collect();
+        colors.sort();
+        colors.dedup();
+        colors.len()
+    };
+
+    assert_eq!(
+        colors("nearest"),
+        2,
+        "Nearest should only pick source colors"
+    );
+    assert!(colors("lanczos3") > 2, "Lanczos3 should blend cell edges");
+
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "24".as_ref(),
+        "--filter".as_ref(),
+        "bicubic".as_ref(),
+        "-o".as_ref(),
+        temp_path.join("rejected").as_os_str(),
+    ]);
+    assert!(!output.status.success(), "Unknown filter should fail");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown filter"));
+}
+
+/// Test that `--sharpen` adds contrast to small sizes only
+#[test]
+fn test_sharpen_small_sizes() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    // A soft horizontal ramp from black to white across the middle
+    let source_path = temp_path.join("ramp.png");
+    RgbaImage::from_fn(128, 128, |x, _| {
+        let value = (x.saturating_sub(32) * 4).min(255) as u8;
+        Rgba([value, value, value, 255])
+    })
+    .save(&source_path)
+    .unwrap();
+
+    let generate = |name: &str, sharpen: Option<&str>| {
+        let output_dir = temp_path.join(name);
+        let mut args = vec![
+            source_path.as_os_str(),
+            "-p".as_ref(),
+            "32,64".as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ];
+        if let Some(amount) = sharpen {
+            args.extend(["--sharpen", amount].map(std::ffi::OsStr::new));
+        }
+        let output = run_icon_gen(&args);
+        assert!(output.status.success(), "icon-gen {sharpen:?} failed");
+        output_dir
+    };
+    let plain_dir = generate("plain", None);
+    let sharp_dir = generate("sharp", Some("1.5"));
+
+    // Contrast as the total deviation from mid-gray along a row
+    let contrast = |dir: &Path, name: &str| {
+        let icon = image::open(dir.join(name)).unwrap().to_rgba8();
+        let y = icon.height() / 2;
+        (0..icon.width())
+            .map(|x| (i32::from(icon.get_pixel(x, y)[0]) - 128).abs())
+            .sum::<i32>()
+    };
+    assert!(contrast(&sharp_dir, "32x32.png") > contrast(&plain_dir, "32x32.png"));
+
+    let plain = image::open(plain_dir.join("64x64.png")).unwrap().to_rgba8();
+    let sharp = image::open(sharp_dir.join("64x64.png")).unwrap().to_rgba8();
+    assert_eq!(plain, sharp, "Sizes above 48px are not sharpened");
+}
+
+/// Test that --supersample only changes outputs of 32px and below
+#[test]
+fn test_supersample_small_sizes() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    // One-pixel grid lines, the kind of detail a single downscale aliases away
+    let source_path = temp_path.join("grid.png");
+    RgbaImage::from_fn(256, 256, |x, y| {
+        if x % 7 == 0 || y % 11 == 0 {
+            Rgba([0, 0, 0, 255])
+        } else {
+            Rgba([255, 255, 255, 255])
+        }
+    })
+    .save(&source_path)
+    .unwrap();
+
+    let generate = |name: &str, supersample: bool| {
+        let output_dir = temp_path.join(name);
+        let mut args = vec![
+            source_path.as_os_str(),
+            "-p".as_ref(),
+            "16,64".as_ref(),
+            "--filter".as_ref(),
+            "triangle".as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ];
+        if supersample {
+            args.push("--supersample".as_ref());
+        }
+        let output = run_icon_gen(&args);
+        assert!(
+            output.status.success(),
+            "icon-gen supersample={supersample} failed"
+        );
+        output_dir
+    };
+    let plain_dir = generate("plain", false);
+    let super_dir = generate("super", true);
+
+    let load = |dir: &Path, name: &str| image::open(dir.join(name)).unwrap().to_rgba8();
+    let plain = load(&plain_dir, "16x16.png");
+    let supersampled = load(&super_dir, "16x16.png");
+    assert_eq!(supersampled.dimensions(), (16, 16));
+    assert_ne!(plain, supersampled, "16px output is rendered through 4x");
+
+    assert_eq!(
+        load(&plain_dir, "64x64.png"),
+        load(&super_dir, "64x64.png"),
+        "Sizes above 32px are resized directly"
+    );
+}
+
+/// Test that unknown formats are rejected
+#[test]
+fn test_unknown_format_rejected() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 64, 64);
+
+    let output_dir = temp_path.join("test_output");
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "32".as_ref(),
+        "--formats".as_ref(),
+        "tga".as_ref(),
+        "-o".as_ref(),
+        output_dir.as_os_str(),
+    ]);
+
+    assert!(!output.status.success(), "Unknown format should fail");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown output format"));
+}
+
+/// Test that --legibility thickens and sharpens outputs of 32px and below only
+#[test]
+fn test_legibility_boost() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    // Thin gray strokes on transparency, which fade to a faint smudge at 16px
+    let source_path = temp_path.join("strokes.png");
+    RgbaImage::from_fn(256, 256, |x, y| {
+        if x % 32 < 4 || y % 32 < 4 {
+            Rgba([96, 96, 96, 255])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    })
+    .save(&source_path)
+    .unwrap();
+
+    let generate = |name: &str, legibility: bool| {
+        let output_dir = temp_path.join(name);
+        let mut args = vec![
+            source_path.as_os_str(),
+            "-p".as_ref(),
+            "16,64".as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ];
+        if legibility {
+            args.push("--legibility".as_ref());
+        }
+        let output = run_icon_gen(&args);
+        assert!(
+            output.status.success(),
+            "icon-gen legibility={legibility} failed"
+        );
+        output_dir
+    };
+    let plain_dir = generate("plain", false);
+    let boosted_dir = generate("boosted", true);
+
+    let load = |dir: &Path, name: &str| image::open(dir.join(name)).unwrap().to_rgba8();
+    let coverage = |image: &RgbaImage| image.pixels().map(|p| p[3] as u32).sum::<u32>();
+    let plain = load(&plain_dir, "16x16.png");
+    let boosted = load(&boosted_dir, "16x16.png");
+    assert!(
+        coverage(&boosted) > coverage(&plain),
+        "Strokes are thickened at 16px"
+    );
+    let darkest = |image: &RgbaImage| {
+        image
+            .pixels()
+            .filter(|p| p[3] > 0)
+            .map(|p| p[0])
+            .min()
+            .unwrap()
+    };
+    assert!(
+        darkest(&boosted) < darkest(&plain),
+        "Contrast is boosted at 16px"
+    );
+
+    assert_eq!(
+        load(&plain_dir, "64x64.png"),
+        load(&boosted_dir, "64x64.png"),
+        "Sizes above 32px are left as resized"
+    );
+}
+
+/// Test that --size-rule swaps in its own source and padding below the threshold
+#[test]
+fn test_size_rule() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let solid = |name: &str, color: Rgba<u8>| {
+        let path = temp_path.join(name);
+        RgbaImage::from_pixel(256, 256, color).save(&path).unwrap();
+        path
+    };
+    let source_path = solid("large.png", Rgba([255, 0, 0, 255]));
+    let small_path = solid("small.png", Rgba([0, 0, 255, 255]));
+
+    let output_dir = temp_path.join("icons");
+    let rule = format!("<48:source={},padding=25", small_path.display());
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "32,48,64".as_ref(),
+        "--size-rule".as_ref(),
+        rule.as_ref(),
+        "-o".as_ref(),
+        output_dir.as_os_str(),
+    ]);
+    assert!(output.status.success(), "icon-gen with --size-rule failed");
+
+    let load = |name: &str| image::open(output_dir.join(name)).unwrap().to_rgba8();
+    let small = load("32x32.png");
+    assert_eq!(*small.get_pixel(16, 16), Rgba([0, 0, 255, 255]));
+    assert_eq!(
+        small.get_pixel(0, 0)[3],
+        0,
+        "Rule padding applies below 48px"
+    );
+    for name in ["48x48.png", "64x64.png"] {
+        let icon = load(name);
+        assert_eq!(*icon.get_pixel(0, 0), Rgba([255, 0, 0, 255]), "{name}");
+    }
+
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "32".as_ref(),
+        "--size-rule".as_ref(),
+        "<48:glow=off".as_ref(),
+        "-o".as_ref(),
+        temp_path.join("invalid").as_os_str(),
+    ]);
+    assert!(
+        !output.status.success(),
+        "Unknown rule settings are rejected"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown size rule setting"));
+}
+
+/// Test that parallel generation writes the same files as a single worker
+#[test]
+fn test_jobs_output_is_deterministic() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let generate = |name: &str, jobs: &str| {
+        let output_dir = temp_path.join(name);
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "--windows".as_ref(),
+            "--linux".as_ref(),
+            "--ios".as_ref(),
+            "--jobs".as_ref(),
+            jobs.as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ]);
+        assert!(output.status.success(), "icon-gen --jobs {jobs} failed");
+        output_dir
+    };
+    let serial_dir = generate("serial", "1");
+    let parallel_dir = generate("parallel", "4");
+
+    for file in [
+        "windows/icon.ico",
+        "linux/32x32.png",
+        "linux/icon.png",
+        "ios/AppIcon-20x20@2x.png",
+        "ios/AppIcon-1024x1024.png",
+        "ios/Contents.json",
+    ] {
+        assert_eq!(
+            std::fs::read(serial_dir.join(file)).unwrap(),
+            std::fs::read(parallel_dir.join(file)).unwrap(),
+            "{file} differs between --jobs 1 and --jobs 4"
+        );
+    }
+
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "--jobs".as_ref(),
+        "0".as_ref(),
+        "-o".as_ref(),
+        temp_path.join("invalid").as_os_str(),
+    ]);
+    assert!(!output.status.success(), "--jobs 0 is rejected");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid --jobs value"));
+}
+
+/// Test that resizing from the mipmap chain stays close to resizing the full source
+#[test]
+fn test_resize_strategy_mipmap() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 1024, 1024);
+
+    let generate = |strategy: &str| {
+        let output_dir = temp_path.join(strategy);
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "-p".as_ref(),
+            "16,48,300".as_ref(),
+            "--resize-strategy".as_ref(),
+            strategy.as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ]);
+        assert!(
+            output.status.success(),
+            "icon-gen --resize-strategy {strategy} failed"
+        );
+        output_dir
+    };
+    let direct_dir = generate("direct");
+    let mipmap_dir = generate("mipmap");
+
+    for name in ["16x16.png", "48x48.png", "300x300.png"] {
+        let direct = image::open(direct_dir.join(name)).unwrap().to_rgba8();
+        let mipmap = image::open(mipmap_dir.join(name)).unwrap().to_rgba8();
+        assert_eq!(direct.dimensions(), mipmap.dimensions(), "{name}");
+
+        let total: u64 = direct
+            .as_raw()
+            .iter()
+            .zip(mipmap.as_raw())
+            .map(|(&a, &b)| (a as i32 - b as i32).unsigned_abs() as u64)
+            .sum();
+        let mean = total as f64 / direct.as_raw().len() as f64;
+        assert!(
+            mean < 4.0,
+            "{name} differs by {mean:.2} per channel on average"
+        );
+    }
+
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "--resize-strategy".as_ref(),
+        "pyramid".as_ref(),
+        "-o".as_ref(),
+        temp_path.join("invalid").as_os_str(),
+    ]);
+    assert!(!output.status.success(), "Unknown strategies are rejected");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown resize strategy"));
+}
+
+/// Test that a rerun with unchanged inputs and options skips the set, and that
+/// changed options, edited outputs and --no-cache regenerate it
+#[test]
+fn test_generation_cache() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+    let output_dir = temp_path.join("icons");
+
+    let generate = |extra: &[&str]| {
+        let mut args = vec![
+            source_path.as_os_str(),
+            "-p".as_ref(),
+            "16,32".as_ref(),
+            "--variant".as_ref(),
+            "grayscale".as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ];
+        args.extend(extra.iter().map(std::ffi::OsStr::new));
+        let output = run_icon_gen(&args);
+        assert!(
+            output.status.success(),
+            "icon-gen {extra:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let up_to_date = |stdout: &str, dir: &str| {
+        let suffix = format!("{dir} is up to date");
+        stdout.lines().any(|line| line.ends_with(&suffix))
+    };
+
+    let first = generate(&[]);
+    assert!(
+        !up_to_date(&first, "icons"),
+        "First run generates everything"
+    );
+    let cache = std::fs::read_to_string(output_dir.join(".icon-gen-cache.json")).unwrap();
+    assert!(
+        cache.contains("16x16.png"),
+        "Cache records the generated files"
+    );
+
+    let second = generate(&[]);
+    assert!(
+        up_to_date(&second, "icons"),
+        "Unchanged main set is skipped"
+    );
+    assert!(
+        up_to_date(&second, "icons_gray"),
+        "Unchanged variant set is skipped"
+    );
+    assert!(!second.contains("Generated 16x16.png"));
+
+    // An edited output makes its set stale
+    std::fs::write(output_dir.join("16x16.png"), b"edited").unwrap();
+    let third = generate(&[]);
+    assert!(
+        !up_to_date(&third, "/icons"),
+        "Edited main set is regenerated"
+    );
+    assert!(up_to_date(&third, "/icons_gray"));
+    assert!(
+        image::open(output_dir.join("16x16.png")).is_ok(),
+        "Edited output is restored"
+    );
+
+    // Changed options regenerate every set
+    let fourth = generate(&["--filter", "triangle"]);
+    assert!(!fourth.contains("is up to date"));
+
+    let fifth = generate(&["--filter", "triangle", "--no-cache"]);
+    assert!(
+        !fifth.contains("is up to date"),
+        "--no-cache regenerates everything"
+    );
+}
+
+/// Test that identical outputs are not rewritten, and that --skip-existing and
+/// --if-newer leave existing sets alone
+#[test]
+fn test_skip_up_to_date_outputs() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 128, 128);
+    let output_dir = temp_path.join("icons");
+    let icon_path = output_dir.join("16x16.png");
+
+    let generate = |extra: &[&str]| {
+        let mut args = vec![
+            source_path.as_os_str(),
+            "-p".as_ref(),
+            "16,32".as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ];
+        args.extend(extra.iter().map(std::ffi::OsStr::new));
+        let output = run_icon_gen(&args);
+        assert!(output.status.success(), "icon-gen {extra:?} failed");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let modified = |path: &Path| std::fs::metadata(path).unwrap().modified().unwrap();
+    let set_modified = |path: &Path, time: SystemTime| {
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    };
+
+    generate(&[]);
+    let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+    set_modified(&icon_path, an_hour_ago);
+    set_modified(&source_path, an_hour_ago - Duration::from_secs(60));
+
+    // A full regeneration leaves identical files untouched
+    generate(&["--no-cache"]);
+    assert_eq!(
+        modified(&icon_path),
+        an_hour_ago,
+        "Identical output is not rewritten"
+    );
+
+    let stdout = generate(&["--skip-existing", "--sharpen", "1"]);
+    assert!(stdout.contains("already exists, skipped"), "{stdout}");
+
+    let stdout = generate(&["--if-newer", "--sharpen", "1"]);
+    assert!(
+        stdout.contains("is newer than its inputs, skipped"),
+        "{stdout}"
+    );
+
+    // A source edited after the outputs brings --if-newer back to work
+    set_modified(&source_path, SystemTime::now());
+    let stdout = generate(&["--if-newer", "--sharpen", "1"]);
+    assert!(!stdout.contains("skipped"), "{stdout}");
+    assert!(
+        modified(&icon_path) > an_hour_ago,
+        "Changed output is rewritten"
+    );
+
+    // Files of the project a set is written into don't count as the set's own
+    let res_dir = temp_path.join("res");
+    let strings_path = res_dir.join("values/strings.xml");
+    std::fs::create_dir_all(strings_path.parent().unwrap()).unwrap();
+    std::fs::write(&strings_path, b"<resources/>").unwrap();
+    for option in ["--skip-existing", "--if-newer"] {
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "-p".as_ref(),
+            "16".as_ref(),
+            option.as_ref(),
+            "--manifest".as_ref(),
+            "-o".as_ref(),
+            res_dir.as_os_str(),
+        ]);
+        assert!(output.status.success(), "icon-gen {option} failed");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(!stdout.contains("skipped"), "{option}: {stdout}");
+        let manifest = std::fs::read_to_string(res_dir.join("icons.manifest.json")).unwrap();
+        assert!(manifest.contains("16x16.png"), "{option}: {manifest}");
+        assert!(!manifest.contains("strings.xml"), "{option}: {manifest}");
+        std::fs::remove_file(res_dir.join(".icon-gen-cache.json")).unwrap();
+    }
+}
+
+/// Test that existing files icon-gen didn't generate are only replaced with
+/// --force or --clean, that --no-clobber keeps every file, and that --clean
+/// removes the stale files of earlier runs but never files it didn't generate
+#[test]
+fn test_overwrite_policy() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 64, 64);
+    let output_dir = temp_path.join("icons");
+    let ico_path = output_dir.join("windows/icon.ico");
+    std::fs::create_dir_all(ico_path.parent().unwrap()).unwrap();
+    std::fs::write(&ico_path, b"hand-made").unwrap();
+
+    let generate = |extra: &[&str]| {
+        let mut args = vec![
+            source_path.as_os_str(),
+            "--windows".as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ];
+        args.extend(extra.iter().map(std::ffi::OsStr::new));
+        run_icon_gen(&args)
+    };
+
+    let output = generate(&[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Refusing to overwrite"));
+    assert_eq!(std::fs::read(&ico_path).unwrap(), b"hand-made");
+    // The refusal comes before anything is written, the cache included
+    assert!(!output_dir.join("tauri-desktop").exists());
+    assert!(!output_dir.join(".icon-gen-cache.json").exists());
+
+    assert!(generate(&["--force"]).status.success());
+    assert!(std::fs::read(&ico_path).unwrap().starts_with(&[0, 0, 1, 0]));
+    // Files generated by an earlier run are replaced without asking
+    assert!(generate(&["--padding", "10"]).status.success());
+
+    let generated = std::fs::read(&ico_path).unwrap();
+    let output = generate(&["--no-clobber", "--padding", "20"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--no-clobber"));
+    assert_eq!(std::fs::read(&ico_path).unwrap(), generated);
+
+    // Icons named by another template go stale; a file put there by hand doesn't
+    assert!(generate(&["--name-template", "old-{size}@{scale}x.png"])
+        .status
+        .success());
+    let stale_path = output_dir.join("tauri-desktop/old-32@1x.png");
+    assert!(stale_path.exists());
+    let own_path = output_dir.join("windows/old/icon-48.png");
+    std::fs::create_dir_all(own_path.parent().unwrap()).unwrap();
+    std::fs::write(&own_path, b"hand-made").unwrap();
+    let output = generate(&["--clean"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Removed 3 stale files"));
+    assert!(!stale_path.exists());
+    assert_eq!(std::fs::read(&own_path).unwrap(), b"hand-made");
+    assert!(ico_path.exists());
+
+    let output = generate(&["--force", "--no-clobber"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Only one of"));
+}
+
+/// Test that --keep-going generates the other platforms when one fails, exiting
+/// with 3 when some platforms were generated and 1 when none were
+#[test]
+fn test_keep_going() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 64, 64);
+    let output_dir = temp_path.join("icons");
+    // Files in place of the platform folders make their icons fail
+    std::fs::create_dir_all(&output_dir).unwrap();
+    std::fs::write(output_dir.join("windows"), b"").unwrap();
+    std::fs::write(output_dir.join("android"), b"").unwrap();
+
+    let generate = |extra: &[&str]| {
+        let mut args = vec![
+            source_path.as_os_str(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ];
+        args.extend(extra.iter().map(std::ffi::OsStr::new));
+        run_icon_gen(&args)
+    };
+
+    let output = generate(&["--windows", "--linux"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!output_dir.join("linux").exists());
+
+    // windows fails; linux and tauri-desktop are generated
+    let output = generate(&["--windows", "--linux", "--keep-going"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{stderr}");
+    assert!(stderr.contains("1 of 3 platforms failed"), "{stderr}");
+    assert!(stderr.contains("windows: Failed to access"), "{stderr}");
+    assert!(output_dir.join("linux/icon.png").exists());
+
+    let output = generate(&["--android", "--keep-going"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 of 1 platforms failed"));
+}
+
+/// Test that --name-template names the linux, tauri-desktop and custom size icons
+#[test]
+fn test_name_template() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("logo.png");
+    create_test_image(&source_path, 256, 256);
+    let output_dir = temp_path.join("icons");
+
+    let generate = |extra: &[&str]| {
+        let mut args = vec![
+            source_path.as_os_str(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ];
+        args.extend(extra.iter().map(std::ffi::OsStr::new));
+        run_icon_gen(&args)
+    };
+
+    let output = generate(&[
+        "--linux",
+        "--name-template",
+        "{app}-{platform}-{size}{density}.png",
+    ]);
+    assert!(output.status.success());
+    assert!(output_dir.join("linux/logo-linux-512.png").exists());
+    assert!(output_dir
+        .join("tauri-desktop/logo-tauri-desktop-128@2x.png")
+        .exists());
+    assert_eq!(
+        image::open(output_dir.join("tauri-desktop/logo-tauri-desktop-128@2x.png"))
+            .unwrap()
+            .width(),
+        256
+    );
+
+    // Other --formats swap the template's extension
+    let output = generate(&[
+        "--png",
+        "20",
+        "--formats",
+        "png,webp",
+        "--name-template",
+        "icon_{size}.png",
+    ]);
+    assert!(output.status.success());
+    assert!(output_dir.join("icon_20.png").exists());
+    assert!(output_dir.join("icon_20.webp").exists());
+
+    let output = generate(&["--png", "20", "--name-template", "{ap}-{size}.png"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown variable {ap}"));
+    let output = generate(&["--linux", "--name-template", "{size}.png"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("names two tauri-desktop icons"));
+}
+
+#[test]
+fn test_max_working_size() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("large.png");
+    RgbaImage::from_pixel(512, 512, Rgba([40, 120, 200, 255]))
+        .save(&source_path)
+        .unwrap();
+
+    // A 64px radius is an eighth of the 512px source, whatever size it is worked at
+    let generate = |name: &str, max_working_size: &str| {
+        let output_dir = temp_path.join(name);
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "-p".as_ref(),
+            "64".as_ref(),
+            "--radius".as_ref(),
+            "64px".as_ref(),
+            "--max-working-size".as_ref(),
+            max_working_size.as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ]);
+        assert!(
+            output.status.success(),
+            "icon-gen --max-working-size {max_working_size} failed"
+        );
+        image::open(output_dir.join("64x64.png"))
+            .unwrap()
+            .to_rgba8()
+    };
+    let full = generate("full", "4096");
+    let capped = generate("capped", "128");
+
+    // The corner is still an eighth of the side rather than a full circle
+    assert_eq!(full.get_pixel(0, 0)[3], 0);
+    assert_eq!(capped.get_pixel(0, 0)[3], 0);
+    assert_eq!(capped.get_pixel(4, 4)[3], 255);
+    let diff = full
+        .pixels()
+        .zip(capped.pixels())
+        .map(|(a, b)| (a[3] as i32 - b[3] as i32).unsigned_abs())
+        .sum::<u32>() as f32
+        / (64.0 * 64.0);
+    assert!(diff < 2.0, "capped output differs by {diff} on average");
+
+    // A --png size above the cap raises it
+    let output_dir = temp_path.join("raised");
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "256".as_ref(),
+        "--max-working-size".as_ref(),
+        "128".as_ref(),
+        "-o".as_ref(),
+        output_dir.as_os_str(),
+    ]);
+    assert!(output.status.success());
+    let raised = image::open(output_dir.join("256x256.png")).unwrap();
+    assert_eq!(raised.width(), 256);
+
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "--max-working-size".as_ref(),
+        "0".as_ref(),
+        "-o".as_ref(),
+        temp_path.join("zero").as_os_str(),
+    ]);
+    assert!(!output.status.success(), "--max-working-size 0 should fail");
+}
+
+#[test]
+fn test_progress_falls_back_to_log_lines() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("source.png");
+    create_test_image(&source_path, 64, 64);
+
+    // The test harness captures stdout, so it isn't a terminal
+    let output_dir = temp_path.join("icons");
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "-p".as_ref(),
+        "16,32".as_ref(),
+        "-o".as_ref(),
+        output_dir.as_os_str(),
+    ]);
+    assert!(output.status.success(), "icon-gen failed");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Generating custom sizes..."));
+    assert!(stdout.contains("  ✓ Generated 16x16.png"));
+    assert!(stdout.contains("  ✓ Generated 32x32.png"));
+    assert!(
+        !stdout.contains('\r'),
+        "progress line drawn without a terminal"
+    );
+}
+
+#[test]
+fn test_encode_profiles() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let generate = |profile: &str| {
+        let output_dir = temp_path.join(profile);
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "--windows".as_ref(),
+            "--encode-profile".as_ref(),
+            profile.as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ]);
+        assert!(
+            output.status.success(),
+            "icon-gen --encode-profile {profile} failed"
+        );
+        output_dir
+    };
+    let fast = generate("fast");
+    let best = generate("best");
+
+    // Only the compression differs, never the pixels
+    let read = |dir: &Path, file: &str| std::fs::read(dir.join(file)).unwrap();
+    let fast_png = read(&fast, "tauri-desktop/128x128.png");
+    let best_png = read(&best, "tauri-desktop/128x128.png");
+    assert!(best_png.len() <= fast_png.len());
+    assert_eq!(
+        image::load_from_memory(&fast_png).unwrap().to_rgba8(),
+        image::load_from_memory(&best_png).unwrap().to_rgba8()
+    );
+    assert!(read(&best, "windows/icon.ico").len() <= read(&fast, "windows/icon.ico").len());
+
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "--encode-profile".as_ref(),
+        "turbo".as_ref(),
+        "-o".as_ref(),
+        temp_path.join("turbo").as_os_str(),
+    ]);
+    assert!(!output.status.success(), "unknown profile should fail");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown encode profile: turbo"));
+}
+
+/// Test that --archive writes the generated tree into a zip or tar file instead of the output directory
+#[test]
+fn test_archive_output() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("source.png");
+    create_test_image(&source_path, 128, 128);
+    let output_dir = temp_path.join("icons");
+
+    let archive = |name: &str| {
+        let archive_path = temp_path.join(name);
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "--windows".as_ref(),
+            "--variant".as_ref(),
+            "grayscale".as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+            "--archive".as_ref(),
+            archive_path.as_os_str(),
+        ]);
+        assert!(output.status.success(), "icon-gen --archive {name} failed");
+        std::fs::read(archive_path).unwrap()
+    };
+    let contains = |archive: &[u8], name: &str| {
+        archive
+            .windows(name.len())
+            .any(|window| window == name.as_bytes())
+    };
+
+    let zip = archive("icons.zip");
+    assert!(!output_dir.exists());
+    assert!(!temp_path.join("icons_gray").exists());
+    assert_eq!(zip[..4], [0x50, 0x4b, 0x03, 0x04]);
+    assert!(contains(&zip, "icons/windows/icon.ico"));
+    assert!(contains(&zip, "icons_gray/windows/icon.ico"));
+    // Archives are reproducible
+    assert_eq!(archive("icons.zip"), zip);
+
+    let tar = archive("icons.tar");
+    assert_eq!(tar.len() % 512, 0);
+    assert_eq!(&tar[257..263], b"ustar\0");
+    assert!(contains(&tar, "icons/windows/icon.ico"));
+    assert!(contains(&tar, "icons_gray/tauri-desktop/32x32.png"));
+
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "--archive".as_ref(),
+        temp_path.join("icons.rar").as_os_str(),
+    ]);
+    assert!(
+        !output.status.success(),
+        "unknown archive format should fail"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown archive format"));
+}
+
+/// Test that --spec writes each target of the spec file at its size, name and format
+#[test]
+fn test_spec_targets() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("source.png");
+    create_test_image(&source_path, 256, 256);
+    let output_dir = temp_path.join("icons");
+
+    let spec_path = temp_path.join("sizes.json");
+    std::fs::write(
+        &spec_path,
+        r##"{ "targets": [
+            { "size": 96, "filename": "kiosk/icon-{size}.png" },
+            { "size": "400x240", "filename": "tv/banner.png", "background": "#102030" },
+            { "size": 64, "filename": "legacy/icon.{format}", "format": "bmp" },
+            { "size": 48, "filename": "legacy/icon.jpg" }
+        ] }"##,
+    )
+    .unwrap();
+
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "--spec".as_ref(),
+        spec_path.as_os_str(),
+        "-o".as_ref(),
+        output_dir.as_os_str(),
+    ]);
+    assert!(
+        output.status.success(),
+        "icon-gen --spec failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // The spec replaces the default platforms, like a platform flag
+    assert!(!output_dir.join("windows").exists());
+
+    let kiosk = image::open(output_dir.join("kiosk/icon-96.png")).unwrap();
+    assert_eq!((kiosk.width(), kiosk.height()), (96, 96));
+    assert_eq!(kiosk.to_rgba8().get_pixel(0, 0)[3], 0);
+
+    // Non-square targets center the icon on the background
+    let banner = image::open(output_dir.join("tv/banner.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(banner.dimensions(), (400, 240));
+    assert_eq!(*banner.get_pixel(0, 0), Rgba([16, 32, 48, 255]));
+    assert_eq!(*banner.get_pixel(399, 120), Rgba([16, 32, 48, 255]));
+
+    let bmp = std::fs::read(output_dir.join("legacy/icon.bmp")).unwrap();
+    assert_eq!(&bmp[..2], b"BM");
+    let jpeg = image::open(output_dir.join("legacy/icon.jpg")).unwrap();
+    assert_eq!((jpeg.width(), jpeg.height()), (48, 48));
+
+    std::fs::write(
+        &spec_path,
+        r#"{ "targets": [{ "size": 32, "filename": "{dpi}.png" }] }"#,
+    )
+    .unwrap();
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "--spec".as_ref(),
+        spec_path.as_os_str(),
+        "-o".as_ref(),
+        output_dir.as_os_str(),
+    ]);
+    assert!(!output.status.success(), "invalid spec should fail");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown variable {dpi}"));
+}
+
+/// Test that --report json prints every generated file with its dimensions, format and size
+#[test]
+fn test_report_json() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("source.png");
+    create_test_image(&source_path, 128, 128);
+    let output_dir = temp_path.join("icons");
+
+    let report = || {
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "--windows".as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+            "--report".as_ref(),
+            "json".as_ref(),
+        ]);
+        assert!(output.status.success(), "icon-gen --report json failed");
+        // The log is left out, so stdout is the report alone
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).expect("Invalid report")
+    };
+
+    let first = report();
+    let files = first["files"].as_array().unwrap();
+    let file = |name: &str| {
+        let path = output_dir.join(name);
+        files
+            .iter()
+            .find(|file| file["path"] == path.to_string_lossy().as_ref())
+            .unwrap_or_else(|| panic!("{name} is missing from the report"))
+    };
+
+    let ico = file("windows/icon.ico");
+    assert_eq!(ico["format"], "ico");
+    assert_eq!(
+        (ico["width"].as_u64(), ico["height"].as_u64()),
+        (Some(256), Some(256))
+    );
+    let ico_size = std::fs::metadata(output_dir.join("windows/icon.ico"))
+        .unwrap()
+        .len();
+    assert_eq!(ico["bytes"].as_u64(), Some(ico_size));
+    assert_eq!(ico["reused"], false);
+    assert_eq!(file("tauri-desktop/32x32.png")["width"].as_u64(), Some(32));
+    assert_eq!(
+        file("tauri-desktop/128x128@2x.png")["width"].as_u64(),
+        Some(256)
+    );
+    let total = files
+        .iter()
+        .map(|file| file["bytes"].as_u64().unwrap())
+        .sum::<u64>();
+    assert_eq!(first["total_bytes"].as_u64(), Some(total));
+
+    // Files of an up-to-date set are listed as reused
+    let second = report();
+    assert_eq!(second["files"].as_array().unwrap().len(), files.len());
+    assert!(second["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|file| file["reused"] == true));
+
+    let output = run_icon_gen(&[source_path.as_os_str(), "--report".as_ref(), "xml".as_ref()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown report format: xml"));
+}
+
+/// Test that --manifest writes the SHA-256 of every file of each set into icons.manifest.json
+#[test]
+fn test_output_manifest() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("source.png");
+    create_test_image(&source_path, 128, 128);
+    let output_dir = temp_path.join("icons");
+
+    let generate = || {
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "--windows".as_ref(),
+            "--variant".as_ref(),
+            "grayscale".as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+            "--manifest".as_ref(),
+        ]);
+        assert!(output.status.success(), "icon-gen --manifest failed");
+    };
+    let check = |dir: &Path| {
+        let json = std::fs::read(dir.join("icons.manifest.json")).expect("Missing manifest");
+        let manifest = serde_json::from_slice::<serde_json::Value>(&json).unwrap();
+        assert_eq!(manifest["algorithm"], "sha256");
+        let files = manifest["files"].as_array().unwrap();
+        let paths = files
+            .iter()
+            .map(|file| file["path"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert!(paths.contains(&"windows/icon.ico"));
+        assert!(paths.contains(&"tauri-desktop/32x32.png"));
+        assert!(!paths.contains(&"icons.manifest.json"));
+        assert!(!paths.contains(&".icon-gen-cache.json"));
+        for file in files {
+            let bytes = std::fs::read(dir.join(file["path"].as_str().unwrap())).unwrap();
+            assert_eq!(file["bytes"].as_u64(), Some(bytes.len() as u64));
+            let sha256 = file["sha256"].as_str().unwrap();
+            assert_eq!(sha256.len(), 64);
+            assert!(sha256
+                .bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b)));
+        }
+        let hashes = files
+            .iter()
+            .map(|file| file["sha256"].as_str().unwrap())
+            .collect::<std::collections::HashSet<_>>();
+        assert!(hashes.len() > 1, "Every file has the same hash");
+        files.len()
+    };
+
+    generate();
+    let count = check(&output_dir);
+    check(&temp_path.join("icons_gray"));
+
+    // Sets skipped as up to date are still listed in full
+    generate();
+    assert_eq!(check(&output_dir), count);
+}
+
+/// Test that --dry-run lists the files a run would generate without writing any
+#[test]
+fn test_dry_run() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("source.png");
+    create_test_image(&source_path, 128, 128);
+    let output_dir = temp_path.join("icons");
+
+    let dry_run = |source: &Path, extra: &[&str]| {
+        let mut args = vec![
+            source.as_os_str(),
+            "--windows".as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+            "--dry-run".as_ref(),
+        ];
+        args.extend(extra.iter().map(std::ffi::OsStr::new));
+        run_icon_gen(&args)
+    };
+
+    let output = dry_run(&source_path, &[]);
+    assert!(output.status.success(), "icon-gen --dry-run failed");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let ico = output_dir.join("windows/icon.ico");
+    assert!(stdout.contains(&format!("{} (256x256)", ico.display())));
+    assert!(stdout.contains("files would be generated"));
+    assert!(!output_dir.exists(), "--dry-run must not write anything");
+
+    let output = dry_run(&source_path, &["--report", "json", "--manifest"]);
+    assert!(output.status.success());
+    let plan = serde_json::from_slice::<serde_json::Value>(&output.stdout).expect("Invalid plan");
+    let files = plan["files"].as_array().unwrap();
+    let manifest = output_dir.join("icons.manifest.json");
+    assert!(files
+        .iter()
+        .any(|file| file["path"] == manifest.to_string_lossy().as_ref()));
+    assert!(files.iter().all(|file| file.get("bytes").is_none()));
+    assert!(!output_dir.exists());
+
+    // Only the header of the source is read, so a truncated image passes
+    let png = std::fs::read(&source_path).unwrap();
+    let truncated = temp_path.join("truncated.png");
+    std::fs::write(&truncated, &png[..png.len() / 2]).unwrap();
+    assert!(dry_run(&truncated, &[]).status.success());
+    let output = run_icon_gen(&[
+        truncated.as_os_str(),
+        "--windows".as_ref(),
+        "-o".as_ref(),
+        output_dir.as_os_str(),
+    ]);
+    assert!(!output.status.success(), "a real run decodes the source");
+
+    let wide = temp_path.join("wide.png");
+    create_test_image(&wide, 64, 32);
+    let output = dry_run(&wide, &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("must be square"));
+}
+
+/// Test that --progress json prints one JSON event per line
+#[test]
+fn test_progress_json() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("source.png");
+    create_test_image(&source_path, 128, 128);
+    let output_dir = temp_path.join("icons");
+
+    let events = |mode: &str| {
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "--windows".as_ref(),
+            "--linux".as_ref(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+            "--progress".as_ref(),
+            mode.as_ref(),
+        ]);
+        assert!(output.status.success(), "icon-gen --progress {mode} failed");
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("Invalid event"))
+            .collect::<Vec<_>>()
+    };
+    let of_kind = |events: &[serde_json::Value], kind: &str| {
+        events
+            .iter()
+            .filter(|event| event["event"] == kind)
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    let first = events("json");
+    assert_eq!(first[0]["event"], "started");
+    assert_eq!(first[0]["sets"], 1);
+    assert_eq!(first[1]["event"], "set_started");
+    let last = first.last().unwrap();
+    assert_eq!(last["event"], "finished");
+
+    let written = of_kind(&first, "file_written");
+    assert_eq!(last["files"].as_u64(), Some(written.len() as u64));
+    for event in &written {
+        let path = Path::new(event["path"].as_str().unwrap());
+        assert_eq!(
+            event["bytes"].as_u64(),
+            Some(std::fs::metadata(path).unwrap().len())
+        );
+    }
+    let platforms = of_kind(&first, "platform_done")
+        .iter()
+        .flat_map(|event| event["platforms"].as_array().unwrap().clone())
+        .collect::<Vec<_>>();
+    assert!(platforms.contains(&"windows".into()));
+    assert!(platforms.contains(&"linux".into()));
+
+    // An up-to-date set writes nothing and says so
+    let second = events("json");
+    assert!(of_kind(&second, "file_written").is_empty());
+    assert!(of_kind(&second, "note")[0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("is up to date"));
+    assert_eq!(second.last().unwrap()["files"], 0);
+
+    let output = run_icon_gen(&[
+        source_path.as_os_str(),
+        "--progress".as_ref(),
+        "xml".as_ref(),
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown progress mode: xml"));
+}
+
+/// Test that two runs with the same inputs produce byte-identical files
+#[test]
+fn test_reproducible_outputs() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("source.png");
+    create_test_image(&source_path, 128, 128);
+
+    let generate = |name: &str| {
+        let output_dir = temp_path.join(name);
+        let output = run_icon_gen(&[
+            source_path.as_os_str(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+            "--macos".as_ref(),
+            "--windows".as_ref(),
+        ]);
+        assert!(output.status.success(), "icon-gen failed");
+        let mut files = std::collections::BTreeMap::new();
+        collect_files(&output_dir, &output_dir, &mut files);
+        // The cache names its own directory
+        files.remove(Path::new(".icon-gen-cache.json"));
+        files
+    };
+
+    let first = generate("first");
+    let second = generate("second");
+    assert!(first.contains_key(Path::new("macos/Contents.json")));
+    assert_eq!(
+        first.keys().collect::<Vec<_>>(),
+        second.keys().collect::<Vec<_>>()
+    );
+    for (path, bytes) in &first {
+        assert!(
+            second[path] == *bytes,
+            "{} differs between runs",
+            path.display()
+        );
+    }
+}
+
+/// Test that `--png-metadata srgb` tags every generated PNG as sRGB, that
+/// `strip` leaves nothing but the image chunks, and that unknown modes fail
+#[test]
+fn test_png_metadata() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let source_path = temp_path.join("source.png");
+    create_test_image(&source_path, 128, 128);
+    let output_dir = temp_path.join("icons");
+
+    let generate = |mode: &str| {
+        run_icon_gen(&[
+            source_path.as_os_str(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+            "--linux".as_ref(),
+            "--quantize".as_ref(),
+            "--png-metadata".as_ref(),
+            mode.as_ref(),
+        ])
+    };
+    let chunk_types = |path: &Path| {
+        let png = std::fs::read(path).unwrap();
+        let mut types = Vec::new();
+        let mut rest = &png[8..];
+        while rest.len() >= 12 {
+            let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            types.push(String::from_utf8_lossy(&rest[4..8]).into_owned());
+            rest = &rest[length + 12..];
+        }
+        types
+    };
+    let icons = ["linux/32x32.png", "linux/icon.png"].map(|icon| output_dir.join(icon));
+    // A PNG put into the set by hand keeps its chunks
+    let user_path = output_dir.join("linux/user.png");
+    std::fs::create_dir_all(user_path.parent().unwrap()).unwrap();
+    create_test_image(&user_path, 32, 32);
+    let user = std::fs::read(&user_path).unwrap();
+
+    assert!(
+        generate("srgb").status.success(),
+        "--png-metadata srgb failed"
+    );
+    for icon in &icons {
+        let types = chunk_types(icon);
+        assert_eq!(
+            types[..4],
+            ["IHDR", "sRGB", "gAMA", "cHRM"],
+            "{}",
+            icon.display()
+        );
+        image::open(icon).expect("A tagged PNG should still decode");
+    }
+    // The quantized icon keeps its palette and transparency
+    assert!(chunk_types(&icons[0]).contains(&"tRNS".to_string()));
+    assert_eq!(std::fs::read(&user_path).unwrap(), user);
+
+    assert!(
+        generate("strip").status.success(),
+        "--png-metadata strip failed"
+    );
+    for icon in &icons {
+        assert!(!chunk_types(icon).contains(&"sRGB".to_string()));
+    }
+
+    let output = generate("icc");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Unknown PNG metadata mode: icc. Available modes: keep, srgb, strip"));
+}
+
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    files: &mut std::collections::BTreeMap<std::path::PathBuf, Vec<u8>>,
+) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_files(root, &path, files);
+        } else {
+            let relative = path.strip_prefix(root).unwrap().to_path_buf();
+            files.insert(relative, std::fs::read(&path).unwrap());
+        }
+    }
+}
+
+/// Runs icon-gen with the given arguments
+fn run_icon_gen(args: &[&std::ffi::OsStr]) -> std::process::Output {
+    Command::new(get_icon_gen_binary_path())
+        .args(args)
+        .output()
+        .expect("Failed to run icon-gen command")
+}
+
+/// Creates a test image whose left half is transparent and right half an opaque gradient
+fn create_test_image(path: &Path, width: u32, height: u32) {
+    let mut image = RgbaImage::new(width, height);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        if x < width / 2 {
+            *pixel = Rgba([0, 0, 0, 0]);
+        } else {
+            let green = (255.0 * y as f32 / height as f32) as u8;
+            *pixel = Rgba([200, green, 64, 255]);
+        }
+    }
+
+    image.save(path).expect("Failed to save test image");
+}
+
+/// Gets the path to the icon-gen binary
+fn get_icon_gen_binary_path() -> std::path::PathBuf {
+    // First try to find in target/debug
+    let debug_path = std::path::Path::new("target/debug/icon-gen");
+    if debug_path.exists() {
+        return debug_path.to_path_buf();
+    }
+
+    // If not found, build it first
+    let build_output = Command::new("cargo")
+        .args(["build", "--bin", "icon-gen"])
+        .output()
+        .expect("Failed to run cargo build");
+
+    if !build_output.status.success() {
+        panic!(
+            "Failed to build icon-gen binary: {}",
+            String::from_utf8_lossy(&build_output.stderr)
+        );
+    }
+
+    debug_path.to_path_buf()
+}
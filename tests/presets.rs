@@ -0,0 +1,292 @@
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Test that `--preset steam,itch` writes each preset into its own folder
+/// with the expected dimensions, and nothing else.
+#[test]
+fn test_game_store_presets() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 512, 512);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--preset")
+        .arg("steam,itch")
+        .arg("--preset-bg")
+        .arg("#1b2838")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --preset failed");
+    }
+
+    let expected = [
+        ("steam/184x184.png", 184, 184),
+        ("steam/256x256.png", 256, 256),
+        ("steam/community_icon.jpg", 184, 184),
+        ("steam/capsule_header.png", 460, 215),
+        ("steam/library_capsule.png", 600, 900),
+        ("itch/cover.png", 630, 500),
+        ("itch/icon.png", 512, 512),
+    ];
+    for (file, width, height) in expected {
+        let image = image::open(output_dir.join(file))
+            .unwrap_or_else(|_| panic!("Failed to load {}", file));
+        assert_eq!((image.width(), image.height()), (width, height), "{}", file);
+    }
+
+    assert!(output_dir.join("steam/client_icon.ico").exists());
+
+    // Capsules are filled with the preset background color
+    let capsule = image::open(output_dir.join("steam/capsule_header.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(capsule.get_pixel(0, 0).0, [0x1b, 0x28, 0x38, 255]);
+
+    // Presets alone do not trigger the default platform set
+    assert!(!output_dir.join("windows").exists());
+    assert!(!output_dir.join("ios").exists());
+
+    // A typo fails instead of filling the capsules with white
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--preset", "steam", "--preset-bg", "1b2838"])
+        .arg("-o")
+        .arg(temp_path.join("invalid_output"))
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid --preset-bg: 1b2838"));
+}
+
+/// Test that the engine presets produce the Unity PlayerSettings layout and the Godot
+/// project/export files.
+#[test]
+fn test_engine_presets() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_transparent_test_image(&source_path, 1024);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--preset")
+        .arg("unity,godot")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --preset unity,godot failed");
+    }
+
+    let unity_dir = output_dir.join("unity");
+    for file in [
+        "Standalone/icon_1024.png",
+        "Standalone/icon_16.png",
+        "iOS/icon_180.png",
+        "Android/Legacy/icon_192.png",
+        "Android/Round/icon_36.png",
+        "Android/Adaptive/foreground_432.png",
+    ] {
+        assert!(unity_dir.join(file).exists(), "unity/{} should exist", file);
+    }
+
+    // iOS icons are flattened, round icons are masked
+    let ios = image::open(unity_dir.join("iOS/icon_180.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(
+        ios.get_pixel(0, 0)[3],
+        255,
+        "Unity iOS icons must be opaque"
+    );
+    let round = image::open(unity_dir.join("Android/Round/icon_192.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(
+        round.get_pixel(0, 0)[3],
+        0,
+        "Round icon corners must be clear"
+    );
+
+    let godot_dir = output_dir.join("godot");
+    assert_eq!(
+        image::open(godot_dir.join("icon.png")).unwrap().width(),
+        256
+    );
+    assert!(godot_dir.join("icons/icon.ico").exists());
+    assert!(godot_dir.join("icons/icon.icns").exists());
+    let snippet = std::fs::read_to_string(godot_dir.join("export_presets.cfg.snippet"))
+        .expect("Godot export preset snippet should exist");
+    assert!(snippet.contains(r#"application/icon="res://icons/icon.ico""#));
+}
+
+/// Test that the GitHub social preset renders a 1280x640 card with the icon on
+/// the left and the project name drawn to its right.
+#[test]
+fn test_github_social_preset() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_transparent_test_image(&source_path, 512);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--preset")
+        .arg("github-social")
+        .arg("--preset-bg")
+        .arg("#ffffff")
+        .arg("--project-name")
+        .arg("icon-generator")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --preset github-social failed");
+    }
+
+    let card = image::open(output_dir.join("github-social").join("social-preview.png"))
+        .expect("Social preview card should exist")
+        .to_rgba8();
+    assert_eq!(card.dimensions(), (1280, 640));
+
+    let white = Rgba([255, 255, 255, 255]);
+    assert_eq!(*card.get_pixel(10, 10), white);
+
+    // The icon sits on the left, vertically centered
+    assert_eq!(*card.get_pixel(280, 320), Rgba([40, 120, 200, 255]));
+
+    // Dark text is drawn on the light background to the right of the icon
+    let text_pixels = (560..1180)
+        .flat_map(|x| (240..400).map(move |y| (x, y)))
+        .filter(|&(x, y)| card.get_pixel(x, y)[0] < 100)
+        .count();
+    assert!(
+        text_pixels > 1000,
+        "Project name should be drawn on the card"
+    );
+}
+
+/// Test that `--preset play-store` writes the 512x512 listing icon, and that an
+/// icon over the Play Store's 1024 KB limit is warned about with the options
+/// that can shrink it
+#[test]
+fn test_play_store_preset_limits() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let run = |source: &Path, output_dir: &Path| {
+        Command::new(get_icon_gen_binary_path())
+            .arg(source)
+            .args(["--preset", "play-store", "--no-config"])
+            .arg("-o")
+            .arg(output_dir)
+            .output()
+            .expect("Failed to run icon-gen command")
+    };
+
+    let source_path = temp_path.join("test_source.png");
+    create_transparent_test_image(&source_path, 512);
+    let output_dir = temp_path.join("test_output");
+    let output = run(&source_path, &output_dir);
+    assert!(output.status.success(), "--preset play-store failed");
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Warning"));
+    let icon = image::open(output_dir.join("play-store").join("icon-512.png"))
+        .expect("Play Store icon should exist");
+    assert_eq!((icon.width(), icon.height()), (512, 512));
+
+    // Noise doesn't compress, so the icon takes more than its raw 1024 KB
+    let noise_path = temp_path.join("noise.png");
+    let mut seed = 1u32;
+    RgbaImage::from_fn(512, 512, |_, _| {
+        Rgba([0; 4].map(|_: u8| {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 24) as u8
+        }))
+    })
+    .save(&noise_path)
+    .expect("Failed to save test image");
+    let output = run(&noise_path, &temp_path.join("noise_output"));
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("play-store/icon-512.png is 1025 KB, over the Play Store limit of 1024 KB; try --optimize and --compression zopfli"),
+        "{stderr}"
+    );
+}
+
+/// Creates a test image with a transparent border around an opaque square
+fn create_transparent_test_image(path: &Path, size: u32) {
+    let mut image = RgbaImage::new(size, size);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let inside = x > size / 8 && x < size * 7 / 8 && y > size / 8 && y < size * 7 / 8;
+        *pixel = if inside {
+            Rgba([40, 120, 200, 255])
+        } else {
+            Rgba([0, 0, 0, 0])
+        };
+    }
+
+    image.save(path).expect("Failed to save test image");
+}
+
+/// Creates an opaque test image with a gradient
+fn create_test_image(path: &Path, width: u32, height: u32) {
+    let mut image = RgbaImage::new(width, height);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let red = (255.0 * x as f32 / width as f32) as u8;
+        let green = (255.0 * y as f32 / height as f32) as u8;
+        *pixel = Rgba([red, green, 128, 255]);
+    }
+
+    image.save(path).expect("Failed to save test image");
+}
+
+/// Gets the path to the icon-gen binary
+fn get_icon_gen_binary_path() -> std::path::PathBuf {
+    // First try to find in target/debug
+    let debug_path = std::path::Path::new("target/debug/icon-gen");
+    if debug_path.exists() {
+        return debug_path.to_path_buf();
+    }
+
+    // If not found, build it first
+    let build_output = Command::new("cargo")
+        .args(["build", "--bin", "icon-gen"])
+        .output()
+        .expect("Failed to run cargo build");
+
+    if !build_output.status.success() {
+        panic!(
+            "Failed to build icon-gen binary: {}",
+            String::from_utf8_lossy(&build_output.stderr)
+        );
+    }
+
+    debug_path.to_path_buf()
+}
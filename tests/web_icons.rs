@@ -0,0 +1,148 @@
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::png::PngDecoder;
+use image::{AnimationDecoder, Delay, Frame, Rgba, RgbaImage};
+use std::fs::File;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Test that `--animated-favicon` turns an animated GIF source into 32x32
+/// animated GIF and APNG favicons, honoring `--favicon-fps`.
+#[test]
+fn test_animated_favicon_from_gif() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("animated_source.gif");
+    create_animated_gif(&source_path, 64, 3);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--animated-favicon")
+        .arg("--favicon-fps")
+        .arg("5")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --animated-favicon failed");
+    }
+
+    let web_dir = output_dir.join("web");
+
+    let gif = GifDecoder::new(File::open(web_dir.join("favicon.gif")).unwrap()).unwrap();
+    let gif_frames = gif.into_frames().collect_frames().unwrap();
+    assert_eq!(gif_frames.len(), 3, "GIF favicon should keep every frame");
+    for frame in &gif_frames {
+        assert_eq!(frame.buffer().dimensions(), (32, 32));
+        assert_eq!(
+            frame.delay().numer_denom_ms(),
+            (200, 1),
+            "5 fps is 200ms per frame"
+        );
+    }
+
+    let png = PngDecoder::new(File::open(web_dir.join("favicon.apng")).unwrap()).unwrap();
+    assert!(png.is_apng(), "favicon.apng should be animated");
+    let apng_frames = png.apng().into_frames().collect_frames().unwrap();
+    assert_eq!(apng_frames.len(), 3, "APNG favicon should keep every frame");
+    assert_eq!(apng_frames[0].buffer().dimensions(), (32, 32));
+
+    // Only the favicon was requested
+    assert!(!output_dir.join("windows").exists());
+}
+
+/// Test that `--pwa-startup` writes portrait and landscape startup images and a
+/// `<link>` tag for each one.
+#[test]
+fn test_pwa_startup_images() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    RgbaImage::from_pixel(128, 128, Rgba([200, 50, 50, 255]))
+        .save(&source_path)
+        .unwrap();
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--pwa-startup")
+        .arg("--splash-bg")
+        .arg("#000000")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --pwa-startup failed");
+    }
+
+    let web_dir = output_dir.join("web");
+    let portrait = image::open(web_dir.join("splash").join("apple-splash-1170-2532.png"))
+        .expect("iPhone 12-14 portrait startup image should exist")
+        .to_rgba8();
+    assert_eq!(portrait.dimensions(), (1170, 2532));
+    assert_eq!(*portrait.get_pixel(0, 0), Rgba([0, 0, 0, 255]));
+    assert_eq!(*portrait.get_pixel(585, 1266), Rgba([200, 50, 50, 255]));
+    assert!(web_dir
+        .join("splash")
+        .join("apple-splash-2532-1170.png")
+        .exists());
+
+    let html = std::fs::read_to_string(web_dir.join("apple-touch-startup-image.html"))
+        .expect("Startup image HTML snippet should exist");
+    let links: Vec<_> = html.lines().collect();
+    let images = std::fs::read_dir(web_dir.join("splash")).unwrap().count();
+    assert_eq!(links.len(), images, "One <link> per startup image");
+    assert!(html.contains(
+        r#"media="screen and (device-width: 390px) and (device-height: 844px) and (-webkit-device-pixel-ratio: 3) and (orientation: landscape)" href="splash/apple-splash-2532-1170.png""#
+    ));
+}
+
+/// Creates an animated GIF whose frames cycle through solid colors
+fn create_animated_gif(path: &Path, size: u32, frame_count: u32) {
+    let mut encoder = GifEncoder::new(File::create(path).expect("Failed to create GIF"));
+    encoder.set_repeat(Repeat::Infinite).unwrap();
+
+    for i in 0..frame_count {
+        let shade = (255 * (i + 1) / frame_count) as u8;
+        let image = RgbaImage::from_pixel(size, size, Rgba([shade, 64, 255 - shade, 255]));
+        let delay = Delay::from_numer_denom_ms(100, 1);
+        encoder
+            .encode_frame(Frame::from_parts(image, 0, 0, delay))
+            .expect("Failed to encode GIF frame");
+    }
+}
+
+/// Gets the path to the icon-gen binary
+fn get_icon_gen_binary_path() -> std::path::PathBuf {
+    // First try to find in target/debug
+    let debug_path = std::path::Path::new("target/debug/icon-gen");
+    if debug_path.exists() {
+        return debug_path.to_path_buf();
+    }
+
+    // If not found, build it first
+    let build_output = Command::new("cargo")
+        .args(["build", "--bin", "icon-gen"])
+        .output()
+        .expect("Failed to run cargo build");
+
+    if !build_output.status.success() {
+        panic!(
+            "Failed to build icon-gen binary: {}",
+            String::from_utf8_lossy(&build_output.stderr)
+        );
+    }
+
+    debug_path.to_path_buf()
+}
@@ -0,0 +1,205 @@
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Test that `--cursor` writes a CUR file whose directory carries the scaled hotspot,
+/// and that `--cursor-frames` produces a RIFF ANI container.
+#[test]
+fn test_cursor_generation() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("cursor_source.png");
+    create_test_image(&source_path, 128, 128);
+    let frame_path = temp_path.join("cursor_frame.png");
+    create_test_image(&frame_path, 64, 64);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--cursor")
+        .arg("--cursor-hotspot")
+        .arg("64,32")
+        .arg("--cursor-frames")
+        .arg(&frame_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("icon-gen command with --cursor failed");
+    }
+
+    let cur = std::fs::read(output_dir.join("windows").join("cursor.cur"))
+        .expect("cursor.cur should exist");
+    let read_u16 = |offset: usize| u16::from_le_bytes([cur[offset], cur[offset + 1]]);
+
+    assert_eq!(read_u16(2), 2, "CUR files use image type 2");
+    assert_eq!(
+        read_u16(4),
+        3,
+        "Cursor should contain 32, 48 and 64 px frames"
+    );
+
+    // First entry is the 32px frame: hotspot 64,32 on 128px scales to 16,8
+    assert_eq!(cur[6], 32);
+    assert_eq!(
+        read_u16(6 + 4),
+        16,
+        "Hotspot X should be scaled to the frame"
+    );
+    assert_eq!(
+        read_u16(6 + 6),
+        8,
+        "Hotspot Y should be scaled to the frame"
+    );
+
+    let ani = std::fs::read(output_dir.join("windows").join("cursor.ani"))
+        .expect("cursor.ani should exist");
+    assert_eq!(&ani[0..4], b"RIFF");
+    assert_eq!(&ani[8..12], b"ACON");
+    assert_eq!(&ani[12..16], b"anih");
+    let frame_count = u32::from_le_bytes([ani[24], ani[25], ani[26], ani[27]]);
+    assert_eq!(
+        frame_count, 2,
+        "ANI should hold the source plus one extra frame"
+    );
+
+    // Only the cursor was requested, so no other platform output should exist
+    assert!(!output_dir.join("windows").join("icon.ico").exists());
+    assert!(!output_dir.join("android").exists());
+
+    // The longest delay converts to jiffies without overflowing
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args([
+            "--cursor",
+            "--cursor-delay",
+            "4294967295",
+            "--cursor-frames",
+        ])
+        .arg(&frame_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+    assert!(
+        output.status.success(),
+        "icon-gen with the longest --cursor-delay failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let ani = std::fs::read(output_dir.join("windows").join("cursor.ani")).unwrap();
+    let display_rate = u32::from_le_bytes([ani[48], ani[49], ani[50], ani[51]]);
+    assert_eq!(display_rate, 257_698_037);
+}
+
+/// Test that `--windows-rc` writes the resource script and build.rs fragment
+#[test]
+fn test_windows_resource_script() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .arg("--windows")
+        .arg("--windows-rc")
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    assert!(output.status.success(), "icon-gen --windows-rc failed");
+
+    let windows_dir = output_dir.join("windows");
+    assert!(windows_dir.join("icon.ico").exists());
+
+    let app_rc = std::fs::read_to_string(windows_dir.join("app.rc")).expect("app.rc should exist");
+    assert!(app_rc.contains(r#"IDI_ICON1 ICON "icon.ico""#));
+
+    let build_rs = std::fs::read_to_string(windows_dir.join("winres-build.rs"))
+        .expect("winres-build.rs should exist");
+    assert!(build_rs.contains("winres::WindowsResource::new()"));
+
+    // An icon.ico left by an earlier run doesn't get resource scripts
+    let stale_dir = temp_path.join("stale_output");
+    for args in [&["--windows"][..], &["--linux", "--windows-rc"]] {
+        let output = Command::new(get_icon_gen_binary_path())
+            .arg(&source_path)
+            .args(args)
+            .arg("-o")
+            .arg(&stale_dir)
+            .output()
+            .expect("Failed to run icon-gen command");
+        assert!(output.status.success(), "icon-gen {args:?} failed");
+    }
+    assert!(stale_dir.join("windows/icon.ico").exists());
+    assert!(!stale_dir.join("windows/app.rc").exists());
+}
+
+/// Test that --verify decodes icon.ico and icon.icns back after writing them
+#[test]
+fn test_verify_containers() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let source_path = temp_path.join("test_source.png");
+    create_test_image(&source_path, 256, 256);
+
+    let output_dir = temp_path.join("test_output");
+    let output = Command::new(get_icon_gen_binary_path())
+        .arg(&source_path)
+        .args(["--windows", "--macos", "--verify", "-o"])
+        .arg(&output_dir)
+        .output()
+        .expect("Failed to run icon-gen command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "icon-gen --verify failed");
+    assert!(stdout.contains("✓ Verified windows/icon.ico (6 frames)"));
+    assert!(stdout.contains("✓ Verified macos/icon.icns (10 icons)"));
+}
+
+/// Creates an opaque test image with a gradient
+fn create_test_image(path: &Path, width: u32, height: u32) {
+    let mut image = RgbaImage::new(width, height);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let red = (255.0 * x as f32 / width as f32) as u8;
+        let green = (255.0 * y as f32 / height as f32) as u8;
+        *pixel = Rgba([red, green, 128, 255]);
+    }
+
+    image.save(path).expect("Failed to save test image");
+}
+
+/// Gets the path to the icon-gen binary
+fn get_icon_gen_binary_path() -> std::path::PathBuf {
+    // First try to find in target/debug
+    let debug_path = std::path::Path::new("target/debug/icon-gen");
+    if debug_path.exists() {
+        return debug_path.to_path_buf();
+    }
+
+    // If not found, build it first
+    let build_output = Command::new("cargo")
+        .args(["build", "--bin", "icon-gen"])
+        .output()
+        .expect("Failed to run cargo build");
+
+    if !build_output.status.success() {
+        panic!(
+            "Failed to build icon-gen binary: {}",
+            String::from_utf8_lossy(&build_output.stderr)
+        );
+    }
+
+    debug_path.to_path_buf()
+}